@@ -0,0 +1,232 @@
+// Compares the concurrent union-finds: `AUnionFind`, which stores each
+// element’s parent and rank in separate `AtomicUsize`s, `PackedAUnionFind`,
+// which packs both into one `AtomicUsize`, and `AUnionFind32`, which packs
+// both into one `AtomicU32`, and then `AUnionFind`’s find-then-link union
+// against its Rem’s algorithm with splicing, `union_splicing`.
+//
+// Run with `cargo bench --bench concurrent`.
+//
+// Each thread performs random unions and then random finds. With `LEN`
+// large enough to spill out of the cache, the packed layouts touch half
+// (or a quarter) as much memory per step of `find`, and linking a root
+// takes one compare-and-swap with no separate read of its rank. Whether
+// that shows up in the timings depends on the machine’s memory system
+// and core count; with small `LEN`, the difference disappears.
+//
+// It also times `AUnionFind` resolving its finds in one batch with
+// `find_many`, which walks several paths in step.
+//
+// Next, it times the path splitting that the concurrent `find`s use,
+// with every thread finding random elements of the same trees, built by
+// unions to the greatest depth that ranks allow, so that the threads
+// race to compress the same paths.
+//
+// Finally, it compares `AUnionFind`’s default layout against
+// `CachePadded` on many small union-finds, which the threads work through
+// in step. Each thread unions only its own elements, but those interleave
+// with the other threads’, so with the default layout every write
+// invalidates cache lines that the others are using.
+//
+// Every run checks its results: the union-finds given the same unions
+// must end up with the same sets, however the threads interleaved, and
+// every find must reach the right root.
+
+extern crate disjoint_sets;
+
+use disjoint_sets::{AUnionFind, AUnionFind32, CachePadded, Compact, EntryLayout,
+                    PackedAUnionFind};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The number of elements in each union-find.
+const LEN: usize = 1 << 22;
+
+// The number of random unions per thread, followed by the same number of
+// finds.
+const OPS: usize = 1 << 21;
+
+const THREADS: u64 = 4;
+
+// The number of elements in each deep tree, for timing path compression.
+const TREE_LEN: usize = 1 << 12;
+
+// The number of elements in each small union-find, and how many of them
+// to work through, for comparing layouts.
+const HOT_LEN: usize = 64;
+const ROUNDS: usize = 1 << 12;
+
+// A small, deterministic pseudo-random number generator (xorshift).
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % LEN as u64) as usize
+    }
+}
+
+// Times `THREADS` threads each running `work` with its own generator,
+// returning the time and the union-find.
+fn time<T, F>(uf: T, work: F) -> (Duration, T)
+    where T: Send + Sync + 'static,
+          F: Fn(&T, &mut Rng) + Send + Sync + 'static {
+
+    let uf = Arc::new(uf);
+    let work = Arc::new(work);
+    let start = Instant::now();
+
+    let handles: Vec<_> = (0 .. THREADS).map(|t| {
+        let (uf, work) = (uf.clone(), work.clone());
+        thread::spawn(move || work(&uf, &mut Rng(0x2545_f491_4f6c_dd1d + t)))
+    }).collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    (elapsed, Arc::try_unwrap(uf).ok().unwrap())
+}
+
+// Numbers the sets of a vector of representatives in order of first
+// appearance, so that partitions can be compared whatever their roots.
+fn labels<T: Copy + Eq + Hash>(representatives: Vec<T>) -> Vec<usize> {
+    let mut numbers = HashMap::new();
+    representatives.into_iter()
+        .map(|root| {
+            let next = numbers.len();
+            *numbers.entry(root).or_insert(next)
+        })
+        .collect()
+}
+
+// Joins each run of `TREE_LEN` elements into one binomial tree, whose
+// deepest element is log₂ `TREE_LEN` links from the root, by always
+// joining two trees of equal rank. Returns each tree’s root.
+fn build_trees<T>(uf: &T, union: fn(&T, usize, usize) -> bool, find: fn(&T, usize) -> usize)
+                  -> Vec<usize> {
+    for start in (0 .. LEN).step_by(TREE_LEN) {
+        let mut size = 1;
+        while size < TREE_LEN {
+            for i in (start .. start + TREE_LEN).step_by(2 * size) {
+                assert!(union(uf, i, i + size));
+            }
+            size *= 2;
+        }
+    }
+
+    (0 .. LEN).step_by(TREE_LEN).map(|start| find(uf, start)).collect()
+}
+
+// Times `THREADS` threads finding random elements of the deep trees,
+// checking that each finds its tree’s root.
+fn time_trees<T>(uf: T, union: fn(&T, usize, usize) -> bool, find: fn(&T, usize) -> usize)
+                 -> Duration
+    where T: Send + Sync + 'static {
+
+    let roots = build_trees(&uf, union, find);
+    time(uf, move |uf, rng| {
+        for _ in 0 .. OPS {
+            let element = rng.next();
+            assert_eq!(find(uf, element), roots[element / TREE_LEN]);
+        }
+    }).0
+}
+
+// Times `THREADS` threads working through `ROUNDS` small union-finds
+// with layout `L`, each thread joining its own elements into one set.
+fn time_hot<L: EntryLayout + 'static>() -> Duration {
+    let ufs: Arc<Vec<AUnionFind<L>>> =
+        Arc::new((0 .. ROUNDS).map(|_| AUnionFind::with_layout(HOT_LEN)).collect());
+    let barrier = Arc::new(Barrier::new(THREADS as usize));
+    let start = Instant::now();
+
+    let handles: Vec<_> = (0 .. THREADS as usize).map(|t| {
+        let (ufs, barrier) = (ufs.clone(), barrier.clone());
+        thread::spawn(move || {
+            let own = |i: usize| i * THREADS as usize + t;
+            for uf in ufs.iter() {
+                barrier.wait();
+                for i in 1 .. HOT_LEN / THREADS as usize {
+                    uf.union(own(i - 1), own(i));
+                    uf.find(own(0));
+                }
+            }
+        })
+    }).collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let elapsed = start.elapsed();
+
+    // The threads’ own elements, and only those, are in one set.
+    for uf in ufs.iter() {
+        for t in 0 .. THREADS as usize {
+            let root = uf.find(t);
+            for i in 0 .. HOT_LEN {
+                assert_eq!(uf.find(i) == root, i % THREADS as usize == t);
+            }
+        }
+    }
+
+    elapsed
+}
+
+fn main() {
+    let (separate, uf) = time(AUnionFind::new(LEN), |uf, rng| {
+        for _ in 0 .. OPS { uf.union(rng.next(), rng.next()); }
+        for _ in 0 .. OPS { uf.find(rng.next()); }
+    });
+    let expected = labels(uf.to_vec());
+
+    let (batched, uf) = time(AUnionFind::new(LEN), |uf, rng| {
+        for _ in 0 .. OPS { uf.union(rng.next(), rng.next()); }
+        let mut elements: Vec<_> = (0 .. OPS).map(|_| rng.next()).collect();
+        uf.find_many(&mut elements);
+    });
+    assert_eq!(labels(uf.to_vec()), expected);
+
+    let (packed, uf) = time(PackedAUnionFind::new(LEN), |uf, rng| {
+        for _ in 0 .. OPS { uf.union(rng.next(), rng.next()); }
+        for _ in 0 .. OPS { uf.find(rng.next()); }
+    });
+    assert_eq!(labels(uf.to_vec()), expected);
+
+    let (packed32, uf) = time(AUnionFind32::new(LEN), |uf, rng| {
+        for _ in 0 .. OPS { uf.union(rng.next() as u32, rng.next() as u32); }
+        for _ in 0 .. OPS { uf.find(rng.next() as u32); }
+    });
+    assert_eq!(labels(uf.to_vec()), expected);
+
+    let (rem, uf) = time(AUnionFind::new(LEN), |uf, rng| {
+        for _ in 0 .. OPS { uf.union_splicing(rng.next(), rng.next()); }
+        for _ in 0 .. OPS { uf.find(rng.next()); }
+    });
+    assert_eq!(labels(uf.to_vec()), expected);
+
+    println!("separate words:   {:?}", separate);
+    println!("  with find_many: {:?}", batched);
+    println!("packed usize:     {:?}", packed);
+    println!("packed u32:       {:?}", packed32);
+    println!("Rem’s splicing:   {:?}", rem);
+
+    println!("deep, separate:   {:?}",
+             time_trees(AUnionFind::new(LEN), AUnionFind::union, AUnionFind::find));
+    println!("deep, packed:     {:?}",
+             time_trees(PackedAUnionFind::new(LEN),
+                        PackedAUnionFind::union, PackedAUnionFind::find));
+    println!("deep, packed u32: {:?}",
+             time_trees(AUnionFind32::new(LEN),
+                        |uf, a, b| uf.union(a as u32, b as u32),
+                        |uf, element| uf.find(element as u32) as usize));
+
+    println!("compact, hot:     {:?}", time_hot::<Compact>());
+    println!("padded, hot:      {:?}", time_hot::<CachePadded>());
+}
@@ -0,0 +1,228 @@
+// Compares storage layouts for a `u32` union-find: separate vectors of
+// parents and ranks, as `UnionFind` keeps them, against two layouts that
+// interleave each element’s parent and rank in one vector, and against
+// `CompactUnionFind`, which packs both into one `u32`. The first three
+// share one implementation of union and find, so that only the layout
+// differs; `UnionFind<u32>` itself is timed too, for reference.
+//
+// Run with `cargo bench --bench layout`.
+//
+// Interleaving puts a root’s rank in the same cache line as its parent,
+// which saves a miss per root in `union`. But with `u32` elements,
+// alignment pads each entry from 5 bytes to 8, so on uniformly random
+// workloads, where `find` is dominated by cache misses on parents, the
+// interleaved layout comes out slower. Packing the entries to 5 bytes
+// removes the padding, but then some entries straddle two cache lines.
+// Neither measured faster than separate vectors, which is why `UnionFind`
+// keeps them.
+//
+// Every run checks its results: given the same unions, every layout must
+// link the same number of times.
+
+extern crate disjoint_sets;
+
+use disjoint_sets::{CompactUnionFind, UnionFind};
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+// The number of elements in each union-find.
+const LEN: usize = 1 << 22;
+
+// The number of random unions, followed by the same number of finds.
+const OPS: usize = 1 << 23;
+
+// The storage of a union-find, with the same union by rank and path
+// splitting as `UnionFind` written once over it.
+trait Layout {
+    fn parent(&self, element: u32) -> u32;
+    fn set_parent(&self, element: u32, parent: u32);
+    fn rank(&self, element: u32) -> u8;
+    fn set_rank(&self, element: u32, rank: u8);
+
+    fn find(&self, mut element: u32) -> u32 {
+        let mut parent = self.parent(element);
+
+        while element != parent {
+            let grandparent = self.parent(parent);
+            self.set_parent(element, grandparent);
+            element = parent;
+            parent = grandparent;
+        }
+
+        element
+    }
+
+    fn union(&mut self, a: u32, b: u32) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b { return false; }
+
+        let rank_a = self.rank(a);
+        let rank_b = self.rank(b);
+
+        if rank_a > rank_b {
+            self.set_parent(b, a);
+        } else {
+            self.set_parent(a, b);
+            if rank_a == rank_b {
+                self.set_rank(b, rank_b.saturating_add(1));
+            }
+        }
+
+        true
+    }
+}
+
+// Separate vectors, as in `UnionFind`.
+struct SplitUnionFind {
+    parents: Vec<Cell<u32>>,
+    ranks:   Vec<Cell<u8>>,
+}
+
+impl SplitUnionFind {
+    fn new(size: usize) -> Self {
+        SplitUnionFind {
+            parents: (0 .. size as u32).map(Cell::new).collect(),
+            ranks:   (0 .. size).map(|_| Cell::new(0)).collect(),
+        }
+    }
+}
+
+impl Layout for SplitUnionFind {
+    fn parent(&self, element: u32) -> u32 {
+        self.parents[element as usize].get()
+    }
+
+    fn set_parent(&self, element: u32, parent: u32) {
+        self.parents[element as usize].set(parent);
+    }
+
+    fn rank(&self, element: u32) -> u8 {
+        self.ranks[element as usize].get()
+    }
+
+    fn set_rank(&self, element: u32, rank: u8) {
+        self.ranks[element as usize].set(rank);
+    }
+}
+
+// Entries aligned as usual, so padded to 8 bytes.
+#[derive(Clone, Copy)]
+struct Entry {
+    parent: u32,
+    rank:   u8,
+}
+
+// Entries packed into 5 bytes.
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+struct PackedEntry {
+    parent: u32,
+    rank:   u8,
+}
+
+macro_rules! interleaved {
+    ($name:ident, $entry:ident) => {
+        struct $name(Vec<Cell<$entry>>);
+
+        impl $name {
+            fn new(size: usize) -> Self {
+                $name((0 .. size as u32)
+                          .map(|parent| Cell::new($entry { parent, rank: 0 }))
+                          .collect())
+            }
+        }
+
+        impl Layout for $name {
+            fn parent(&self, element: u32) -> u32 {
+                self.0[element as usize].get().parent
+            }
+
+            fn set_parent(&self, element: u32, parent: u32) {
+                let cell = &self.0[element as usize];
+                cell.set($entry { parent, ..cell.get() });
+            }
+
+            fn rank(&self, element: u32) -> u8 {
+                self.0[element as usize].get().rank
+            }
+
+            fn set_rank(&self, element: u32, rank: u8) {
+                let cell = &self.0[element as usize];
+                cell.set($entry { rank, ..cell.get() });
+            }
+        }
+    };
+}
+
+interleaved!(AlignedUnionFind, Entry);
+interleaved!(PackedUnionFind, PackedEntry);
+
+// A small, deterministic pseudo-random number generator (xorshift).
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        Rng(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % LEN as u64) as u32
+    }
+}
+
+// Times `OPS` random unions followed by `OPS` random finds, returning
+// the time and the number of unions that linked.
+fn time<U, F>(mut union: U, find: F) -> (Duration, usize)
+    where U: FnMut(u32, u32) -> bool,
+          F: Fn(u32) -> u32 {
+
+    let mut rng = Rng::new();
+    let start = Instant::now();
+
+    let mut links = 0;
+    for _ in 0 .. OPS {
+        if union(rng.next(), rng.next()) { links += 1; }
+    }
+    for _ in 0 .. OPS {
+        find(rng.next());
+    }
+
+    (start.elapsed(), links)
+}
+
+fn main() {
+    let union_find = RefCell::new(UnionFind::<u32>::new(LEN));
+    let (actual, actual_links) = time(|a, b| union_find.borrow_mut().union(a, b),
+                                      |a| union_find.borrow().find(a));
+
+    let split_uf = RefCell::new(SplitUnionFind::new(LEN));
+    let (split, split_links) = time(|a, b| split_uf.borrow_mut().union(a, b),
+                                    |a| split_uf.borrow().find(a));
+
+    let aligned_uf = RefCell::new(AlignedUnionFind::new(LEN));
+    let (aligned, aligned_links) = time(|a, b| aligned_uf.borrow_mut().union(a, b),
+                                        |a| aligned_uf.borrow().find(a));
+
+    let packed_uf = RefCell::new(PackedUnionFind::new(LEN));
+    let (packed, packed_links) = time(|a, b| packed_uf.borrow_mut().union(a, b),
+                                      |a| packed_uf.borrow().find(a));
+
+    let compact_uf = RefCell::new(CompactUnionFind::new(LEN));
+    let (compact, compact_links) = time(|a, b| compact_uf.borrow_mut().union(a, b),
+                                        |a| compact_uf.borrow().find(a));
+
+    assert_eq!(actual_links, split_links);
+    assert_eq!(aligned_links, split_links);
+    assert_eq!(packed_links, split_links);
+    assert_eq!(compact_links, split_links);
+
+    println!("UnionFind<u32>:            {:?}", actual);
+    println!("separate vectors:          {:?}", split);
+    println!("interleaved, 8-byte entry: {:?}", aligned);
+    println!("interleaved, 5-byte entry: {:?}", packed);
+    println!("packed words:              {:?}", compact);
+}
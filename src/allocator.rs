@@ -0,0 +1,101 @@
+//! The allocator interface behind `UnionFind`’s allocator parameter.
+//!
+//! With Cargo feature `"allocator-api2"`, this is that crate’s stable
+//! mirror of the standard library’s allocator API. Compiled with
+//! `--cfg disjoint_sets_nightly` on a nightly compiler, it is the
+//! standard library’s unstable API itself. Otherwise, the only allocator
+//! is the global one, and `AllocVec` is an ordinary `Vec` that carries
+//! it along.
+
+#[cfg(disjoint_sets_nightly)]
+pub use alloc::alloc::{Allocator, Global};
+#[cfg(disjoint_sets_nightly)]
+pub(crate) use alloc::vec::Vec as AllocVec;
+
+#[cfg(all(not(disjoint_sets_nightly), feature = "allocator-api2"))]
+pub use allocator_api2::alloc::{Allocator, Global};
+#[cfg(all(not(disjoint_sets_nightly), feature = "allocator-api2"))]
+pub(crate) use allocator_api2::vec::Vec as AllocVec;
+
+#[cfg(not(any(disjoint_sets_nightly, feature = "allocator-api2")))]
+pub use self::global_only::{Allocator, Global, AllocVec};
+
+#[cfg(not(any(disjoint_sets_nightly, feature = "allocator-api2")))]
+mod global_only {
+    use alloc::collections::TryReserveError;
+    use alloc::vec::{self, Vec};
+    use core::iter::FromIterator;
+    use core::ops::{Deref, DerefMut};
+
+    /// An allocator. Without Cargo feature `"allocator-api2"`, the only
+    /// one is [`Global`](struct.Global.html).
+    pub trait Allocator { }
+
+    /// The global memory allocator.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct Global;
+
+    impl Allocator for Global { }
+
+    // A `Vec` paired with its allocator, which is always `Global`.
+    #[derive(Clone)]
+    pub struct AllocVec<T, A: Allocator = Global> {
+        vec:   Vec<T>,
+        alloc: A,
+    }
+
+    impl<T, A: Allocator> AllocVec<T, A> {
+        pub fn new_in(alloc: A) -> Self {
+            AllocVec { vec: Vec::new(), alloc }
+        }
+
+        pub fn allocator(&self) -> &A {
+            &self.alloc
+        }
+
+        pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+            self.vec.try_reserve(additional)
+        }
+    }
+
+    impl<T, A: Allocator + Default> Default for AllocVec<T, A> {
+        fn default() -> Self {
+            AllocVec::new_in(A::default())
+        }
+    }
+
+    impl<T, A: Allocator> Deref for AllocVec<T, A> {
+        type Target = Vec<T>;
+
+        fn deref(&self) -> &Vec<T> {
+            &self.vec
+        }
+    }
+
+    impl<T, A: Allocator> DerefMut for AllocVec<T, A> {
+        fn deref_mut(&mut self) -> &mut Vec<T> {
+            &mut self.vec
+        }
+    }
+
+    impl<T, A: Allocator> Extend<T> for AllocVec<T, A> {
+        fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            self.vec.extend(iter)
+        }
+    }
+
+    impl<T> FromIterator<T> for AllocVec<T> {
+        fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+            AllocVec { vec: iter.into_iter().collect(), alloc: Global }
+        }
+    }
+
+    impl<T, A: Allocator> IntoIterator for AllocVec<T, A> {
+        type Item = T;
+        type IntoIter = vec::IntoIter<T>;
+
+        fn into_iter(self) -> vec::IntoIter<T> {
+            self.vec.into_iter()
+        }
+    }
+}
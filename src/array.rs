@@ -1,23 +1,48 @@
 use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::ops::Range;
 
-use super::ElementType;
+use allocator::{Allocator, AllocVec, Global};
+
+use super::{ElementType, Error, MemoryUsage, Partition};
+use traits::Sealed;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use validate::validate_forest;
 
 /// Vector-based union-find representing a set of disjoint sets.
 ///
 /// If configured with Cargo feature `"serde"`, impls for `Serialize`
-/// and `Deserialize` will be defined.
+/// and `Deserialize` will be defined. Deserialization checks that the
+/// input describes a valid union-find, failing otherwise.
+///
+/// The elements are stored in memory obtained from the allocator `A`,
+/// which defaults to the global allocator. With Cargo feature
+/// `"allocator-api2"`, any implementation of the
+/// [`allocator-api2`](https://docs.rs/allocator-api2) `Allocator` trait,
+/// such as an arena or bump allocator, may be supplied using
+/// [`new_in`](#method.new_in). Built with `--cfg disjoint_sets_nightly`,
+/// that trait is the standard library’s unstable `std::alloc::Allocator`.
 #[derive(Clone)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct UnionFind<Element: ElementType = usize> {
-    elements: Vec<Cell<Element>>,
-    ranks: Vec<u8>,
+pub struct UnionFind<Element: ElementType = usize, A: Allocator = Global> {
+    elements: AllocVec<Cell<Element>, A>,
+    ranks:    AllocVec<u8, A>,
 }
-// Invariant: self.elements.len() == self.ranks.len()
+// Invariant: `elements` and `ranks` have the same length, and every
+// parent is in bounds. For element types whose conversions are trusted,
+// the hot paths in `find` and `union` rely on this to skip bounds
+// checks.
 
-impl<Element: Debug + ElementType> Debug for UnionFind<Element> {
+impl<Element: Debug + ElementType, A: Allocator> Debug for UnionFind<Element, A> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "UnionFind({:?})", self.elements)
+        write!(formatter, "UnionFind(")?;
+        formatter.debug_list()
+            .entries(self.elements.iter()).finish()?;
+        write!(formatter, ")")
     }
 }
 
@@ -27,6 +52,73 @@ impl<Element: ElementType> Default for UnionFind<Element> {
     }
 }
 
+/// An iterator over the elements of a [`UnionFind`](struct.UnionFind.html)
+/// paired with their representatives.
+///
+/// This is produced by [`UnionFind::iter`](struct.UnionFind.html#method.iter).
+#[derive(Clone, Debug)]
+pub struct UnionFindIter<'a, Element: ElementType + 'a = usize,
+                         A: Allocator + 'a = Global> {
+    union_find: &'a UnionFind<Element, A>,
+    range:      Range<usize>,
+}
+
+impl<'a, Element: ElementType, A: Allocator> Iterator
+        for UnionFindIter<'a, Element, A> {
+    type Item = (Element, Element);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.range.next().map(|i| self.union_find.element_and_root(i))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a, Element: ElementType, A: Allocator> DoubleEndedIterator
+        for UnionFindIter<'a, Element, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range.next_back().map(|i| self.union_find.element_and_root(i))
+    }
+}
+
+impl<'a, Element: ElementType, A: Allocator> ExactSizeIterator
+        for UnionFindIter<'a, Element, A> { }
+
+impl<'a, Element: ElementType, A: Allocator> IntoIterator
+        for &'a UnionFind<Element, A> {
+    type Item = (Element, Element);
+    type IntoIter = UnionFindIter<'a, Element, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the path from an element to its set’s representative.
+///
+/// This is produced by
+/// [`UnionFind::path_to_root`](struct.UnionFind.html#method.path_to_root).
+#[derive(Clone, Debug)]
+pub struct PathToRoot<'a, Element: ElementType + 'a = usize,
+                      A: Allocator + 'a = Global> {
+    union_find: &'a UnionFind<Element, A>,
+    next:       Option<Element>,
+}
+
+impl<'a, Element: ElementType, A: Allocator> Iterator
+        for PathToRoot<'a, Element, A> {
+    type Item = Element;
+
+    fn next(&mut self) -> Option<Element> {
+        let element = self.next?;
+        let parent = self.union_find.parent(element);
+        self.next = if parent == element { None } else { Some(parent) };
+        Some(element)
+    }
+}
+
 impl<Element: ElementType> UnionFind<Element> {
     /// Creates a new union-find of `size` elements.
     ///
@@ -34,13 +126,184 @@ impl<Element: ElementType> UnionFind<Element> {
     ///
     /// If `size` elements would overflow the element type `Element`.
     pub fn new(size: usize) -> Self {
-        UnionFind {
-            elements: (0..size).map(|i| {
-                let e = Element::from_usize(i).expect("UnionFind::new: overflow");
-                Cell::new(e)
-            }).collect(),
-            ranks: vec![0; size],
+        Self::new_in(size, Global)
+    }
+
+    /// Creates a new union-find of `size` elements, if possible.
+    ///
+    /// Like [`new`](#method.new), but returns `Err(Error::Overflow)`
+    /// instead of panicking if `size` elements would overflow the element
+    /// type `Element`, and `Err(Error::AllocationFailed)` if the memory
+    /// cannot be allocated.
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        Self::try_new_in(size, Global)
+    }
+
+    /// Creates a new, empty union-find with space for at least `capacity`
+    /// elements.
+    ///
+    /// Returns `Err(Error::AllocationFailed)` if the memory cannot be
+    /// allocated.
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, Error> {
+        Self::try_with_capacity_in(capacity, Global)
+    }
+
+    /// Creates a union-find of the connected components of a graph given
+    /// as adjacency lists.
+    ///
+    /// The graph has one element for each adjacency list, and the list at
+    /// index `i` holds the neighbors of element `i`. Since the components
+    /// are the same either way, an edge need only appear in one of its
+    /// endpoints’ lists.
+    ///
+    /// # Panics
+    ///
+    /// If the number of lists would overflow the element type `Element`,
+    /// or if a neighbor is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFind;
+    ///
+    /// let graph = vec![vec![1], vec![0, 2], vec![1], vec![], vec![3]];
+    /// let uf = UnionFind::<u8>::from_graph(&graph);
+    ///
+    /// assert!(uf.equiv(0, 2));
+    /// assert!(uf.equiv(3, 4));
+    /// assert!(!uf.equiv(2, 3));
+    /// ```
+    pub fn from_graph(graph: &[Vec<Element>]) -> Self {
+        let mut result = Self::new(graph.len());
+
+        for (i, neighbors) in graph.iter().enumerate() {
+            let element = Element::from_usize(i).unwrap();
+            for &neighbor in neighbors {
+                result.union(element, neighbor);
+            }
         }
+
+        result
+    }
+
+    /// Creates a union-find of the connected components of a graph with
+    /// `size` vertices and the given edges.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`, or
+    /// if an endpoint is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFind;
+    ///
+    /// let uf = UnionFind::<u8>::from_edges(5, vec![(0, 1), (2, 1), (3, 4)]);
+    ///
+    /// assert!(uf.equiv(0, 2));
+    /// assert!(!uf.equiv(2, 3));
+    /// ```
+    pub fn from_edges<I>(size: usize, edges: I) -> Self
+        where I: IntoIterator<Item = (Element, Element)> {
+
+        let mut result = Self::new(size);
+
+        for (a, b) in edges {
+            result.union(a, b);
+        }
+
+        result
+    }
+
+    // Builds a union-find directly from each element’s parent and rank.
+    //
+    // PRECONDITION: The parents form a forest over the elements.
+    pub(crate) fn from_parents_and_ranks<I>(entries: I) -> Self
+        where I: IntoIterator<Item = (Element, u8)> {
+
+        let (elements, ranks) = entries.into_iter()
+            .map(|(parent, rank)| (Cell::new(parent), rank))
+            .unzip();
+
+        UnionFind { elements, ranks }
+    }
+}
+
+impl<Element: ElementType, A: Allocator + Clone> UnionFind<Element, A> {
+    /// Creates a new union-find of `size` elements, stored in memory from
+    /// the given allocator.
+    ///
+    /// The parents and ranks are kept in separate vectors, each holding
+    /// its own clone of `alloc`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new_in(size: usize, alloc: A) -> Self {
+        Self::try_new_in(size, alloc).expect("UnionFind::new: overflow")
+    }
+
+    /// Creates a new union-find of `size` elements, stored in memory from
+    /// the given allocator, if possible.
+    ///
+    /// Like [`new_in`](#method.new_in), but returns `Err(Error::Overflow)`
+    /// instead of panicking if `size` elements would overflow the element
+    /// type `Element`, and `Err(Error::AllocationFailed)` if the memory
+    /// cannot be allocated.
+    pub fn try_new_in(size: usize, alloc: A) -> Result<Self, Error> {
+        // By the `ElementType` laws, if the last element fits then so
+        // do all the others.
+        if size > 0 && Element::from_usize(size - 1).is_none() {
+            return Err(Error::Overflow);
+        }
+
+        let mut result = Self::try_with_capacity_in(size, alloc)?;
+        result.elements.extend((0..size).map(|i| {
+            Cell::new(Element::from_usize(i).unwrap())
+        }));
+        result.ranks.resize(size, 0);
+        Ok(result)
+    }
+
+    /// Creates a new, empty union-find with space for at least `capacity`
+    /// elements, stored in memory from the given allocator.
+    ///
+    /// Returns `Err(Error::AllocationFailed)` if the memory cannot be
+    /// allocated.
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, Error> {
+        let mut result = UnionFind {
+            elements: AllocVec::new_in(alloc.clone()),
+            ranks:    AllocVec::new_in(alloc),
+        };
+        result.try_reserve(capacity)?;
+        Ok(result)
+    }
+}
+
+impl<Element: ElementType, A: Allocator> UnionFind<Element, A> {
+    /// Returns a reference to the allocator backing the union-find.
+    pub fn allocator(&self) -> &A {
+        self.elements.allocator()
+    }
+
+    /// Reserves space for at least `additional` more elements to be
+    /// allocated.
+    ///
+    /// Returns `Err(Error::AllocationFailed)` if the memory cannot be
+    /// allocated, in which case the union-find is unchanged.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        self.elements.try_reserve(additional)
+            .and_then(|()| self.ranks.try_reserve(additional))
+            .map_err(|_| Error::AllocationFailed)
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<Cell<Element>>("elements", self.elements.len(),
+                                    self.elements.capacity())
+            .array::<u8>("ranks", self.ranks.len(), self.ranks.capacity())
     }
 
     /// The number of elements in all the sets.
@@ -63,11 +326,51 @@ impl<Element: ElementType> UnionFind<Element> {
     /// If allocating another element would overflow the element type
     /// `Element`.
     pub fn alloc(&mut self) -> Element {
+        self.try_alloc().expect("UnionFind::alloc: overflow")
+    }
+
+    /// Creates a new element in a singleton set, if possible.
+    ///
+    /// Like [`alloc`](#method.alloc), but returns `Err(Error::Overflow)`
+    /// instead of panicking if another element would overflow the element
+    /// type `Element`.
+    pub fn try_alloc(&mut self) -> Result<Element, Error> {
         let result = Element::from_usize(self.elements.len())
-                       .expect("UnionFind::alloc: overflow");
+                       .ok_or(Error::Overflow)?;
         self.elements.push(Cell::new(result));
         self.ranks.push(0);
-        result
+        Ok(result)
+    }
+
+    /// Moves all the elements of `other` into `self`, keeping their sets
+    /// separate from those of `self`.
+    ///
+    /// Element `e` of `other` becomes element `offset + e` of `self`,
+    /// where `offset` is the returned value, equal to `self`’s length before
+    /// appending.
+    ///
+    /// # Panics
+    ///
+    /// If the combined elements would overflow the element type `Element`.
+    pub fn append<B: Allocator>(&mut self, other: UnionFind<Element, B>) -> Element {
+        let offset_usize = self.len();
+        let offset = Element::from_usize(offset_usize)
+                       .expect("UnionFind::append: overflow");
+        if let Some(last) = other.len().checked_sub(1) {
+            offset_usize.checked_add(last)
+                .and_then(Element::from_usize)
+                .expect("UnionFind::append: overflow");
+        }
+
+        // Every parent of `other` is less than its length, so after the
+        // check above, each shifted parent fits.
+        self.elements.extend(other.elements.into_iter().map(|parent| {
+            let parent = parent.get().to_usize() + offset_usize;
+            Cell::new(Element::from_usize(parent).unwrap())
+        }));
+        self.ranks.extend(other.ranks);
+
+        offset
     }
 
     /// Joins the sets of the two given elements.
@@ -81,47 +384,236 @@ impl<Element: ElementType> UnionFind<Element> {
 
         if a == b { return false; }
 
-        let rank_a = self.rank(a);
-        let rank_b = self.rank(b);
+        // SAFETY: `find` returns an element in bounds.
+        unsafe {
+            let rank_a = self.rank_unchecked(a);
+            let rank_b = self.rank_unchecked(b);
 
-        if rank_a > rank_b {
-            self.set_parent(b, a);
-        } else if rank_b > rank_a {
-            self.set_parent(a, b);
-        } else {
-            self.set_parent(a, b);
-            self.increment_rank(b);
+            if rank_a > rank_b {
+                self.set_parent_unchecked(b, a);
+            } else if rank_b > rank_a {
+                self.set_parent_unchecked(a, b);
+            } else {
+                self.set_parent_unchecked(a, b);
+                self.increment_rank_unchecked(b);
+            }
         }
 
         true
     }
 
+    /// Joins the sets of the two given elements, checking that they are
+    /// in bounds.
+    ///
+    /// Like [`union`](#method.union), but returns an error instead of
+    /// panicking if either element is out of bounds.
+    pub fn try_union(&mut self, a: Element, b: Element) -> Result<bool, Error> {
+        self.check(a)?;
+        self.check(b)?;
+        Ok(self.union(a, b))
+    }
+
+    /// Joins the sets of `other` into `self`.
+    ///
+    /// Afterward, two elements are in the same set of `self` if they were
+    /// in the same set of either `self` or `other` (or are connected by
+    /// a chain of such sets). That is, `self` becomes the finest
+    /// partition that is coarser than both.
+    ///
+    /// Returns whether anything changed.
+    ///
+    /// # Panics
+    ///
+    /// If `self` and `other` have different lengths.
+    pub fn absorb<B: Allocator>(&mut self, other: &UnionFind<Element, B>) -> bool {
+        assert_eq!(self.len(), other.len(), "UnionFind::absorb: length mismatch");
+
+        let mut changed = false;
+
+        for i in 0 .. self.len() {
+            let element = Element::from_usize(i).unwrap();
+            if self.union(element, other.find(element)) {
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Joins the sets of all elements with equal keys, as computed by `key`.
+    ///
+    /// Each distinct key is hashed to the first element that has it, and
+    /// every later element with that key is joined to it. This suits a
+    /// coarse grouping by one attribute, to be refined afterward by
+    /// explicit unions.
+    ///
+    /// Returns whether anything changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFind;
+    ///
+    /// let zip_codes = ["60201", "02139", "60201", "94305", "02139"];
+    ///
+    /// let mut uf = UnionFind::<u8>::new(zip_codes.len());
+    /// uf.union_by_key(|i| zip_codes[i as usize]);
+    ///
+    /// assert!(uf.equiv(0, 2));
+    /// assert!(uf.equiv(1, 4));
+    /// assert!(!uf.equiv(0, 3));
+    /// ```
+    pub fn union_by_key<K, F>(&mut self, mut key: F) -> bool
+        where K: Hash + Eq,
+              F: FnMut(Element) -> K {
+
+        let mut firsts = HashMap::new();
+        let mut changed = false;
+
+        for i in 0 .. self.len() {
+            let element = Element::from_usize(i).unwrap();
+            let first = *firsts.entry(key(element)).or_insert(element);
+            if self.union(first, element) {
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Determines whether `self` refines `other`.
+    ///
+    /// That is, returns whether every set of `self` is contained in some
+    /// set of `other`.
+    ///
+    /// # Panics
+    ///
+    /// If `self` and `other` have different lengths.
+    pub fn is_refinement_of<B: Allocator>(&self, other: &UnionFind<Element, B>) -> bool {
+        assert_eq!(self.len(), other.len(),
+                   "UnionFind::is_refinement_of: length mismatch");
+
+        (0 .. self.len()).all(|i| {
+            let element = Element::from_usize(i).unwrap();
+            other.equiv(element, self.find(element))
+        })
+    }
+
+    /// Determines whether two union-finds represent the same partition.
+    ///
+    /// Unlike a structural comparison, this ignores the internal shape of
+    /// the forests, including which elements are chosen as
+    /// representatives. Union-finds of different lengths are never the
+    /// same partition.
+    pub fn same_partition<B: Allocator>(&self, other: &UnionFind<Element, B>) -> bool {
+        self.len() == other.len()
+            && self.canonical_labels() == other.canonical_labels()
+    }
+
+    /// Returns the canonical form of the partition.
+    ///
+    /// Union-finds that represent the same partition produce equal
+    /// [`Partition`](struct.Partition.html)s with equal hashes, which
+    /// makes the result suitable for memoization.
+    pub fn partition(&self) -> Partition {
+        Partition::from_labels(self.canonical_labels())
+    }
+
     /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
     pub fn find(&self, mut element: Element) -> Element {
-        let mut parent = self.parent(element);
+        assert!(element.to_usize() < self.len(), "UnionFind::find: out of bounds");
 
-        while element != parent {
-            let grandparent = self.parent(parent);
-            self.set_parent(element, grandparent);
-            element = parent;
-            parent = grandparent;
+        // SAFETY: `element` is in bounds, and by the invariant, so is
+        // every parent.
+        unsafe {
+            let mut parent = self.parent_unchecked(element);
+
+            while element != parent {
+                let grandparent = self.parent_unchecked(parent);
+                self.set_parent_unchecked(element, grandparent);
+                element = parent;
+                parent = grandparent;
+            }
         }
 
         element
     }
 
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: Element) -> Result<Element, Error> {
+        self.check(element)?;
+        Ok(self.find(element))
+    }
+
+    /// Finds the representative element for the given element’s set, or
+    /// returns `None` if the element is out of bounds.
+    pub fn get(&self, element: Element) -> Option<Element> {
+        self.try_find(element).ok()
+    }
+
+    /// Returns an iterator over the path from the given element to its
+    /// set’s representative, following parent pointers.
+    ///
+    /// The iterator yields the element itself first and the
+    /// representative last. Unlike [`find`](#method.find), this does not
+    /// compress the path, so it can be used to inspect the shape of the
+    /// internal forest.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn path_to_root(&self, element: Element) -> PathToRoot<'_, Element, A> {
+        self.check(element).expect("UnionFind::path_to_root: out of bounds");
+        PathToRoot {
+            union_find: self,
+            next:       Some(element),
+        }
+    }
+
     /// Determines whether two elements are in the same set.
     pub fn equiv(&self, a: Element, b: Element) -> bool {
         self.find(a) == self.find(b)
     }
 
+    /// Determines whether the given element is alone in its set.
+    ///
+    /// This takes constant time: a root whose rank is still zero can
+    /// never have had another element linked beneath it.
+    pub fn is_singleton(&self, element: Element) -> bool {
+        self.parent(element) == element && self.rank(element) == 0
+    }
+
     /// Forces all laziness, so that each element points directly to its
     /// set’s representative.
+    ///
+    /// This takes time linear in the number of elements: each element’s
+    /// path is compressed fully, in two passes, so no element is visited
+    /// more than twice before it points to its root.
     pub fn force(&self) {
         for i in 0 .. self.len() {
             let element = Element::from_usize(i).unwrap();
-            let root = self.find(element);
-            self.set_parent(element, root);
+
+            let mut root = element;
+            let mut parent = self.parent(root);
+            while root != parent {
+                root = parent;
+                parent = self.parent(root);
+            }
+
+            let mut current = element;
+            while current != root {
+                let next = self.parent(current);
+                self.set_parent(current, root);
+                current = next;
+            }
         }
     }
 
@@ -131,15 +623,111 @@ impl<Element: ElementType> UnionFind<Element> {
         self.elements.iter().map(Cell::get).collect()
     }
 
+    /// Returns an iterator over the elements, each paired with its set’s
+    /// representative.
+    pub fn iter(&self) -> UnionFindIter<'_, Element, A> {
+        UnionFindIter {
+            union_find: self,
+            range:      0 .. self.len(),
+        }
+    }
+
+    /// Finds the largest set, returning its representative and size.
+    ///
+    /// Ties are broken in favor of the smallest representative. Returns
+    /// `None` if the union-find is empty.
+    ///
+    /// Sizes are not maintained between calls, so this finds every
+    /// element and allocates a count for each, taking time and space
+    /// linear in the number of elements. To query sizes repeatedly,
+    /// take a [`partition`](#method.partition) once and ask it instead.
+    pub fn largest_set(&self) -> Option<(Element, usize)> {
+        let mut result: Option<(usize, usize)> = None;
+
+        for (root, size) in self.set_sizes().into_iter().enumerate() {
+            if size > result.map_or(0, |(_, best)| best) {
+                result = Some((root, size));
+            }
+        }
+
+        result.map(|(root, size)| (Element::from_usize(root).unwrap(), size))
+    }
+
+    /// Summarizes the set sizes as a histogram.
+    ///
+    /// The result maps each set size that occurs to the number of sets
+    /// having that size.
+    ///
+    /// Like [`largest_set`](#method.largest_set), this takes time and
+    /// space linear in the number of elements on every call.
+    pub fn set_size_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut result = BTreeMap::new();
+
+        for size in self.set_sizes() {
+            if size > 0 {
+                *result.entry(size).or_insert(0) += 1;
+            }
+        }
+
+        result
+    }
+
     // HELPERS
 
-    fn rank(&self, element: Element) -> u8 {
-        self.ranks[element.to_usize()]
+    // Returns each element’s parent, as a `usize`, and rank.
+    pub(crate) fn parents_and_ranks(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        self.elements.iter().zip(self.ranks.iter())
+            .map(|(parent, &rank)| (parent.get().to_usize(), rank))
+    }
+
+    fn check(&self, element: Element) -> Result<(), Error> {
+        let index = element.to_usize();
+        if index < self.len() {
+            Ok(())
+        } else {
+            Err(Error::OutOfBounds { element: index, len: self.len() })
+        }
+    }
+
+    // Returns a vector mapping each root to the size of its set, and each
+    // non-root to 0.
+    fn set_sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.len()];
+
+        for i in 0 .. self.len() {
+            let element = Element::from_usize(i).unwrap();
+            sizes[self.find(element).to_usize()] += 1;
+        }
+
+        sizes
     }
 
-    fn increment_rank(&mut self, element: Element) {
-        let i = element.to_usize();
-        self.ranks[i] = self.ranks[i].saturating_add(1);
+    // Numbers the sets in order of their first elements, returning each
+    // element’s set number. Two union-finds represent the same partition
+    // exactly when their labels are equal.
+    fn canonical_labels(&self) -> Vec<usize> {
+        const UNLABELED: usize = !0;
+
+        let mut root_labels = vec![UNLABELED; self.len()];
+        let mut next_label = 0;
+
+        (0 .. self.len()).map(|i| {
+            let root = self.find(Element::from_usize(i).unwrap()).to_usize();
+            if root_labels[root] == UNLABELED {
+                root_labels[root] = next_label;
+                next_label += 1;
+            }
+            root_labels[root]
+        }).collect()
+    }
+
+    fn element_and_root(&self, i: usize) -> (Element, Element) {
+        let element = Element::from_usize(i).unwrap();
+        (element, self.find(element))
+    }
+
+    fn rank(&self, element: Element) -> u8 {
+        self.ranks[element.to_usize()]
     }
 
     fn parent(&self, element: Element) -> Element {
@@ -149,6 +737,146 @@ impl<Element: ElementType> UnionFind<Element> {
     fn set_parent(&self, element: Element, parent: Element) {
         self.elements[element.to_usize()].set(parent);
     }
+
+    // The unchecked accessors skip the bounds check only if `Element`’s
+    // conversions are trusted, and otherwise behave like the checked ones.
+
+    // PRECONDITION: `element` is in bounds.
+    unsafe fn rank_unchecked(&self, element: Element) -> u8 {
+        *get_unchecked(&self.ranks, element)
+    }
+
+    // PRECONDITION: `element` is in bounds.
+    unsafe fn increment_rank_unchecked(&mut self, element: Element) {
+        let rank = get_unchecked_mut(&mut self.ranks, element);
+        *rank = rank.saturating_add(1);
+    }
+
+    // PRECONDITION: `element` is in bounds.
+    unsafe fn parent_unchecked(&self, element: Element) -> Element {
+        get_unchecked(&self.elements, element).get()
+    }
+
+    // PRECONDITION: `element` is in bounds.
+    unsafe fn set_parent_unchecked(&self, element: Element, parent: Element) {
+        get_unchecked(&self.elements, element).set(parent);
+    }
+}
+
+// PRECONDITION: `element` is in bounds of `slice`.
+#[inline]
+unsafe fn get_unchecked<Element: ElementType, T>(slice: &[T], element: Element) -> &T {
+    let index = element.to_usize();
+    debug_assert!(index < slice.len());
+    if Element::is_trusted(Sealed) {
+        slice.get_unchecked(index)
+    } else {
+        &slice[index]
+    }
+}
+
+// PRECONDITION: `element` is in bounds of `slice`.
+#[inline]
+unsafe fn get_unchecked_mut<Element: ElementType, T>(slice: &mut [T], element: Element)
+                                                     -> &mut T {
+    let index = element.to_usize();
+    debug_assert!(index < slice.len());
+    if Element::is_trusted(Sealed) {
+        slice.get_unchecked_mut(index)
+    } else {
+        &mut slice[index]
+    }
+}
+
+impl<Element: ElementType, A: Allocator + Clone> UnionFind<Element, A> {
+    /// Computes the common refinement of two partitions.
+    ///
+    /// In the result, two elements are in the same set if they are in
+    /// the same set of both `self` and `other`. That is, it is the
+    /// coarsest partition that refines both. The result is stored in
+    /// memory from `self`’s allocator.
+    ///
+    /// # Panics
+    ///
+    /// If `self` and `other` have different lengths.
+    pub fn meet<B: Allocator>(&self, other: &UnionFind<Element, B>) -> Self {
+        assert_eq!(self.len(), other.len(), "UnionFind::meet: length mismatch");
+
+        let mut result = UnionFind::new_in(self.len(), self.allocator().clone());
+        let mut firsts = HashMap::new();
+
+        for i in 0 .. self.len() {
+            let element = Element::from_usize(i).unwrap();
+            let key = (self.find(element).to_usize(),
+                       other.find(element).to_usize());
+            let first = *firsts.entry(key).or_insert(element);
+            result.union(first, element);
+        }
+
+        result
+    }
+}
+
+// The serialized form stores the parents and ranks as separate sequences.
+
+#[cfg(feature = "serde")]
+impl<Element, A> Serialize for UnionFind<Element, A>
+        where Element: ElementType + Serialize,
+              A: Allocator {
+
+    fn serialize<S: Serializer>(&self, serializer: S)
+                                -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    {
+        use serde::ser::SerializeStruct;
+
+        struct Parents<'a, Element: ElementType + 'a>(&'a [Cell<Element>]);
+
+        impl<'a, Element: ElementType + Serialize> Serialize for Parents<'a, Element> {
+            fn serialize<S: Serializer>(&self, serializer: S)
+                                        -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.iter().map(Cell::get))
+            }
+        }
+
+        let mut state = serializer.serialize_struct("UnionFind", 2)?;
+        state.serialize_field("elements", &Parents(&self.elements))?;
+        state.serialize_field("ranks", &self.ranks[..])?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Element> Deserialize<'de> for UnionFind<Element>
+        where Element: ElementType + Deserialize<'de> {
+
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as DeError;
+
+        #[derive(Deserialize)]
+        #[serde(rename = "UnionFind")]
+        struct Raw<Element> {
+            elements: Vec<Element>,
+            ranks:    Vec<u8>,
+        }
+
+        let raw: Raw<Element> = Raw::deserialize(deserializer)?;
+
+        if raw.elements.len() != raw.ranks.len() {
+            return Err(D::Error::custom(format!(
+                "UnionFind: {} elements but {} ranks",
+                raw.elements.len(), raw.ranks.len())));
+        }
+
+        validate_forest(raw.elements.len(),
+                        |i| raw.elements[i].to_usize(),
+                        |i| raw.ranks[i] as usize)
+            .map_err(|msg| D::Error::custom(format!("UnionFind: {}", msg)))?;
+
+        Ok(UnionFind {
+            elements: raw.elements.into_iter().map(Cell::new).collect(),
+            ranks:    raw.ranks.into_iter().collect(),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -194,6 +922,407 @@ mod tests {
         assert!(uf.equiv(5, 7));
     }
 
+    #[test]
+    fn is_singleton() {
+        let mut uf = UnionFind::<u32>::new(4);
+        assert!(uf.is_singleton(0));
+        assert!(uf.is_singleton(1));
+        uf.union(0, 1);
+        assert!(!uf.is_singleton(0));
+        assert!(!uf.is_singleton(1));
+        assert!(uf.is_singleton(2));
+
+        uf.union(2, 1);
+        assert!(!uf.is_singleton(2));
+        assert!(uf.is_singleton(3));
+    }
+
+    #[test]
+    fn set_sizes() {
+        let mut uf = UnionFind::<u32>::new(7);
+        assert_eq!(uf.largest_set(), Some((0, 1)));
+
+        uf.union(1, 2);
+        uf.union(3, 4);
+        uf.union(4, 5);
+        assert_eq!(uf.largest_set(), Some((uf.find(3), 3)));
+
+        let histogram = uf.set_size_histogram();
+        assert_eq!(histogram.into_iter().collect::<Vec<_>>(),
+                   vec![(1, 2), (2, 1), (3, 1)]);
+
+        assert_eq!(UnionFind::<u32>::new(0).largest_set(), None);
+    }
+
+    #[test]
+    fn absorb() {
+        let mut uf0 = UnionFind::<u32>::new(6);
+        uf0.union(0, 1);
+        uf0.union(3, 4);
+
+        let mut uf1 = UnionFind::<u32>::new(6);
+        uf1.union(1, 2);
+        uf1.union(4, 5);
+
+        assert!(uf0.absorb(&uf1));
+        assert!(uf0.equiv(0, 2));
+        assert!(uf0.equiv(3, 5));
+        assert!(!uf0.equiv(2, 3));
+
+        assert!(!uf0.absorb(&uf1));
+    }
+
+    #[test]
+    fn meet() {
+        let mut uf0 = UnionFind::<u32>::new(6);
+        uf0.union(0, 1);
+        uf0.union(1, 2);
+        uf0.union(3, 4);
+
+        let mut uf1 = UnionFind::<u32>::new(6);
+        uf1.union(1, 2);
+        uf1.union(2, 3);
+        uf1.union(4, 5);
+
+        let uf2 = uf0.meet(&uf1);
+        assert!(uf2.equiv(1, 2));
+        assert!(!uf2.equiv(0, 1));
+        assert!(!uf2.equiv(2, 3));
+        assert!(!uf2.equiv(3, 4));
+        assert!(!uf2.equiv(4, 5));
+    }
+
+    #[test]
+    fn is_refinement_of() {
+        let mut uf0 = UnionFind::<u32>::new(5);
+        let mut uf1 = UnionFind::<u32>::new(5);
+        assert!(uf0.is_refinement_of(&uf1));
+
+        uf0.union(0, 1);
+        assert!(!uf0.is_refinement_of(&uf1));
+        assert!(uf1.is_refinement_of(&uf0));
+
+        uf1.union(0, 2);
+        uf1.union(1, 2);
+        assert!(uf0.is_refinement_of(&uf1));
+        assert!(!uf1.is_refinement_of(&uf0));
+    }
+
+    #[test]
+    fn same_partition() {
+        let mut uf0 = UnionFind::<u32>::new(4);
+        let mut uf1 = UnionFind::<u32>::new(4);
+        assert!(uf0.same_partition(&uf1));
+
+        uf0.union(0, 1);
+        uf0.union(1, 2);
+        assert!(!uf0.same_partition(&uf1));
+
+        uf1.union(2, 1);
+        uf1.union(0, 2);
+        assert!(uf0.same_partition(&uf1));
+
+        assert!(!uf0.same_partition(&UnionFind::new(3)));
+    }
+
+    #[test]
+    fn from_graph() {
+        let graph: Vec<Vec<u32>> = vec![vec![3], vec![], vec![4, 1], vec![], vec![]];
+        let uf = UnionFind::from_graph(&graph);
+        let edges = graph.iter().enumerate()
+            .flat_map(|(i, neighbors)| neighbors.iter().map(move |&j| (i as u32, j)));
+
+        assert!(uf.same_partition(&UnionFind::from_edges(graph.len(), edges)));
+        assert_eq!(uf.partition().labels(), &[0, 1, 1, 0, 1]);
+        assert!(UnionFind::<u8>::from_graph(&[]).is_empty());
+    }
+
+    #[test]
+    fn union_by_key() {
+        let mut uf = UnionFind::<u16>::new(10);
+        assert!( uf.union_by_key(|i| i % 3));
+        assert!(!uf.union_by_key(|i| i % 6));
+        assert!( uf.equiv(1, 7));
+        assert!(!uf.equiv(1, 2));
+
+        uf.union(1, 2);
+        assert!(!uf.union_by_key(|i| i % 3 == 0));
+        assert_eq!(uf.set_size_histogram().into_iter().collect::<Vec<_>>(),
+                   vec![(4, 1), (6, 1)]);
+    }
+
+    #[test]
+    fn partition() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash(uf: &UnionFind<u32>) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            uf.partition().hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut uf0 = UnionFind::<u32>::new(5);
+        uf0.union(0, 3);
+        uf0.union(3, 4);
+        uf0.union(1, 2);
+
+        let mut uf1 = UnionFind::<u32>::new(5);
+        uf1.union(2, 1);
+        uf1.union(4, 0);
+        uf1.union(4, 3);
+
+        assert_eq!(uf0.partition(), uf1.partition());
+        assert_eq!(hash(&uf0), hash(&uf1));
+        assert_eq!(uf0.partition().labels(), &[0, 1, 1, 0, 0]);
+
+        uf1.union(0, 1);
+        assert_ne!(uf0.partition(), uf1.partition());
+    }
+
+    #[test]
+    fn iter() {
+        let mut uf = UnionFind::<u32>::new(4);
+        uf.union(0, 1);
+        uf.union(2, 1);
+
+        let root = uf.find(0);
+        assert_eq!(uf.iter().collect::<Vec<_>>(),
+                   vec![(0, root), (1, root), (2, root), (3, 3)]);
+        assert_eq!(uf.iter().len(), 4);
+        assert_eq!(uf.iter().next_back(), Some((3, 3)));
+
+        let mut count = 0;
+        for (element, rep) in &uf {
+            assert!(uf.equiv(element, rep));
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn try_union_find() {
+        let mut uf = UnionFind::<u32>::new(4);
+        assert_eq!(uf.try_union(0, 1), Ok(true));
+        assert_eq!(uf.try_union(1, 0), Ok(false));
+        assert_eq!(uf.try_find(0), Ok(uf.find(1)));
+
+        let error = Error::OutOfBounds { element: 4, len: 4 };
+        assert_eq!(uf.try_union(0, 4), Err(error));
+        assert_eq!(uf.try_union(4, 0), Err(error));
+        assert_eq!(uf.try_find(4), Err(error));
+    }
+
+    #[test]
+    fn u64_elements() {
+        let mut uf = UnionFind::<u64>::new(4);
+        assert!(uf.union(3, 1));
+        assert!(uf.equiv(1, 3));
+
+        let error = Error::OutOfBounds { element: usize::MAX, len: 4 };
+        assert_eq!(uf.try_find(u64::MAX), Err(error));
+        assert_eq!(uf.try_union(0, u64::MAX), Err(error));
+    }
+
+    #[test]
+    fn nonzero_elements() {
+        use std::mem::size_of;
+        use std::num::{NonZeroU8, NonZeroU32};
+
+        let one = NonZeroU32::new(1).unwrap();
+        let three = NonZeroU32::new(3).unwrap();
+        let mut uf = UnionFind::<NonZeroU32>::new(3);
+        assert!(uf.union(one, three));
+        assert!(uf.equiv(three, one));
+        assert_eq!(uf.partition().labels(), &[0, 1, 0]);
+        assert_eq!(size_of::<Option<NonZeroU32>>(), size_of::<u32>());
+
+        // Element 255 would be represented by 256.
+        assert_eq!(UnionFind::<NonZeroU8>::try_new(255).map(|uf| uf.len()), Ok(255));
+        assert!(UnionFind::<NonZeroU8>::try_new(256).is_err());
+    }
+
+    #[test]
+    fn get() {
+        let mut uf = UnionFind::<u32>::new(3);
+        uf.union(0, 2);
+        assert_eq!(uf.get(0), Some(uf.find(2)));
+        assert_eq!(uf.get(1), Some(1));
+        assert_eq!(uf.get(3), None);
+    }
+
+    #[test]
+    fn try_new() {
+        assert_eq!(UnionFind::<u8>::try_new(256).map(|uf| uf.len()), Ok(256));
+        assert_eq!(UnionFind::<u8>::try_new(257).map(|uf| uf.len()),
+                   Err(Error::Overflow));
+        assert_eq!(UnionFind::<u8>::try_new(0).map(|uf| uf.len()), Ok(0));
+    }
+
+    #[test]
+    fn append() {
+        let mut uf0 = UnionFind::<u32>::new(3);
+        uf0.union(0, 1);
+
+        let mut uf1 = UnionFind::<u32>::new(4);
+        uf1.union(0, 3);
+        uf1.union(1, 2);
+
+        assert_eq!(uf0.append(uf1), 3);
+        assert_eq!(uf0.len(), 7);
+        assert!(uf0.equiv(0, 1));
+        assert!(uf0.equiv(3, 6));
+        assert!(uf0.equiv(4, 5));
+        assert!(!uf0.equiv(2, 3));
+        assert!(!uf0.equiv(1, 4));
+        assert!(!uf0.equiv(5, 6));
+    }
+
+    #[test]
+    #[should_panic]
+    fn append_overflow() {
+        let mut uf = UnionFind::<u8>::new(200);
+        uf.append(UnionFind::new(100));
+    }
+
+    #[test]
+    fn append_to_capacity() {
+        let mut uf = UnionFind::<u8>::new(200);
+        let mut other = UnionFind::<u8>::new(56);
+        other.union(0, 55);
+
+        assert_eq!(uf.append(other), 200);
+        assert_eq!(uf.len(), 256);
+        assert!(uf.equiv(200, 255));
+    }
+
+    #[test]
+    fn force() {
+        let mut uf = UnionFind::<u32>::new(16);
+        for step in 0 .. 4 {
+            let stride = 1 << step;
+            for i in (0 .. 16).step_by(2 * stride as usize) {
+                uf.union(i, i + stride);
+            }
+        }
+
+        assert!(uf.path_to_root(0).count() > 2);
+        uf.force();
+
+        let root = uf.find(0);
+        for i in 0 .. 16 {
+            assert_eq!(uf.parent(i), root);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn find_out_of_bounds() {
+        UnionFind::<u32>::new(3).find(3);
+    }
+
+    // An element type whose `to_usize` changes its answer after the first
+    // call, as no implementation should.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct Fickle(usize);
+
+    static FICKLE_CALLS: ::std::sync::atomic::AtomicUsize =
+        ::std::sync::atomic::AtomicUsize::new(0);
+
+    impl ElementType for Fickle {
+        fn from_usize(n: usize) -> Option<Self> { Some(Fickle(n)) }
+
+        fn to_usize(self) -> usize {
+            use std::sync::atomic::Ordering;
+            if FICKLE_CALLS.fetch_add(1, Ordering::Relaxed) == 0 { self.0 } else { usize::MAX }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn untrusted_element_type_is_checked() {
+        let uf = UnionFind::<Fickle>::new(3);
+        assert!(!Fickle::is_trusted(Sealed));
+        assert!(u32::is_trusted(Sealed));
+        uf.find(Fickle(1));
+    }
+
+    #[test]
+    fn path_to_root() {
+        let mut uf = UnionFind::<u32>::new(4);
+        assert_eq!(uf.path_to_root(2).collect::<Vec<_>>(), vec![2]);
+
+        uf.union(0, 1);
+        uf.union(2, 3);
+        uf.union(1, 3);
+        assert_eq!(uf.path_to_root(0).collect::<Vec<_>>(), vec![0, 1, 3]);
+        assert_eq!(uf.path_to_root(0).collect::<Vec<_>>(), vec![0, 1, 3]);
+
+        uf.find(0);
+        assert_eq!(uf.path_to_root(0).collect::<Vec<_>>(), vec![0, 3]);
+    }
+
+    #[test]
+    fn try_reserve() {
+        let mut uf = UnionFind::<u32>::try_with_capacity(10).unwrap();
+        assert!(uf.is_empty());
+        assert_eq!(uf.try_reserve(100), Ok(()));
+        assert_eq!(uf.try_reserve(!0), Err(Error::AllocationFailed));
+        assert_eq!(UnionFind::<usize>::try_new(!0).map(|uf| uf.len()),
+                   Err(Error::AllocationFailed));
+    }
+
+    #[test]
+    fn try_alloc() {
+        let mut uf = UnionFind::<u8>::new(255);
+        assert_eq!(uf.try_alloc(), Ok(255));
+        assert_eq!(uf.try_alloc(), Err(Error::Overflow));
+        assert_eq!(uf.len(), 256);
+    }
+
+    #[cfg(any(feature = "allocator-api2", disjoint_sets_nightly))]
+    #[test]
+    fn custom_allocator() {
+        #[cfg(not(disjoint_sets_nightly))]
+        use allocator_api2::alloc::AllocError;
+        #[cfg(disjoint_sets_nightly)]
+        use std::alloc::AllocError;
+        use std::alloc::Layout;
+        use std::ptr::NonNull;
+
+        #[derive(Clone, Default)]
+        struct Counting(std::rc::Rc<Cell<usize>>);
+
+        unsafe impl Allocator for Counting {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.0.set(self.0.get() + 1);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let counting = Counting::default();
+        // Each union-find allocates its parents and its ranks.
+        let mut uf = UnionFind::<u32, _>::new_in(8, counting.clone());
+        assert_eq!(counting.0.get(), 2);
+
+        uf.union(0, 1);
+        uf.union(2, 3);
+        let meet = uf.meet(&UnionFind::new(8));
+        assert_eq!(counting.0.get(), 4);
+        assert!(!uf.same_partition(&UnionFind::new(8)));
+        assert_eq!(meet.to_vec(), UnionFind::<u32>::new(8).to_vec());
+
+        let mut clone = uf.clone();
+        assert_eq!(counting.0.get(), 6);
+        clone.union(1, 2);
+        assert!(clone.equiv(0, 3));
+        assert!(!uf.equiv(0, 3));
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn serde_round_trip() {
@@ -212,4 +1341,26 @@ mod tests {
         assert!(!uf1.equiv(1, 2));
         assert!( uf1.equiv(2, 3));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_validation() {
+        extern crate serde_json;
+
+        fn parse(json: &str) -> Result<UnionFind<u8>, serde_json::Error> {
+            serde_json::from_str(json)
+        }
+
+        assert!(parse(r#"{"elements":[1,1,2],"ranks":[0,1,0]}"#).is_ok());
+        assert!(parse(r#"{"elements":[1,1,2],"ranks":[0,1]}"#).is_err());
+        assert!(parse(r#"{"elements":[1,1,3],"ranks":[0,1,0]}"#).is_err());
+        assert!(parse(r#"{"elements":[1,2,0],"ranks":[0,0,0]}"#).is_err());
+        assert!(parse(r#"{"elements":[1,1],"ranks":[0,0]}"#).is_err());
+        assert!(parse(r#"{"elements":[0,1],"ranks":[1,0]}"#).is_ok());
+        assert!(parse(r#"{"elements":[0,1],"ranks":[2,0]}"#).is_err());
+
+        let huge: Result<UnionFind<u64>, _> =
+            serde_json::from_str(r#"{"elements":[0,18446744073709551615],"ranks":[0,0]}"#);
+        assert!(huge.is_err());
+    }
 }
@@ -0,0 +1,76 @@
+use std::hint;
+use std::thread;
+
+/// What a concurrent union does when its compare-and-swap loses a race
+/// and it must try again.
+///
+/// Under light contention retries are rare, and retrying at once is
+/// fastest. But when many threads hammer the same few roots, or there are
+/// more threads than cores, a tight retry loop burns the time that the
+/// thread it lost to needs to finish. Select a strategy with
+/// [`AUnionFind::with_backoff`](struct.AUnionFind.html#method.with_backoff).
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{AUnionFind, Backoff};
+///
+/// let uf = AUnionFind::new(8).with_backoff(Backoff::Yield);
+/// assert!(uf.union(0, 1));
+/// assert_eq!(uf.backoff(), Backoff::Yield);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backoff {
+    /// Hints to the processor that it is spinning, then retries at once.
+    /// This is the default.
+    Spin,
+    /// Yields to the operating system’s scheduler before each retry,
+    /// which suits oversubscribed machines.
+    Yield,
+    /// Spins for twice as long on each consecutive retry, and once that
+    /// grows past a limit, yields instead.
+    Exponential,
+}
+
+// Written out rather than derived, which needs `#[default]`.
+#[allow(clippy::derivable_impls)]
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::Spin
+    }
+}
+
+// The most consecutive retries that `Backoff::Exponential` spins for,
+// doubling each time, before it starts yielding.
+const SPIN_LIMIT: u32 = 6;
+
+// Counts the retries of one operation, backing off according to the
+// strategy.
+pub(crate) struct Retries {
+    backoff: Backoff,
+    count:   u32,
+}
+
+impl Retries {
+    pub(crate) fn new(backoff: Backoff) -> Self {
+        Retries { backoff, count: 0 }
+    }
+
+    // Waits before the next retry.
+    pub(crate) fn back_off(&mut self) {
+        match self.backoff {
+            Backoff::Spin => hint::spin_loop(),
+            Backoff::Yield => thread::yield_now(),
+            Backoff::Exponential => {
+                if self.count < SPIN_LIMIT {
+                    for _ in 0 .. 1 << self.count {
+                        hint::spin_loop();
+                    }
+                    self.count += 1;
+                } else {
+                    thread::yield_now();
+                }
+            }
+        }
+    }
+}
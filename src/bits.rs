@@ -0,0 +1,275 @@
+use std::fmt::{self, Debug};
+
+use super::Error;
+
+/// Union-find over at most 128 elements, representing each set as a bit
+/// mask.
+///
+/// Every element records the mask of its set’s members, so
+/// [`equiv`](#method.equiv) is a single bit test and
+/// [`find`](#method.find), which returns the least element of the set, is
+/// a single trailing-zeros count. [`union`](#method.union) takes time
+/// proportional to the size of the resulting set. Like
+/// [`ArrayUnionFind`](struct.ArrayUnionFind.html), it does not allocate
+/// and is `Copy`.
+///
+/// Its queries, and construction via [`new`](#method.new) and
+/// [`with_union`](#method.with_union), are `const fn`s, so tables of
+/// equivalence classes can be computed at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::BitUnionFind;
+///
+/// let mut uf = BitUnionFind::new(100);
+/// uf.union(10, 20);
+/// uf.union(30, 20);
+///
+/// assert_eq!(uf.find(30), 10);
+/// assert_eq!(uf.members(20), (1 << 10) | (1 << 20) | (1 << 30));
+/// ```
+///
+/// Computing classes at compile time:
+///
+/// ```
+/// use disjoint_sets::BitUnionFind;
+///
+/// const CLASSES: BitUnionFind = BitUnionFind::new(6)
+///     .with_union(0, 2)
+///     .with_union(4, 2)
+///     .with_union(1, 5);
+///
+/// const CLASS_OF_4: usize = CLASSES.find(4);
+///
+/// assert_eq!(CLASS_OF_4, 0);
+/// assert!(CLASSES.equiv(5, 1));
+/// ```
+#[derive(Clone, Copy)]
+pub struct BitUnionFind {
+    len:   usize,
+    masks: [u128; BitUnionFind::MAX_LEN],
+}
+// Invariant: for every element i < self.len, bit i is set in masks[i],
+// and for every element j whose bit is set in masks[i], masks[j] ==
+// masks[i].
+
+impl Debug for BitUnionFind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "BitUnionFind(")?;
+        formatter.debug_list()
+            .entries((0 .. self.len).map(|i| self.find(i)))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl Default for BitUnionFind {
+    fn default() -> Self {
+        BitUnionFind::new(0)
+    }
+}
+
+impl BitUnionFind {
+    /// The maximum number of elements.
+    pub const MAX_LEN: usize = 128;
+
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` exceeds [`MAX_LEN`](#associatedconstant.MAX_LEN).
+    pub const fn new(size: usize) -> Self {
+        assert!(size <= Self::MAX_LEN, "BitUnionFind::new: overflow");
+
+        let mut masks = [0; Self::MAX_LEN];
+        let mut i = 0;
+        while i < Self::MAX_LEN {
+            masks[i] = 1 << i;
+            i += 1;
+        }
+
+        BitUnionFind { len: size, masks }
+    }
+
+    /// Creates a new union-find of `size` elements, if possible.
+    ///
+    /// Returns `Err(Error::Overflow)` if `size` exceeds
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN).
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        if size > Self::MAX_LEN {
+            Err(Error::Overflow)
+        } else {
+            Ok(Self::new(size))
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the union-find devoid of elements?
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If the union-find already has
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN) elements.
+    pub fn alloc(&mut self) -> usize {
+        self.try_alloc().expect("BitUnionFind::alloc: overflow")
+    }
+
+    /// Creates a new element in a singleton set, if possible.
+    ///
+    /// Returns `Err(Error::Overflow)` if the union-find already has
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN) elements.
+    pub fn try_alloc(&mut self) -> Result<usize, Error> {
+        if self.len == Self::MAX_LEN {
+            return Err(Error::Overflow);
+        }
+
+        self.len += 1;
+        Ok(self.len - 1)
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        if self.equiv(a, b) { return false; }
+
+        let union = self.masks[a] | self.masks[b];
+        let mut rest = union;
+        while rest != 0 {
+            self.masks[rest.trailing_zeros() as usize] = union;
+            rest &= rest - 1;
+        }
+
+        true
+    }
+
+    /// Returns the union-find with the sets of the two given elements
+    /// joined.
+    ///
+    /// This is a by-value form of [`union`](#method.union) for use in
+    /// constant expressions.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub const fn with_union(mut self, a: usize, b: usize) -> Self {
+        if !self.equiv(a, b) {
+            let union = self.masks[a] | self.masks[b];
+            let mut rest = union;
+            while rest != 0 {
+                self.masks[rest.trailing_zeros() as usize] = union;
+                rest &= rest - 1;
+            }
+        }
+
+        self
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// which is the set’s least element.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub const fn find(&self, element: usize) -> usize {
+        self.members(element).trailing_zeros() as usize
+    }
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub const fn equiv(&self, a: usize, b: usize) -> bool {
+        self.check(b);
+        self.members(a) & (1 << b) != 0
+    }
+
+    /// Determines whether the given element is alone in its set.
+    pub const fn is_singleton(&self, element: usize) -> bool {
+        self.members(element).count_ones() == 1
+    }
+
+    /// Returns the members of the given element’s set as a bit mask, where
+    /// bit *i* is set if element *i* is a member.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub const fn members(&self, element: usize) -> u128 {
+        self.check(element);
+        self.masks[element]
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<usize> {
+        (0 .. self.len).map(|i| self.find(i)).collect()
+    }
+
+    // HELPERS
+
+    const fn check(&self, element: usize) {
+        assert!(element < self.len, "BitUnionFind: out of bounds");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut uf = BitUnionFind::new(128);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(1, 2));
+        assert!(uf.union(127, 3));
+        assert!(uf.union(3, 2));
+        assert!(! uf.union(0, 3));
+
+        assert!(uf.equiv(0, 127));
+        assert!(!uf.equiv(0, 5));
+        assert!(uf.is_singleton(5));
+        assert_eq!(uf.find(127), 0);
+        assert_eq!(uf.members(2).count_ones(), 5);
+    }
+
+    #[test]
+    fn const_construction() {
+        const UF: BitUnionFind = BitUnionFind::new(5)
+            .with_union(3, 4)
+            .with_union(1, 4)
+            .with_union(1, 3);
+        const ROOT: usize = UF.find(4);
+
+        assert_eq!(ROOT, 1);
+        assert!(UF.equiv(1, 3));
+        assert!(!UF.equiv(0, 1));
+        assert!(UF.is_singleton(2));
+    }
+
+    #[test]
+    fn alloc() {
+        let mut uf = BitUnionFind::new(127);
+        assert_eq!(uf.try_alloc(), Ok(127));
+        assert_eq!(uf.try_alloc(), Err(Error::Overflow));
+        assert!(BitUnionFind::try_new(129).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        BitUnionFind::new(4).equiv(0, 4);
+    }
+}
@@ -0,0 +1,276 @@
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt::{self, Debug};
+
+use super::{ElementType, Error};
+
+/// Union-find stored in slices borrowed from the caller.
+///
+/// This behaves like a [`UnionFind`](struct.UnionFind.html), but never
+/// allocates: each element’s parent and rank live in a pair of slices
+/// supplied to [`new`](#method.new), so the data can be placed in static
+/// buffers, on the stack, or in memory shared across an FFI boundary. When
+/// the `UnionFindRef` is dropped, the parent slice maps each element to
+/// another element of its set, and following parents from any element
+/// reaches its set’s representative, which is its own parent.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::UnionFindRef;
+///
+/// let mut parents = [0u16; 4];
+/// let mut ranks = [0u8; 4];
+///
+/// {
+///     let mut uf = UnionFindRef::new(&mut parents, &mut ranks);
+///     uf.union(0, 1);
+///     uf.union(2, 1);
+///     assert!(uf.equiv(0, 2));
+///     assert!(!uf.equiv(0, 3));
+///     uf.force();
+/// }
+///
+/// assert_eq!(parents[0], parents[2]);
+/// assert_eq!(parents[3], 3);
+/// ```
+pub struct UnionFindRef<'a, Element: ElementType + 'a = usize> {
+    parents: &'a [Cell<Element>],
+    ranks:   &'a mut [u8],
+}
+// Invariant: `parents` and `ranks` have the same length, and every parent
+// is in bounds.
+
+impl<'a, Element: Debug + ElementType> Debug for UnionFindRef<'a, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "UnionFindRef(")?;
+        formatter.debug_list().entries(self.parents).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<'a, Element: ElementType> UnionFindRef<'a, Element> {
+    /// Creates a new union-find over the given slices, which are
+    /// overwritten so that each element is in its own singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If the slices have different lengths, or if their length would
+    /// overflow the element type `Element`.
+    pub fn new(parents: &'a mut [Element], ranks: &'a mut [u8]) -> Self {
+        Self::try_new(parents, ranks).expect("UnionFindRef::new: overflow")
+    }
+
+    /// Creates a new union-find over the given slices, if possible.
+    ///
+    /// Like [`new`](#method.new), but returns `Err(Error::Overflow)`
+    /// instead of panicking if the slices’ length would overflow the
+    /// element type `Element`. The slices are unchanged in that case.
+    ///
+    /// # Panics
+    ///
+    /// If the slices have different lengths.
+    pub fn try_new(parents: &'a mut [Element], ranks: &'a mut [u8])
+                   -> Result<Self, Error> {
+        assert_eq!(parents.len(), ranks.len(),
+                   "UnionFindRef::new: length mismatch");
+
+        // By the `ElementType` laws, if the last element fits then so
+        // do all the others.
+        if !parents.is_empty()
+            && Element::from_usize(parents.len() - 1).is_none() {
+            return Err(Error::Overflow);
+        }
+
+        for (i, parent) in parents.iter_mut().enumerate() {
+            *parent = Element::from_usize(i).unwrap();
+        }
+
+        for rank in ranks.iter_mut() {
+            *rank = 0;
+        }
+
+        Ok(UnionFindRef {
+            parents: Cell::from_mut(parents).as_slice_of_cells(),
+            ranks,
+        })
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b { return false; }
+
+        let rank_a = self.ranks[a.to_usize()];
+        let rank_b = self.ranks[b.to_usize()];
+
+        if rank_a > rank_b {
+            self.set_parent(b, a);
+        } else if rank_b > rank_a {
+            self.set_parent(a, b);
+        } else {
+            self.set_parent(a, b);
+            self.ranks[b.to_usize()] = rank_b.saturating_add(1);
+        }
+
+        true
+    }
+
+    /// Joins the sets of the two given elements, checking that they are
+    /// in bounds.
+    ///
+    /// Like [`union`](#method.union), but returns an error instead of
+    /// panicking if either element is out of bounds.
+    pub fn try_union(&mut self, a: Element, b: Element) -> Result<bool, Error> {
+        self.check(a)?;
+        self.check(b)?;
+        Ok(self.union(a, b))
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, mut element: Element) -> Element {
+        let mut parent = self.parent(element);
+
+        while element != parent {
+            let grandparent = self.parent(parent);
+            self.set_parent(element, grandparent);
+            element = parent;
+            parent = grandparent;
+        }
+
+        element
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: Element) -> Result<Element, Error> {
+        self.check(element)?;
+        Ok(self.find(element))
+    }
+
+    /// Finds the representative element for the given element’s set, or
+    /// returns `None` if the element is out of bounds.
+    pub fn get(&self, element: Element) -> Option<Element> {
+        self.try_find(element).ok()
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Determines whether the given element is alone in its set.
+    pub fn is_singleton(&self, element: Element) -> bool {
+        self.parent(element) == element && self.ranks[element.to_usize()] == 0
+    }
+
+    /// Forces all laziness, so that each element points directly to its
+    /// set’s representative.
+    pub fn force(&self) {
+        for i in 0 .. self.len() {
+            let element = Element::from_usize(i).unwrap();
+            let root = self.find(element);
+            self.set_parent(element, root);
+        }
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<Element> {
+        self.force();
+        self.parents.iter().map(Cell::get).collect()
+    }
+
+    // HELPERS
+
+    fn check(&self, element: Element) -> Result<(), Error> {
+        let index = element.to_usize();
+        if index < self.len() {
+            Ok(())
+        } else {
+            Err(Error::OutOfBounds { element: index, len: self.len() })
+        }
+    }
+
+    fn parent(&self, element: Element) -> Element {
+        self.parents[element.to_usize()].get()
+    }
+
+    fn set_parent(&self, element: Element, parent: Element) {
+        self.parents[element.to_usize()].set(parent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut parents = [7usize; 6];
+        let mut ranks = [3u8; 6];
+        let mut uf = UnionFindRef::new(&mut parents, &mut ranks);
+
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert!(uf.union(1, 3));
+        assert!(!uf.union(0, 2));
+        assert!(uf.equiv(0, 3));
+        assert!(!uf.equiv(0, 4));
+        assert!(uf.is_singleton(5));
+        assert!(!uf.is_singleton(0));
+        assert_eq!(uf.try_union(0, 6),
+                   Err(Error::OutOfBounds { element: 6, len: 6 }));
+        assert_eq!(uf.get(6), None);
+
+        let root = uf.find(0);
+        assert_eq!(uf.to_vec(), vec![root, root, root, root, 4, 5]);
+        assert_eq!(parents, [root, root, root, root, 4, 5]);
+    }
+
+    #[test]
+    fn overflow() {
+        let mut parents = [9u8; 257];
+        let mut ranks = [9u8; 257];
+        assert!(UnionFindRef::try_new(&mut parents, &mut ranks).is_err());
+        assert_eq!(parents[0], 9);
+        assert_eq!(ranks[0], 9);
+
+        let uf = UnionFindRef::try_new(&mut parents[.. 256], &mut ranks[.. 256]);
+        assert_eq!(uf.unwrap().len(), 256);
+    }
+
+    #[test]
+    #[should_panic]
+    fn length_mismatch() {
+        let mut parents = [0usize; 3];
+        let mut ranks = [0u8; 2];
+        UnionFindRef::new(&mut parents, &mut ranks);
+    }
+}
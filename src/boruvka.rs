@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use super::AUnionFind;
+
+/// Finds a minimum spanning forest in parallel, using Borůvka’s
+/// algorithm.
+///
+/// The graph has `len` vertices, numbered from 0, and the given edges,
+/// each a pair of vertices and a weight. Returns the indices in `edges`
+/// of the forest’s edges, in increasing order. Edges of equal weight are
+/// preferred in order of their indices, so the forest is unique. Requires
+/// Cargo feature `"rayon"`.
+///
+/// Each round, every component picks its lightest outgoing edge, in
+/// parallel over the edges, and then all the picked edges are joined at
+/// once through an [`AUnionFind`](struct.AUnionFind.html). Each round at
+/// least halves the number of components that still have outgoing edges,
+/// so there are at most ⌈log₂ `len`⌉ rounds.
+///
+/// # Panics
+///
+/// If any vertex is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::par_minimum_spanning_forest;
+///
+/// //   0 --1-- 1 --5-- 2
+/// //    \      |
+/// //     4     2
+/// //      \    |
+/// //       `-- 3     4
+/// let edges = [(0, 1, 1), (1, 2, 5), (1, 3, 2), (0, 3, 4)];
+///
+/// assert_eq!(par_minimum_spanning_forest(5, &edges), vec![0, 1, 2]);
+/// ```
+pub fn par_minimum_spanning_forest<W>(len: usize, edges: &[(usize, usize, W)]) -> Vec<usize>
+    where W: Ord + Sync {
+
+    const NONE: usize = !0;
+
+    for &(a, b, _) in edges {
+        assert!(a < len && b < len,
+                "par_minimum_spanning_forest: edge ({}, {}) out of bounds for {} vertices",
+                a, b, len);
+    }
+
+    let union_find = AUnionFind::new(len);
+    let lightest: Vec<_> = (0 .. len).map(|_| AtomicUsize::new(NONE)).collect();
+    let key = |edge: usize| (&edges[edge].2, edge);
+
+    let mut live: Vec<usize> = (0 .. edges.len())
+        .filter(|&edge| edges[edge].0 != edges[edge].1)
+        .collect();
+    let mut forest = Vec::new();
+
+    while !live.is_empty() {
+        // Each root records the lightest edge leaving its component.
+        live.par_iter().for_each(|&edge| {
+            let (a, b, _) = edges[edge];
+            for &root in &[union_find.find(a), union_find.find(b)] {
+                let slot = &lightest[root];
+                let mut current = slot.load(Ordering::Relaxed);
+                while current == NONE || key(edge) < key(current) {
+                    match slot.compare_exchange_weak(current, edge,
+                                                     Ordering::Relaxed,
+                                                     Ordering::Relaxed) {
+                        Ok(_)         => break,
+                        Err(previous) => current = previous,
+                    }
+                }
+            }
+        });
+
+        // The lightest edges form a forest, so each links two components
+        // exactly once, even if both its components picked it.
+        let picked: Vec<usize> = lightest.par_iter()
+            .filter_map(|slot| {
+                let edge = slot.swap(NONE, Ordering::Relaxed);
+                if edge != NONE && union_find.union(edges[edge].0, edges[edge].1) {
+                    Some(edge)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        forest.extend(picked);
+
+        live = live.into_par_iter()
+            .filter(|&edge| !union_find.equiv(edges[edge].0, edges[edge].1))
+            .collect();
+    }
+
+    forest.sort_unstable();
+    forest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomized::splitmix64;
+    use UnionFind;
+
+    // Kruskal’s algorithm, for comparison.
+    fn kruskal(len: usize, edges: &[(usize, usize, u64)]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0 .. edges.len()).collect();
+        order.sort_by_key(|&edge| (edges[edge].2, edge));
+
+        let mut union_find = UnionFind::<usize>::new(len);
+        let mut forest: Vec<usize> = order.into_iter()
+            .filter(|&edge| union_find.union(edges[edge].0, edges[edge].1))
+            .collect();
+        forest.sort_unstable();
+        forest
+    }
+
+    #[test]
+    fn matches_kruskal() {
+        const SIZE: usize = 1 << 10;
+
+        let edges: Vec<_> = (0 .. 4 * SIZE as u64)
+            .map(|i| {
+                let random = splitmix64(i);
+                ((random % SIZE as u64) as usize,
+                 (random / SIZE as u64 % SIZE as u64) as usize,
+                 random >> 56)
+            })
+            .collect();
+
+        assert_eq!(par_minimum_spanning_forest(SIZE, &edges), kruskal(SIZE, &edges));
+    }
+
+    #[test]
+    fn ties_and_loops() {
+        let edges = [(0, 0, 0), (0, 1, 3), (1, 2, 3), (2, 0, 3), (3, 4, 1), (4, 3, 1)];
+        assert_eq!(par_minimum_spanning_forest(6, &edges), vec![1, 2, 4]);
+        assert_eq!(par_minimum_spanning_forest(6, &edges[.. 0]), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::UnionFind;
+
+/// An extension trait for grouping the items of an iterator into clusters.
+///
+/// Both methods return the clusters in the order of their first items,
+/// with each cluster’s items in iteration order.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::Cluster;
+///
+/// let words = vec!["apple", "avocado", "banana", "blueberry", "cherry"];
+/// let clusters = words.into_iter().cluster_by_key(|word| word.chars().next());
+///
+/// assert_eq!(clusters, vec![vec!["apple", "avocado"],
+///                           vec!["banana", "blueberry"],
+///                           vec!["cherry"]]);
+/// ```
+pub trait Cluster: Iterator + Sized {
+    /// Groups the items into the smallest clusters such that related
+    /// items share a cluster.
+    ///
+    /// Relatedness need not be transitive: if `a` is related to `b` and
+    /// `b` to `c`, all three end up together. This compares every pair of
+    /// items, so it takes O(*n*²) time; prefer
+    /// [`cluster_by_key`](#method.cluster_by_key) where a key will do.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::Cluster;
+    ///
+    /// let readings = vec![1.0_f64, 9.6, 1.4, 10.0, 1.8, 5.0];
+    /// let clusters = readings.into_iter().cluster_by(|a, b| (a - b).abs() < 0.5);
+    ///
+    /// assert_eq!(clusters, vec![vec![1.0, 1.4, 1.8], vec![9.6, 10.0], vec![5.0]]);
+    /// ```
+    fn cluster_by<F>(self, mut related: F) -> Vec<Vec<Self::Item>>
+        where F: FnMut(&Self::Item, &Self::Item) -> bool {
+
+        let items: Vec<_> = self.collect();
+        let mut union_find = UnionFind::<usize>::new(items.len());
+
+        for i in 0 .. items.len() {
+            for j in i + 1 .. items.len() {
+                if !union_find.equiv(i, j) && related(&items[i], &items[j]) {
+                    union_find.union(i, j);
+                }
+            }
+        }
+
+        group(items, &union_find)
+    }
+
+    /// Groups the items into clusters of items whose keys are equal.
+    fn cluster_by_key<K, F>(self, mut key: F) -> Vec<Vec<Self::Item>>
+        where K: Hash + Eq,
+              F: FnMut(&Self::Item) -> K {
+
+        let items: Vec<_> = self.collect();
+        let mut union_find = UnionFind::<usize>::new(items.len());
+        let mut firsts = HashMap::new();
+
+        for (i, item) in items.iter().enumerate() {
+            let first = *firsts.entry(key(item)).or_insert(i);
+            union_find.union(first, i);
+        }
+
+        group(items, &union_find)
+    }
+}
+
+impl<I: Iterator> Cluster for I {}
+
+// Moves the items into their sets, ordered by first item.
+fn group<T>(items: Vec<T>, union_find: &UnionFind<usize>) -> Vec<Vec<T>> {
+    let mut clusters: Vec<Vec<T>> = Vec::new();
+    let mut positions = HashMap::new();
+
+    for (i, item) in items.into_iter().enumerate() {
+        let position = *positions.entry(union_find.find(i)).or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[position].push(item);
+    }
+
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitive() {
+        let clusters = vec![0, 10, 3, 13, 6, 20, 9]
+            .into_iter()
+            .cluster_by(|a: &i32, b: &i32| (a - b).abs() <= 3);
+        assert_eq!(clusters, vec![vec![0, 10, 3, 13, 6, 9], vec![20]]);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(Vec::<u8>::new().into_iter().cluster_by(|_, _| true).is_empty());
+        assert!(Vec::<u8>::new().into_iter().cluster_by_key(|_| ()).is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use std::cell::Cell;
+use std::fmt::{self, Debug};
+
+use super::{Error, MemoryUsage};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use validate::validate_forest;
+
+/// Vector-based union-find with `u32` elements, storing each element’s
+/// rank and parent in a single word.
+///
+/// This behaves like a [`UnionFind<u32>`](struct.UnionFind.html), but
+/// uses four bytes per element rather than five, as their
+/// [`memory_usage`](#method.memory_usage) reports. The price is a smaller
+/// universe: it can hold at most
+/// [`MAX_LEN`](#associatedconstant.MAX_LEN) elements.
+///
+/// If configured with Cargo feature `"serde"`, impls for `Serialize`
+/// and `Deserialize` will be defined. Deserialization checks that the
+/// input describes a valid union-find, failing otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::CompactUnionFind;
+///
+/// let mut uf = CompactUnionFind::new(4);
+/// uf.union(0, 1);
+/// uf.union(2, 1);
+/// assert!(uf.equiv(0, 2));
+/// assert!(!uf.equiv(0, 3));
+/// ```
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CompactUnionFind(Vec<Cell<u32>>);
+// Each word holds the element’s rank in its high RANK_BITS bits and its
+// parent in the remaining low bits. Since a root’s rank is at most the
+// base-2 log of its set’s size, the rank always fits.
+
+const PARENT_BITS: u32 = 27;
+const PARENT_MASK: u32 = (1 << PARENT_BITS) - 1;
+
+impl Debug for CompactUnionFind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "CompactUnionFind(")?;
+        formatter.debug_list()
+            .entries(self.0.iter().map(|word| word.get() & PARENT_MASK))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl CompactUnionFind {
+    /// The maximum number of elements.
+    pub const MAX_LEN: usize = 1 << PARENT_BITS;
+
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` exceeds [`MAX_LEN`](#associatedconstant.MAX_LEN).
+    pub fn new(size: usize) -> Self {
+        Self::try_new(size).expect("CompactUnionFind::new: overflow")
+    }
+
+    /// Creates a new union-find of `size` elements, if possible.
+    ///
+    /// Returns `Err(Error::Overflow)` if `size` exceeds
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN).
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        if size > Self::MAX_LEN {
+            return Err(Error::Overflow);
+        }
+
+        Ok(CompactUnionFind((0 .. size as u32).map(Cell::new).collect()))
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<Cell<u32>>("words", self.0.len(), self.0.capacity())
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If the union-find already has
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN) elements.
+    pub fn alloc(&mut self) -> u32 {
+        self.try_alloc().expect("CompactUnionFind::alloc: overflow")
+    }
+
+    /// Creates a new element in a singleton set, if possible.
+    ///
+    /// Returns `Err(Error::Overflow)` if the union-find already has
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN) elements.
+    pub fn try_alloc(&mut self) -> Result<u32, Error> {
+        if self.len() >= Self::MAX_LEN {
+            return Err(Error::Overflow);
+        }
+
+        let result = self.len() as u32;
+        self.0.push(Cell::new(result));
+        Ok(result)
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: u32, b: u32) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b { return false; }
+
+        let rank_a = self.rank(a);
+        let rank_b = self.rank(b);
+
+        if rank_a > rank_b {
+            self.set_parent(b, a);
+        } else if rank_b > rank_a {
+            self.set_parent(a, b);
+        } else {
+            self.set_parent(a, b);
+            self.increment_rank(b);
+        }
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    pub fn find(&self, mut element: u32) -> u32 {
+        let mut parent = self.parent(element);
+
+        while element != parent {
+            let grandparent = self.parent(parent);
+            self.set_parent(element, grandparent);
+            element = parent;
+            parent = grandparent;
+        }
+
+        element
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: u32, b: u32) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Determines whether the given element is alone in its set.
+    pub fn is_singleton(&self, element: u32) -> bool {
+        self.parent(element) == element && self.rank(element) == 0
+    }
+
+    /// Forces all laziness, so that each element points directly to its
+    /// set’s representative.
+    ///
+    /// This takes time linear in the number of elements.
+    pub fn force(&self) {
+        for element in 0 .. self.len() as u32 {
+            let mut root = element;
+            let mut parent = self.parent(root);
+            while root != parent {
+                root = parent;
+                parent = self.parent(root);
+            }
+
+            let mut current = element;
+            while current != root {
+                let next = self.parent(current);
+                self.set_parent(current, root);
+                current = next;
+            }
+        }
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<u32> {
+        self.force();
+        self.0.iter().map(|word| word.get() & PARENT_MASK).collect()
+    }
+
+    // HELPERS
+
+    fn rank(&self, element: u32) -> u32 {
+        self.0[element as usize].get() >> PARENT_BITS
+    }
+
+    fn increment_rank(&mut self, element: u32) {
+        let word = &self.0[element as usize];
+        word.set(word.get() + (1 << PARENT_BITS));
+    }
+
+    fn parent(&self, element: u32) -> u32 {
+        self.0[element as usize].get() & PARENT_MASK
+    }
+
+    fn set_parent(&self, element: u32, parent: u32) {
+        let word = &self.0[element as usize];
+        word.set(word.get() & !PARENT_MASK | parent);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for CompactUnionFind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as DeError;
+
+        #[derive(Deserialize)]
+        #[serde(rename = "CompactUnionFind")]
+        struct Raw(Vec<u32>);
+
+        let Raw(words) = Raw::deserialize(deserializer)?;
+
+        if words.len() > Self::MAX_LEN {
+            return Err(D::Error::custom("CompactUnionFind: too many elements"));
+        }
+
+        validate_forest(words.len(),
+                        |i| (words[i] & PARENT_MASK) as usize,
+                        |i| (words[i] >> PARENT_BITS) as usize)
+            .map_err(|msg| D::Error::custom(format!("CompactUnionFind: {}", msg)))?;
+
+        Ok(CompactUnionFind(words.into_iter().map(Cell::new).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut uf = CompactUnionFind::new(8);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(1, 2));
+        assert!(uf.union(4, 3));
+        assert!(uf.union(3, 2));
+        assert!(! uf.union(0, 3));
+
+        assert!(uf.equiv(0, 4));
+        assert!(!uf.equiv(0, 5));
+        assert!(uf.is_singleton(5));
+        assert!(!uf.is_singleton(0));
+
+        uf.union(6, 7);
+        uf.union(0, 7);
+        assert!(uf.equiv(4, 6));
+
+        let root = uf.find(0);
+        assert_eq!(uf.to_vec(), vec![root, root, root, root, root, 5, root, root]);
+    }
+
+    #[test]
+    fn ranks_stay_out_of_parents() {
+        let mut uf = CompactUnionFind::new(64);
+        for step in 0 .. 6 {
+            let stride: u32 = 1 << step;
+            for i in (0 .. 64).step_by(2 * stride as usize) {
+                uf.union(i, i + stride);
+            }
+        }
+
+        let root = uf.find(0);
+        assert_eq!(uf.rank(root), 6);
+        assert!((0 .. 64).all(|i| uf.find(i) == root));
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(CompactUnionFind::try_new(CompactUnionFind::MAX_LEN + 1).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        extern crate serde_json;
+
+        let mut uf0 = CompactUnionFind::new(4);
+        uf0.union(0, 1);
+        uf0.union(2, 3);
+
+        let json = serde_json::to_string(&uf0).unwrap();
+        let uf1: CompactUnionFind = serde_json::from_str(&json).unwrap();
+        assert!( uf1.equiv(0, 1));
+        assert!(!uf1.equiv(1, 2));
+        assert!( uf1.equiv(2, 3));
+
+        assert!(serde_json::from_str::<CompactUnionFind>("[1, 2, 0]").is_err());
+        assert!(serde_json::from_str::<CompactUnionFind>("[1, 1]").is_err());
+
+        // A rank of 31 would overflow on the next union of equal ranks.
+        let rank_31 = format!("[{}, 1]", 31 << PARENT_BITS);
+        assert!(serde_json::from_str::<CompactUnionFind>(&rank_31).is_err());
+    }
+}
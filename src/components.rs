@@ -0,0 +1,267 @@
+use std::fmt::{self, Debug};
+
+use super::{AUnionFind, Backoff, Error, Partition};
+
+/// Computes connected components from edges pushed by many threads.
+///
+/// Each producer thread takes an [`EdgeSink`](struct.EdgeSink.html) from
+/// [`sink`](#method.sink) and pushes edges into it. A sink collects edges
+/// in a buffer of its own, so pushing an edge touches no shared memory.
+/// When the buffer fills, the sink applies its edges to a shared
+/// [`AUnionFind`](struct.AUnionFind.html) one union at a time. Once every
+/// sink has been dropped, which flushes it, [`finish`](#method.finish)
+/// labels the components.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::ComponentBuilder;
+/// use std::thread;
+///
+/// let builder = ComponentBuilder::new(8);
+///
+/// thread::scope(|scope| {
+///     for t in 0 .. 2 {
+///         let builder = &builder;
+///         scope.spawn(move || {
+///             let mut sink = builder.sink();
+///             for i in (t .. 6).step_by(2) {
+///                 sink.push(i, i + 1);
+///             }
+///         });
+///     }
+/// });
+///
+/// let components = builder.finish();
+/// assert_eq!(components.labels(), &[0, 0, 0, 0, 0, 0, 0, 1]);
+/// assert_eq!(components.num_sets(), 2);
+/// ```
+#[derive(Debug)]
+pub struct ComponentBuilder {
+    union_find: AUnionFind,
+    batch_size: usize,
+}
+
+/// A producer’s buffered handle for pushing edges into a
+/// [`ComponentBuilder`](struct.ComponentBuilder.html).
+///
+/// Edges are applied when the buffer fills, on [`flush`](#method.flush),
+/// and when the sink is dropped.
+pub struct EdgeSink<'a> {
+    builder: &'a ComponentBuilder,
+    buffer:  Vec<(usize, usize)>,
+}
+
+/// The number of edges a sink buffers by default.
+const DEFAULT_BATCH_SIZE: usize = 1 << 10;
+
+impl Default for ComponentBuilder {
+    fn default() -> Self {
+        ComponentBuilder::new(0)
+    }
+}
+
+impl ComponentBuilder {
+    /// Creates a builder for the components of a graph with `len`
+    /// vertices, numbered from 0, and no edges yet.
+    pub fn new(len: usize) -> Self {
+        ComponentBuilder::with_batch_size(len, DEFAULT_BATCH_SIZE)
+    }
+
+    /// Creates a builder whose sinks each buffer up to `batch_size`
+    /// edges before applying them.
+    ///
+    /// # Panics
+    ///
+    /// If `batch_size` is 0.
+    pub fn with_batch_size(len: usize, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "ComponentBuilder::with_batch_size: zero batch size");
+        ComponentBuilder {
+            union_find: AUnionFind::new(len),
+            batch_size,
+        }
+    }
+
+    /// Sets what the underlying union-find does when a union loses a
+    /// race and must retry, which matters most when there are more
+    /// producers than cores. The default is
+    /// [`Backoff::Spin`](enum.Backoff.html).
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.union_find = self.union_find.with_backoff(backoff);
+        self
+    }
+
+    /// The number of vertices.
+    pub fn len(&self) -> usize {
+        self.union_find.len()
+    }
+
+    /// Is the graph devoid of vertices?
+    pub fn is_empty(&self) -> bool {
+        self.union_find.is_empty()
+    }
+
+    /// Returns a new buffered handle for pushing edges.
+    ///
+    /// Each producer thread should take its own sink.
+    pub fn sink(&self) -> EdgeSink<'_> {
+        EdgeSink {
+            builder: self,
+            buffer:  Vec::with_capacity(self.batch_size),
+        }
+    }
+
+    /// Adds an edge immediately, bypassing any buffer.
+    ///
+    /// Returns whether the edge joined two components.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn add_edge(&self, a: usize, b: usize) -> bool {
+        self.union_find.union(a, b)
+    }
+
+    /// The number of components, counting only edges applied so far.
+    ///
+    /// Edges still in a sink’s buffer are not yet counted.
+    pub fn count_components(&self) -> usize {
+        self.union_find.count_sets()
+    }
+
+    /// Labels the components of all the edges pushed.
+    ///
+    /// Components are numbered consecutively from 0 in the order of their
+    /// least vertices.
+    pub fn finish(self) -> Partition {
+        self.union_find.freeze()
+    }
+
+    /// Returns the underlying union-find, with all the edges pushed
+    /// applied.
+    pub fn into_inner(self) -> AUnionFind {
+        self.union_find
+    }
+}
+
+impl<'a> EdgeSink<'a> {
+    /// Pushes an edge, applying the buffer if it is full.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn push(&mut self, a: usize, b: usize) {
+        if let Err(error) = self.try_push(a, b) {
+            panic!("EdgeSink::push: {}", error);
+        }
+    }
+
+    /// Pushes an edge, applying the buffer if it is full, checking that
+    /// both vertices are in bounds.
+    ///
+    /// Like [`push`](#method.push), but returns an error instead of
+    /// panicking if either vertex is out of bounds.
+    pub fn try_push(&mut self, a: usize, b: usize) -> Result<(), Error> {
+        let len = self.builder.len();
+        for &element in &[a, b] {
+            if element >= len {
+                return Err(Error::OutOfBounds { element, len });
+            }
+        }
+
+        // A self-loop joins nothing, so it need not take up the buffer.
+        if a != b {
+            self.buffer.push((a, b));
+            if self.buffer.len() >= self.builder.batch_size {
+                self.flush();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the buffered edges to the shared union-find, with one
+    /// union per edge.
+    pub fn flush(&mut self) {
+        let union_find = &self.builder.union_find;
+        for (a, b) in self.buffer.drain(..) {
+            union_find.union(a, b);
+        }
+    }
+}
+
+impl<'a> Extend<(usize, usize)> for EdgeSink<'a> {
+    fn extend<I: IntoIterator<Item=(usize, usize)>>(&mut self, edges: I) {
+        for (a, b) in edges {
+            self.push(a, b);
+        }
+    }
+}
+
+impl<'a> Drop for EdgeSink<'a> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl<'a> Debug for EdgeSink<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("EdgeSink")
+            .field("buffered", &self.buffer.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomized::splitmix64;
+    use std::thread;
+    use UnionFind;
+
+    #[test]
+    fn batches() {
+        let builder = ComponentBuilder::with_batch_size(6, 2);
+        let mut sink = builder.sink();
+
+        sink.push(0, 1);
+        sink.push(3, 3);
+        assert_eq!(builder.count_components(), 6);
+        sink.push(2, 1);
+        assert_eq!(builder.count_components(), 4);
+        sink.push(4, 5);
+        sink.flush();
+        assert_eq!(builder.count_components(), 3);
+        assert_eq!(sink.try_push(6, 0), Err(Error::OutOfBounds { element: 6, len: 6 }));
+        drop(sink);
+
+        assert_eq!(builder.finish().labels(), &[0, 0, 0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn concurrent_producers() {
+        const THREADS: usize = 4;
+        const SIZE: usize = 1 << 12;
+
+        let edges: Vec<_> = (0 .. SIZE / 2)
+            .map(|i| (i, (splitmix64(i as u64) % SIZE as u64) as usize))
+            .collect();
+        let mut expected = UnionFind::<usize>::new(SIZE);
+        for &(a, b) in &edges {
+            expected.union(a, b);
+        }
+
+        let builder = ComponentBuilder::with_batch_size(SIZE, 64)
+            .with_backoff(Backoff::Exponential);
+        thread::scope(|scope| {
+            for t in 0 .. THREADS {
+                let (builder, edges) = (&builder, &edges);
+                scope.spawn(move || {
+                    builder.sink().extend(edges.iter().skip(t).step_by(THREADS).cloned());
+                });
+            }
+        });
+
+        assert_eq!(builder.finish(), expected.partition());
+    }
+}
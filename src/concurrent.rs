@@ -1,8 +1,24 @@
 use std::fmt::{self, Debug};
+use std::mem;
+use std::sync::mpsc::Sender;
+#[cfg(not(all(test, loom)))]
 use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(all(test, loom))]
+use loom::sync::atomic::{AtomicUsize, Ordering};
 
+use allocator::Allocator;
+
+use super::{Backoff, ElementType, Error, ForestStats, MemoryUsage, Partition, UnionFind};
+use backoff::Retries;
+use concurrent_forest::{AtomicForest, AtomicLinking};
+use concurrent_watch::{Merged, Watchers};
+
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 #[cfg(feature = "serde")]
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use validate::validate_concurrent_forest;
 
 /// Lock-free, concurrent union-find representing a set of disjoint sets.
 ///
@@ -10,32 +26,212 @@ use serde::{Serialize, Serializer, Deserialize, Deserializer};
 /// and `Deserialize` will be defined. Note that if the union-find is
 /// modified while being serialized, the view of the structure
 /// preserved by may not correspond to any particular moment in time.
+/// Deserialization checks that the input describes a valid union-find,
+/// failing otherwise.
+///
+/// # Guarantees
+///
+/// Sets only ever grow. Each operation below promises something about
+/// some moment during the call, but these promises are made one call at
+/// a time, not as a single history of all calls:
+///
+///   - If [`union`](#method.union) returns `true`, it joined two
+///     distinct sets with one compare-and-swap, and of several threads
+///     racing to join the same two sets, exactly one returns `true`. If
+///     it returns `false`, the elements were in the same set at some
+///     moment during the call.
+///
+///   - [`find`](#method.find) returns the root of the element’s set as of
+///     some moment during the call.
+///
+///   - If [`equiv`](#method.equiv) returns `true`, the elements are in the
+///     same set from some moment during the call on. If it returns
+///     `false`, they were in different sets at some moment during the
+///     call, but may have been joined since.
+///
+///   - Unions publish what came before them: once a thread observes, by
+///     any of the above, that two elements are in the same set, it also
+///     observes everything that the thread that joined them did before
+///     the union.
+///
+/// Aggregate queries are not atomic snapshots while other threads make
+/// unions. [`count_sets`](#method.count_sets) may lag behind links
+/// already made; [`to_vec`](#method.to_vec), [`sets`](#method.sets), and
+/// [`stats`](#method.stats) read each element at a different moment.
+/// Once every union has returned, all of them are exact.
+///
+/// These properties are model-checked with
+/// [Loom](https://docs.rs/loom) for small interleavings; see the
+/// `loom_tests` module in the source.
+///
+/// # Complexity
+///
+/// Ranks can go stale under contention, so the usual amortized bounds
+/// are not proven to hold. For proven bounds under contention, see
+/// [`RandomizedAUnionFind`](struct.RandomizedAUnionFind.html).
+///
+/// # Layout
 ///
-/// # Warning
+/// By default, entries are packed densely, so neighboring elements share
+/// a cache line. When threads union nearby elements of a small, hot
+/// union-find, each write then invalidates the line for the others, even
+/// though they touch different entries. The [`CachePadded`](enum.CachePadded.html)
+/// layout gives every entry a line of its own, at several times the
+/// memory:
 ///
-/// This should always produce correct answers, but the expected complexity
-/// guarantees may not hold.
-#[derive(Clone)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct AUnionFind(Box<[Entry]>);
+/// ```
+/// use disjoint_sets::{AUnionFind, CachePadded};
+///
+/// let uf = AUnionFind::<CachePadded>::with_layout(8);
+/// uf.union(0, 1);
+/// assert!(uf.equiv(1, 0));
+/// ```
+pub struct AUnionFind<L: EntryLayout = Compact> {
+    entries:  Box<[L::Slot]>,
+    sets:     AtomicUsize,
+    backoff:  Backoff,
+    watchers: Watchers,
+}
+// Invariant: self.sets is the number of roots in self.entries, less any
+// linked by unions that have not yet decremented it. Subscriptions are
+// not copied by `Clone` or kept by conversions.
+
+/// The result of [`AUnionFind::union_roots`](struct.AUnionFind.html#method.union_roots).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UnionOutcome {
+    /// The sets were different, and `absorbed`, the root of one, now
+    /// points to `root`, the root of the other.
+    Linked {
+        /// The root of the joined set.
+        root: usize,
+        /// The former root that was linked beneath `root`.
+        absorbed: usize,
+    },
+    /// The elements were already in the same set.
+    Same {
+        /// The root of the set.
+        root: usize,
+    },
+}
+
+impl UnionOutcome {
+    /// The root of the joined set.
+    pub fn root(self) -> usize {
+        match self {
+            UnionOutcome::Linked { root, .. } | UnionOutcome::Same { root } => root,
+        }
+    }
+
+    /// Did the union link two different sets?
+    pub fn is_linked(self) -> bool {
+        matches!(self, UnionOutcome::Linked { .. })
+    }
+}
+
+/// How [`AUnionFind`](struct.AUnionFind.html) lays out its entries in
+/// memory.
+///
+/// This is implemented by [`Compact`](enum.Compact.html), the default,
+/// and [`CachePadded`](enum.CachePadded.html), and cannot be implemented
+/// outside this crate.
+pub trait EntryLayout {
+    #[doc(hidden)]
+    type Slot: Slot;
+}
 
-struct Entry {
+/// The default [`EntryLayout`](trait.EntryLayout.html), which stores
+/// entries contiguously, two words each.
+#[derive(Clone, Copy, Debug)]
+pub enum Compact { }
+
+/// An [`EntryLayout`](trait.EntryLayout.html) that aligns each entry to
+/// its own cache line, to avoid false sharing.
+///
+/// The line is taken to be 128 bytes on x86-64 and AArch64, whose
+/// prefetchers fetch lines in pairs, and 64 bytes elsewhere.
+#[derive(Clone, Copy, Debug)]
+pub enum CachePadded { }
+
+impl EntryLayout for Compact {
+    type Slot = Entry;
+}
+
+impl EntryLayout for CachePadded {
+    type Slot = PaddedEntry;
+}
+
+// Public, but unnameable outside the crate, which seals `EntryLayout`.
+pub trait Slot: Send + Sync + Sized {
+    fn from_entry(entry: Entry) -> Self;
+    fn entry(&self) -> &Entry;
+    fn into_entry(self) -> Entry;
+}
+
+pub struct Entry {
     id:   AtomicUsize,
     rank: AtomicUsize,
 }
 
+// Set in a rank once it may no longer change.
+const FROZEN: usize = !(!0 >> 1);
+
+#[cfg_attr(any(target_arch = "x86_64", target_arch = "aarch64"), repr(align(128)))]
+#[cfg_attr(not(any(target_arch = "x86_64", target_arch = "aarch64")), repr(align(64)))]
+pub struct PaddedEntry(Entry);
+
+impl Slot for Entry {
+    fn from_entry(entry: Entry) -> Self {
+        entry
+    }
+
+    fn entry(&self) -> &Entry {
+        self
+    }
+
+    fn into_entry(self) -> Entry {
+        self
+    }
+}
+
+impl Slot for PaddedEntry {
+    fn from_entry(entry: Entry) -> Self {
+        PaddedEntry(entry)
+    }
+
+    fn entry(&self) -> &Entry {
+        &self.0
+    }
+
+    fn into_entry(self) -> Entry {
+        self.0
+    }
+}
+
 impl Clone for Entry {
     fn clone(&self) -> Self {
-        Entry::with_rank(self.id.load(Ordering::SeqCst),
-                         self.rank.load(Ordering::SeqCst))
+        Entry::with_rank(self.id.load(Ordering::Acquire),
+                         self.rank.load(Ordering::Relaxed))
     }
 }
 
-impl Debug for AUnionFind {
+impl<L: EntryLayout> Clone for AUnionFind<L> {
+    fn clone(&self) -> Self {
+        AUnionFind {
+            entries:  self.entries.iter()
+                .map(|slot| L::Slot::from_entry(slot.entry().clone()))
+                .collect(),
+            sets:     AtomicUsize::new(self.sets.load(Ordering::Relaxed)),
+            backoff:  self.backoff,
+            watchers: Watchers::default(),
+        }
+    }
+}
+
+impl<L: EntryLayout> Debug for AUnionFind<L> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         write!(formatter, "AUnionFind(")?;
         formatter.debug_list()
-            .entries(self.0.iter().map(|entry| &entry.id)).finish()?;
+            .entries(self.entries.iter().map(|slot| &slot.entry().id)).finish()?;
         write!(formatter, ")")
     }
 }
@@ -46,6 +242,67 @@ impl Default for AUnionFind {
     }
 }
 
+/// Converts a sequential union-find into a concurrent one with the same
+/// sets.
+///
+/// This copies each element’s parent and rank as they are, without
+/// finding any roots, so it takes a single pass.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{AUnionFind, UnionFind};
+///
+/// let mut uf = UnionFind::<u32>::new(4);
+/// uf.union(0, 2);
+///
+/// let auf: AUnionFind = AUnionFind::from(uf);
+/// assert!(auf.equiv(2, 0));
+/// assert_eq!(auf.count_sets(), 3);
+///
+/// let uf = UnionFind::from(auf);
+/// assert!(uf.equiv(0, 2));
+/// assert!(!uf.equiv(1, 3));
+/// ```
+impl<Element, A, L> From<UnionFind<Element, A>> for AUnionFind<L>
+    where Element: ElementType,
+          A: Allocator,
+          L: EntryLayout
+{
+    fn from(union_find: UnionFind<Element, A>) -> Self {
+        let entries: Box<[L::Slot]> = union_find.parents_and_ranks()
+            .map(|(parent, rank)| L::Slot::from_entry(Entry::with_rank(parent, rank as usize)))
+            .collect();
+        let sets = (0 .. entries.len())
+            .filter(|&i| entries[i].entry().id.load(Ordering::Relaxed) == i)
+            .count();
+
+        AUnionFind {
+            entries,
+            sets:     AtomicUsize::new(sets),
+            backoff:  Backoff::default(),
+            watchers: Watchers::default(),
+        }
+    }
+}
+
+/// Converts a concurrent union-find into a sequential one with the same
+/// sets.
+///
+/// Like the reverse conversion, this copies the parents and ranks in a
+/// single pass. Ranks beyond `u8::MAX`, which only contention could
+/// produce, are capped.
+impl<L: EntryLayout> From<AUnionFind<L>> for UnionFind {
+    fn from(union_find: AUnionFind<L>) -> Self {
+        UnionFind::from_parents_and_ranks(
+            union_find.entries.into_vec().into_iter().map(|slot| {
+                let entry = slot.into_entry();
+                let rank = (entry.rank.into_inner() & !FROZEN).min(u8::MAX as usize) as u8;
+                (entry.id.into_inner(), rank)
+            }))
+    }
+}
+
 impl Entry {
     fn new(id: usize) -> Self {
         Self::with_rank(id, 0)
@@ -60,17 +317,83 @@ impl Entry {
 }
 
 impl AUnionFind {
+    /// The maximum number of elements, which is as many entries as fit
+    /// in the largest possible allocation with the default layout.
+    pub const MAX_LEN: usize = isize::MAX as usize / mem::size_of::<Entry>();
+
     /// Creates a new asynchronous union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` exceeds [`MAX_LEN`](#associatedconstant.MAX_LEN).
     pub fn new(size: usize) -> Self {
-        AUnionFind((0..size)
-            .map(Entry::new)
-            .collect::<Vec<_>>()
-            .into_boxed_slice())
+        Self::try_new(size).expect("AUnionFind::new: overflow")
+    }
+
+    /// Creates a new asynchronous union-find of `size` elements, if
+    /// possible.
+    ///
+    /// Like [`new`](#method.new), but returns `Err(Error::Overflow)`
+    /// instead of panicking if `size` exceeds
+    /// [`MAX_LEN`](#associatedconstant.MAX_LEN), and
+    /// `Err(Error::AllocationFailed)` if the memory cannot be allocated.
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        Self::try_with_layout(size)
+    }
+}
+
+impl<L: EntryLayout> AUnionFind<L> {
+    /// Creates a new asynchronous union-find of `size` elements, with
+    /// entries laid out as specified by `L`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` entries would not fit in the largest possible
+    /// allocation.
+    pub fn with_layout(size: usize) -> Self {
+        Self::try_with_layout(size).expect("AUnionFind::with_layout: overflow")
+    }
+
+    /// Creates a new asynchronous union-find of `size` elements, with
+    /// entries laid out as specified by `L`, if possible.
+    ///
+    /// Like [`with_layout`](#method.with_layout), but returns
+    /// `Err(Error::Overflow)` instead of panicking if `size` is too large,
+    /// and `Err(Error::AllocationFailed)` if the memory cannot be
+    /// allocated.
+    pub fn try_with_layout(size: usize) -> Result<Self, Error> {
+        if size > isize::MAX as usize / mem::size_of::<L::Slot>() {
+            return Err(Error::Overflow);
+        }
+
+        let mut entries = Vec::new();
+        entries.try_reserve_exact(size)
+            .map_err(|_| Error::AllocationFailed)?;
+        entries.extend((0..size).map(|i| L::Slot::from_entry(Entry::new(i))));
+
+        Ok(AUnionFind {
+            entries:  entries.into_boxed_slice(),
+            sets:     AtomicUsize::new(size),
+            backoff:  Backoff::default(),
+            watchers: Watchers::default(),
+        })
+    }
+
+    /// Sets what [`union`](#method.union) does when it loses a race and
+    /// must retry. The default is [`Backoff::Spin`](enum.Backoff.html).
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// The strategy that unions follow when they must retry.
+    pub fn backoff(&self) -> Backoff {
+        self.backoff
     }
 
     /// The number of elements in all the sets.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.entries.len()
     }
 
     /// Is the union-find devoid of elements?
@@ -79,7 +402,31 @@ impl AUnionFind {
     /// [`UnionFind`](struct.UnionFind.html) it is not possible to add
     /// elements.
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.entries.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<L::Slot>("entries", self.entries.len(), self.entries.len())
+    }
+
+    /// Reports the shape of the forest, for diagnosing performance.
+    ///
+    /// This only reads the forest, so unlike [`find`](#method.find) it
+    /// compresses no paths. If other threads modify the union-find
+    /// meanwhile, the statistics may not describe any single moment.
+    pub fn stats(&self) -> ForestStats {
+        ForestStats::from_forest(self.len(), |i| self.parent(i), |i| self.rank(i))
+    }
+
+    /// The number of sets.
+    ///
+    /// Each union that links two sets decrements this once, so after
+    /// concurrent unions have returned it is exact. While they are still
+    /// running, it may lag behind the links they have already made.
+    pub fn count_sets(&self) -> usize {
+        self.sets.load(Ordering::Relaxed)
     }
 
     /// Joins the sets of the two given elements.
@@ -87,88 +434,545 @@ impl AUnionFind {
     /// Returns whether anything changed. That is, if the sets were
     /// different, it returns `true`, but if they were already the same
     /// then it returns `false`.
-    pub fn union(&self, mut a: usize, mut b: usize) -> bool {
+    pub fn union(&self, a: usize, b: usize) -> bool {
+        AtomicLinking::union(self, a, b)
+    }
+
+    /// Joins the sets of the two given elements, reporting the roots
+    /// involved.
+    ///
+    /// If the sets were different, returns which root was linked beneath
+    /// which; otherwise returns the root of the common set. Either way,
+    /// the root is the representative of the joined set at the moment of
+    /// the union, which a concurrent union may since have linked
+    /// elsewhere.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::{AUnionFind, UnionOutcome};
+    ///
+    /// let uf = AUnionFind::new(4);
+    /// uf.union(0, 1);
+    ///
+    /// match uf.union_roots(1, 2) {
+    ///     UnionOutcome::Linked { root, absorbed } => {
+    ///         assert_eq!((root, absorbed), (1, 2));
+    ///     }
+    ///     UnionOutcome::Same { .. } => unreachable!(),
+    /// }
+    ///
+    /// assert_eq!(uf.union_roots(0, 2), UnionOutcome::Same { root: 1 });
+    /// ```
+    pub fn union_roots(&self, a: usize, b: usize) -> UnionOutcome {
+        AtomicLinking::union_roots(self, a, b)
+    }
+
+    /// Joins the sets of the two given elements using Rem’s algorithm
+    /// with splicing.
+    ///
+    /// Rather than finding both roots and then linking them, as
+    /// [`union`](#method.union) does, this climbs both paths at once,
+    /// always from the side whose current parent ranks lower. Each step
+    /// up splices the element beneath the other side’s parent, so the two
+    /// trees merge as the paths are climbed, and the climb often stops
+    /// early at a common ancestor. This tends to do less work than
+    /// find-then-link, and to contend less, as the benchmark `cargo bench
+    /// --bench concurrent` measures.
+    ///
+    /// Returns whether this call linked two sets. If it returns `false`,
+    /// the elements were already in the same set, though this call may
+    /// still have shortened their paths.
+    ///
+    /// # Consistency
+    ///
+    /// A splice moves part of one set into the other before the two are
+    /// linked, so while this runs, sets do not only grow. A concurrent
+    /// [`equiv`](#method.equiv) or [`find`](#method.find) may see two
+    /// elements of the same set as separate, and `equiv` is not
+    /// linearizable even in the per-call sense of the [type’s
+    /// guarantees](struct.AUnionFind.html#guarantees). Once every union
+    /// has returned, the sets are exactly those that the unions describe.
+    /// This suits bulk computations such as connected components, where
+    /// queries follow the unions, but not uses that query while others
+    /// union.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let uf = Arc::new(AUnionFind::new(100));
+    ///
+    /// let handles: Vec<_> = (0 .. 4).map(|t| {
+    ///     let uf = uf.clone();
+    ///     thread::spawn(move || {
+    ///         for i in (t .. 99).step_by(4) {
+    ///             uf.union_splicing(i, i + 1);
+    ///         }
+    ///     })
+    /// }).collect();
+    ///
+    /// for handle in handles {
+    ///     handle.join().unwrap();
+    /// }
+    ///
+    /// assert!(uf.equiv(0, 99));
+    /// ```
+    pub fn union_splicing(&self, mut a: usize, mut b: usize) -> bool {
+        let mut retries = Retries::new(self.backoff);
+
         loop {
-            a = self.find(a);
-            b = self.find(b);
+            let mut parent_a = self.parent(a);
+            let mut parent_b = self.parent(b);
+
+            if parent_a == parent_b { return false; }
 
-            if a == b { return false; }
+            let mut rank_a = self.rank(parent_a);
+            let mut rank_b = self.rank(parent_b);
 
-            let rank_a = self.rank(a);
-            let rank_b = self.rank(b);
+            // Climb from the side with the lower parent, by the same
+            // order that `union_roots` links in.
+            if (rank_a, parent_a) > (rank_b, parent_b) {
+                mem::swap(&mut a, &mut b);
+                mem::swap(&mut parent_a, &mut parent_b);
+                mem::swap(&mut rank_a, &mut rank_b);
+            }
 
-            if rank_a > rank_b {
-                if self.change_parent(b, b, a) { return true; }
-            } else if rank_b > rank_a {
-                if self.change_parent(a, a, b) { return true; }
-            } else if self.change_parent(a, a, b) {
-                self.increment_rank(b);
+            if a != parent_a {
+                // Splice `a` beneath `parent_b`, which ranks above its
+                // current parent, and climb. If the splice fails, then
+                // another thread has already moved `a` higher.
+                self.change_parent(a, parent_a, parent_b);
+                a = parent_a;
+            } else if b != parent_b {
+                // `a` is a root, but only a root can absorb it, so climb
+                // the other side.
+                b = parent_b;
+            } else if self.link(a, rank_a, b, rank_b) {
                 return true;
+            } else {
+                retries.back_off();
             }
         }
     }
 
-    /// Finds the representative element for the given element’s set.
-    pub fn find(&self, mut element: usize) -> usize {
-        let mut parent = self.parent(element);
-
-        while element != parent {
-            let grandparent = self.parent(parent);
-            self.change_parent(element, parent, grandparent);
-            element = parent;
-            parent = grandparent;
+    /// Asks to be notified when the given element’s set is next merged
+    /// with another.
+    ///
+    /// The next union that links the set’s root beneath another root, or
+    /// another root beneath it, sends a [`Merged`](struct.Merged.html)
+    /// naming both, after which the subscription ends; subscribe again
+    /// to hear about later merges. Many subscriptions can share one
+    /// channel. The notice is sent by the thread that made the union,
+    /// once the link is in place, so a receiver that hears of it sees
+    /// the sets joined. If the receiver has been dropped, the notice is
+    /// discarded.
+    ///
+    /// Unions check for subscriptions with one atomic load, and the
+    /// subscriptions’ storage is allocated by the first one, so they cost
+    /// little when there are none.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::{AUnionFind, Merged};
+    /// use std::sync::mpsc;
+    ///
+    /// let uf = AUnionFind::new(4);
+    /// let (sender, receiver) = mpsc::channel();
+    /// uf.subscribe(0, sender.clone());
+    /// uf.subscribe(2, sender);
+    ///
+    /// uf.union(0, 1);
+    /// assert_eq!(receiver.try_recv(), Ok(Merged { root: 1, absorbed: 0 }));
+    /// assert!(receiver.try_recv().is_err());
+    ///
+    /// uf.union(3, 2);
+    /// assert_eq!(receiver.try_recv(), Ok(Merged { root: 3, absorbed: 2 }));
+    /// ```
+    pub fn subscribe(&self, element: usize, sender: Sender<Merged>) {
+        let root = self.find(element);
+        self.watchers.add(root, sender);
+
+        // If a union linked `root` meanwhile, it may have missed the
+        // subscription, so notify in its stead. This must be a
+        // read-modify-write, as `Watchers` explains.
+        if let Err(parent) = self.entry(root).id.compare_exchange(root, root,
+                                                               Ordering::AcqRel,
+                                                               Ordering::Acquire) {
+            self.watchers.notify(&[root], Merged { root: parent, absorbed: root });
         }
+    }
+
+    /// Joins the sets of each pair of elements, in parallel.
+    ///
+    /// The edges are spread across Rayon’s thread pool, each thread
+    /// calling [`union`](#method.union) on its share. Returns the number
+    /// of unions that linked two sets, which is the number by which
+    /// [`count_sets`](#method.count_sets) has decreased. Requires Cargo
+    /// feature `"rayon"`.
+    ///
+    /// # Panics
+    ///
+    /// If any element is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind;
+    ///
+    /// let uf = AUnionFind::new(1000);
+    /// let edges: Vec<_> = (0 .. 999).map(|i| (i, i + 1)).collect();
+    ///
+    /// assert_eq!(uf.par_union_all(edges), 999);
+    /// assert_eq!(uf.count_sets(), 1);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_union_all<I>(&self, edges: I) -> usize
+        where I: IntoParallelIterator<Item = (usize, usize)> {
 
-        element
+        edges.into_par_iter()
+            .filter(|&(a, b)| self.union(a, b))
+            .count()
     }
 
-    /// Determines whether two elements are in the same set.
-    pub fn equiv(&self, mut a: usize, mut b: usize) -> bool {
-        loop {
-            a = self.find(a);
-            b = self.find(b);
+    /// Joins the sets of the two given elements, checking that they are
+    /// in bounds.
+    ///
+    /// Like [`union`](#method.union), but returns an error instead of
+    /// panicking if either element is out of bounds.
+    pub fn try_union(&self, a: usize, b: usize) -> Result<bool, Error> {
+        AtomicLinking::try_union(self, a, b)
+    }
 
-            if a == b { return true; }
-            if self.parent(a) == a { return false; }
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// This compacts the path by splitting: it points each element it
+    /// visits at its grandparent, with a compare-and-swap of its own, and
+    /// moves on to the element’s old parent. A failed compare-and-swap is
+    /// never retried, since it means another thread has already moved
+    /// the element closer to the root. Path halving, which moves on to
+    /// the grandparent instead, would make half as many compare-and-swaps
+    /// but leave every other element on the path as it was, for other
+    /// threads to walk again. The benchmark `cargo bench --bench
+    /// concurrent` times finds racing on the deepest trees that ranks
+    /// allow.
+    pub fn find(&self, element: usize) -> usize {
+        AtomicForest::find(self, element)
+    }
+
+    /// Replaces each element of a slice with the representative of its
+    /// set.
+    ///
+    /// This gives the same results as calling [`find`](#method.find) on
+    /// each element, compacting paths the same way, but it walks several
+    /// paths in step, so that the processor can overlap the memory
+    /// accesses of independent walks rather than waiting on each parent
+    /// in turn. This can pay off when resolving many elements of a
+    /// union-find too large for the cache; `cargo bench --bench
+    /// concurrent` measures it.
+    ///
+    /// # Panics
+    ///
+    /// If any element is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind;
+    ///
+    /// let uf = AUnionFind::new(6);
+    /// uf.union(0, 1);
+    /// uf.union(2, 1);
+    /// uf.union(4, 5);
+    ///
+    /// let mut elements = [0, 2, 3, 4, 5];
+    /// uf.find_many(&mut elements);
+    /// assert_eq!(elements, [1, 1, 3, 5, 5]);
+    /// ```
+    pub fn find_many(&self, elements: &mut [usize]) {
+        const LANES: usize = 8;
+
+        for chunk in elements.chunks_mut(LANES) {
+            let mut parents = [0; LANES];
+            for (parent, &element) in parents.iter_mut().zip(chunk.iter()) {
+                *parent = self.parent(element);
+            }
+
+            let mut walking = true;
+            while walking {
+                walking = false;
+                for (element, parent) in chunk.iter_mut().zip(parents.iter_mut()) {
+                    if *element != *parent {
+                        let grandparent = self.parent(*parent);
+                        self.change_parent(*element, *parent, grandparent);
+                        *element = *parent;
+                        *parent = grandparent;
+                        walking = true;
+                    }
+                }
+            }
         }
     }
 
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        AtomicForest::try_find(self, element)
+    }
+
+    /// Finds the representative element for the given element’s set, or
+    /// returns `None` if the element is out of bounds.
+    pub fn get(&self, element: usize) -> Option<usize> {
+        self.try_find(element).ok()
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        AtomicForest::equiv(self, a, b)
+    }
+
     /// Forces all laziness, so that each element points directly to its
     /// set’s representative.
     pub fn force(&self) {
         for i in 0 .. self.len() {
-            loop {
-                let parent = self.parent(i);
-                if i == parent {
-                    break
-                } else {
-                    let root = self.find(parent);
-                    if parent == root || self.change_parent(i, parent, root) {
-                        break;
-                    }
-                }
+            self.force_element(i);
+        }
+    }
+
+    /// Forces all laziness, like [`force`](#method.force), but using
+    /// Rayon’s thread pool to flatten the elements in parallel. Requires
+    /// Cargo feature `"rayon"`.
+    #[cfg(feature = "rayon")]
+    pub fn par_force(&self) {
+        use rayon::iter::IndexedParallelIterator;
+
+        // Small chunks would spend more on scheduling than on flattening.
+        (0 .. self.len()).into_par_iter()
+            .with_min_len(1 << 12)
+            .for_each(|i| self.force_element(i));
+    }
+
+    /// Returns a fully compressed, canonical copy of the union-find.
+    ///
+    /// In the copy, the root of each set is its least element, every
+    /// other element points directly at its root, and ranks are reset,
+    /// to 1 for the roots of sets with more than one element and to 0
+    /// for everything else. So two union-finds that partition their
+    /// elements the same way yield identical copies, however their
+    /// unions happened to interleave, which suits archiving, hashing, or
+    /// comparing serialized forms. By contrast, `clone` copies the
+    /// forest as it happens to be.
+    ///
+    /// Like [`to_vec`](#method.to_vec), this forces all laziness first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind;
+    ///
+    /// let uf = AUnionFind::new(4);
+    /// uf.union(0, 1);
+    /// uf.union(3, 1);
+    ///
+    /// let canonical = uf.clone_compressed();
+    /// assert_eq!(canonical.find(3), 0);
+    /// assert_eq!(canonical.stats().max_path_length(), 1);
+    /// ```
+    pub fn clone_compressed(&self) -> Self {
+        const NONE: usize = !0;
+
+        let representatives = self.to_vec();
+        let mut least = vec![NONE; self.len()];
+        let mut sizes = vec![0usize; self.len()];
+
+        for (element, &representative) in representatives.iter().enumerate() {
+            if least[representative] == NONE {
+                least[representative] = element;
             }
+            sizes[representative] += 1;
+        }
+
+        let mut sets = 0;
+        let entries = representatives.iter().enumerate()
+            .map(|(element, &representative)| {
+                let root = least[representative];
+                let rank = if root == element {
+                    sets += 1;
+                    usize::from(sizes[representative] > 1)
+                } else {
+                    0
+                };
+                L::Slot::from_entry(Entry::with_rank(root, rank))
+            })
+            .collect();
+
+        AUnionFind {
+            entries,
+            sets:     AtomicUsize::new(sets),
+            backoff:  self.backoff,
+            watchers: Watchers::default(),
         }
     }
 
     /// Returns a vector of set representatives.
+    ///
+    /// This forces all laziness first. For a large union-find, calling
+    /// [`par_force`](#method.par_force) beforehand does most of that work
+    /// in parallel.
     pub fn to_vec(&self) -> Vec<usize> {
         self.force();
-        self.0.iter().map(|entry| entry.id.load(Ordering::SeqCst)).collect()
+        self.entries.iter()
+            .map(|slot| slot.entry().id.load(Ordering::Acquire))
+            .collect()
+    }
+
+    /// Returns the elements of each set.
+    ///
+    /// The sets are in order of their first elements, and each set’s
+    /// elements are in increasing order. This finds each element’s root
+    /// once and groups the elements by root, without forcing the whole
+    /// union-find first.
+    ///
+    /// If unions run concurrently, each group still lies within a single
+    /// set as of the end of the call, since sets only ever grow, but a set
+    /// that was joined while the call ran may appear as several groups.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind;
+    ///
+    /// let uf = AUnionFind::new(5);
+    /// uf.union(3, 0);
+    /// uf.union(4, 2);
+    ///
+    /// assert_eq!(uf.sets(), vec![vec![0, 3], vec![1], vec![2, 4]]);
+    /// ```
+    pub fn sets(&self) -> Vec<Vec<usize>> {
+        let roots = (0 .. self.len()).map(|i| self.find(i)).collect();
+        Partition::from_representatives(roots).sets()
+    }
+
+    /// Consumes the union-find, returning its partition into sets.
+    ///
+    /// Each element’s set number and each set’s size are computed once,
+    /// so queries on the result are plain reads, with no atomic
+    /// operations. This suits building the sets concurrently and then
+    /// serving queries from many threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let uf = Arc::new(AUnionFind::new(6));
+    /// let handles: Vec<_> = vec![(0, 3), (4, 5), (3, 1)].into_iter()
+    ///     .map(|(a, b)| {
+    ///         let uf = uf.clone();
+    ///         thread::spawn(move || uf.union(a, b))
+    ///     })
+    ///     .collect();
+    /// for handle in handles {
+    ///     handle.join().unwrap();
+    /// }
+    ///
+    /// let partition = Arc::try_unwrap(uf).unwrap().freeze();
+    /// assert_eq!(partition.labels(), &[0, 0, 1, 0, 2, 2]);
+    /// assert_eq!(partition.set_sizes(), &[3, 1, 2]);
+    /// ```
+    pub fn freeze(self) -> Partition {
+        Partition::from_representatives(self.to_vec())
     }
 
     // HELPERS
 
-    fn rank(&self, element: usize) -> usize {
-        self.0[element].rank.load(Ordering::SeqCst)
+    // Points the element directly at its root.
+    fn force_element(&self, element: usize) {
+        loop {
+            let parent = self.parent(element);
+            if element == parent {
+                break
+            } else {
+                let root = self.find(parent);
+                if parent == root || self.change_parent(element, parent, root) {
+                    break;
+                }
+            }
+        }
     }
 
-    fn increment_rank(&self, element: usize) {
-        self.0[element].rank.fetch_add(1, Ordering::SeqCst);
+    // ORDERINGS
+    //
+    // The orderings provide publication and nothing more. What keeps the
+    // forest sound comes from the atomicity of each word, not from any
+    // order between words:
+    //
+    //   - Every parent is loaded with `Acquire` and changed with
+    //     `AcqRel`. A thread that reads a parent written by a link thus
+    //     sees everything the linking thread did before the link,
+    //     including the links it had itself observed. This is the
+    //     publication promised in the type’s documentation.
+    //
+    //   - Nothing orders writes to different parents as a whole, so two
+    //     threads may observe two independent unions in opposite orders.
+    //     `SeqCst` would rule that out; instead, the documentation
+    //     promises only what each call sees on its own.
+    //
+    //   - Each link is one read-modify-write of a root’s parent, from
+    //     itself to another root, so at most one union links a given root.
+    //     That alone does not prevent cycles, since two racing unions may
+    //     link two different roots beneath each other.
+    //
+    //   - Cycles are prevented by ranks. A root is only linked beneath a
+    //     root of higher rank, or of equal rank and higher index. A rank
+    //     only grows, until it is frozen, at exactly the rank the union
+    //     compared, just before its element is linked, and a frozen rank
+    //     is never thawed. So each element is linked beneath one whose
+    //     rank, once final, is higher. The argument uses only each rank’s
+    //     own modification order, so ranks are `Relaxed`.
+    //
+    //   - The count of sets is `Relaxed` too: each successful link
+    //     decrements it exactly once, and nothing else is inferred from
+    //     it, so it need only be exact once the unions are joined.
+    //
+    //   - `subscribe` checks its root with a read-modify-write that
+    //     leaves the parent unchanged, so that it and a racing link are
+    //     ordered; see `Watchers`.
+    //
+    // Links use a strong `compare_exchange`, so that a failed link means
+    // the element is no longer a root, and its rank may stay frozen.
+    // Other changes of parent only shorten paths, and every caller either
+    // retries them or can skip them, so they use `compare_exchange_weak`.
+
+    fn entry(&self, element: usize) -> &Entry {
+        self.entries[element].entry()
+    }
+}
+
+impl<L: EntryLayout> AtomicForest for AUnionFind<L> {
+    fn len(&self) -> usize {
+        self.entries.len()
     }
 
     fn parent(&self, element: usize) -> usize {
-        self.0[element].id.load(Ordering::SeqCst)
+        self.entry(element).id.load(Ordering::Acquire)
     }
 
     fn change_parent(&self,
@@ -176,10 +980,77 @@ impl AUnionFind {
                      old_parent: usize,
                      new_parent: usize)
                      -> bool {
-        self.0[element].id.compare_and_swap(old_parent,
-                                            new_parent,
-                                            Ordering::SeqCst)
-            == old_parent
+        self.entry(element).id.compare_exchange_weak(old_parent,
+                                                 new_parent,
+                                                 Ordering::AcqRel,
+                                                 Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+impl<L: EntryLayout> AtomicLinking for AUnionFind<L> {
+    type Rank = usize;
+
+    fn rank(&self, element: usize) -> usize {
+        self.entry(element).rank.load(Ordering::Relaxed) & !FROZEN
+    }
+
+    // The absorbed root’s rank is frozen first, so that it cannot grow
+    // past `root`’s while the link is made.
+    fn link(&self, absorbed: usize, rank: usize, root: usize, root_rank: usize) -> bool {
+        let entry = self.entry(absorbed);
+
+        match entry.rank.compare_exchange(rank, rank | FROZEN,
+                                          Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => (),
+            // Another union froze it at the same rank, and is linking it.
+            Err(word) if word == rank | FROZEN => (),
+            Err(_) => return false,
+        }
+
+        // If this fails, another union has linked `absorbed`, which it
+        // froze at this same rank, so the rank stays frozen.
+        if entry.id.compare_exchange(absorbed, root,
+                                     Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return false;
+        }
+
+        // If `root`’s rank has changed or been frozen since, the
+        // increment is moot.
+        if rank == root_rank {
+            let _ = self.entry(root).rank.compare_exchange(root_rank, root_rank + 1,
+                                                           Ordering::Relaxed,
+                                                           Ordering::Relaxed);
+        }
+
+        self.sets.fetch_sub(1, Ordering::Relaxed);
+        self.watchers.linked(root, absorbed);
+        true
+    }
+
+    fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+}
+
+// Serializes only the entries, since the count of sets is determined by
+// them, and the layout does not affect the format.
+#[cfg(feature = "serde")]
+impl<L: EntryLayout> Serialize for AUnionFind<L> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+                                -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    {
+        struct Entries<'a, T: 'a>(&'a [T]);
+
+        impl<'a, T: Slot> Serialize for Entries<'a, T> {
+            fn serialize<S: Serializer>(&self, serializer: S)
+                                        -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+            {
+                serializer.collect_seq(self.0.iter().map(Slot::entry))
+            }
+        }
+
+        serializer.serialize_newtype_struct("AUnionFind", &Entries(&self.entries))
     }
 }
 
@@ -192,7 +1063,7 @@ impl Serialize for Entry {
 
         let mut tuple = serializer.serialize_struct("Entry", 2)?;
         tuple.serialize_field("id", &self.id.load(Ordering::Relaxed))?;
-        tuple.serialize_field("rank", &self.rank.load(Ordering::Relaxed))?;
+        tuple.serialize_field("rank", &(self.rank.load(Ordering::Relaxed) & !FROZEN))?;
         tuple.end()
     }
 }
@@ -251,14 +1122,44 @@ impl<'de> Deserialize<'de> for Entry {
             }
         }
 
-        const FIELDS: &'static [&'static str] = &["id", "rank"];
+        const FIELDS: &[&str] = &["id", "rank"];
         deserializer.deserialize_struct("Entry", FIELDS, EntryVisitor)
     }
 }
 
+#[cfg(feature = "serde")]
+impl<'de, L: EntryLayout> Deserialize<'de> for AUnionFind<L> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as DeError;
+
+        #[derive(Deserialize)]
+        #[serde(rename = "AUnionFind")]
+        struct Raw(Box<[Entry]>);
+
+        let Raw(entries) = Raw::deserialize(deserializer)?;
+
+        validate_concurrent_forest(entries.len(),
+                                   |i| entries[i].id.load(Ordering::Relaxed),
+                                   |i| entries[i].rank.load(Ordering::Relaxed))
+            .map_err(|msg| D::Error::custom(format!("AUnionFind: {}", msg)))?;
+
+        let sets = (0 .. entries.len())
+            .filter(|&i| entries[i].id.load(Ordering::Relaxed) == i)
+            .count();
+
+        Ok(AUnionFind {
+            entries:  entries.into_vec().into_iter().map(L::Slot::from_entry).collect(),
+            sets:     AtomicUsize::new(sets),
+            backoff:  Backoff::default(),
+            watchers: Watchers::default(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use randomized::splitmix64;
 
     #[test]
     fn len() {
@@ -300,6 +1201,55 @@ mod tests {
         assert!(uf.equiv(5, 7));
     }
 
+    #[test]
+    fn unions_splicing() {
+        let uf = AUnionFind::new(8);
+        assert!(uf.union_splicing(0, 1));
+        assert!(uf.union_splicing(2, 3));
+        assert!(uf.union_splicing(1, 3));
+        assert!(!uf.union_splicing(0, 2));
+        assert!(uf.union_splicing(7, 4));
+        assert_eq!(uf.count_sets(), 4);
+        assert_eq!(uf.to_vec(), vec![3, 3, 3, 3, 7, 5, 6, 7]);
+    }
+
+    #[test]
+    fn concurrent_unions_splicing() {
+        use std::sync::Arc;
+        use std::thread;
+
+        const THREADS: usize = 4;
+        const SIZE: usize = 1 << 12;
+
+        let uf = Arc::new(AUnionFind::new(SIZE));
+        let mut expected = UnionFind::<usize>::new(SIZE);
+        let edges: Vec<_> = (0 .. SIZE)
+            .map(|i| (i, (splitmix64(i as u64) % SIZE as u64) as usize))
+            .collect();
+        for &(a, b) in &edges {
+            expected.union(a, b);
+        }
+
+        let edges = Arc::new(edges);
+        let handles: Vec<_> = (0 .. THREADS).map(|t| {
+            let (uf, edges) = (uf.clone(), edges.clone());
+            thread::spawn(move || {
+                for &(a, b) in edges.iter().skip(t).step_by(THREADS) {
+                    uf.union_splicing(a, b);
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(uf.count_sets(), (0 .. SIZE).filter(|&i| uf.find(i) == i).count());
+        for i in 0 .. SIZE {
+            assert_eq!(uf.equiv(i, 0), expected.equiv(i, 0));
+        }
+    }
+
     #[test]
     fn changed() {
         let uf = AUnionFind::new(8);
@@ -309,8 +1259,8 @@ mod tests {
         assert!(!uf.union(0, 2))
     }
 
-    // This assumes that for equal-ranked roots, the first argument
-    // to union is pointed to the second.
+    // This assumes that of equal-ranked roots, the one with the lower
+    // index is linked beneath the other.
     #[test]
     fn to_vec() {
         let uf = AUnionFind::new(6);
@@ -323,6 +1273,304 @@ mod tests {
         assert_eq!(uf.to_vec(), vec![3, 3, 3, 3, 4, 5]);
     }
 
+    #[test]
+    fn find_many() {
+        const SIZE: usize = 100;
+
+        let uf = AUnionFind::new(SIZE);
+        for i in 0 .. SIZE {
+            uf.union(i, (splitmix64(i as u64) % SIZE as u64) as usize);
+        }
+
+        let mut elements: Vec<_> = (0 .. SIZE).rev().collect();
+        uf.find_many(&mut elements);
+        let expected: Vec<_> = (0 .. SIZE).rev().map(|i| uf.find(i)).collect();
+        assert_eq!(elements, expected);
+    }
+
+    #[test]
+    fn backoff() {
+        use std::sync::Arc;
+        use std::thread;
+
+        for &backoff in &[Backoff::Spin, Backoff::Yield, Backoff::Exponential] {
+            let uf = Arc::new(AUnionFind::new(64).with_backoff(backoff));
+            assert_eq!(uf.backoff(), backoff);
+
+            let handles: Vec<_> = (0 .. 4).map(|t| {
+                let uf = uf.clone();
+                thread::spawn(move || {
+                    for i in 1 .. 64 {
+                        uf.union((i + t) % 64, (i + t - 1) % 64);
+                    }
+                })
+            }).collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            assert_eq!(uf.count_sets(), 1);
+        }
+    }
+
+    #[test]
+    fn clone_compressed() {
+        let first = AUnionFind::new(6);
+        first.union(0, 1);
+        first.union(2, 3);
+        first.union(1, 3);
+        first.union(5, 4);
+
+        let second = AUnionFind::new(6);
+        second.union(3, 2);
+        second.union(4, 5);
+        second.union(2, 0);
+        second.union(1, 0);
+
+        let (first, second) = (first.clone_compressed(), second.clone_compressed());
+        assert_eq!(format!("{:?}", first), format!("{:?}", second));
+        assert_eq!(first.to_vec(), vec![0, 0, 0, 0, 4, 4]);
+        assert_eq!(first.stats(), second.stats());
+        assert_eq!(first.stats().rank_counts(), &[4, 2]);
+        assert_eq!(first.count_sets(), 2);
+    }
+
+    #[test]
+    fn subscribe() {
+        use std::sync::mpsc;
+        use std::sync::Arc;
+        use std::thread;
+        use std::time::Duration;
+
+        const SIZE: usize = 64;
+
+        let uf = Arc::new(AUnionFind::new(SIZE));
+        let (sender, receiver) = mpsc::channel();
+        uf.subscribe(5, sender.clone());
+        uf.subscribe(7, sender.clone());
+        uf.subscribe(7, sender);
+
+        let handles: Vec<_> = (0 .. 4).map(|t| {
+            let uf = uf.clone();
+            thread::spawn(move || {
+                for i in (t .. SIZE - 1).step_by(4) {
+                    uf.union(i, i + 1);
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // The unions have all returned, so a missed notice times out.
+        let notices: Vec<Merged> = (0 .. 3).map(|_| {
+            receiver.recv_timeout(Duration::from_secs(10)).expect("missed notice")
+        }).collect();
+        for notice in notices {
+            assert!(uf.equiv(notice.root, notice.absorbed));
+        }
+        assert!(receiver.try_recv().is_err());
+
+        let (sender, receiver) = mpsc::channel();
+        uf.subscribe(0, sender);
+        assert!(!uf.union(0, SIZE - 1));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn count_sets() {
+        let uf = AUnionFind::new(5);
+        assert_eq!(uf.count_sets(), 5);
+        uf.union(0, 1);
+        uf.union(2, 3);
+        uf.union(1, 0);
+        assert_eq!(uf.count_sets(), 3);
+        uf.union(3, 0);
+        assert_eq!(uf.clone().count_sets(), 2);
+    }
+
+    #[test]
+    fn concurrent_count_sets() {
+        use std::sync::Arc;
+        use std::thread;
+
+        const THREADS: usize = 4;
+        const SIZE: usize = 1000;
+
+        let uf = Arc::new(AUnionFind::new(SIZE));
+        let handles: Vec<_> = (0 .. THREADS).map(|_| {
+            let uf = uf.clone();
+            thread::spawn(move || {
+                for i in 0 .. SIZE / 2 - 1 {
+                    uf.union(i, i + 1);
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(uf.count_sets(), SIZE / 2 + 1);
+    }
+
+    #[test]
+    fn try_new() {
+        assert_eq!(AUnionFind::try_new(3).map(|uf| uf.len()), Ok(3));
+        assert_eq!(AUnionFind::try_new(AUnionFind::MAX_LEN + 1).err(),
+                   Some(Error::Overflow));
+        assert_eq!(AUnionFind::try_new(usize::MAX).err(),
+                   Some(Error::Overflow));
+        assert_eq!(AUnionFind::<CachePadded>::try_with_layout(AUnionFind::MAX_LEN).err(),
+                   Some(Error::Overflow));
+    }
+
+    #[test]
+    fn cache_padded() {
+        let compact = AUnionFind::new(6);
+        let padded = AUnionFind::<CachePadded>::with_layout(6);
+        for &(a, b) in &[(0, 1), (2, 3), (1, 3), (5, 4)] {
+            assert_eq!(compact.union(a, b), padded.union(a, b));
+        }
+        assert_eq!(compact.to_vec(), padded.to_vec());
+        assert_eq!(padded.count_sets(), 2);
+
+        let line = mem::align_of::<PaddedEntry>();
+        assert!(line >= 64);
+        assert_eq!(padded.memory_usage().bytes(), 6 * line);
+        assert_eq!(&padded.entries[1] as *const _ as usize % line, 0);
+    }
+
+    #[test]
+    fn conversions() {
+        let mut uf = UnionFind::<u8>::new(6);
+        uf.union(0, 1);
+        uf.union(2, 3);
+        uf.union(1, 3);
+
+        let auf: AUnionFind = AUnionFind::from(uf.clone());
+        assert_eq!(auf.count_sets(), 3);
+        assert_eq!(auf.to_vec(),
+                   uf.to_vec().into_iter().map(usize::from).collect::<Vec<_>>());
+
+        auf.union(4, 5);
+        uf.union(4, 5);
+        assert_eq!(UnionFind::from(auf).partition(), uf.partition());
+
+        let padded = AUnionFind::<CachePadded>::from(uf.clone());
+        assert_eq!(padded.count_sets(), 2);
+        padded.union(5, 0);
+        assert!(UnionFind::from(padded).equiv(4, 0));
+    }
+
+    #[test]
+    fn freeze() {
+        let uf = AUnionFind::new(5);
+        uf.union(4, 1);
+        uf.union(2, 4);
+
+        let partition = uf.freeze();
+        assert_eq!(partition.labels(), &[0, 1, 1, 2, 1]);
+        assert_eq!(partition.set_size(1), 3);
+        assert!(partition.equiv(2, 1));
+    }
+
+    #[test]
+    fn sets() {
+        let uf = AUnionFind::new(6);
+        assert_eq!(uf.sets().len(), 6);
+
+        uf.union(5, 1);
+        uf.union(2, 5);
+        uf.union(0, 4);
+        assert_eq!(uf.sets(), vec![vec![0, 4], vec![1, 2, 5], vec![3]]);
+        assert!(AUnionFind::new(0).sets().is_empty());
+    }
+
+    #[test]
+    fn union_roots() {
+        let uf = AUnionFind::new(4);
+        assert_eq!(uf.union_roots(0, 1), UnionOutcome::Linked { root: 1, absorbed: 0 });
+        assert_eq!(uf.union_roots(2, 1), UnionOutcome::Linked { root: 1, absorbed: 2 });
+        assert_eq!(uf.union_roots(2, 0), UnionOutcome::Same { root: 1 });
+        assert_eq!(uf.union_roots(1, 3).root(), 1);
+        assert!(!uf.union_roots(3, 0).is_linked());
+        assert_eq!(uf.count_sets(), 1);
+    }
+
+    #[test]
+    fn stats() {
+        let uf = AUnionFind::new(8);
+        for i in 1 .. 8 {
+            uf.union(i - 1, i);
+        }
+
+        let stats = uf.stats();
+        assert_eq!(stats.root_count(), 1);
+        assert_eq!(stats.rank_counts().iter().sum::<usize>(), 8);
+
+        uf.force();
+        let stats = uf.stats();
+        assert_eq!(stats.max_path_length(), 1);
+        assert_eq!(stats.flat_fraction(), 1.0);
+    }
+
+    #[test]
+    fn try_union_find() {
+        let uf = AUnionFind::new(4);
+        assert_eq!(uf.try_union(0, 1), Ok(true));
+        assert_eq!(uf.try_find(0), Ok(uf.find(1)));
+
+        let error = Error::OutOfBounds { element: 9, len: 4 };
+        assert_eq!(uf.try_union(9, 0), Err(error));
+        assert_eq!(uf.try_find(9), Err(error));
+    }
+
+    #[test]
+    fn get() {
+        let uf = AUnionFind::new(3);
+        uf.union(0, 2);
+        assert_eq!(uf.get(0), Some(uf.find(2)));
+        assert_eq!(uf.get(1), Some(1));
+        assert_eq!(uf.get(3), None);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_union_all() {
+        use UnionFind;
+
+        const SIZE: usize = 1 << 14;
+
+        let edges: Vec<_> = (0 .. SIZE)
+            .map(|i| (i, (splitmix64(i as u64) % SIZE as u64) as usize))
+            .collect();
+
+        let mut expected = UnionFind::<usize>::new(SIZE);
+        let linked = edges.iter().filter(|&&(a, b)| expected.union(a, b)).count();
+
+        let uf = AUnionFind::new(SIZE);
+        assert_eq!(uf.par_union_all(edges), linked);
+        assert_eq!(uf.count_sets(), SIZE - linked);
+        assert!((0 .. SIZE).all(|i| uf.equiv(i, 0) == expected.equiv(i, 0)));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_force() {
+        const SIZE: usize = 1 << 14;
+
+        let uf = AUnionFind::new(SIZE);
+        for i in 1 .. SIZE {
+            uf.union(i - 1, i);
+            uf.union(i, i / 3);
+        }
+
+        let root = uf.find(0);
+        uf.par_force();
+        assert!((0 .. SIZE).all(|i| uf.parent(i) == root));
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn serde_round_trip() {
@@ -337,8 +1585,141 @@ mod tests {
 
         let json = serde_json::to_string(&uf0).unwrap();
         let uf1: AUnionFind = serde_json::from_str(&json).unwrap();
+        assert_eq!(uf1.count_sets(), 6);
+
+        let padded: AUnionFind<CachePadded> = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&padded).unwrap(), json);
         assert!( uf1.equiv(0, 1));
         assert!(!uf1.equiv(1, 2));
         assert!( uf1.equiv(2, 3));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_validation() {
+        extern crate serde_json;
+
+        fn parse(json: &str) -> Result<AUnionFind, serde_json::Error> {
+            serde_json::from_str(json)
+        }
+
+        assert!(parse(r#"[{"id":1,"rank":0},{"id":1,"rank":1}]"#).is_ok());
+        assert!(parse(r#"[{"id":2,"rank":0},{"id":1,"rank":1}]"#).is_err());
+        assert!(parse(r#"[{"id":1,"rank":0},{"id":0,"rank":0}]"#).is_err());
+        assert!(parse(r#"[{"id":1,"rank":0},{"id":1,"rank":0}]"#).is_ok());
+        assert!(parse(r#"[{"id":0,"rank":0},{"id":0,"rank":0}]"#).is_err());
+        assert!(parse(r#"[{"id":0,"rank":2},{"id":1,"rank":0}]"#).is_err());
+    }
+}
+
+// Run with `RUSTFLAGS="--cfg loom" cargo test --release --lib loom`.
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn racing_unions_link_once() {
+        loom::model(|| {
+            let uf = Arc::new(AUnionFind::new(2));
+            let other = uf.clone();
+            let handle = thread::spawn(move || other.union(0, 1));
+
+            let here = uf.union(1, 0);
+            let there = handle.join().unwrap();
+
+            assert!(here != there);
+            assert_eq!(uf.count_sets(), 1);
+        });
+    }
+
+    // Joining 0 to 1 may raise 1’s rank between the other unions’
+    // reads of it, so they disagree about which of 1 and 2 outranks the
+    // other.
+    #[test]
+    fn stale_ranks_form_no_cycle() {
+        loom::model(|| {
+            let uf = Arc::new(AUnionFind::new(3));
+            let handles: Vec<_> = [(0, 1), (1, 2)].iter().map(|&(a, b)| {
+                let other = uf.clone();
+                thread::spawn(move || other.union(a, b))
+            }).collect();
+
+            uf.union(2, 1);
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            assert!(uf.equiv(0, 2));
+            assert_eq!(uf.count_sets(), 1);
+        });
+    }
+
+    #[test]
+    fn overlapping_unions() {
+        loom::model(|| {
+            let uf = Arc::new(AUnionFind::new(3));
+            let other = uf.clone();
+            let handle = thread::spawn(move || other.union(0, 1));
+
+            assert!(uf.union(1, 2));
+            assert!(handle.join().unwrap());
+
+            assert!(uf.equiv(0, 2));
+            assert_eq!(uf.count_sets(), 1);
+        });
+    }
+
+    #[test]
+    fn equiv_is_monotonic() {
+        loom::model(|| {
+            let uf = Arc::new(AUnionFind::new(3));
+            let other = uf.clone();
+            let handle = thread::spawn(move || {
+                other.union(0, 1);
+                other.union(1, 2);
+            });
+
+            let far = uf.equiv(0, 2);
+            let near = uf.equiv(0, 1);
+            assert!(!far || near);
+
+            handle.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn racing_subscription_is_notified() {
+        loom::model(|| {
+            let uf = Arc::new(AUnionFind::new(2));
+            let other = uf.clone();
+            let handle = thread::spawn(move || other.union(0, 1));
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            uf.subscribe(0, sender);
+            handle.join().unwrap();
+
+            assert_eq!(receiver.try_recv(), Ok(Merged { root: 1, absorbed: 0 }));
+        });
+    }
+
+    #[test]
+    fn union_publishes_writes() {
+        loom::model(|| {
+            let uf = Arc::new(AUnionFind::new(2));
+            let data = Arc::new(AtomicUsize::new(0));
+            let (other, other_data) = (uf.clone(), data.clone());
+            let handle = thread::spawn(move || {
+                other_data.store(1, Ordering::Relaxed);
+                other.union(0, 1);
+            });
+
+            if uf.equiv(1, 0) {
+                assert_eq!(data.load(Ordering::Relaxed), 1);
+            }
+
+            handle.join().unwrap();
+        });
+    }
 }
@@ -0,0 +1,385 @@
+use std::fmt::{self, Debug};
+use std::mem;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crossbeam_epoch::{self as epoch, Atomic, Owned, Shared};
+
+use super::{Error, Merge, MemoryUsage};
+use concurrent_forest::AtomicForest;
+
+/// Concurrent union-find with associated data that readers never block
+/// on.
+///
+/// Like [`AUnionFindMap`](struct.AUnionFindMap.html), each set carries
+/// data, and unions and updates serialize on striped mutexes. But here
+/// each set’s data lives behind an atomic pointer, and every change
+/// installs a fresh value rather than modifying the old one in place, so
+/// [`read`](#method.read) takes no lock at all: it only pins the current
+/// epoch and follows the pointer. Replaced values are freed by
+/// [crossbeam’s epoch-based reclamation](https://docs.rs/crossbeam-epoch)
+/// once no reader can still see them. This suits read-mostly workloads;
+/// for write-heavy ones, `AUnionFindMap`’s in-place updates copy less.
+/// Requires Cargo feature `"crossbeam-epoch"`.
+///
+/// A read sees the data of the element’s set as of some moment during
+/// the call. As with `AUnionFindMap`, the closures passed to
+/// [`union_with`](#method.union_with) and [`update`](#method.update) run
+/// while holding stripe locks that unrelated sets share, so they must
+/// not call back into the same map.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{EpochAUnionFindMap, Sum};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let uf = Arc::new(EpochAUnionFindMap::new(vec![Sum(1); 100]));
+///
+/// let handles: Vec<_> = (0 .. 4).map(|t| {
+///     let uf = uf.clone();
+///     thread::spawn(move || {
+///         for i in (t .. 99).step_by(4) {
+///             uf.union_merged(i, i + 1);
+///             assert!(uf.read(i, |sum| sum.0) >= 2);
+///         }
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(uf.read(42, |sum| sum.0), 100);
+/// ```
+pub struct EpochAUnionFindMap<Data> {
+    parents: Box<[AtomicUsize]>,
+    ranks:   Box<[AtomicU8]>,
+    data:    Box<[Atomic<Data>]>,
+    stripes: Box<[Mutex<()>]>,
+}
+// Invariants:
+//   - the rank and data of element `i` change only while holding stripe
+//     `i % STRIPES`, and the parent of a root only while holding the
+//     root’s stripe;
+//   - a root’s data is never null, and once an element has been linked
+//     beneath another, its data is null.
+
+const STRIPES: usize = 64;
+
+impl<Data> Debug for EpochAUnionFindMap<Data> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "EpochAUnionFindMap(")?;
+        formatter.debug_list().entries(self.parents.iter()).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Data> Default for EpochAUnionFindMap<Data> {
+    fn default() -> Self {
+        EpochAUnionFindMap::new(Vec::new())
+    }
+}
+
+impl<Data> Drop for EpochAUnionFindMap<Data> {
+    fn drop(&mut self) {
+        // SAFETY: We have the only reference, so no reader can hold any of
+        // the pointers, and each non-null one is owned by its slot.
+        unsafe {
+            let guard = epoch::unprotected();
+            for slot in self.data.iter() {
+                let data = slot.swap(Shared::null(), Ordering::Relaxed, guard);
+                if !data.is_null() {
+                    drop(data.into_owned());
+                }
+            }
+        }
+    }
+}
+
+impl<Data> EpochAUnionFindMap<Data> {
+    /// Creates a new asynchronous union-find with a singleton set for
+    /// each datum.
+    pub fn new(data: Vec<Data>) -> Self {
+        EpochAUnionFindMap {
+            parents: (0 .. data.len()).map(AtomicUsize::new).collect(),
+            ranks:   (0 .. data.len()).map(|_| AtomicU8::new(0)).collect(),
+            data:    data.into_iter().map(Atomic::new).collect(),
+            stripes: (0 .. STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find, counting each
+    /// set’s current data as a node but not any memory that the data owns
+    /// or that awaits reclamation.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let sets = (0 .. self.len()).filter(|&i| self.is_root(i)).count();
+        MemoryUsage::new()
+            .array::<AtomicUsize>("parents", self.parents.len(), self.parents.len())
+            .array::<AtomicU8>("ranks", self.ranks.len(), self.ranks.len())
+            .array::<Atomic<Data>>("data", self.data.len(), self.data.len())
+            .array::<Mutex<()>>("stripes", STRIPES, STRIPES)
+            .nodes(sets, mem::size_of::<Data>())
+    }
+
+    /// Finds the representative element for the given element’s set.
+    pub fn find(&self, element: usize) -> usize {
+        AtomicForest::find(self, element)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        AtomicForest::try_find(self, element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        AtomicForest::equiv(self, a, b)
+    }
+
+    /// Passes the data associated with the given element’s set to a
+    /// closure, without taking any lock.
+    ///
+    /// The closure sees the data as of some moment during the call; a
+    /// concurrent union or update may replace it meanwhile, but the value
+    /// the closure sees stays valid until it returns.
+    pub fn read<R, F>(&self, element: usize, f: F) -> R
+            where F: FnOnce(&Data) -> R {
+
+        let guard = epoch::pin();
+        loop {
+            let root = self.find(element);
+            // SAFETY: The pointer is either null or was installed by
+            // `new`, `union_with`, or `update`, and any value replaced
+            // since is only freed once `guard` is unpinned.
+            if let Some(data) = unsafe { self.data[root].load(Ordering::Acquire, &guard).as_ref() } {
+                return f(data);
+            }
+            // Null means that `root` has just been linked, so find again.
+        }
+    }
+
+    /// Returns a clone of the data associated with the given element’s
+    /// set.
+    pub fn clone_data(&self, element: usize) -> Data
+            where Data: Clone {
+        self.read(element, Data::clone)
+    }
+}
+
+impl<Data: Send + 'static> EpochAUnionFindMap<Data> {
+    /// Joins the sets of the two given elements, combining their data.
+    ///
+    /// The closure `f` is passed the data of `a`’s set and of `b`’s set,
+    /// in that order, and returns the data for the joined set. Since
+    /// concurrent readers may still be looking at the old data, `f` only
+    /// borrows it. Returns whether the sets were different; if they were
+    /// the same, `f` is not called.
+    pub fn union_with<F>(&self, a: usize, b: usize, f: F) -> bool
+            where F: FnOnce(&Data, &Data) -> Data {
+
+        let guard = epoch::pin();
+        loop {
+            let root_a = self.find(a);
+            let root_b = self.find(b);
+
+            if root_a == root_b { return false; }
+
+            let _guards = self.lock_pair(root_a, root_b);
+            if !self.is_root(root_a) || !self.is_root(root_b) { continue; }
+
+            let data_a = self.data[root_a].load(Ordering::Acquire, &guard);
+            let data_b = self.data[root_b].load(Ordering::Acquire, &guard);
+            // SAFETY: Both are roots, and we hold their stripes, so their
+            // data is non-null and stays installed while we hold them.
+            let data = unsafe { f(data_a.deref(), data_b.deref()) };
+
+            let rank_a = self.ranks[root_a].load(Ordering::Relaxed);
+            let rank_b = self.ranks[root_b].load(Ordering::Relaxed);
+            let (child, parent) = if rank_a > rank_b {
+                (root_b, root_a)
+            } else {
+                if rank_a == rank_b {
+                    self.ranks[root_b].store(rank_b.saturating_add(1), Ordering::Relaxed);
+                }
+                (root_a, root_b)
+            };
+
+            // Install the joined data before linking, so that a reader
+            // that finds `parent` after the link sees it, and clear the
+            // child’s after, so that a reader that sees null finds again.
+            self.data[parent].store(Owned::new(data), Ordering::Release);
+            self.parents[child].store(parent, Ordering::Release);
+            self.data[child].store(Shared::null(), Ordering::Release);
+
+            // SAFETY: Neither old value is reachable any longer, and
+            // readers that loaded them are pinned.
+            unsafe {
+                guard.defer_destroy(data_a);
+                guard.defer_destroy(data_b);
+            }
+            return true;
+        }
+    }
+
+    /// Joins the sets of the two given elements, combining clones of
+    /// their data with [`Merge::merge`](trait.Merge.html).
+    pub fn union_merged(&self, a: usize, b: usize) -> bool
+            where Data: Merge + Clone {
+        self.union_with(a, b, |x, y| x.clone().merge(y.clone()))
+    }
+
+    /// Replaces the data associated with the given element’s set with
+    /// the result of a closure applied to it.
+    ///
+    /// Concurrent readers see either the old data or the new, never a
+    /// mixture.
+    pub fn update<F>(&self, element: usize, f: F)
+            where F: FnOnce(&Data) -> Data {
+
+        let guard = epoch::pin();
+        loop {
+            let root = self.find(element);
+            let _lock = self.lock(root);
+            if !self.is_root(root) { continue; }
+
+            let old = self.data[root].load(Ordering::Acquire, &guard);
+            // SAFETY: As in `union_with`.
+            let new = unsafe { f(old.deref()) };
+            self.data[root].store(Owned::new(new), Ordering::Release);
+            unsafe { guard.defer_destroy(old); }
+            return;
+        }
+    }
+}
+
+impl<Data> EpochAUnionFindMap<Data> {
+    // HELPERS
+
+    // A panic in a closure happens before anything changes, so poisoning
+    // is ignored.
+    fn lock(&self, element: usize) -> MutexGuard<'_, ()> {
+        self.stripes[element % STRIPES].lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    // Locking in stripe order prevents deadlock.
+    fn lock_pair(&self, a: usize, b: usize)
+                 -> (MutexGuard<'_, ()>, Option<MutexGuard<'_, ()>>) {
+        let (first, second) = if a % STRIPES <= b % STRIPES { (a, b) } else { (b, a) };
+        let first_guard = self.lock(first);
+        let second_guard = if first % STRIPES == second % STRIPES {
+            None
+        } else {
+            Some(self.lock(second))
+        };
+        (first_guard, second_guard)
+    }
+
+    fn is_root(&self, element: usize) -> bool {
+        self.parent(element) == element
+    }
+}
+
+// The orderings follow those of `AUnionFind`; see the comment there.
+// In addition, data is stored with `Release` and loaded with
+// `Acquire`, so a reader sees the value fully constructed.
+
+impl<Data> AtomicForest for EpochAUnionFindMap<Data> {
+    fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    fn parent(&self, element: usize) -> usize {
+        self.parents[element].load(Ordering::Acquire)
+    }
+
+    fn change_parent(&self,
+                     element: usize,
+                     old_parent: usize,
+                     new_parent: usize)
+                     -> bool {
+        self.parents[element].compare_exchange_weak(old_parent,
+                                                    new_parent,
+                                                    Ordering::AcqRel,
+                                                    Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn union_with() {
+        let uf = EpochAUnionFindMap::new(vec!['a', 'b', 'c', 'd']);
+        assert!(uf.union_with(0, 1, |&x, &y| x.max(y)));
+        assert!(!uf.union_with(1, 0, |_, _| panic!("same set")));
+        assert!(uf.union_with(2, 0, |&x, &y| x.min(y)));
+        assert!(uf.equiv(1, 2));
+        assert!(!uf.equiv(1, 3));
+        assert_eq!(uf.clone_data(0), 'b');
+        assert_eq!(uf.clone_data(3), 'd');
+        uf.update(2, |_| 'z');
+        assert_eq!(uf.read(1, |&data| data), 'z');
+        assert_eq!(uf.try_find(4), Err(Error::OutOfBounds { element: 4, len: 4 }));
+    }
+
+    #[test]
+    fn readers_during_replacement() {
+        const READERS: usize = 3;
+        const SIZE: usize = 1 << 9;
+
+        // Each set’s data lists its members, so a reader that followed a
+        // cleared or freed pointer would not find its element there.
+        let uf = Arc::new(EpochAUnionFindMap::new((0 .. SIZE).map(|i| vec![i]).collect()));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0 .. READERS).map(|t| {
+            let uf = uf.clone();
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut i = t;
+                while !done.load(Ordering::Relaxed) {
+                    assert!(uf.read(i, |members| members.contains(&i)));
+                    i = (i + 7) % SIZE;
+                }
+            })
+        }).collect();
+
+        // Every union clears a root’s pointer, and every update replaces
+        // the value behind one.
+        for i in 1 .. SIZE {
+            uf.union_with(i - 1, i, |a, b| a.iter().chain(b).cloned().collect());
+            for _ in 0 .. 4 {
+                uf.update(i, |members| members.clone());
+            }
+        }
+        done.store(true, Ordering::Relaxed);
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        assert_eq!(uf.read(0, Vec::len), SIZE);
+    }
+}
@@ -0,0 +1,124 @@
+use super::{Backoff, Error};
+use backoff::Retries;
+use concurrent::UnionOutcome;
+
+// The algorithms that the lock-free union-finds share, written once over
+// each type’s access to its slots. Each type keeps its public methods,
+// with their own documentation, and forwards them here.
+//
+// Implementors supply the parent of each element and a compare-and-swap
+// on it, with the orderings described in `AUnionFind`’s ORDERINGS
+// comment, or stronger.
+pub(crate) trait AtomicForest {
+    fn len(&self) -> usize;
+
+    fn parent(&self, element: usize) -> usize;
+
+    // Changes the element’s parent, if it is still `old_parent`. Used
+    // only to shorten paths, so it may fail spuriously.
+    fn change_parent(&self, element: usize, old_parent: usize, new_parent: usize) -> bool;
+
+    fn check(&self, element: usize) -> Result<(), Error> {
+        if element < self.len() {
+            Ok(())
+        } else {
+            Err(Error::OutOfBounds { element, len: self.len() })
+        }
+    }
+
+    // Finds the root, compacting the path by splitting. A failed
+    // compare-and-swap is never retried, since it means another thread
+    // has already moved the element closer to the root.
+    fn find(&self, mut element: usize) -> usize {
+        let mut parent = self.parent(element);
+
+        while element != parent {
+            let grandparent = self.parent(parent);
+            self.change_parent(element, parent, grandparent);
+            element = parent;
+            parent = grandparent;
+        }
+
+        element
+    }
+
+    fn try_find(&self, element: usize) -> Result<usize, Error> {
+        self.check(element)?;
+        Ok(self.find(element))
+    }
+
+    // If `a`’s root is still a root after `b`’s has been found, then at
+    // that moment the two were roots of different sets.
+    fn equiv(&self, mut a: usize, mut b: usize) -> bool {
+        loop {
+            a = self.find(a);
+            b = self.find(b);
+
+            if a == b { return true; }
+            if self.parent(a) == a { return false; }
+        }
+    }
+}
+
+// A forest whose unions link the root of lower rank beneath the other,
+// breaking ties by index, so that roots are totally ordered. Racing
+// unions then cannot form a cycle, provided that `link` fails once the
+// absorbed root’s rank has changed.
+pub(crate) trait AtomicLinking: AtomicForest {
+    type Rank: Copy + Ord;
+
+    // The rank of a root, which may have grown by the time it is used.
+    fn rank(&self, root: usize) -> Self::Rank;
+
+    // Links root `absorbed` beneath `root`, provided that `absorbed` is
+    // still a root of rank `rank`, and then raises `root`’s rank if it
+    // was the same. Fails, changing nothing, if `absorbed` has since been
+    // linked or gained rank.
+    fn link(&self, absorbed: usize, rank: Self::Rank,
+            root: usize, root_rank: Self::Rank) -> bool;
+
+    // What to do after a failed link, before trying again.
+    fn backoff(&self) -> Backoff {
+        Backoff::default()
+    }
+
+    fn union_roots(&self, mut a: usize, mut b: usize) -> UnionOutcome {
+        let mut retries = Retries::new(self.backoff());
+
+        loop {
+            a = self.find(a);
+            b = self.find(b);
+
+            if a == b { return UnionOutcome::Same { root: a }; }
+
+            let rank_a = self.rank(a);
+            let rank_b = self.rank(b);
+
+            let ((root, rank), (absorbed, absorbed_rank)) =
+                if (rank_a, a) > (rank_b, b) {
+                    ((a, rank_a), (b, rank_b))
+                } else {
+                    ((b, rank_b), (a, rank_a))
+                };
+
+            // If `absorbed` has gained rank since, it may now outrank
+            // `root`, and if it has been linked, it is no longer a root.
+            // Either way, back off and try again.
+            if self.link(absorbed, absorbed_rank, root, rank) {
+                return UnionOutcome::Linked { root, absorbed };
+            }
+
+            retries.back_off();
+        }
+    }
+
+    fn union(&self, a: usize, b: usize) -> bool {
+        self.union_roots(a, b).is_linked()
+    }
+
+    fn try_union(&self, a: usize, b: usize) -> Result<bool, Error> {
+        self.check(a)?;
+        self.check(b)?;
+        Ok(self.union(a, b))
+    }
+}
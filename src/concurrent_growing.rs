@@ -0,0 +1,456 @@
+use std::fmt::{self, Debug};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use super::{Backoff, Error, MemoryUsage};
+use backoff::Retries;
+use concurrent_forest::{AtomicForest, AtomicLinking};
+
+/// Lock-free, concurrent union-find to which elements can be added
+/// concurrently.
+///
+/// This is like [`AUnionFind`](struct.AUnionFind.html), except that
+/// [`alloc`](#method.alloc) adds a new element while other threads
+/// continue to call [`union`](#method.union) and [`find`](#method.find).
+/// Elements are stored in segments that double in size, so existing
+/// elements never move. The first thread to need a segment claims it and
+/// allocates it; until it is installed, other threads that reach an
+/// element in it wait.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::GrowingAUnionFind;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let uf = Arc::new(GrowingAUnionFind::new(1));
+///
+/// let handles: Vec<_> = (0 .. 4).map(|_| {
+///     let uf = uf.clone();
+///     thread::spawn(move || {
+///         for _ in 0 .. 100 {
+///             let element = uf.alloc();
+///             uf.union(0, element);
+///         }
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(uf.len(), 401);
+/// assert!(uf.equiv(17, 400));
+/// ```
+pub struct GrowingAUnionFind {
+    segments: [AtomicPtr<Entry>; SEGMENTS],
+    // The number of elements allocated or being allocated.
+    len:      AtomicUsize,
+}
+// Invariant: segment `s` is null, `installing()` while the thread that
+// claimed it allocates it, or points to `segment_len(s)` entries,
+// initialized as singletons. Every element less than `len` belongs to a
+// segment that is installed or being installed.
+
+struct Entry {
+    id:   AtomicUsize,
+    rank: AtomicUsize,
+}
+
+// Set in a rank once it may no longer change, as in `AUnionFind`.
+const FROZEN: usize = !(!0 >> 1);
+
+// The first segment holds `1 << FIRST_BITS` elements, and each segment
+// after holds twice as many as the one before, so `SEGMENTS` segments
+// hold all but the last `1 << FIRST_BITS` values of `usize`.
+const FIRST_BITS: u32 = 6;
+const SEGMENTS: usize = (usize::BITS - FIRST_BITS) as usize;
+const CAPACITY: usize = usize::MAX - ((1 << FIRST_BITS) - 1);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NULL_SEGMENT: AtomicPtr<Entry> = AtomicPtr::new(ptr::null_mut());
+
+// Marks a segment claimed by a thread that has yet to install it. No
+// allocation of entries is ever at this address.
+fn installing() -> *mut Entry {
+    NonNull::dangling().as_ptr()
+}
+
+fn segment_len(segment: usize) -> usize {
+    1 << (FIRST_BITS as usize + segment)
+}
+
+// The index of the first element in the segment.
+fn segment_start(segment: usize) -> usize {
+    segment_len(segment) - segment_len(0)
+}
+
+// The segment holding an element, and its offset within the segment.
+fn locate(element: usize) -> (usize, usize) {
+    let shifted = (element >> FIRST_BITS) + 1;
+    let segment = (usize::BITS - 1 - shifted.leading_zeros()) as usize;
+    (segment, element - segment_start(segment))
+}
+
+impl Debug for GrowingAUnionFind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "GrowingAUnionFind(")?;
+        formatter.debug_list()
+            .entries((0 .. self.len()).filter_map(|i| self.entry(i))
+                     .map(|entry| &entry.id))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl Default for GrowingAUnionFind {
+    fn default() -> Self {
+        GrowingAUnionFind::new(0)
+    }
+}
+
+impl Drop for GrowingAUnionFind {
+    fn drop(&mut self) {
+        for (s, segment) in self.segments.iter_mut().enumerate() {
+            let entries = *segment.get_mut();
+            if !entries.is_null() && entries != installing() {
+                // SAFETY: By the invariant, `entries` came from a boxed
+                // slice of this length, and nothing else refers to it.
+                unsafe {
+                    let len = segment_len(s);
+                    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(entries, len)));
+                }
+            }
+        }
+    }
+}
+
+impl GrowingAUnionFind {
+    /// Creates a new growable asynchronous union-find of `size`
+    /// elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is within 2<sup>6</sup> of `usize::MAX`.
+    pub fn new(size: usize) -> Self {
+        assert!(size <= CAPACITY, "GrowingAUnionFind::new: overflow");
+
+        let result = GrowingAUnionFind {
+            segments: [NULL_SEGMENT; SEGMENTS],
+            len:      AtomicUsize::new(size),
+        };
+
+        if size > 0 {
+            for segment in 0 ..= locate(size - 1).0 {
+                result.ensure_segment(segment);
+            }
+        }
+
+        result
+    }
+
+    /// The number of elements in all the sets.
+    ///
+    /// While other threads are adding elements, this may count elements
+    /// whose [`alloc`](#method.alloc) has not yet returned.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let len = self.len();
+        let mut result = MemoryUsage::new();
+
+        // Segments may be installed out of order, so check every one.
+        for (s, segment) in self.segments.iter().enumerate() {
+            let entries = segment.load(Ordering::SeqCst);
+            if entries.is_null() || entries == installing() { continue; }
+            let in_use = len.saturating_sub(segment_start(s)).min(segment_len(s));
+            result = result.array::<Entry>("segment", in_use, segment_len(s));
+        }
+
+        result
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// This may be called concurrently with any other operation.
+    ///
+    /// # Panics
+    ///
+    /// If another element would bring the length within 2<sup>6</sup>
+    /// of `usize::MAX`.
+    pub fn alloc(&self) -> usize {
+        let element = self.len.fetch_add(1, Ordering::SeqCst);
+        assert!(element < CAPACITY, "GrowingAUnionFind::alloc: overflow");
+        self.ensure_segment(locate(element).0);
+        element
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn union(&self, a: usize, b: usize) -> bool {
+        AtomicLinking::union(self, a, b)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: usize) -> usize {
+        AtomicForest::find(self, element)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        AtomicForest::try_find(self, element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        AtomicForest::equiv(self, a, b)
+    }
+
+    // HELPERS
+
+    // Allocates and installs the segment, unless another thread has
+    // claimed it already.
+    fn ensure_segment(&self, segment: usize) {
+        let slot = &self.segments[segment];
+        if !slot.load(Ordering::SeqCst).is_null() { return; }
+        if slot.compare_exchange(ptr::null_mut(), installing(),
+                                 Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return;
+        }
+
+        let start = segment_start(segment);
+        let entries: Box<[Entry]> = (start .. start + segment_len(segment))
+            .map(|id| Entry { id: AtomicUsize::new(id), rank: AtomicUsize::new(0) })
+            .collect();
+        slot.store(Box::into_raw(entries) as *mut Entry, Ordering::SeqCst);
+    }
+
+    // Waits for the thread that claimed the segment to install it.
+    fn installed_segment(&self, segment: usize) -> *mut Entry {
+        let mut retries = Retries::new(Backoff::Exponential);
+
+        loop {
+            let entries = self.segments[segment].load(Ordering::SeqCst);
+            if !entries.is_null() && entries != installing() { return entries; }
+            retries.back_off();
+        }
+    }
+
+    fn entry(&self, element: usize) -> Option<&Entry> {
+        if element >= self.len().min(CAPACITY) { return None; }
+
+        // The element has been allocated, but perhaps not installed.
+        let (segment, offset) = locate(element);
+        let entries = self.installed_segment(segment);
+
+        // SAFETY: By the invariant, an installed segment points to
+        // `segment_len(segment)` initialized entries, which live until
+        // `self` is dropped, and `locate` returns an offset less than
+        // that.
+        unsafe { Some(&*entries.add(offset)) }
+    }
+
+    fn entry_or_panic(&self, element: usize) -> &Entry {
+        self.entry(element).unwrap_or_else(|| {
+            panic!("GrowingAUnionFind: element {} out of bounds", element)
+        })
+    }
+}
+
+impl AtomicForest for GrowingAUnionFind {
+    // Past `CAPACITY`, `alloc` panics rather than adding the element.
+    fn len(&self) -> usize {
+        GrowingAUnionFind::len(self).min(CAPACITY)
+    }
+
+    fn parent(&self, element: usize) -> usize {
+        self.entry_or_panic(element).id.load(Ordering::SeqCst)
+    }
+
+    fn change_parent(&self,
+                     element: usize,
+                     old_parent: usize,
+                     new_parent: usize)
+                     -> bool {
+        self.entry_or_panic(element).id
+            .compare_exchange(old_parent, new_parent,
+                              Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+impl AtomicLinking for GrowingAUnionFind {
+    type Rank = usize;
+
+    fn rank(&self, element: usize) -> usize {
+        self.entry_or_panic(element).rank.load(Ordering::SeqCst) & !FROZEN
+    }
+
+    // Freezing the absorbed root’s rank first keeps it from growing past
+    // `root`’s before the link lands.
+    fn link(&self, absorbed: usize, rank: usize, root: usize, root_rank: usize) -> bool {
+        let entry = self.entry_or_panic(absorbed);
+
+        match entry.rank.compare_exchange(rank, rank | FROZEN,
+                                          Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => (),
+            // Another union froze it at the same rank, and is linking it.
+            Err(word) if word == rank | FROZEN => (),
+            Err(_) => return false,
+        }
+
+        // If this fails, another union has linked `absorbed`, which it
+        // froze at this same rank, so the rank stays frozen.
+        if entry.id.compare_exchange(absorbed, root,
+                                     Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return false;
+        }
+
+        // If `root`’s rank has changed or been frozen since, the
+        // increment is moot.
+        if rank == root_rank {
+            let _ = self.entry_or_panic(root).rank
+                .compare_exchange(root_rank, root_rank + 1,
+                                  Ordering::SeqCst, Ordering::SeqCst);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn segments() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(63), (0, 63));
+        assert_eq!(locate(64), (1, 0));
+        assert_eq!(locate(191), (1, 127));
+        assert_eq!(locate(192), (2, 0));
+        assert_eq!(locate(CAPACITY - 1), (SEGMENTS - 1, segment_len(SEGMENTS - 1) - 1));
+    }
+
+    #[test]
+    fn alloc() {
+        let uf = GrowingAUnionFind::new(100);
+        assert_eq!(uf.memory_usage().arrays().len(), 2);
+        assert!(uf.union(3, 99));
+        assert_eq!(uf.alloc(), 100);
+        assert!(uf.union(100, 3));
+        assert!(uf.equiv(99, 100));
+        assert_eq!(uf.try_find(101), Err(Error::OutOfBounds { element: 101, len: 101 }));
+    }
+
+    #[test]
+    fn concurrent_allocs() {
+        const THREADS: usize = 4;
+        const PER_THREAD: usize = 1000;
+
+        let uf = Arc::new(GrowingAUnionFind::default());
+        let first = uf.alloc();
+
+        let handles: Vec<_> = (0 .. THREADS).map(|_| {
+            let uf = uf.clone();
+            thread::spawn(move || {
+                let mut previous = first;
+                for _ in 0 .. PER_THREAD {
+                    let element = uf.alloc();
+                    assert_eq!(uf.find(element), element);
+                    uf.union(previous, element);
+                    previous = element;
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(uf.len(), THREADS * PER_THREAD + 1);
+        assert!((0 .. uf.len()).all(|i| uf.equiv(i, first)));
+    }
+
+    #[test]
+    fn find_before_installed() {
+        let uf = Arc::new(GrowingAUnionFind::new(0));
+        let reader = {
+            let uf = uf.clone();
+            thread::spawn(move || {
+                while uf.len() < 1000 {
+                    if let Some(last) = uf.len().checked_sub(1) {
+                        assert_eq!(uf.try_find(last).map(|_| ()), Ok(()));
+                    }
+                }
+            })
+        };
+
+        for _ in 0 .. 1000 {
+            uf.alloc();
+        }
+        reader.join().unwrap();
+    }
+
+    #[test]
+    fn segments_out_of_order() {
+        let uf = GrowingAUnionFind::new(0);
+        uf.ensure_segment(2);
+        assert_eq!(uf.memory_usage().arrays().len(), 1);
+    }
+
+    #[test]
+    fn mirrored_unions() {
+        const PAIRS: usize = 100_000;
+
+        let uf = Arc::new(GrowingAUnionFind::new(2 * PAIRS));
+        let handles: Vec<_> = (0 .. 4).map(|t| {
+            let uf = uf.clone();
+            thread::spawn(move || {
+                for i in 0 .. PAIRS {
+                    let (a, b) = (2 * i, 2 * i + 1);
+                    if t % 2 == 0 { uf.union(a, b); } else { uf.union(b, a); }
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Linking each root beneath the other would leave no root.
+        for i in 0 .. PAIRS {
+            assert!(uf.parent(2 * i) == 2 * i || uf.parent(2 * i + 1) == 2 * i + 1);
+        }
+    }
+}
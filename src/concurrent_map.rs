@@ -0,0 +1,328 @@
+use std::cell::UnsafeCell;
+use std::fmt::{self, Debug};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use super::{Error, Merge, MemoryUsage};
+use concurrent_forest::AtomicForest;
+
+/// Concurrent union-find with associated data for each set.
+///
+/// Like [`AUnionFind`](struct.AUnionFind.html), `find` and `equiv` are
+/// lock-free, but each set carries data that
+/// [`union_with`](#method.union_with) combines and
+/// [`with_data`](#method.with_data) reads or modifies. The data is guarded
+/// by a fixed array of mutexes, or stripes, with element `i` belonging
+/// to stripe `i % 64`, so the locks take constant space no matter how
+/// many elements there are. A union locks the stripes of the two roots,
+/// combines their data, and only then links one root beneath the other.
+///
+/// The closures passed to `with_data` and `union_with` run while holding
+/// stripe locks that unrelated sets share, so they must not call back
+/// into the same map, or they may deadlock. If one panics, the data of
+/// the sets involved is lost, and later access to it panics.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{AUnionFindMap, Sum};
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let uf = Arc::new(AUnionFindMap::new(vec![Sum(1); 100]));
+///
+/// let handles: Vec<_> = (0 .. 4).map(|t| {
+///     let uf = uf.clone();
+///     thread::spawn(move || {
+///         for i in (t .. 99).step_by(4) {
+///             uf.union_merged(i, i + 1);
+///         }
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(uf.clone_data(42), Sum(100));
+/// ```
+pub struct AUnionFindMap<Data> {
+    parents: Box<[AtomicUsize]>,
+    slots:   Box<[UnsafeCell<Slot<Data>>]>,
+    stripes: Box<[Mutex<()>]>,
+}
+// Invariants:
+//   - slot `i` is accessed only while holding stripe `i % STRIPES`;
+//   - the parent of a root changes only while holding the root’s stripe;
+//   - a root’s slot holds its set’s data, unless a closure panicked, and
+//     every other slot holds `None`.
+
+struct Slot<Data> {
+    rank: u8,
+    data: Option<Data>,
+}
+
+const STRIPES: usize = 64;
+
+// SAFETY: The slots are only accessed under the stripe locks, so as with
+// `Mutex<Data>`, sharing the map only ever sends `Data` between threads.
+unsafe impl<Data: Send> Sync for AUnionFindMap<Data> { }
+
+impl<Data> Debug for AUnionFindMap<Data> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "AUnionFindMap(")?;
+        formatter.debug_list().entries(self.parents.iter()).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Data> Default for AUnionFindMap<Data> {
+    fn default() -> Self {
+        AUnionFindMap::new(Vec::new())
+    }
+}
+
+impl<Data> AUnionFindMap<Data> {
+    /// Creates a new asynchronous union-find with a singleton set for
+    /// each datum.
+    pub fn new(data: Vec<Data>) -> Self {
+        AUnionFindMap {
+            parents: (0 .. data.len()).map(AtomicUsize::new).collect(),
+            slots:   data.into_iter()
+                .map(|data| UnsafeCell::new(Slot { rank: 0, data: Some(data) }))
+                .collect(),
+            stripes: (0 .. STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find, not counting any
+    /// owned by the data.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<AtomicUsize>("parents", self.parents.len(), self.parents.len())
+            .array::<Slot<Data>>("slots", self.slots.len(), self.slots.len())
+            .array::<Mutex<()>>("stripes", STRIPES, STRIPES)
+    }
+
+    /// Joins the sets of the two given elements, combining their data.
+    ///
+    /// The closure `f` is passed the data of `a`’s set and of `b`’s set,
+    /// in that order, and returns the data for the joined set. Returns
+    /// whether the sets were different; if they were the same, `f` is not
+    /// called.
+    pub fn union_with<F>(&self, a: usize, b: usize, f: F) -> bool
+            where F: FnOnce(Data, Data) -> Data {
+
+        loop {
+            let root_a = self.find(a);
+            let root_b = self.find(b);
+
+            if root_a == root_b { return false; }
+
+            let _guards = self.lock_pair(root_a, root_b);
+            if !self.is_root(root_a) || !self.is_root(root_b) { continue; }
+
+            // SAFETY: The roots differ, and we hold both their stripes.
+            let (slot_a, slot_b) = unsafe {
+                (&mut *self.slots[root_a].get(), &mut *self.slots[root_b].get())
+            };
+
+            let data = f(take_data(slot_a), take_data(slot_b));
+
+            let (child, parent, parent_slot) = if slot_a.rank > slot_b.rank {
+                (root_b, root_a, slot_a)
+            } else {
+                if slot_a.rank == slot_b.rank { slot_b.rank = slot_b.rank.saturating_add(1); }
+                (root_a, root_b, slot_b)
+            };
+
+            parent_slot.data = Some(data);
+            // Only holders of `child`’s stripe change its parent while it
+            // is a root, so this needs no compare-and-swap.
+            self.parents[child].store(parent, Ordering::Release);
+            return true;
+        }
+    }
+
+    /// Joins the sets of the two given elements, combining their data
+    /// with [`Merge::merge`](trait.Merge.html).
+    pub fn union_merged(&self, a: usize, b: usize) -> bool
+            where Data: Merge {
+        self.union_with(a, b, Data::merge)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    pub fn find(&self, element: usize) -> usize {
+        AtomicForest::find(self, element)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        AtomicForest::try_find(self, element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        AtomicForest::equiv(self, a, b)
+    }
+
+    /// Allows modifying the data associated with the given element’s
+    /// set.
+    ///
+    /// The closure runs while holding the lock for the set’s current
+    /// root, so no union involving the set can happen meanwhile.
+    pub fn with_data<R, F>(&self, element: usize, f: F) -> R
+            where F: FnOnce(&mut Data) -> R {
+
+        loop {
+            let root = self.find(element);
+            let _guard = self.lock(root);
+            if !self.is_root(root) { continue; }
+
+            // SAFETY: We hold the root’s stripe.
+            let slot = unsafe { &mut *self.slots[root].get() };
+            return f(slot.data.as_mut().expect(LOST));
+        }
+    }
+
+    /// Returns a clone of the data associated with the given element’s
+    /// set.
+    pub fn clone_data(&self, element: usize) -> Data
+            where Data: Clone {
+        self.with_data(element, |data| data.clone())
+    }
+
+    // HELPERS
+
+    // A panic while a lock is held loses data but breaks no links, so
+    // poisoning is ignored, as for `SyncUnionFindNode`.
+    fn lock(&self, element: usize) -> MutexGuard<'_, ()> {
+        self.stripes[element % STRIPES].lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    // Locking in stripe order prevents deadlock.
+    fn lock_pair(&self, a: usize, b: usize)
+                 -> (MutexGuard<'_, ()>, Option<MutexGuard<'_, ()>>) {
+        let (first, second) = if a % STRIPES <= b % STRIPES { (a, b) } else { (b, a) };
+        let first_guard = self.lock(first);
+        let second_guard = if first % STRIPES == second % STRIPES {
+            None
+        } else {
+            Some(self.lock(second))
+        };
+        (first_guard, second_guard)
+    }
+
+    fn is_root(&self, element: usize) -> bool {
+        self.parent(element) == element
+    }
+}
+
+// The orderings follow those of `AUnionFind`; see the comment there.
+
+impl<Data> AtomicForest for AUnionFindMap<Data> {
+    fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    fn parent(&self, element: usize) -> usize {
+        self.parents[element].load(Ordering::Acquire)
+    }
+
+    fn change_parent(&self,
+                     element: usize,
+                     old_parent: usize,
+                     new_parent: usize)
+                     -> bool {
+        self.parents[element].compare_exchange_weak(old_parent,
+                                                    new_parent,
+                                                    Ordering::AcqRel,
+                                                    Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+const LOST: &str = "AUnionFindMap: data lost to a panic";
+
+fn take_data<Data>(slot: &mut Slot<Data>) -> Data {
+    slot.data.take().expect(LOST)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use Sum;
+
+    #[test]
+    fn union_with() {
+        let uf = AUnionFindMap::new(vec!['a', 'b', 'c', 'd']);
+        assert!(uf.union_with(0, 1, |x, y| x.max(y)));
+        assert!(!uf.union_with(1, 0, |_, _| panic!("same set")));
+        assert!(uf.union_with(2, 0, |x, y| x.min(y)));
+        assert!(uf.equiv(1, 2));
+        assert!(!uf.equiv(1, 3));
+        assert_eq!(uf.clone_data(0), 'b');
+        assert_eq!(uf.clone_data(3), 'd');
+        assert_eq!(uf.with_data(2, |data| std::mem::replace(data, 'z')), 'b');
+        assert_eq!(uf.clone_data(1), 'z');
+        assert_eq!(uf.try_find(4), Err(Error::OutOfBounds { element: 4, len: 4 }));
+    }
+
+    #[test]
+    fn shared_stripes() {
+        let uf = AUnionFindMap::new(vec![Sum(1); 3 * STRIPES]);
+        assert!(uf.union_merged(0, STRIPES));
+        assert!(uf.union_merged(2 * STRIPES, 0));
+        assert_eq!(uf.clone_data(STRIPES), Sum(3));
+        assert_eq!(uf.clone_data(1), Sum(1));
+    }
+
+    #[test]
+    fn concurrent_stripe_collisions() {
+        const THREADS: usize = 4;
+
+        // Elements `j`, `j + STRIPES`, … share stripe `j`, so every union
+        // here locks one stripe for two roots, while other threads update
+        // other sets under the same stripe.
+        let uf = Arc::new(AUnionFindMap::new(vec![Sum(1); THREADS * STRIPES]));
+
+        let handles: Vec<_> = (0 .. THREADS).map(|t| {
+            let uf = uf.clone();
+            thread::spawn(move || {
+                for j in 0 .. STRIPES {
+                    let a = j + t * STRIPES;
+                    let b = j + (t + 1) % THREADS * STRIPES;
+                    uf.union_merged(a, b);
+                    uf.with_data(a, |data| data.0 += 1);
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for j in 0 .. STRIPES {
+            assert!((0 .. THREADS).all(|t| uf.equiv(j, j + t * STRIPES)));
+            assert_eq!(uf.clone_data(j), Sum(2 * THREADS));
+        }
+        assert!(!uf.equiv(0, 1));
+    }
+}
@@ -0,0 +1,374 @@
+use std::fmt::{self, Debug};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use super::{ElementType, Error, MemoryUsage};
+use concurrent_forest::{AtomicForest, AtomicLinking};
+
+// Each element’s rank takes the low bits of its word, and its parent the
+// rest. Six bits hold any rank reachable with as many elements as the
+// remaining bits can number.
+const RANK_BITS: u32 = 6;
+
+// Defines a union-find that packs each element’s parent and rank into
+// one atomic word, with elements of the word’s type.
+macro_rules! packed_union_find {
+    ($(#[$attr:meta])* pub struct $name:ident($atomic:ident, $word:ident);) => {
+        $(#[$attr])*
+        pub struct $name(Box<[$atomic]>);
+
+        impl Clone for $name {
+            fn clone(&self) -> Self {
+                $name(self.0.iter()
+                    .map(|word| $atomic::new(word.load(Ordering::SeqCst)))
+                    .collect())
+            }
+        }
+
+        impl Debug for $name {
+            fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, concat!(stringify!($name), "("))?;
+                formatter.debug_list()
+                    .entries(self.0.iter()
+                             .map(|word| Self::parent_of(word.load(Ordering::SeqCst))))
+                    .finish()?;
+                write!(formatter, ")")
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::new(0)
+            }
+        }
+
+        impl $name {
+            /// The maximum number of elements.
+            pub const MAX_LEN: usize = 1 << (<$word>::BITS - RANK_BITS);
+
+            const RANK_MASK: $word = (1 << RANK_BITS) - 1;
+
+            /// Creates a new asynchronous union-find of `size` elements.
+            ///
+            /// # Panics
+            ///
+            /// If `size` exceeds [`MAX_LEN`](#associatedconstant.MAX_LEN).
+            pub fn new(size: usize) -> Self {
+                Self::try_new(size).expect(concat!(stringify!($name), "::new: overflow"))
+            }
+
+            /// Creates a new asynchronous union-find of `size` elements, if
+            /// possible.
+            ///
+            /// Like [`new`](#method.new), but returns `Err(Error::Overflow)`
+            /// instead of panicking if `size` is too large.
+            pub fn try_new(size: usize) -> Result<Self, Error> {
+                if size > Self::MAX_LEN {
+                    return Err(Error::Overflow);
+                }
+
+                Ok($name((0 .. size)
+                    .map(|i| $atomic::new(Self::pack($word::from_usize(i).unwrap(), 0)))
+                    .collect()))
+            }
+
+            /// The number of elements in all the sets.
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            /// Is the union-find devoid of elements?
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// Reports the heap memory used by the union-find.
+            pub fn memory_usage(&self) -> MemoryUsage {
+                MemoryUsage::new()
+                    .array::<$atomic>("entries", self.0.len(), self.0.len())
+            }
+
+            /// Joins the sets of the two given elements.
+            ///
+            /// Returns whether anything changed. That is, if the sets were
+            /// different, it returns `true`, but if they were already the same
+            /// then it returns `false`.
+            pub fn union(&self, a: $word, b: $word) -> bool {
+                AtomicLinking::union(self, a.to_usize(), b.to_usize())
+            }
+
+            /// Joins the sets of the two given elements, checking that they are
+            /// in bounds.
+            ///
+            /// Like [`union`](#method.union), but returns an error instead of
+            /// panicking if either element is out of bounds.
+            pub fn try_union(&self, a: $word, b: $word) -> Result<bool, Error> {
+                AtomicLinking::try_union(self, a.to_usize(), b.to_usize())
+            }
+
+            /// Finds the representative element for the given element’s set.
+            pub fn find(&self, element: $word) -> $word {
+                Self::element(AtomicForest::find(self, element.to_usize()))
+            }
+
+            /// Finds the representative element for the given element’s set,
+            /// checking that it is in bounds.
+            ///
+            /// Like [`find`](#method.find), but returns an error instead of
+            /// panicking if the element is out of bounds.
+            pub fn try_find(&self, element: $word) -> Result<$word, Error> {
+                AtomicForest::try_find(self, element.to_usize()).map(Self::element)
+            }
+
+            /// Determines whether two elements are in the same set.
+            pub fn equiv(&self, a: $word, b: $word) -> bool {
+                AtomicForest::equiv(self, a.to_usize(), b.to_usize())
+            }
+
+            /// Returns a vector of set representatives.
+            pub fn to_vec(&self) -> Vec<$word> {
+                (0 .. self.len())
+                    .map(|i| Self::element(AtomicForest::find(self, i)))
+                    .collect()
+            }
+
+            // HELPERS
+
+            // Every element in bounds fits in `$word`.
+            fn element(element: usize) -> $word {
+                $word::from_usize(element).unwrap()
+            }
+
+            fn pack(parent: $word, rank: $word) -> $word {
+                parent << RANK_BITS | rank
+            }
+
+            fn parent_of(word: $word) -> $word {
+                word >> RANK_BITS
+            }
+
+            fn rank_of(word: $word) -> $word {
+                word & Self::RANK_MASK
+            }
+
+            fn load(&self, element: usize) -> $word {
+                self.0[element].load(Ordering::SeqCst)
+            }
+
+            fn cas(&self, element: usize, old: $word, new: $word) -> bool {
+                self.0[element]
+                    .compare_exchange(old, new, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            }
+        }
+
+        impl AtomicForest for $name {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            fn parent(&self, element: usize) -> usize {
+                Self::parent_of(self.load(element)).to_usize()
+            }
+
+            // Non-roots’ ranks never change, so this only fails if another
+            // thread has compressed the path already.
+            fn change_parent(&self, element: usize, old_parent: usize, new_parent: usize)
+                             -> bool {
+                let word = self.load(element);
+                Self::parent_of(word).to_usize() == old_parent
+                    && self.cas(element, word,
+                                Self::pack(Self::element(new_parent), Self::rank_of(word)))
+            }
+        }
+
+        // A link is a single compare-and-swap of the absorbed root’s whole
+        // word, which fails if its rank has changed, and a root’s rank
+        // only grows, so no two links can form a cycle.
+        impl AtomicLinking for $name {
+            type Rank = $word;
+
+            fn rank(&self, root: usize) -> $word {
+                Self::rank_of(self.load(root))
+            }
+
+            fn link(&self, absorbed: usize, rank: $word, root: usize, root_rank: $word)
+                    -> bool {
+                let (absorbed_id, root_id) = (Self::element(absorbed), Self::element(root));
+                if !self.cas(absorbed, Self::pack(absorbed_id, rank), Self::pack(root_id, rank)) {
+                    return false;
+                }
+
+                // If `root` has changed since, it has a new parent or rank
+                // already, and either way this increment is moot.
+                if rank == root_rank {
+                    self.cas(root, Self::pack(root_id, root_rank),
+                             Self::pack(root_id, root_rank + 1));
+                }
+
+                true
+            }
+        }
+    }
+}
+
+packed_union_find! {
+    /// Lock-free, concurrent union-find with one word per element.
+    ///
+    /// This is like [`AUnionFind`](struct.AUnionFind.html), but each
+    /// element’s parent and rank share a single `AtomicUsize`, with the rank
+    /// in the low 6 bits. That halves the memory, and linking a root takes a
+    /// single compare-and-swap that also checks that its rank has not changed,
+    /// with no separate read of the rank. The cost is that elements are
+    /// limited to the remaining bits, which on 64-bit platforms is still
+    /// 2<sup>58</sup>.
+    ///
+    /// Run `cargo bench --bench concurrent` to compare this against
+    /// `AUnionFind` and [`AUnionFind32`](struct.AUnionFind32.html). The packed
+    /// layout stands to gain the most when the union-find is too big for the
+    /// cache, since each step of `find` then touches one word instead of two,
+    /// but how much depends on the machine, so measure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::PackedAUnionFind;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let uf = Arc::new(PackedAUnionFind::new(100));
+    ///
+    /// let handles: Vec<_> = (0 .. 4).map(|t| {
+    ///     let uf = uf.clone();
+    ///     thread::spawn(move || {
+    ///         for i in (t .. 99).step_by(4) {
+    ///             uf.union(i, i + 1);
+    ///         }
+    ///     })
+    /// }).collect();
+    ///
+    /// for handle in handles {
+    ///     handle.join().unwrap();
+    /// }
+    ///
+    /// assert!(uf.equiv(0, 99));
+    /// ```
+    pub struct PackedAUnionFind(AtomicUsize, usize);
+}
+
+packed_union_find! {
+    /// Lock-free, concurrent union-find packed into 32 bits per element.
+    ///
+    /// This is like [`PackedAUnionFind`](struct.PackedAUnionFind.html), but
+    /// each element’s parent and rank share a single `AtomicU32`, which takes
+    /// a quarter of the memory of [`AUnionFind`](struct.AUnionFind.html)’s
+    /// two `AtomicUsize`s on 64-bit platforms. The parent gets 26 bits, so
+    /// there can be at most 2<sup>26</sup> elements, and the rank gets the
+    /// remaining 6, which is enough for any rank reachable with that many
+    /// elements. Since parent and rank change together, linking a root also
+    /// checks that its rank has not changed in the meantime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::AUnionFind32;
+    /// use std::sync::Arc;
+    /// use std::thread;
+    ///
+    /// let uf = Arc::new(AUnionFind32::new(100));
+    ///
+    /// let handles: Vec<_> = (0 .. 4).map(|t| {
+    ///     let uf = uf.clone();
+    ///     thread::spawn(move || {
+    ///         for i in (t .. 99).step_by(4) {
+    ///             uf.union(i, i + 1);
+    ///         }
+    ///     })
+    /// }).collect();
+    ///
+    /// for handle in handles {
+    ///     handle.join().unwrap();
+    /// }
+    ///
+    /// assert!(uf.equiv(0, 99));
+    /// ```
+    pub struct AUnionFind32(AtomicU32, u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    macro_rules! packed_tests {
+        ($module:ident, $name:ident, $word:ident) => {
+            mod $module {
+                use super::*;
+
+                #[test]
+                fn unions() {
+                    let uf = $name::new(8);
+                    assert!(uf.union(0, 1));
+                    assert!(uf.union(2, 3));
+                    assert!(uf.union(1, 3));
+                    assert!(!uf.union(0, 2));
+                    assert!(uf.equiv(0, 3));
+                    assert!(!uf.equiv(0, 4));
+                    assert_eq!(uf.to_vec(), vec![3, 3, 3, 3, 4, 5, 6, 7]);
+                    assert_eq!($name::rank_of(uf.load(3)), 2);
+                }
+
+                #[test]
+                fn mirrored_unions() {
+                    const PAIRS: $word = 100_000;
+
+                    let uf = Arc::new($name::new(2 * PAIRS as usize));
+                    let handles: Vec<_> = (0 .. 4).map(|t| {
+                        let uf = uf.clone();
+                        thread::spawn(move || {
+                            for i in 0 .. PAIRS {
+                                let (a, b) = (2 * i, 2 * i + 1);
+                                if t % 2 == 0 { uf.union(a, b); } else { uf.union(b, a); }
+                            }
+                        })
+                    }).collect();
+
+                    for handle in handles {
+                        handle.join().unwrap();
+                    }
+
+                    // Linking each root beneath the other would leave no
+                    // root.
+                    let is_root = |i: $word| $name::parent_of(uf.load(i.to_usize())) == i;
+                    for i in 0 .. PAIRS {
+                        assert!(is_root(2 * i) != is_root(2 * i + 1));
+                    }
+                }
+
+                #[test]
+                fn bounds() {
+                    assert_eq!($name::try_new($name::MAX_LEN + 1).err(),
+                               Some(Error::Overflow));
+
+                    let uf = $name::new(2);
+                    assert_eq!(uf.try_union(0, 2),
+                               Err(Error::OutOfBounds { element: 2, len: 2 }));
+                    assert_eq!(uf.try_find(1), Ok(1));
+                    assert_eq!(uf.memory_usage().bytes(),
+                               2 * std::mem::size_of::<$word>());
+                }
+
+                #[test]
+                fn largest_element() {
+                    let last = ($name::MAX_LEN - 1) as $word;
+                    let word = $name::pack(last, $name::RANK_MASK);
+                    assert_eq!($name::parent_of(word), last);
+                    assert_eq!($name::rank_of(word), $name::RANK_MASK);
+                }
+            }
+        }
+    }
+
+    packed_tests!(packed, PackedAUnionFind, usize);
+    packed_tests!(packed32, AUnionFind32, u32);
+}
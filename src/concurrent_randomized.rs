@@ -0,0 +1,251 @@
+use std::fmt::{self, Debug};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{Error, MemoryUsage};
+use concurrent_forest::{AtomicForest, AtomicLinking};
+use randomized::splitmix64;
+
+/// Lock-free, concurrent union-find with proven complexity bounds.
+///
+/// This implements the randomized concurrent union-find of Jayanti and
+/// Tarjan (“Concurrent Disjoint Set Union”, 2016/2021). As in
+/// [`RandomizedUnionFind`](struct.RandomizedUnionFind.html), each element
+/// has a pseudo-random priority determined by its index and a seed, and
+/// [`union`](#method.union) links the root with the lower priority
+/// beneath the other. [`find`](#method.find) compacts paths by splitting,
+/// pointing each node it visits at its grandparent with a single
+/// compare-and-swap.
+///
+/// Unlike with [`AUnionFind`](struct.AUnionFind.html), whose ranks can
+/// go stale under contention, the analysis holds for any interleaving:
+/// with `p` threads performing `m` operations on `n` elements, the
+/// expected total work is
+/// O(*m* · (α(*n*, *m*/(*np*)) + log(*np*/*m* + 1))), provided the seed is
+/// chosen independently of the operations. With one thread, that is the
+/// usual near-constant amortized time per operation.
+///
+/// Like `AUnionFind`, this is lock-free rather than wait-free: a thread
+/// can retry a union indefinitely only while other threads are making
+/// progress.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::RandomizedAUnionFind;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let uf = Arc::new(RandomizedAUnionFind::new(100, 0x5eed));
+///
+/// let handles: Vec<_> = (0 .. 4).map(|t| {
+///     let uf = uf.clone();
+///     thread::spawn(move || {
+///         for i in (t .. 99).step_by(4) {
+///             uf.union(i, i + 1);
+///         }
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert!(uf.equiv(0, 99));
+/// ```
+pub struct RandomizedAUnionFind {
+    parents: Box<[AtomicUsize]>,
+    seed:    u64,
+}
+
+impl Clone for RandomizedAUnionFind {
+    fn clone(&self) -> Self {
+        RandomizedAUnionFind {
+            parents: self.parents.iter()
+                .map(|parent| AtomicUsize::new(parent.load(Ordering::Acquire)))
+                .collect(),
+            seed:    self.seed,
+        }
+    }
+}
+
+impl Debug for RandomizedAUnionFind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "RandomizedAUnionFind(")?;
+        formatter.debug_list().entries(self.parents.iter()).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl RandomizedAUnionFind {
+    /// Creates a new asynchronous union-find of `size` elements, with
+    /// priorities determined by `seed`.
+    pub fn new(size: usize, seed: u64) -> Self {
+        RandomizedAUnionFind {
+            parents: (0 .. size).map(AtomicUsize::new).collect(),
+            seed,
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<AtomicUsize>("parents", self.parents.len(), self.parents.len())
+    }
+
+    /// The seed that determines the priorities.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The priority of the given element.
+    pub fn priority(&self, element: usize) -> u64 {
+        splitmix64(splitmix64(self.seed) ^ element as u64)
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&self, a: usize, b: usize) -> bool {
+        AtomicLinking::union(self, a, b)
+    }
+
+    /// Joins the sets of the two given elements, checking that they are
+    /// in bounds.
+    ///
+    /// Like [`union`](#method.union), but returns an error instead of
+    /// panicking if either element is out of bounds.
+    pub fn try_union(&self, a: usize, b: usize) -> Result<bool, Error> {
+        AtomicLinking::try_union(self, a, b)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    pub fn find(&self, element: usize) -> usize {
+        AtomicForest::find(self, element)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        AtomicForest::try_find(self, element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        AtomicForest::equiv(self, a, b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<usize> {
+        (0 .. self.len()).map(|i| self.find(i)).collect()
+    }
+}
+
+// The orderings follow those of `AUnionFind`; see the comment there.
+
+impl AtomicForest for RandomizedAUnionFind {
+    fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    fn parent(&self, element: usize) -> usize {
+        self.parents[element].load(Ordering::Acquire)
+    }
+
+    fn change_parent(&self,
+                     element: usize,
+                     old_parent: usize,
+                     new_parent: usize)
+                     -> bool {
+        self.parents[element].compare_exchange_weak(old_parent,
+                                                    new_parent,
+                                                    Ordering::AcqRel,
+                                                    Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+// Priorities serve as ranks that never change, so ties are broken by
+// index, as in `RandomizedUnionFind`, and linking needs no freezing.
+impl AtomicLinking for RandomizedAUnionFind {
+    type Rank = u64;
+
+    fn rank(&self, element: usize) -> u64 {
+        self.priority(element)
+    }
+
+    fn link(&self, absorbed: usize, _: u64, root: usize, _: u64) -> bool {
+        self.parents[absorbed].compare_exchange(absorbed, root,
+                                                Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {RandomizedUnionFind, UnionFind};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn links_like_sequential() {
+        let concurrent = RandomizedAUnionFind::new(16, 7);
+        let mut sequential = RandomizedUnionFind::<usize>::new(16, 7);
+
+        for &(a, b) in &[(0, 1), (2, 3), (1, 3), (5, 6), (15, 0), (6, 9)] {
+            assert_eq!(concurrent.union(a, b), sequential.union(a, b));
+        }
+
+        assert_eq!(concurrent.to_vec(), sequential.to_vec());
+        assert_eq!(concurrent.priority(3), sequential.priority(3));
+    }
+
+    #[test]
+    fn concurrent_unions() {
+        const THREADS: usize = 4;
+        const SIZE: usize = 1 << 12;
+
+        let uf = Arc::new(RandomizedAUnionFind::new(SIZE, 99));
+        let mut expected = UnionFind::<usize>::new(SIZE);
+        let edges: Vec<_> = (0 .. SIZE)
+            .map(|i| (i, (splitmix64(i as u64) % SIZE as u64) as usize))
+            .collect();
+        for &(a, b) in &edges {
+            expected.union(a, b);
+        }
+
+        let edges = Arc::new(edges);
+        let handles: Vec<_> = (0 .. THREADS).map(|t| {
+            let (uf, edges) = (uf.clone(), edges.clone());
+            thread::spawn(move || {
+                for &(a, b) in edges.iter().skip(t).step_by(THREADS) {
+                    uf.union(a, b);
+                }
+            })
+        }).collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for i in 0 .. SIZE {
+            assert_eq!(uf.equiv(i, 0), expected.equiv(i, 0));
+        }
+        assert_eq!(uf.try_find(SIZE), Err(Error::OutOfBounds { element: SIZE, len: SIZE }));
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt::{self, Debug};
+use std::mem;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::Error;
+use concurrent_forest::{AtomicForest, AtomicLinking};
+
+/// Lock-free, concurrent union-find in a caller-provided memory region,
+/// such as a segment of memory shared between processes.
+///
+/// This is [`AUnionFind`](struct.AUnionFind.html)’s algorithm over a
+/// fixed `repr(C)` layout that holds no pointers and needs no allocation,
+/// so several processes that map the same region can operate on one
+/// union-find concurrently. One process lays out the region with
+/// [`init`](#method.init), and the others, having learned that it has,
+/// open it with [`attach`](#method.attach).
+///
+/// # Layout
+///
+/// The region consists of native-endian words of `usize`’s width *w*,
+/// so every process must run on the same kind of machine:
+///
+/// | offset              | size | contents                                   |
+/// | :------------------ | :--- | :----------------------------------------- |
+/// | 0                   | *w*  | the magic number `0x6473_6175`             |
+/// | *w*                 | *w*  | the number of elements *n*                 |
+/// | 2*w*                | *w*  | the number of sets                         |
+/// | (3 + 2*i*)*w*       | *w*  | the parent of element *i*, for each *i* < *n* |
+/// | (4 + 2*i*)*w*       | *w*  | the rank of element *i*, whose top bit marks it final |
+///
+/// Operations check every index they follow, so a region corrupted by a
+/// misbehaving process can make them panic, loop, or answer wrongly, but
+/// cannot make them access memory outside the region.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::SharedAUnionFind;
+///
+/// // A stand-in for a shared-memory segment, aligned for `usize`.
+/// let size = SharedAUnionFind::required_size(8).unwrap();
+/// let mut region = vec![0usize; size / std::mem::size_of::<usize>()];
+/// let ptr = region.as_mut_ptr() as *mut u8;
+///
+/// // SAFETY: The region is valid and aligned, and outlives both views.
+/// let creator = unsafe { SharedAUnionFind::init(ptr, size, 8).unwrap() };
+/// let other   = unsafe { SharedAUnionFind::attach(ptr, size).unwrap() };
+///
+/// creator.union(0, 1);
+/// other.union(1, 2);
+/// assert!(creator.equiv(0, 2));
+/// assert_eq!(other.count_sets(), 6);
+/// ```
+#[derive(Clone, Copy)]
+pub struct SharedAUnionFind<'a> {
+    header:  &'a Header,
+    entries: &'a [Entry],
+}
+
+#[repr(C)]
+struct Header {
+    magic: AtomicUsize,
+    len:   AtomicUsize,
+    sets:  AtomicUsize,
+}
+
+#[repr(C)]
+struct Entry {
+    parent: AtomicUsize,
+    rank:   AtomicUsize,
+}
+
+const MAGIC: usize = 0x6473_6175;
+
+// Set in a rank once it may no longer change.
+const FROZEN: usize = !(!0 >> 1);
+
+impl<'a> Debug for SharedAUnionFind<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "SharedAUnionFind(")?;
+        formatter.debug_list()
+            .entries(self.entries.iter().map(|entry| &entry.parent)).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<'a> SharedAUnionFind<'a> {
+    /// The alignment that a region requires, which is that of `usize`.
+    pub const ALIGN: usize = mem::align_of::<AtomicUsize>();
+
+    /// The size in bytes of the region for a union-find of `len`
+    /// elements, or `None` if it would overflow.
+    pub fn required_size(len: usize) -> Option<usize> {
+        len.checked_mul(mem::size_of::<Entry>())?
+            .checked_add(mem::size_of::<Header>())
+            .filter(|&size| size <= isize::MAX as usize)
+    }
+
+    /// Lays out a union-find of `len` singleton sets at the start of
+    /// the given region, and returns a view of it.
+    ///
+    /// The region’s previous contents are overwritten. Other processes
+    /// must not attach until this returns; if one tries, it may get
+    /// `Err(Error::InvalidFormat)`, and should try again later.
+    ///
+    /// Returns `Err(Error::Overflow)` if the region is smaller than
+    /// [`required_size(len)`](#method.required_size), and
+    /// `Err(Error::InvalidFormat)` if it is not aligned to
+    /// [`ALIGN`](#associatedconstant.ALIGN).
+    ///
+    /// # Safety
+    ///
+    /// The `size` bytes at `region` must be valid for reads and writes
+    /// for the lifetime `'a`, and, while `'a` lasts, must be accessed,
+    /// by this or any other process, only through `SharedAUnionFind`s.
+    pub unsafe fn init(region: *mut u8, size: usize, len: usize)
+                       -> Result<Self, Error> {
+        if region.align_offset(Self::ALIGN) != 0 {
+            return Err(Error::InvalidFormat);
+        }
+        match Self::required_size(len) {
+            Some(required) if required <= size => (),
+            _ => return Err(Error::Overflow),
+        }
+
+        let result = Self::view(region, len);
+        result.header.magic.store(0, Ordering::Relaxed);
+        result.header.len.store(len, Ordering::Relaxed);
+        result.header.sets.store(len, Ordering::Relaxed);
+        for (i, entry) in result.entries.iter().enumerate() {
+            entry.parent.store(i, Ordering::Relaxed);
+            entry.rank.store(0, Ordering::Relaxed);
+        }
+        // Publishes the entries to processes that see the magic number.
+        result.header.magic.store(MAGIC, Ordering::Release);
+
+        Ok(result)
+    }
+
+    /// Returns a view of a union-find that [`init`](#method.init) has
+    /// laid out at the start of the given region.
+    ///
+    /// Returns `Err(Error::InvalidFormat)` if the region is not aligned,
+    /// does not start with the magic number, or is too small for the
+    /// number of elements in its header.
+    ///
+    /// # Safety
+    ///
+    /// As for [`init`](#method.init).
+    pub unsafe fn attach(region: *mut u8, size: usize) -> Result<Self, Error> {
+        if region.align_offset(Self::ALIGN) != 0 || size < mem::size_of::<Header>() {
+            return Err(Error::InvalidFormat);
+        }
+
+        let header = &*(region as *const Header);
+        if header.magic.load(Ordering::Acquire) != MAGIC {
+            return Err(Error::InvalidFormat);
+        }
+
+        let len = header.len.load(Ordering::Relaxed);
+        match Self::required_size(len) {
+            Some(required) if required <= size => Ok(Self::view(region, len)),
+            _ => Err(Error::InvalidFormat),
+        }
+    }
+
+    // PRECONDITION: The region is aligned and large enough for `len`
+    // elements, and valid as `init` requires.
+    unsafe fn view(region: *mut u8, len: usize) -> Self {
+        let entries = region.add(mem::size_of::<Header>()) as *const Entry;
+        SharedAUnionFind {
+            header:  &*(region as *const Header),
+            entries: slice::from_raw_parts(entries, len),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The number of sets.
+    ///
+    /// As with [`AUnionFind::count_sets`](struct.AUnionFind.html#method.count_sets),
+    /// this may lag behind links that concurrent unions have already
+    /// made.
+    pub fn count_sets(&self) -> usize {
+        self.header.sets.load(Ordering::Relaxed)
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn union(&self, a: usize, b: usize) -> bool {
+        AtomicLinking::union(self, a, b)
+    }
+
+    /// Joins the sets of the two given elements, checking that they are
+    /// in bounds.
+    ///
+    /// Like [`union`](#method.union), but returns an error instead of
+    /// panicking if either element is out of bounds.
+    pub fn try_union(&self, a: usize, b: usize) -> Result<bool, Error> {
+        AtomicLinking::try_union(self, a, b)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: usize) -> usize {
+        AtomicForest::find(self, element)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        AtomicForest::try_find(self, element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        AtomicForest::equiv(self, a, b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<usize> {
+        (0 .. self.len()).map(|i| self.find(i)).collect()
+    }
+}
+
+// The orderings follow those of `AUnionFind`; see the comment there.
+// They hold between processes as between threads, since the atomics are
+// lock-free.
+
+impl<'a> AtomicForest for SharedAUnionFind<'a> {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn parent(&self, element: usize) -> usize {
+        self.entries[element].parent.load(Ordering::Acquire)
+    }
+
+    fn change_parent(&self,
+                     element: usize,
+                     old_parent: usize,
+                     new_parent: usize)
+                     -> bool {
+        self.entries[element].parent.compare_exchange_weak(old_parent,
+                                                           new_parent,
+                                                           Ordering::AcqRel,
+                                                           Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+// As in `AUnionFind`, the absorbed root’s rank is frozen before it is
+// linked.
+impl<'a> AtomicLinking for SharedAUnionFind<'a> {
+    type Rank = usize;
+
+    fn rank(&self, element: usize) -> usize {
+        self.entries[element].rank.load(Ordering::Relaxed) & !FROZEN
+    }
+
+    fn link(&self, absorbed: usize, rank: usize, root: usize, root_rank: usize) -> bool {
+        let entry = &self.entries[absorbed];
+
+        match entry.rank.compare_exchange(rank, rank | FROZEN,
+                                          Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => (),
+            Err(word) if word == rank | FROZEN => (),
+            Err(_) => return false,
+        }
+
+        // If this fails, another union has linked `absorbed`, which it
+        // froze at this same rank, so the rank stays frozen.
+        if entry.parent.compare_exchange(absorbed, root,
+                                         Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return false;
+        }
+
+        if rank == root_rank {
+            let _ = self.entries[root].rank.compare_exchange(
+                root_rank, root_rank + 1, Ordering::Relaxed, Ordering::Relaxed);
+        }
+
+        self.header.sets.fetch_sub(1, Ordering::Relaxed);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn region(len: usize) -> Vec<usize> {
+        vec![0; SharedAUnionFind::required_size(len).unwrap() / mem::size_of::<usize>()]
+    }
+
+    #[test]
+    fn init_and_attach() {
+        let mut words = region(4);
+        let (ptr, size) = (words.as_mut_ptr() as *mut u8, words.len() * mem::size_of::<usize>());
+
+        unsafe {
+            assert_eq!(SharedAUnionFind::attach(ptr, size).unwrap_err(), Error::InvalidFormat);
+            assert_eq!(SharedAUnionFind::init(ptr, size, 5).unwrap_err(), Error::Overflow);
+            assert_eq!(SharedAUnionFind::init(ptr.add(1), size - 1, 1).unwrap_err(),
+                       Error::InvalidFormat);
+
+            let uf = SharedAUnionFind::init(ptr, size, 4).unwrap();
+            assert!(uf.union(0, 1));
+            assert!(uf.union(2, 3));
+            assert_eq!(uf.try_union(4, 0), Err(Error::OutOfBounds { element: 4, len: 4 }));
+
+            assert_eq!(SharedAUnionFind::attach(ptr, size - 1).unwrap_err(),
+                       Error::InvalidFormat);
+            let other = SharedAUnionFind::attach(ptr, size).unwrap();
+            assert_eq!(other.len(), 4);
+            assert_eq!(other.to_vec(), vec![1, 1, 3, 3]);
+            assert_eq!(other.count_sets(), 2);
+        }
+    }
+
+    #[test]
+    fn concurrent_views() {
+        const THREADS: usize = 4;
+        const SIZE: usize = 1 << 10;
+
+        let mut words = region(SIZE);
+        let (ptr, size) = (words.as_mut_ptr() as *mut u8, words.len() * mem::size_of::<usize>());
+        let uf = unsafe { SharedAUnionFind::init(ptr, size, SIZE).unwrap() };
+        // Each thread attaches its own view, as another process would.
+        let address = ptr as usize;
+
+        thread::scope(|scope| {
+            for t in 0 .. THREADS {
+                scope.spawn(move || {
+                    let view = unsafe {
+                        SharedAUnionFind::attach(address as *mut u8, size).unwrap()
+                    };
+                    for i in (t .. SIZE - 1).step_by(THREADS) {
+                        view.union(i, i + 1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(uf.count_sets(), 1);
+        assert!((0 .. SIZE).all(|i| uf.equiv(i, 0)));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::mpsc::Sender;
+use std::sync::{Mutex, MutexGuard};
+#[cfg(not(all(test, loom)))]
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+#[cfg(all(test, loom))]
+use loom::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// A notice that a set watched with
+/// [`AUnionFind::subscribe`](struct.AUnionFind.html#method.subscribe)
+/// has been merged with another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Merged {
+    /// The root of the joined set, as of the union.
+    pub root: usize,
+    /// The former root that was linked beneath `root`.
+    pub absorbed: usize,
+}
+
+type Roots = Mutex<HashMap<usize, Vec<Sender<Merged>>>>;
+
+// The subscriptions of an `AUnionFind`, keyed by the root of the watched
+// set. Each subscription is notified once and then dropped. The map is
+// allocated by the first subscription, so until then a union pays only
+// for loading `count`.
+#[derive(Debug, Default)]
+pub(crate) struct Watchers {
+    count: AtomicUsize,
+    roots: AtomicPtr<Roots>,
+}
+// Invariants: self.count is the number of senders in self.roots, though
+// it may briefly run ahead of them while one is added; self.roots, once
+// set, is never changed until drop.
+
+impl Watchers {
+    // A subscriber adds itself and then checks whether its root is still
+    // a root, while a union links a root and then checks `count`. Plain
+    // loads could each miss the other’s write, so the subscriber makes
+    // its check with a read-modify-write of the root’s parent that
+    // leaves it unchanged, with `AcqRel`, after adding itself. The link
+    // is a read-modify-write of the same word, so one of the two comes
+    // first in its modification order:
+    //
+    //   - If the link does, the check reads its new parent, and the
+    //     subscriber notifies itself.
+    //
+    //   - If the check does, the link reads from it, and so
+    //     synchronizes with the subscriber. The union’s `Acquire` load
+    //     of `count` then sees the subscription, and the union notifies.
+    //
+    // So no fence is needed on either side. A union that makes the
+    // watched root absorb another changes only the other’s parent, and
+    // may miss a concurrent subscription; that merge then counts as one
+    // made before the subscription.
+
+    pub(crate) fn add(&self, root: usize, sender: Sender<Merged>) {
+        self.lock_or_allocate().entry(root).or_default().push(sender);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Called by a union after it links `absorbed` beneath `root`.
+    pub(crate) fn linked(&self, root: usize, absorbed: usize) {
+        if self.count.load(Ordering::Acquire) > 0 {
+            self.notify(&[root, absorbed], Merged { root, absorbed });
+        }
+    }
+
+    // Notifies and drops the subscriptions of the given roots. Whichever
+    // of a union and a subscriber gets here first takes the senders, so
+    // each is notified once.
+    pub(crate) fn notify(&self, roots: &[usize], merged: Merged) {
+        let mut senders = Vec::new();
+        if let Some(mut map) = self.lock() {
+            for root in roots {
+                senders.extend(map.remove(root).into_iter().flatten());
+            }
+        }
+
+        self.count.fetch_sub(senders.len(), Ordering::Relaxed);
+        for sender in senders {
+            // The receiver may have lost interest, which is fine.
+            let _ = sender.send(merged);
+        }
+    }
+
+    // A panic while the lock is held leaves the map consistent, so
+    // poisoning is ignored. Returns `None` if nothing has subscribed.
+    fn lock(&self) -> Option<MutexGuard<'_, HashMap<usize, Vec<Sender<Merged>>>>> {
+        let roots = self.roots.load(Ordering::Acquire);
+        if roots.is_null() { return None; }
+
+        // Safe because the map lives as long as `self`.
+        let roots = unsafe { &*roots };
+        Some(roots.lock().unwrap_or_else(|error| error.into_inner()))
+    }
+
+    // Racing subscribers may each allocate a map; the loser frees its own.
+    fn lock_or_allocate(&self) -> MutexGuard<'_, HashMap<usize, Vec<Sender<Merged>>>> {
+        if let Some(map) = self.lock() { return map; }
+
+        let fresh = Box::into_raw(Box::new(Roots::default()));
+        if self.roots.compare_exchange(ptr::null_mut(), fresh,
+                                       Ordering::AcqRel, Ordering::Acquire).is_err() {
+            drop(unsafe { Box::from_raw(fresh) });
+        }
+
+        self.lock().expect("Watchers::lock_or_allocate: map installed")
+    }
+}
+
+impl Drop for Watchers {
+    fn drop(&mut self) {
+        let roots = self.roots.load(Ordering::Acquire);
+        if !roots.is_null() {
+            drop(unsafe { Box::from_raw(roots) });
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+
+use super::{ElementType, UnionFind};
+
+/// A collection that can absorb another of its kind, for use as a per-set
+/// container in a [`ContainerUnionFind`](struct.ContainerUnionFind.html).
+pub trait Container {
+    /// The number of items in the container, which decides which of two
+    /// containers absorbs the other.
+    fn len(&self) -> usize;
+
+    /// Is the container empty?
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Moves the items of `other` into `self`.
+    fn absorb(&mut self, other: Self);
+}
+
+impl<T> Container for Vec<T> {
+    fn len(&self) -> usize { Vec::len(self) }
+    fn absorb(&mut self, other: Self) { self.extend(other) }
+}
+
+impl<T> Container for VecDeque<T> {
+    fn len(&self) -> usize { VecDeque::len(self) }
+    fn absorb(&mut self, other: Self) { self.extend(other) }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> Container for HashSet<T, S> {
+    fn len(&self) -> usize { HashSet::len(self) }
+    fn absorb(&mut self, other: Self) { self.extend(other) }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> Container for HashMap<K, V, S> {
+    fn len(&self) -> usize { HashMap::len(self) }
+    fn absorb(&mut self, other: Self) { self.extend(other) }
+}
+
+impl<T: Ord> Container for BTreeSet<T> {
+    fn len(&self) -> usize { BTreeSet::len(self) }
+    fn absorb(&mut self, other: Self) { self.extend(other) }
+}
+
+impl<K: Ord, V> Container for BTreeMap<K, V> {
+    fn len(&self) -> usize { BTreeMap::len(self) }
+    fn absorb(&mut self, other: Self) { self.extend(other) }
+}
+
+/// Union-find that keeps a container for each set, merging them smaller
+/// into larger.
+///
+/// When two sets are joined, the container with fewer items is moved into
+/// the other, so each item moves at most O(log *n*) times over any
+/// sequence of unions. Merging in a fixed direction instead, which is
+/// easy to do by accident, takes quadratic time in the worst case.
+///
+/// Note that for a map, an item of the smaller container replaces an item
+/// of the larger container with the same key.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::ContainerUnionFind;
+/// use std::collections::BTreeSet;
+///
+/// let tags = vec!["red", "blue", "red", "green"];
+/// let mut uf = ContainerUnionFind::<BTreeSet<&str>>::new(
+///     tags.into_iter().map(|tag| Some(tag).into_iter().collect()).collect());
+///
+/// uf.union(0, 1);
+/// uf.union(1, 2);
+///
+/// let tags: Vec<_> = uf.container_of(2).iter().cloned().collect();
+/// assert_eq!(tags, ["blue", "red"]);
+/// assert_eq!(uf.container_of(3).len(), 1);
+/// ```
+#[derive(Clone)]
+pub struct ContainerUnionFind<C, Element: ElementType = usize> {
+    union_find: UnionFind<Element>,
+    // The container for each set, at the index of its representative.
+    containers: Vec<Option<C>>,
+}
+
+impl<C: Debug, Element: ElementType> Debug for ContainerUnionFind<C, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "ContainerUnionFind(")?;
+        formatter.debug_map()
+            .entries(self.containers.iter().enumerate().filter_map(|(i, container)| {
+                container.as_ref().map(|container| (i, container))
+            }))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<C, Element: ElementType> Default for ContainerUnionFind<C, Element> {
+    fn default() -> Self {
+        ContainerUnionFind {
+            union_find: UnionFind::default(),
+            containers: Vec::new(),
+        }
+    }
+}
+
+impl<C: Container, Element: ElementType> ContainerUnionFind<C, Element> {
+    /// Creates a new union-find with an element in a singleton set for
+    /// each of the given containers.
+    ///
+    /// # Panics
+    ///
+    /// If the number of containers would overflow the element type
+    /// `Element`.
+    pub fn new(containers: Vec<C>) -> Self {
+        ContainerUnionFind {
+            union_find: UnionFind::new(containers.len()),
+            containers: containers.into_iter().map(Some).collect(),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.union_find.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.union_find.is_empty()
+    }
+
+    /// Creates a new element in a singleton set, with the given
+    /// container.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self, container: C) -> Element {
+        self.containers.push(Some(container));
+        self.union_find.alloc()
+    }
+
+    /// Joins the sets of the two given elements, merging their
+    /// containers.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if !self.union_find.union(a, b) { return false; }
+
+        let mut larger = self.containers[a.to_usize()].take().unwrap();
+        let mut smaller = self.containers[b.to_usize()].take().unwrap();
+        if larger.len() < smaller.len() {
+            mem::swap(&mut larger, &mut smaller);
+        }
+        larger.absorb(smaller);
+
+        let root = self.find(a);
+        self.containers[root.to_usize()] = Some(larger);
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: Element) -> Element {
+        self.union_find.find(element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.union_find.equiv(a, b)
+    }
+
+    /// The container of the given element’s set.
+    pub fn container_of(&self, element: Element) -> &C {
+        let root = self.find(element);
+        self.containers[root.to_usize()].as_ref().unwrap()
+    }
+
+    /// The container of the given element’s set, mutably.
+    pub fn container_of_mut(&mut self, element: Element) -> &mut C {
+        let root = self.find(element);
+        self.containers[root.to_usize()].as_mut().unwrap()
+    }
+
+    /// Consumes the union-find, returning each set’s representative with
+    /// its container.
+    pub fn into_containers(self) -> Vec<(Element, C)> {
+        self.containers.into_iter().enumerate()
+            .filter_map(|(i, container)| {
+                container.map(|container| (Element::from_usize(i).unwrap(), container))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // Counts the items moved by each merge.
+    struct Counted(Vec<usize>, Rc<Cell<usize>>);
+
+    impl Container for Counted {
+        fn len(&self) -> usize { self.0.len() }
+        fn absorb(&mut self, other: Self) {
+            self.1.set(self.1.get() + other.0.len());
+            self.0.extend(other.0);
+        }
+    }
+
+    #[test]
+    fn smaller_into_larger() {
+        const SIZE: usize = 1 << 12;
+        let moved = Rc::new(Cell::new(0));
+
+        let mut uf = ContainerUnionFind::<Counted>::new(
+            (0 .. SIZE).map(|i| Counted(vec![i], moved.clone())).collect());
+
+        // Always naming the growing set first would be quadratic if merged
+        // in a fixed direction.
+        for i in 1 .. SIZE {
+            uf.union(0, i);
+        }
+
+        assert_eq!(uf.container_of(SIZE - 1).len(), SIZE);
+        assert_eq!(moved.get(), SIZE - 1);
+    }
+
+    #[test]
+    fn into_containers() {
+        let mut uf = ContainerUnionFind::<Vec<u8>, u8>::new(vec![vec![1], vec![2], vec![3]]);
+        uf.union(2, 0);
+        uf.container_of_mut(1).push(4);
+        let e = uf.alloc(vec![5]);
+        assert!(!uf.union(0, 2));
+
+        let mut sets: Vec<_> = uf.into_containers().into_iter()
+            .map(|(_, mut items)| { items.sort(); items })
+            .collect();
+        sets.sort();
+        assert_eq!(sets, vec![vec![1, 3], vec![2, 4], vec![e + 2]]);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+use std::mem;
+
+use super::{ElementType, MemoryUsage, UnionFind};
+
+/// Union-find with constant-time, copy-on-write snapshots.
+///
+/// The elements are stored in fixed-size chunks that are shared between
+/// a union-find and its [`snapshot`](#method.snapshot)s. Taking a
+/// snapshot copies only a pointer; afterward, changing either copy
+/// duplicates just the chunks it writes to, so a speculative sequence of
+/// unions costs time and memory in proportion to the unions, not to the
+/// number of elements.
+///
+/// To avoid writing to shared chunks, [`find`](#method.find) does not
+/// compress paths. Union by rank alone keeps every path logarithmic in
+/// the size of its set.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::CowUnionFind;
+///
+/// let mut uf = CowUnionFind::<u32>::new(1_000_000);
+/// uf.union(0, 1);
+///
+/// let mut speculative = uf.snapshot();
+/// speculative.union(1, 2);
+/// assert!(speculative.equiv(0, 2));
+///
+/// // The original is unaffected.
+/// assert!(!uf.equiv(0, 2));
+/// ```
+#[derive(Clone)]
+pub struct CowUnionFind<Element: ElementType = usize> {
+    chunks: Rc<Vec<Rc<Vec<Entry<Element>>>>>,
+    len:    usize,
+}
+// Invariant: every chunk but the last holds CHUNK_LEN entries, and every
+// parent is in bounds.
+
+#[derive(Clone)]
+struct Entry<Element> {
+    parent: Element,
+    rank:   u8,
+}
+
+const CHUNK_BITS: usize = 12;
+const CHUNK_LEN: usize = 1 << CHUNK_BITS;
+
+impl<Element: ElementType> Default for CowUnionFind<Element> {
+    fn default() -> Self {
+        CowUnionFind {
+            chunks: Rc::new(Vec::new()),
+            len:    0,
+        }
+    }
+}
+
+impl<Element: Debug + ElementType> Debug for CowUnionFind<Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "CowUnionFind(")?;
+        formatter.debug_list()
+            .entries(self.chunks.iter().flat_map(|chunk| chunk.iter())
+                         .map(|entry| &entry.parent))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Element: ElementType> CowUnionFind<Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        let mut result = CowUnionFind::default();
+        for _ in 0 .. size {
+            result.alloc();
+        }
+        result
+    }
+
+    /// Returns a logical copy of the union-find, in constant time.
+    ///
+    /// This is the same as `clone`.
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reports the heap memory used by the union-find.
+    ///
+    /// Chunks shared with snapshots are counted in full, so the memory
+    /// used by a union-find and its snapshots together may be less than
+    /// the sum of their reports.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let entries = self.chunks.iter().map(|chunk| chunk.len()).sum();
+        let capacity = self.chunks.iter().map(|chunk| chunk.capacity()).sum();
+
+        MemoryUsage::new()
+            .array::<Rc<Vec<Entry<Element>>>>("chunks", self.chunks.len(),
+                                              self.chunks.capacity())
+            .array::<Entry<Element>>("entries", entries, capacity)
+            .nodes(self.chunks.len() + 1, 2 * mem::size_of::<usize>()
+                                          + mem::size_of::<Vec<Entry<Element>>>())
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = Element::from_usize(self.len)
+            .expect("CowUnionFind::alloc: overflow");
+        let entry = Entry { parent: result, rank: 0 };
+
+        let chunks = Rc::make_mut(&mut self.chunks);
+        if self.len & (CHUNK_LEN - 1) == 0 {
+            let mut chunk = Vec::with_capacity(CHUNK_LEN);
+            chunk.push(entry);
+            chunks.push(Rc::new(chunk));
+        } else {
+            Rc::make_mut(chunks.last_mut().unwrap()).push(entry);
+        }
+
+        self.len += 1;
+        result
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b { return false; }
+
+        let rank_a = self.entry(a).rank;
+        let rank_b = self.entry(b).rank;
+
+        if rank_a > rank_b {
+            self.entry_mut(b).parent = a;
+        } else {
+            self.entry_mut(a).parent = b;
+            if rank_a == rank_b {
+                self.entry_mut(b).rank = rank_b.saturating_add(1);
+            }
+        }
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, mut element: Element) -> Element {
+        assert!(element.to_usize() < self.len, "CowUnionFind::find: out of bounds");
+
+        loop {
+            let parent = self.entry(element).parent;
+            if parent == element { return element; }
+            element = parent;
+        }
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<Element> {
+        (0 .. self.len)
+            .map(|i| self.find(Element::from_usize(i).unwrap()))
+            .collect()
+    }
+
+    /// Copies the sets into a new `UnionFind`.
+    pub fn to_union_find(&self) -> UnionFind<Element> {
+        let mut result = UnionFind::new(self.len);
+        for i in 0 .. self.len {
+            let element = Element::from_usize(i).unwrap();
+            result.union(element, self.find(element));
+        }
+        result
+    }
+
+    // HELPERS
+
+    fn entry(&self, element: Element) -> &Entry<Element> {
+        let index = element.to_usize();
+        &self.chunks[index >> CHUNK_BITS][index & (CHUNK_LEN - 1)]
+    }
+
+    // Copies the table of chunks and the chunk holding `element` if they
+    // are shared.
+    fn entry_mut(&mut self, element: Element) -> &mut Entry<Element> {
+        let index = element.to_usize();
+        let chunks = Rc::make_mut(&mut self.chunks);
+        &mut Rc::make_mut(&mut chunks[index >> CHUNK_BITS])[index & (CHUNK_LEN - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_are_independent() {
+        let mut uf = CowUnionFind::<u32>::new(3 * CHUNK_LEN);
+        uf.union(0, 1);
+
+        let mut snapshot = uf.snapshot();
+        snapshot.union(1, 2);
+        let e = snapshot.alloc();
+        snapshot.union(e, 0);
+
+        uf.union(2, 3);
+
+        assert!( snapshot.equiv(0, 2));
+        assert!(!snapshot.equiv(2, 3));
+        assert!( snapshot.equiv(e, 1));
+        assert_eq!(snapshot.len(), 3 * CHUNK_LEN + 1);
+
+        assert!(!uf.equiv(0, 2));
+        assert!( uf.equiv(2, 3));
+        assert_eq!(uf.len(), 3 * CHUNK_LEN);
+    }
+
+    #[test]
+    fn writes_copy_only_touched_chunks() {
+        let mut uf = CowUnionFind::<usize>::new(4 * CHUNK_LEN);
+        let snapshot = uf.snapshot();
+        assert!(Rc::ptr_eq(&uf.chunks, &snapshot.chunks));
+
+        uf.union(0, 1);
+        assert!(!Rc::ptr_eq(&uf.chunks, &snapshot.chunks));
+        assert!(!Rc::ptr_eq(&uf.chunks[0], &snapshot.chunks[0]));
+        for i in 1 .. 4 {
+            assert!(Rc::ptr_eq(&uf.chunks[i], &snapshot.chunks[i]));
+        }
+    }
+
+    #[test]
+    fn matches_union_find() {
+        let mut cow = CowUnionFind::<u16>::new(100);
+        let mut expected = UnionFind::<u16>::new(100);
+
+        for i in 0 .. 100 {
+            cow.union(i, i * 7 % 100);
+            expected.union(i, i * 7 % 100);
+        }
+
+        assert!(cow.to_union_find().same_partition(&expected));
+    }
+}
@@ -0,0 +1,263 @@
+use std::mem;
+
+use super::{ElementType, Error, UnionFind};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// A union-find that tracks its changes since the last marker, so that
+/// they can be shipped to a replica as a [`Delta`](struct.Delta.html).
+///
+/// Only the changes to the partition are tracked: each union that merges
+/// two sets contributes one link, and path compression contributes
+/// nothing. A delta is thus proportional to the number of merges since
+/// the marker, not to the number of elements.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{DeltaUnionFind, UnionFind};
+///
+/// let mut primary = DeltaUnionFind::<u32>::new(4);
+/// let mut replica = UnionFind::<u32>::new(4);
+///
+/// primary.union(0, 1);
+/// primary.union(2, 3);
+/// primary.mark().apply_to(&mut replica).unwrap();
+/// assert!(replica.equiv(2, 3));
+///
+/// let e = primary.alloc();
+/// primary.union(1, e);
+/// let delta = primary.mark();
+/// assert_eq!(delta.links().len(), 1);
+/// delta.apply_to(&mut replica).unwrap();
+/// assert!(replica.same_partition(primary.as_union_find()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DeltaUnionFind<Element: ElementType = usize> {
+    union_find: UnionFind<Element>,
+    pending:    Delta<Element>,
+}
+
+/// The changes to a union-find since a marker.
+///
+/// This is produced by [`DeltaUnionFind::mark`](struct.DeltaUnionFind.html#method.mark),
+/// and can be serialized when configured with Cargo feature `"serde"`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Delta<Element = usize> {
+    base_len: usize,
+    len:      usize,
+    links:    Vec<(Element, Element)>,
+}
+
+impl<Element: ElementType> DeltaUnionFind<Element> {
+    /// Creates a new union-find of `size` elements, with a marker at its
+    /// initial state.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        UnionFind::new(size).into()
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.union_find.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.union_find.is_empty()
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = self.union_find.alloc();
+        self.pending.len += 1;
+        result
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.union_find.find(a);
+        let b = self.union_find.find(b);
+
+        if self.union_find.union(a, b) {
+            self.pending.links.push((a, b));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Finds the representative element for the given element’s set.
+    pub fn find(&self, element: Element) -> Element {
+        self.union_find.find(element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.union_find.equiv(a, b)
+    }
+
+    /// Returns the changes since the last marker, without moving it.
+    pub fn delta(&self) -> &Delta<Element> {
+        &self.pending
+    }
+
+    /// Returns the changes since the last marker, and moves the marker to
+    /// the current state.
+    pub fn mark(&mut self) -> Delta<Element> {
+        let len = self.len();
+        mem::replace(&mut self.pending, Delta::empty(len))
+    }
+
+    /// Returns the underlying union-find.
+    pub fn as_union_find(&self) -> &UnionFind<Element> {
+        &self.union_find
+    }
+
+    /// Returns the underlying union-find, discarding the tracked changes.
+    pub fn into_union_find(self) -> UnionFind<Element> {
+        self.union_find
+    }
+}
+
+impl<Element: ElementType> From<UnionFind<Element>> for DeltaUnionFind<Element> {
+    fn from(union_find: UnionFind<Element>) -> Self {
+        let pending = Delta::empty(union_find.len());
+        DeltaUnionFind { union_find, pending }
+    }
+}
+
+impl<Element: ElementType> Delta<Element> {
+    fn empty(len: usize) -> Self {
+        Delta {
+            base_len: len,
+            len,
+            links:    Vec::new(),
+        }
+    }
+
+    /// The number of elements at the marker.
+    pub fn base_len(&self) -> usize {
+        self.base_len
+    }
+
+    /// The number of elements after the changes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Does the delta lack changes?
+    pub fn is_empty(&self) -> bool {
+        self.base_len == self.len && self.links.is_empty()
+    }
+
+    /// The pairs of sets merged since the marker, in order.
+    pub fn links(&self) -> &[(Element, Element)] {
+        &self.links
+    }
+
+    /// Applies the changes to a union-find with the same sets as the
+    /// source had at the marker.
+    ///
+    /// The result has the same sets as the source had when the delta was
+    /// taken. Returns an error, possibly after applying some of the
+    /// changes, if the delta refers to elements that do not exist or
+    /// would overflow the element type.
+    ///
+    /// # Panics
+    ///
+    /// If `union_find`’s length differs from the delta’s
+    /// [`base_len`](#method.base_len).
+    pub fn apply_to(&self, union_find: &mut UnionFind<Element>) -> Result<(), Error> {
+        assert_eq!(union_find.len(), self.base_len,
+                   "Delta::apply_to: length mismatch");
+
+        for _ in self.base_len .. self.len {
+            union_find.try_alloc()?;
+        }
+
+        for &(a, b) in &self.links {
+            union_find.try_union(a, b)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chained_deltas() {
+        let mut primary = DeltaUnionFind::<u16>::new(10);
+        let mut replica = UnionFind::<u16>::new(10);
+
+        for round in 0 .. 4u16 {
+            for i in 0 .. 10u16 {
+                primary.union(i, (i * 3 + round) % 10);
+            }
+            primary.union(round, round);
+            primary.alloc();
+
+            let delta = primary.mark();
+            assert!(delta.links().len() <= 9);
+            assert_eq!(delta.len(), delta.base_len() + 1);
+            delta.apply_to(&mut replica).unwrap();
+            assert!(replica.same_partition(primary.as_union_find()));
+        }
+
+        assert!(primary.delta().is_empty());
+        assert!(primary.mark().is_empty());
+    }
+
+    #[test]
+    fn redundant_unions_are_not_tracked() {
+        let mut uf = DeltaUnionFind::<usize>::new(3);
+        assert!(uf.union(0, 1));
+        assert!(!uf.union(1, 0));
+        assert_eq!(uf.delta().links().len(), 1);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut source = DeltaUnionFind::<u8>::new(3);
+        source.union(1, 2);
+        let delta = source.mark();
+
+        let mut replica = UnionFind::<u8>::new(3);
+        assert!(delta.apply_to(&mut replica).is_ok());
+        assert!(replica.equiv(1, 2));
+
+        let bogus = Delta::<u8> { base_len: 2, len: 2, links: vec![(0, 5)] };
+        assert_eq!(bogus.apply_to(&mut UnionFind::new(2)),
+                   Err(Error::OutOfBounds { element: 5, len: 2 }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        extern crate serde_json;
+
+        let mut source = DeltaUnionFind::<u32>::new(5);
+        source.union(0, 4);
+        source.union(4, 2);
+        let delta = source.mark();
+
+        let json = serde_json::to_string(&delta).unwrap();
+        let copy: Delta<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy, delta);
+    }
+}
@@ -0,0 +1,568 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use randomized::splitmix64;
+
+/// Connectivity of an undirected graph under edge insertions and
+/// deletions.
+///
+/// This is the structure of Holm, de Lichtenberg, and Thorup. Unlike a
+/// union-find, it supports [`delete`](#method.delete), at an amortized
+/// cost of O(log² *n*) per update; [`connected`](#method.connected) takes
+/// O(log *n*) time. Each edge has a level, which only increases, and the
+/// structure keeps a spanning forest of the edges at each level or above,
+/// with every tree stored as an Euler tour in a balanced search tree.
+/// When a tree edge is deleted, the search for a replacement edge raises
+/// the level of every edge it examines in vain, which pays for the search.
+///
+/// If the updates are all known in advance,
+/// [`OfflineConnectivity`](struct.OfflineConnectivity.html) is simpler and
+/// faster.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::DynamicConnectivity;
+///
+/// let mut graph = DynamicConnectivity::new(4);
+/// graph.insert(0, 1);
+/// graph.insert(1, 2);
+/// graph.insert(2, 0);
+/// graph.insert(2, 3);
+/// assert_eq!(graph.component_count(), 1);
+///
+/// // The cycle 0–1–2 survives losing an edge; vertex 3 does not.
+/// graph.delete(0, 1);
+/// graph.delete(2, 3);
+/// assert!( graph.connected(0, 1));
+/// assert!(!graph.connected(0, 3));
+/// assert_eq!(graph.component_size(3), 1);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DynamicConnectivity {
+    size:       usize,
+    // The spanning forest of the edges at each level or above.
+    forests:    Vec<EulerTourForest>,
+    // For each level and vertex, the neighbors along tree edges and
+    // along non-tree edges of exactly that level.
+    tree_adj:   Vec<Vec<HashSet<usize>>>,
+    other_adj:  Vec<Vec<HashSet<usize>>>,
+    // Each edge, with its smaller endpoint first, mapped to its level and
+    // whether it is a tree edge.
+    edges:      HashMap<(usize, usize), (usize, bool)>,
+    components: usize,
+}
+
+// Flags on the vertex nodes of the Euler tour forest at each level.
+const HAS_TREE_EDGES: u8 = 1;
+const HAS_OTHER_EDGES: u8 = 2;
+
+impl DynamicConnectivity {
+    /// Creates a new graph of `size` vertices and no edges.
+    pub fn new(size: usize) -> Self {
+        let mut result = DynamicConnectivity {
+            size,
+            components: size,
+            ..DynamicConnectivity::default()
+        };
+        result.ensure_level(0);
+        result
+    }
+
+    /// The number of vertices.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Is the graph devoid of vertices?
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// The number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// The number of connected components.
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    /// Is there an edge between the two given vertices?
+    pub fn contains_edge(&self, a: usize, b: usize) -> bool {
+        self.edges.contains_key(&edge(a, b))
+    }
+
+    /// Inserts an edge.
+    ///
+    /// Returns whether anything changed. That is, if the edge was absent
+    /// it returns `true`, but if it was already present, or if `a == b`,
+    /// it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn insert(&mut self, a: usize, b: usize) -> bool {
+        self.check(a, b);
+
+        if a == b || self.contains_edge(a, b) { return false; }
+
+        if self.forests[0].connected(a, b) {
+            self.edges.insert(edge(a, b), (0, false));
+            self.add_adjacency(0, a, b, false);
+        } else {
+            self.edges.insert(edge(a, b), (0, true));
+            self.add_adjacency(0, a, b, true);
+            self.forests[0].link(a, b);
+            self.components -= 1;
+        }
+
+        true
+    }
+
+    /// Deletes an edge.
+    ///
+    /// Returns whether anything changed. That is, if the edge was present
+    /// it returns `true`, but if it was absent it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn delete(&mut self, a: usize, b: usize) -> bool {
+        self.check(a, b);
+
+        let (level, is_tree) = match self.edges.remove(&edge(a, b)) {
+            Some(info) => info,
+            None       => return false,
+        };
+
+        self.remove_adjacency(level, a, b, is_tree);
+        if !is_tree { return true; }
+
+        for forest in &mut self.forests[..= level] {
+            forest.cut(a, b);
+        }
+
+        for i in (0 ..= level).rev() {
+            if self.replace(i, a, b) { return true; }
+        }
+
+        self.components += 1;
+        true
+    }
+
+    /// Determines whether two vertices are connected.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn connected(&self, a: usize, b: usize) -> bool {
+        self.check(a, b);
+        self.forests[0].connected(a, b)
+    }
+
+    /// The number of vertices in the given vertex’s component.
+    ///
+    /// # Panics
+    ///
+    /// If `vertex` is out of bounds.
+    pub fn component_size(&self, vertex: usize) -> usize {
+        self.check(vertex, vertex);
+        self.forests[0].tree_size(vertex)
+    }
+
+    // HELPERS
+
+    fn check(&self, a: usize, b: usize) {
+        assert!(a < self.size && b < self.size,
+                "DynamicConnectivity: out of bounds");
+    }
+
+    fn ensure_level(&mut self, level: usize) {
+        while self.forests.len() <= level {
+            self.forests.push(EulerTourForest::new(self.size));
+            self.tree_adj.push(vec![HashSet::new(); self.size]);
+            self.other_adj.push(vec![HashSet::new(); self.size]);
+        }
+    }
+
+    // Looks for an edge at `level` to replace the deleted tree edge
+    // between `a` and `b`, which are now in separate trees at that level.
+    // Edges in the smaller tree that do not qualify move up a level.
+    fn replace(&mut self, level: usize, a: usize, b: usize) -> bool {
+        self.ensure_level(level + 1);
+
+        let forest = &self.forests[level];
+        let (small, large) = if forest.tree_size(a) <= forest.tree_size(b) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+
+        // The smaller tree will fit at the next level, so its edges move
+        // there.
+        while let Some(x) = self.forests[level].find_flagged(small, HAS_TREE_EDGES) {
+            let neighbors = mem::take(&mut self.tree_adj[level][x]);
+            for y in neighbors {
+                self.remove_adjacency(level, x, y, true);
+                self.add_adjacency(level + 1, x, y, true);
+                self.edges.insert(edge(x, y), (level + 1, true));
+                self.forests[level + 1].link(x, y);
+            }
+        }
+
+        while let Some(x) = self.forests[level].find_flagged(small, HAS_OTHER_EDGES) {
+            let neighbors: Vec<usize> = self.other_adj[level][x].iter().cloned().collect();
+            for y in neighbors {
+                self.remove_adjacency(level, x, y, false);
+
+                if self.forests[level].connected(y, large) {
+                    self.add_adjacency(level, x, y, true);
+                    self.edges.insert(edge(x, y), (level, true));
+                    for forest in &mut self.forests[..= level] {
+                        forest.link(x, y);
+                    }
+                    return true;
+                }
+
+                self.add_adjacency(level + 1, x, y, false);
+                self.edges.insert(edge(x, y), (level + 1, false));
+            }
+        }
+
+        false
+    }
+
+    fn adjacency(&mut self, is_tree: bool) -> &mut Vec<Vec<HashSet<usize>>> {
+        if is_tree { &mut self.tree_adj } else { &mut self.other_adj }
+    }
+
+    fn add_adjacency(&mut self, level: usize, a: usize, b: usize, is_tree: bool) {
+        self.adjacency(is_tree)[level][a].insert(b);
+        self.adjacency(is_tree)[level][b].insert(a);
+        self.refresh_flags(level, a);
+        self.refresh_flags(level, b);
+    }
+
+    fn remove_adjacency(&mut self, level: usize, a: usize, b: usize, is_tree: bool) {
+        self.adjacency(is_tree)[level][a].remove(&b);
+        self.adjacency(is_tree)[level][b].remove(&a);
+        self.refresh_flags(level, a);
+        self.refresh_flags(level, b);
+    }
+
+    fn refresh_flags(&mut self, level: usize, vertex: usize) {
+        let mut flags = 0;
+        if !self.tree_adj[level][vertex].is_empty() {
+            flags |= HAS_TREE_EDGES;
+        }
+        if !self.other_adj[level][vertex].is_empty() {
+            flags |= HAS_OTHER_EDGES;
+        }
+        self.forests[level].set_flags(vertex, flags);
+    }
+}
+
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+// A forest stored as Euler tours, each kept in a treap ordered by
+// position. Nodes `0 .. size` stand for the vertices, and each tree edge
+// adds a node for each direction.
+#[derive(Clone, Debug, Default)]
+struct EulerTourForest {
+    size:  usize,
+    nodes: Vec<Node>,
+    arcs:  HashMap<(usize, usize), usize>,
+    free:  Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    left:     usize,
+    right:    usize,
+    parent:   usize,
+    priority: u64,
+    // The number of nodes and of vertex nodes in the subtree.
+    count:    usize,
+    vertices: usize,
+    // This node’s flags, and the union of the flags in the subtree.
+    flags:    u8,
+    summary:  u8,
+}
+
+const NIL: usize = usize::MAX;
+
+impl Node {
+    fn new(priority: u64, is_vertex: bool) -> Self {
+        Node {
+            left:     NIL,
+            right:    NIL,
+            parent:   NIL,
+            priority,
+            count:    1,
+            vertices: is_vertex as usize,
+            flags:    0,
+            summary:  0,
+        }
+    }
+}
+
+impl EulerTourForest {
+    fn new(size: usize) -> Self {
+        EulerTourForest {
+            size,
+            nodes: (0 .. size).map(|i| Node::new(splitmix64(i as u64), true)).collect(),
+            arcs:  HashMap::new(),
+            free:  Vec::new(),
+        }
+    }
+
+    fn connected(&self, a: usize, b: usize) -> bool {
+        self.root(a) == self.root(b)
+    }
+
+    fn tree_size(&self, vertex: usize) -> usize {
+        self.nodes[self.root(vertex)].vertices
+    }
+
+    fn link(&mut self, a: usize, b: usize) {
+        let tour_a = self.reroot(a);
+        let tour_b = self.reroot(b);
+        let arc_ab = self.new_arc(a, b);
+        let arc_ba = self.new_arc(b, a);
+
+        let tour = self.merge(tour_a, arc_ab);
+        let tour = self.merge(tour, tour_b);
+        self.merge(tour, arc_ba);
+    }
+
+    fn cut(&mut self, a: usize, b: usize) {
+        let mut first = self.arcs.remove(&(a, b)).unwrap();
+        let mut second = self.arcs.remove(&(b, a)).unwrap();
+        let mut first_index = self.index(first);
+        let mut second_index = self.index(second);
+        if first_index > second_index {
+            mem::swap(&mut first, &mut second);
+            mem::swap(&mut first_index, &mut second_index);
+        }
+
+        // The tour is `before`, `first`, `inside`, `second`, `after`, and
+        // `inside` is the tour of the subtree that is cut off.
+        let root = self.root(first);
+        let (before, rest) = self.split(root, first_index);
+        let (_, rest) = self.split(rest, 1);
+        let (_, rest) = self.split(rest, second_index - first_index - 1);
+        let (_, after) = self.split(rest, 1);
+        self.merge(before, after);
+
+        self.free.push(first);
+        self.free.push(second);
+    }
+
+    fn set_flags(&mut self, vertex: usize, flags: u8) {
+        self.nodes[vertex].flags = flags;
+        let mut node = vertex;
+        while node != NIL {
+            self.update(node);
+            node = self.nodes[node].parent;
+        }
+    }
+
+    // Finds a vertex with the given flag in the same tree as `vertex`.
+    fn find_flagged(&self, vertex: usize, flag: u8) -> Option<usize> {
+        let mut node = self.root(vertex);
+        if self.nodes[node].summary & flag == 0 { return None; }
+
+        loop {
+            let Node { left, right, flags, .. } = self.nodes[node];
+            if flags & flag != 0 {
+                return Some(node);
+            } else if left != NIL && self.nodes[left].summary & flag != 0 {
+                node = left;
+            } else {
+                node = right;
+            }
+        }
+    }
+
+    fn new_arc(&mut self, a: usize, b: usize) -> usize {
+        let node = match self.free.pop() {
+            Some(node) => node,
+            None => {
+                self.nodes.push(Node::new(0, false));
+                self.nodes.len() - 1
+            }
+        };
+
+        self.nodes[node] = Node::new(splitmix64(node as u64), false);
+        self.arcs.insert((a, b), node);
+        node
+    }
+
+    fn root(&self, mut node: usize) -> usize {
+        while self.nodes[node].parent != NIL {
+            node = self.nodes[node].parent;
+        }
+        node
+    }
+
+    fn index(&self, mut node: usize) -> usize {
+        let mut result = self.count(self.nodes[node].left);
+        while self.nodes[node].parent != NIL {
+            let parent = self.nodes[node].parent;
+            if self.nodes[parent].right == node {
+                result += self.count(self.nodes[parent].left) + 1;
+            }
+            node = parent;
+        }
+        result
+    }
+
+    // Rotates the tour containing `vertex` to start there, and returns
+    // its root.
+    fn reroot(&mut self, vertex: usize) -> usize {
+        let root = self.root(vertex);
+        let index = self.index(vertex);
+        let (before, after) = self.split(root, index);
+        self.merge(after, before)
+    }
+
+    fn count(&self, node: usize) -> usize {
+        if node == NIL { 0 } else { self.nodes[node].count }
+    }
+
+    fn update(&mut self, node: usize) {
+        let Node { left, right, flags, .. } = self.nodes[node];
+        let mut count = 1;
+        let mut vertices = (node < self.size) as usize;
+        let mut summary = flags;
+
+        for &child in &[left, right] {
+            if child != NIL {
+                count += self.nodes[child].count;
+                vertices += self.nodes[child].vertices;
+                summary |= self.nodes[child].summary;
+            }
+        }
+
+        let node = &mut self.nodes[node];
+        node.count = count;
+        node.vertices = vertices;
+        node.summary = summary;
+    }
+
+    fn set_left(&mut self, node: usize, child: usize) {
+        self.nodes[node].left = child;
+        if child != NIL { self.nodes[child].parent = node; }
+        self.update(node);
+    }
+
+    fn set_right(&mut self, node: usize, child: usize) {
+        self.nodes[node].right = child;
+        if child != NIL { self.nodes[child].parent = node; }
+        self.update(node);
+    }
+
+    // Concatenates two tours, given their roots, and returns the root.
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL { return b; }
+        if b == NIL { return a; }
+
+        if self.nodes[a].priority >= self.nodes[b].priority {
+            let right = self.nodes[a].right;
+            let merged = self.merge(right, b);
+            self.set_right(a, merged);
+            self.nodes[a].parent = NIL;
+            a
+        } else {
+            let left = self.nodes[b].left;
+            let merged = self.merge(a, left);
+            self.set_left(b, merged);
+            self.nodes[b].parent = NIL;
+            b
+        }
+    }
+
+    // Splits the tour with the given root into its first `index` nodes and
+    // the rest, and returns their roots.
+    fn split(&mut self, root: usize, index: usize) -> (usize, usize) {
+        if root == NIL { return (NIL, NIL); }
+
+        self.nodes[root].parent = NIL;
+        let left = self.nodes[root].left;
+        let left_count = self.count(left);
+
+        if index <= left_count {
+            let (a, b) = self.split(left, index);
+            self.set_left(root, b);
+            (a, root)
+        } else {
+            let right = self.nodes[root].right;
+            let (a, b) = self.split(right, index - left_count - 1);
+            self.set_right(root, a);
+            (root, b)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use UnionFind;
+
+    #[test]
+    fn matches_rebuilding() {
+        const SIZE: usize = 24;
+
+        let mut graph = DynamicConnectivity::new(SIZE);
+        let mut edges = HashSet::new();
+        let mut state = 12345u64;
+
+        for _ in 0 .. 3000 {
+            state = splitmix64(state);
+            let a = (state % SIZE as u64) as usize;
+            let b = ((state >> 20) % SIZE as u64) as usize;
+
+            // Insert more often than delete, so that the graph has large
+            // components with cycles.
+            if state >> 60 < 9 {
+                assert_eq!(graph.insert(a, b), a != b && edges.insert(edge(a, b)));
+            } else {
+                assert_eq!(graph.delete(a, b), edges.remove(&edge(a, b)));
+            }
+
+            let mut expected = UnionFind::<usize>::new(SIZE);
+            for &(x, y) in &edges {
+                expected.union(x, y);
+            }
+
+            assert_eq!(graph.edge_count(), edges.len());
+            assert_eq!(graph.component_count(), expected.partition().num_sets());
+            for x in 0 .. SIZE {
+                assert_eq!(graph.connected(a, x), expected.equiv(a, x));
+            }
+            assert_eq!(graph.component_size(b),
+                       (0 .. SIZE).filter(|&x| expected.equiv(b, x)).count());
+        }
+    }
+
+    #[test]
+    fn path_and_cycle() {
+        let mut graph = DynamicConnectivity::new(100);
+        for i in 0 .. 99 {
+            graph.insert(i, i + 1);
+        }
+        graph.insert(99, 0);
+
+        graph.delete(50, 51);
+        assert!(graph.connected(0, 99));
+        graph.delete(10, 11);
+        assert!(!graph.connected(10, 11));
+        assert_eq!(graph.component_size(11), 40);
+        assert_eq!(graph.component_count(), 2);
+        assert!(!graph.delete(10, 11));
+    }
+}
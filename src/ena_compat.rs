@@ -0,0 +1,264 @@
+//! An adapter for code written against the [`ena`](https://docs.rs/ena)
+//! crate’s unification tables.
+//!
+//! This module mirrors the parts of `ena::unify` that most call sites
+//! use, backed by a [`UnificationTable`](../struct.UnificationTable.html).
+//! Migrating is usually a matter of replacing `ena::unify` with
+//! `disjoint_sets::ena_compat` in `use` declarations:
+//!
+//! ```
+//! use disjoint_sets::ena_compat::{InPlaceUnificationTable, UnifyKey, NoError};
+//!
+//! #[derive(Clone, Copy, Debug, PartialEq)]
+//! struct IntVar(u32);
+//!
+//! impl UnifyKey for IntVar {
+//!     type Value = ();
+//!     fn index(&self) -> u32 { self.0 }
+//!     fn from_index(u: u32) -> IntVar { IntVar(u) }
+//!     fn tag() -> &'static str { "IntVar" }
+//! }
+//!
+//! let mut table = InPlaceUnificationTable::<IntVar>::new();
+//! let a = table.new_key(());
+//! let b = table.new_key(());
+//! let c = table.new_key(());
+//!
+//! table.union(a, b);
+//! let snapshot = table.snapshot();
+//! table.union(b, c);
+//! assert!(table.unioned(a, c));
+//! table.rollback_to(snapshot);
+//!
+//! assert!(table.unioned(a, b));
+//! assert!(!table.unioned(a, c));
+//! assert_eq!(table.unify_var_var(a, c), Ok::<(), NoError>(()));
+//! ```
+//!
+//! Not everything is supported. In particular, there is no
+//! `UnifyKey::order_roots`, and snapshots are not generic.
+
+use std::fmt::{self, Debug};
+
+use super::{TableKey, UnificationTable};
+
+pub use super::{EqUnifyValue, Snapshot, UnifyValue};
+
+/// A key of an [`InPlaceUnificationTable`](struct.InPlaceUnificationTable.html),
+/// as in `ena`.
+pub trait UnifyKey: Copy + Clone + Debug + PartialEq {
+    /// The value associated with each set of keys.
+    type Value: UnifyValue;
+
+    /// The index of the key.
+    fn index(&self) -> u32;
+
+    /// The key with the given index.
+    fn from_index(u: u32) -> Self;
+
+    /// A name for the key type, for debugging.
+    fn tag() -> &'static str;
+}
+
+/// The error of values that always unify, as in `ena`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NoError {
+    _dummy: (),
+}
+
+impl UnifyValue for () {
+    type Error = NoError;
+
+    fn unify_values(_: &(), _: &()) -> Result<(), NoError> {
+        Ok(())
+    }
+}
+
+// Adapts a `UnifyKey` to a `TableKey`, which needs `Eq`.
+#[derive(Clone, Copy)]
+struct Key<K>(K);
+
+impl<K: UnifyKey> PartialEq for Key<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.index() == other.0.index()
+    }
+}
+
+impl<K: UnifyKey> Eq for Key<K> { }
+
+impl<K: UnifyKey> TableKey for Key<K> {
+    fn index(self) -> u32 { self.0.index() }
+    fn from_index(index: u32) -> Self { Key(K::from_index(index)) }
+}
+
+/// A unification table with the interface of `ena`’s.
+#[derive(Clone)]
+pub struct InPlaceUnificationTable<K: UnifyKey> {
+    table: UnificationTable<Key<K>, K::Value>,
+}
+
+impl<K: UnifyKey> Debug for InPlaceUnificationTable<K> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}: {:?}", K::tag(), self.table)
+    }
+}
+
+impl<K: UnifyKey> Default for InPlaceUnificationTable<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: UnifyKey> InPlaceUnificationTable<K> {
+    /// Creates a new, empty table.
+    pub fn new() -> Self {
+        InPlaceUnificationTable {
+            table: UnificationTable::new(),
+        }
+    }
+
+    /// The number of keys in the table.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Is the table devoid of keys?
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Creates a new key in a singleton set with the given value.
+    pub fn new_key(&mut self, value: K::Value) -> K {
+        self.table.new_key(value).0
+    }
+
+    /// Finds the representative key for the given key’s set.
+    pub fn find<K1: Into<K>>(&self, id: K1) -> K {
+        self.table.find(Key(id.into())).0
+    }
+
+    /// Are the two keys in the same set?
+    pub fn unioned<K1: Into<K>, K2: Into<K>>(&self, a_id: K1, b_id: K2) -> bool {
+        self.table.equiv(Key(a_id.into()), Key(b_id.into()))
+    }
+
+    /// Returns a clone of the value of the key’s set.
+    pub fn probe_value<K1: Into<K>>(&self, id: K1) -> K::Value {
+        self.table.value(Key(id.into())).clone()
+    }
+
+    /// Unifies the sets of two keys, combining their values.
+    pub fn unify_var_var<K1: Into<K>, K2: Into<K>>(&mut self, a_id: K1, b_id: K2)
+            -> Result<(), <K::Value as UnifyValue>::Error> {
+        self.table.unify(Key(a_id.into()), Key(b_id.into()))
+    }
+
+    /// Unifies the value of the key’s set with the given value.
+    pub fn unify_var_value<K1: Into<K>>(&mut self, a_id: K1, b: K::Value)
+            -> Result<(), <K::Value as UnifyValue>::Error> {
+        self.table.unify_value(Key(a_id.into()), b)
+    }
+
+    /// Unifies the sets of two keys, whose values always unify.
+    pub fn union<K1: Into<K>, K2: Into<K>>(&mut self, a_id: K1, b_id: K2)
+            where K::Value: UnifyValue<Error = NoError> {
+        self.unify_var_var(a_id, b_id).unwrap()
+    }
+
+    /// Unifies the value of the key’s set with a value that always
+    /// unifies.
+    pub fn union_value<K1: Into<K>>(&mut self, id: K1, value: K::Value)
+            where K::Value: UnifyValue<Error = NoError> {
+        self.unify_var_value(id, value).unwrap()
+    }
+
+    /// Returns a snapshot of the current state.
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.table.snapshot()
+    }
+
+    /// Restores the state at the given snapshot, closing it and every
+    /// snapshot taken after it.
+    ///
+    /// # Panics
+    ///
+    /// If `snapshot` has already been closed.
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        self.table.rollback_to(snapshot)
+    }
+
+    /// Keeps the changes since the given snapshot, closing it and every
+    /// snapshot taken after it. Committing the outermost snapshot
+    /// discards the undo log.
+    ///
+    /// # Panics
+    ///
+    /// If `snapshot` has already been closed.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.table.commit(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct TyVar(u32);
+
+    impl UnifyKey for TyVar {
+        type Value = Sort;
+        fn index(&self) -> u32 { self.0 }
+        fn from_index(u: u32) -> TyVar { TyVar(u) }
+        fn tag() -> &'static str { "TyVar" }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum Sort { Star, Row }
+
+    impl EqUnifyValue for Sort {}
+
+    #[test]
+    fn eq_values() {
+        let mut table = InPlaceUnificationTable::<TyVar>::new();
+        let a = table.new_key(Sort::Star);
+        let b = table.new_key(Sort::Star);
+        let c = table.new_key(Sort::Row);
+
+        assert_eq!(table.unify_var_var(a, b), Ok(()));
+        assert_eq!(table.unify_var_var(b, c), Err((Sort::Star, Sort::Row)));
+        assert_eq!(table.unify_var_value(c, Sort::Row), Ok(()));
+        assert_eq!(table.probe_value(c), Sort::Row);
+        assert_eq!(table.find(b), table.find(a));
+
+        let snapshot = table.snapshot();
+        table.new_key(Sort::Row);
+        table.commit(snapshot);
+        assert_eq!(table.len(), 4);
+        assert_eq!(format!("{:?}", table),
+                   "TyVar: UnificationTable([Star, Star, Row, Row])");
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_past_inner_snapshot() {
+        let mut table = InPlaceUnificationTable::<TyVar>::new();
+        let outer = table.snapshot();
+        table.new_key(Sort::Star);
+        table.snapshot();
+        table.new_key(Sort::Row);
+        table.rollback_to(outer);
+        assert_eq!(table.len(), 0);
+        table.commit(outer);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_after_commit() {
+        let mut table = InPlaceUnificationTable::<TyVar>::new();
+        let snapshot = table.snapshot();
+        table.new_key(Sort::Star);
+        table.commit(snapshot);
+        table.rollback_to(snapshot);
+    }
+}
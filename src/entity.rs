@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::mem;
+
+use super::UnionFind;
+
+/// How a [`RecordMerger`](struct.RecordMerger.html) chooses the value of
+/// one field of a golden record from the values in a cluster.
+///
+/// Each policy sees the cluster’s values for the field in the order the
+/// records were added, skipping records where the field is missing.
+pub enum MergePolicy<V> {
+    /// Takes the value from the first record that has one.
+    KeepFirst,
+    /// Takes the most frequent value, breaking ties in favor of the value
+    /// seen first.
+    MostFrequent,
+    /// Computes the value with a closure.
+    Custom(Box<MergeFn<V>>),
+}
+
+/// The closure of a [`MergePolicy::Custom`](enum.MergePolicy.html), which
+/// is given the cluster’s values for the field.
+pub type MergeFn<V> = dyn Fn(&[&V]) -> Option<V>;
+
+impl<V> MergePolicy<V> {
+    /// A policy that computes the value with the given closure.
+    pub fn custom<F>(f: F) -> Self
+        where F: Fn(&[&V]) -> Option<V> + 'static {
+
+        MergePolicy::Custom(Box::new(f))
+    }
+}
+
+impl<V> Debug for MergePolicy<V> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MergePolicy::KeepFirst    => write!(formatter, "KeepFirst"),
+            MergePolicy::MostFrequent => write!(formatter, "MostFrequent"),
+            MergePolicy::Custom(_)    => write!(formatter, "Custom(..)"),
+        }
+    }
+}
+
+/// Entity resolution: clusters records that share a key, and merges each
+/// cluster into a golden record.
+///
+/// Records have a fixed number of fields, each of which may be missing,
+/// and each field has a [`MergePolicy`](enum.MergePolicy.html). Each
+/// record is added along with its keys—an email address, a phone number,
+/// a normalized name—and records that share any key, directly or
+/// through other records, belong to the same cluster.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{MergePolicy, RecordMerger};
+///
+/// // Fields are name and city.
+/// let mut merger = RecordMerger::new(vec![
+///     MergePolicy::KeepFirst,
+///     MergePolicy::MostFrequent,
+/// ]);
+///
+/// let a = merger.add(vec![Some("Ada"), Some("London")], vec!["ada@example.com"]);
+/// let b = merger.add(vec![None, Some("Paris")], vec!["555-0100"]);
+/// let c = merger.add(vec![Some("A. Lovelace"), Some("Paris")],
+///                    vec!["ada@example.com", "555-0100"]);
+/// let d = merger.add(vec![Some("Charles"), None], vec!["charles@example.com"]);
+///
+/// assert!(merger.same_entity(a, b));
+/// assert!(!merger.same_entity(c, d));
+/// assert_eq!(merger.golden_record(b), vec![Some("Ada"), Some("Paris")]);
+/// assert_eq!(merger.clusters(), vec![vec![a, b, c], vec![d]]);
+/// ```
+#[derive(Debug)]
+pub struct RecordMerger<K, V> {
+    policies:   Vec<MergePolicy<V>>,
+    records:    Vec<Vec<Option<V>>>,
+    // The first record seen with each key.
+    keys:       HashMap<K, usize>,
+    // The records in each cluster, stored at the index of its root.
+    members:    Vec<Vec<usize>>,
+    union_find: UnionFind<usize>,
+}
+
+impl<K: Hash + Eq, V: Clone + Hash + Eq> RecordMerger<K, V> {
+    /// Creates a new merger for records with one field for each of the
+    /// given policies.
+    pub fn new(policies: Vec<MergePolicy<V>>) -> Self {
+        RecordMerger {
+            policies,
+            records:    Vec::new(),
+            keys:       HashMap::new(),
+            members:    Vec::new(),
+            union_find: UnionFind::new(0),
+        }
+    }
+
+    /// The number of records added.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Is the merger devoid of records?
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// The number of fields in each record.
+    pub fn num_fields(&self) -> usize {
+        self.policies.len()
+    }
+
+    /// Adds a record with the given keys, joining it to the cluster of
+    /// every record that shares one of them. Returns the new record’s
+    /// index.
+    ///
+    /// # Panics
+    ///
+    /// If the record does not have exactly
+    /// [`num_fields`](#method.num_fields) fields.
+    pub fn add<I>(&mut self, record: Vec<Option<V>>, keys: I) -> usize
+        where I: IntoIterator<Item = K> {
+
+        assert_eq!(record.len(), self.num_fields(),
+                   "RecordMerger::add: wrong number of fields");
+
+        let index = self.union_find.alloc();
+        self.records.push(record);
+        self.members.push(vec![index]);
+
+        for key in keys {
+            let other = *self.keys.entry(key).or_insert(index);
+            self.join(index, other);
+        }
+
+        index
+    }
+
+    /// The record with the given index.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn record(&self, index: usize) -> &[Option<V>] {
+        &self.records[index]
+    }
+
+    /// Determines whether two records belong to the same cluster.
+    pub fn same_entity(&self, a: usize, b: usize) -> bool {
+        self.union_find.equiv(a, b)
+    }
+
+    /// The indices of the records in each cluster.
+    ///
+    /// Each cluster lists its records in the order they were added, and
+    /// clusters are ordered by their first record.
+    pub fn clusters(&self) -> Vec<Vec<usize>> {
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        let mut positions = HashMap::new();
+
+        for index in 0 .. self.len() {
+            let root = self.union_find.find(index);
+            let position = *positions.entry(root).or_insert_with(|| {
+                clusters.push(Vec::new());
+                clusters.len() - 1
+            });
+            clusters[position].push(index);
+        }
+
+        clusters
+    }
+
+    /// Merges the cluster of the given record into a golden record,
+    /// choosing each field by its policy.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    ///
+    /// This takes one `find` and time proportional to the size of the
+    /// cluster, not of the whole merger.
+    pub fn golden_record(&self, index: usize) -> Vec<Option<V>> {
+        let mut cluster = self.members[self.union_find.find(index)].clone();
+        cluster.sort_unstable();
+        self.merge(&cluster)
+    }
+
+    /// Merges every cluster into a golden record, in the order of
+    /// [`clusters`](#method.clusters).
+    pub fn golden_records(&self) -> Vec<Vec<Option<V>>> {
+        self.clusters().iter().map(|cluster| self.merge(cluster)).collect()
+    }
+
+    // HELPERS
+
+    // Unions the clusters of two records, moving the smaller member list
+    // into the larger so that each record moves O(log n) times.
+    fn join(&mut self, a: usize, b: usize) {
+        let a = self.union_find.find(a);
+        let b = self.union_find.find(b);
+        if !self.union_find.union(a, b) { return; }
+
+        let root = self.union_find.find(a);
+        let other = if root == a { b } else { a };
+        let mut moved = mem::take(&mut self.members[other]);
+        if moved.len() > self.members[root].len() {
+            mem::swap(&mut moved, &mut self.members[root]);
+        }
+        self.members[root].extend(moved);
+    }
+
+    fn merge(&self, cluster: &[usize]) -> Vec<Option<V>> {
+        self.policies.iter().enumerate().map(|(field, policy)| {
+            let values: Vec<&V> = cluster.iter()
+                .filter_map(|&index| self.records[index][field].as_ref())
+                .collect();
+
+            match *policy {
+                MergePolicy::KeepFirst    => values.first().map(|&value| value.clone()),
+                MergePolicy::MostFrequent => most_frequent(&values).cloned(),
+                MergePolicy::Custom(ref f) => f(&values),
+            }
+        }).collect()
+    }
+}
+
+fn most_frequent<'a, V: Hash + Eq>(values: &[&'a V]) -> Option<&'a V> {
+    let mut counts = HashMap::new();
+    for &value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+
+    // `max_by_key` would favor the last of equals, so scan for the first.
+    let mut best: Option<(&V, usize)> = None;
+    for &value in values {
+        let count = counts[value];
+        match best {
+            Some((_, best_count)) if best_count >= count => (),
+            _ => best = Some((value, count)),
+        }
+    }
+
+    best.map(|(value, _)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies() {
+        let mut merger = RecordMerger::new(vec![
+            MergePolicy::KeepFirst,
+            MergePolicy::MostFrequent,
+            MergePolicy::custom(|values: &[&u32]| values.iter().map(|&&v| v).max()),
+        ]);
+
+        merger.add(vec![None,    Some(1), Some(5)], vec!['a']);
+        merger.add(vec![Some(7), Some(2), Some(3)], vec!['b']);
+        merger.add(vec![Some(8), Some(2), None],    vec!['a', 'b']);
+        merger.add(vec![Some(9), Some(1), Some(4)], vec!['c', 'a']);
+        merger.add(vec![None,    None,    None],    vec!['d']);
+
+        assert_eq!(merger.golden_records(), vec![
+            vec![Some(7), Some(1), Some(5)],
+            vec![None,    None,    None],
+        ]);
+        assert_eq!(merger.golden_record(4), vec![None, None, None]);
+    }
+
+    #[test]
+    fn transitive_keys() {
+        let mut merger = RecordMerger::new(vec![MergePolicy::KeepFirst]);
+        let records: Vec<usize> = (0 .. 6)
+            .map(|i| merger.add(vec![Some(i)], vec![i / 2, 10 + (i + 1) / 2]))
+            .collect();
+
+        assert_eq!(merger.clusters(), vec![records]);
+        assert_eq!(merger.record(3), &[Some(3)]);
+    }
+
+    #[test]
+    fn golden_record_order() {
+        let mut merger = RecordMerger::new(vec![MergePolicy::KeepFirst]);
+        merger.add(vec![None],    vec![0]);
+        merger.add(vec![Some(1)], vec![1]);
+        merger.add(vec![Some(2)], vec![2]);
+        merger.add(vec![Some(3)], vec![2, 3]);
+        merger.add(vec![Some(4)], vec![3, 1, 0]);
+
+        // Record 1 joined the larger cluster of records 2 and 3 last, but
+        // it still comes first.
+        for index in 0 .. 5 {
+            assert_eq!(merger.golden_record(index), vec![Some(1)]);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_number_of_fields() {
+        let mut merger = RecordMerger::<(), u8>::new(vec![MergePolicy::KeepFirst]);
+        merger.add(vec![], vec![]);
+    }
+}
@@ -0,0 +1,64 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+/// The error type for fallible union-find operations.
+///
+/// More kinds of error may be added in minor releases, so matches on it
+/// need a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// An element was not in the union-find.
+    OutOfBounds {
+        /// The offending element, as a `usize`.
+        element: usize,
+        /// The number of elements in the union-find.
+        len: usize,
+    },
+    /// The requested number of elements is more than the union-find can
+    /// hold, either because they would not fit in the element type or
+    /// because they would exceed a fixed capacity.
+    Overflow,
+    /// Memory for the requested number of elements could not be allocated.
+    AllocationFailed,
+    /// Input bytes did not follow the expected layout.
+    InvalidFormat,
+    /// A set’s data was already borrowed, as by a closure operating on
+    /// the set.
+    AlreadyBorrowed,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::OutOfBounds { element, len } =>
+                write!(formatter, "element {} out of bounds for length {}",
+                       element, len),
+            Error::Overflow =>
+                write!(formatter, "too many elements for the union-find"),
+            Error::AllocationFailed =>
+                write!(formatter, "memory allocation failed"),
+            Error::InvalidFormat =>
+                write!(formatter, "invalid union-find layout"),
+            Error::AlreadyBorrowed =>
+                write!(formatter, "set data already borrowed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let error = Error::OutOfBounds { element: 7, len: 5 };
+        assert_eq!(error.to_string(), "element 7 out of bounds for length 5");
+        assert_eq!(Error::Overflow.to_string(),
+                   "too many elements for the union-find");
+    }
+}
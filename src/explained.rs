@@ -0,0 +1,243 @@
+use std::fmt::{self, Debug};
+use std::mem;
+
+use super::{ElementType, UnionFind};
+
+/// Union-find that remembers why each union happened.
+///
+/// Each call to [`union_with_reason`](#method.union_with_reason) stores a
+/// reason of type `R`, and [`explain`](#method.explain) returns the chain
+/// of unions, with their reasons, that connects two elements. This
+/// answers questions like “why did these two records end up merged?”
+///
+/// The unions that joined different sets form a forest, called the
+/// *proof forest*, whose edges connect the elements actually passed to
+/// `union_with_reason` rather than their representatives. Each union
+/// reroots the smaller of the two trees, so building the forest takes
+/// O(*n* log *n*) time in total, and an explanation takes time
+/// proportional to the height of its tree.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::ExplainedUnionFind;
+///
+/// let mut uf = ExplainedUnionFind::<&str, u32>::new(4);
+/// uf.union_with_reason(0, 1, "same email");
+/// uf.union_with_reason(2, 3, "same phone");
+/// uf.union_with_reason(1, 3, "same address");
+///
+/// let steps = uf.explain(0, 2).unwrap();
+/// let reasons: Vec<_> = steps.iter().map(|&(_, _, reason)| *reason).collect();
+/// assert_eq!(reasons, ["same email", "same address", "same phone"]);
+/// ```
+#[derive(Clone)]
+pub struct ExplainedUnionFind<R, Element: ElementType = usize> {
+    union_find: UnionFind<Element>,
+    // The size of each set, indexed by its representative.
+    sizes:      Vec<usize>,
+    // Each element’s parent in the proof forest, with the reason for the
+    // union that linked them.
+    proof:      Vec<Option<(Element, R)>>,
+}
+
+impl<R, Element: ElementType> Default for ExplainedUnionFind<R, Element> {
+    fn default() -> Self {
+        ExplainedUnionFind::new(0)
+    }
+}
+
+impl<R, Element: Debug + ElementType> Debug for ExplainedUnionFind<R, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "ExplainedUnionFind(")?;
+        formatter.debug_list().entries(self.to_vec()).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<R, Element: ElementType> ExplainedUnionFind<R, Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        ExplainedUnionFind {
+            union_find: UnionFind::new(size),
+            sizes:      vec![1; size],
+            proof:      (0 .. size).map(|_| None).collect(),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.union_find.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.union_find.is_empty()
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = self.union_find.alloc();
+        self.sizes.push(1);
+        self.proof.push(None);
+        result
+    }
+
+    /// Joins the sets of the two given elements, recording `reason` as
+    /// the reason for the union.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false` and discards `reason`.
+    pub fn union_with_reason(&mut self, a: Element, b: Element, reason: R) -> bool {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+
+        if root_a == root_b { return false; }
+
+        let size_a = self.sizes[root_a.to_usize()];
+        let size_b = self.sizes[root_b.to_usize()];
+        let (small, large) = if size_a <= size_b { (a, b) } else { (b, a) };
+
+        self.reroot(small);
+        self.proof[small.to_usize()] = Some((large, reason));
+
+        self.union_find.union(root_a, root_b);
+        let root = self.find(a);
+        self.sizes[root.to_usize()] = size_a + size_b;
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: Element) -> Element {
+        self.union_find.find(element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.union_find.equiv(a, b)
+    }
+
+    /// Explains why two elements are in the same set.
+    ///
+    /// Returns the unions along the path from `a` to `b` in the proof
+    /// forest, each as a triple of the elements it connected, oriented
+    /// from `a` toward `b`, and its reason. If `a` and `b` are the same
+    /// element the path is empty, and if they are in different sets the
+    /// result is `None`.
+    pub fn explain(&self, a: Element, b: Element) -> Option<Vec<(Element, Element, &R)>> {
+        if !self.equiv(a, b) { return None; }
+
+        let mut depth_a = self.depth(a);
+        let mut depth_b = self.depth(b);
+        let mut from_a = Vec::new();
+        let mut from_b = Vec::new();
+        let (mut a, mut b) = (a, b);
+
+        while a != b {
+            if depth_a >= depth_b {
+                let (parent, ref reason) = *self.proof[a.to_usize()].as_ref().unwrap();
+                from_a.push((a, parent, reason));
+                a = parent;
+                depth_a -= 1;
+            } else {
+                let (parent, ref reason) = *self.proof[b.to_usize()].as_ref().unwrap();
+                from_b.push((parent, b, reason));
+                b = parent;
+                depth_b -= 1;
+            }
+        }
+
+        from_a.extend(from_b.into_iter().rev());
+        Some(from_a)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<Element> {
+        self.union_find.to_vec()
+    }
+
+    // HELPERS
+
+    fn depth(&self, mut element: Element) -> usize {
+        let mut result = 0;
+        while let Some((parent, _)) = self.proof[element.to_usize()] {
+            element = parent;
+            result += 1;
+        }
+        result
+    }
+
+    // Makes `element` the root of its proof tree by reversing the edges
+    // on its path to the old root.
+    fn reroot(&mut self, element: Element) {
+        let mut current = element;
+        let mut incoming = None;
+
+        while let Some((parent, reason)) =
+                mem::replace(&mut self.proof[current.to_usize()], incoming) {
+            incoming = Some((current, reason));
+            current = parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks that the explanation is a path of recorded unions from `a`
+    // to `b`.
+    fn check_path(uf: &ExplainedUnionFind<(usize, usize)>, a: usize, b: usize) {
+        let steps = uf.explain(a, b).unwrap();
+        let mut current = a;
+        for &(from, to, &(x, y)) in &steps {
+            assert_eq!(from, current);
+            assert!((x, y) == (from, to) || (y, x) == (from, to));
+            current = to;
+        }
+        assert_eq!(current, b);
+    }
+
+    #[test]
+    fn explanations_follow_unions() {
+        let mut uf = ExplainedUnionFind::new(20);
+        for i in 0 .. 20 {
+            let j = (i * 7 + 3) % 20;
+            uf.union_with_reason(i, j, (i, j));
+        }
+
+        for a in 0 .. 20 {
+            for b in 0 .. 20 {
+                if uf.equiv(a, b) {
+                    check_path(&uf, a, b);
+                } else {
+                    assert!(uf.explain(a, b).is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn redundant_union_keeps_first_reason() {
+        let mut uf = ExplainedUnionFind::<&str>::new(3);
+        assert!( uf.union_with_reason(0, 1, "first"));
+        assert!(!uf.union_with_reason(1, 0, "second"));
+        assert_eq!(uf.explain(1, 0), Some(vec![(1, 0, &"first")]));
+        assert_eq!(uf.explain(2, 2), Some(vec![]));
+    }
+}
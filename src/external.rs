@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter;
+use std::path::Path;
+
+use super::UnionFind;
+use mapped::{write_header, ENTRY_SIZE, HEADER_SIZE};
+
+/// Union-find whose elements live in a file rather than in memory.
+///
+/// This is meant for computing the connected components of graphs too
+/// large for RAM. The file holds each element’s representative, in the
+/// layout read by [`MappedUnionFind`](struct.MappedUnionFind.html), so
+/// that when the unions are done it can be memory-mapped and queried in
+/// place.
+///
+/// Unions are not applied immediately. Instead, they are buffered in
+/// memory, and each full batch is applied in two passes over the file:
+/// the first reads the representatives of the batch’s elements, and the
+/// second relabels every element whose set was merged. Both passes read
+/// and write the file sequentially, in chunks, so the cost of a batch is
+/// one or two scans of the file regardless of where the unions fall.
+/// Larger batches thus mean fewer scans, at the cost of more memory: a
+/// batch of *k* unions uses O(*k*) memory.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{ExternalUnionFind, MappedUnionFind};
+/// use std::io::Read;
+///
+/// let path = std::env::temp_dir()
+///     .join(format!("disjoint-sets-doctest-external-{}", std::process::id()));
+///
+/// let mut uf = ExternalUnionFind::create(&path, 5).unwrap();
+/// uf.union(0, 1).unwrap();
+/// uf.union(3, 1).unwrap();
+/// let mut file = uf.finish().unwrap();
+///
+/// let mut bytes = Vec::new();
+/// file.read_to_end(&mut bytes).unwrap();
+/// let mapped = MappedUnionFind::new(&bytes).unwrap();
+/// assert!(mapped.equiv(0, 3));
+/// assert!(!mapped.equiv(0, 4));
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ExternalUnionFind {
+    file:       File,
+    len:        usize,
+    pending:    Vec<(usize, usize)>,
+    batch_size: usize,
+    chunk_len:  usize,
+}
+// Invariant: on disk, each element’s entry is its representative, and
+// each representative’s entry is itself. The pending unions have not yet
+// been applied.
+
+const DEFAULT_BATCH_SIZE: usize = 1 << 22;
+const DEFAULT_CHUNK_LEN: usize = 1 << 16;
+
+impl ExternalUnionFind {
+    /// Creates a file at `path`, replacing any existing file, holding a
+    /// union-find of `size` elements, each in its own singleton set.
+    pub fn create<P: AsRef<Path>>(path: P, size: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true).write(true).create(true).truncate(true)
+            .open(path)?;
+
+        {
+            let mut writer = BufWriter::new(&file);
+            write_header(&mut writer, size)?;
+            for i in 0 .. size {
+                writer.write_all(&(i as u64).to_le_bytes())?;
+            }
+            writer.flush()?;
+        }
+
+        Ok(ExternalUnionFind {
+            file,
+            len:        size,
+            pending:    Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            chunk_len:  DEFAULT_CHUNK_LEN,
+        })
+    }
+
+    /// Sets the number of unions buffered before they are applied to the
+    /// file. The default is 2<sup>22</sup>.
+    ///
+    /// # Panics
+    ///
+    /// If `batch_size` is 0.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "ExternalUnionFind::with_batch_size: zero");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets the number of elements read or written at a time when
+    /// scanning the file. The default is 2<sup>16</sup>.
+    ///
+    /// # Panics
+    ///
+    /// If `chunk_len` is 0.
+    pub fn with_chunk_len(mut self, chunk_len: usize) -> Self {
+        assert!(chunk_len > 0, "ExternalUnionFind::with_chunk_len: zero");
+        self.chunk_len = chunk_len;
+        self
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// The union is buffered, and the buffered unions are applied to the
+    /// file when the batch is full, which may fail.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn union(&mut self, a: usize, b: usize) -> io::Result<()> {
+        assert!(a < self.len && b < self.len,
+                "ExternalUnionFind::union: out of bounds");
+
+        self.pending.push((a, b));
+
+        if self.pending.len() >= self.batch_size {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// This applies any buffered unions first.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&mut self, element: usize) -> io::Result<usize> {
+        assert!(element < self.len, "ExternalUnionFind::find: out of bounds");
+
+        self.flush()?;
+        let mut entry = [0; ENTRY_SIZE];
+        self.file.seek(SeekFrom::Start(offset_of(element)))?;
+        self.file.read_exact(&mut entry)?;
+        Ok(u64::from_le_bytes(entry) as usize)
+    }
+
+    /// Applies the buffered unions to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let roots = self.read_roots()?;
+
+        // Merge the roots in memory, then map each merged root to its
+        // new representative, which is one of the roots of its set.
+        let mut indices = HashMap::new();
+        let mut by_index = Vec::new();
+        for &root in roots.values() {
+            indices.entry(root).or_insert_with(|| {
+                by_index.push(root);
+                by_index.len() - 1
+            });
+        }
+
+        let mut union_find = UnionFind::<usize>::new(by_index.len());
+        for &(a, b) in &self.pending {
+            union_find.union(indices[&roots[&a]], indices[&roots[&b]]);
+        }
+
+        let relabel: HashMap<usize, usize> = by_index.iter().enumerate()
+            .map(|(i, &root)| (root, by_index[union_find.find(i)]))
+            .filter(|&(root, new_root)| root != new_root)
+            .collect();
+
+        self.relabel(&relabel)?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Applies the buffered unions and returns the file, positioned at its
+    /// start.
+    pub fn finish(mut self) -> io::Result<File> {
+        self.flush()?;
+        self.file.seek(SeekFrom::Start(0))?;
+        Ok(self.file)
+    }
+
+    // HELPERS
+
+    // Reads the representative of every element of a pending union, in one
+    // pass over the chunks that contain them.
+    fn read_roots(&mut self) -> io::Result<HashMap<usize, usize>> {
+        let mut elements: Vec<usize> = self.pending.iter()
+            .flat_map(|&(a, b)| iter::once(a).chain(iter::once(b)))
+            .collect();
+        elements.sort_unstable();
+        elements.dedup();
+
+        let mut result = HashMap::with_capacity(elements.len());
+        let mut buffer = Vec::new();
+        let mut loaded = None;
+
+        for element in elements {
+            let chunk = element / self.chunk_len;
+            if loaded != Some(chunk) {
+                self.read_chunk(chunk, &mut buffer)?;
+                loaded = Some(chunk);
+            }
+
+            let index = element % self.chunk_len;
+            result.insert(element, read_entry(&buffer, index));
+        }
+
+        Ok(result)
+    }
+
+    // Rewrites every entry found among the keys of `relabel`, in one pass
+    // over the whole file.
+    fn relabel(&mut self, relabel: &HashMap<usize, usize>) -> io::Result<()> {
+        if relabel.is_empty() {
+            return Ok(());
+        }
+
+        let mut buffer = Vec::new();
+
+        for start in (0 .. self.len).step_by(self.chunk_len) {
+            self.read_chunk(start / self.chunk_len, &mut buffer)?;
+
+            let mut changed = false;
+            for index in 0 .. buffer.len() / ENTRY_SIZE {
+                if let Some(&new_root) = relabel.get(&read_entry(&buffer, index)) {
+                    let start = index * ENTRY_SIZE;
+                    buffer[start .. start + ENTRY_SIZE]
+                        .copy_from_slice(&(new_root as u64).to_le_bytes());
+                    changed = true;
+                }
+            }
+
+            if changed {
+                self.file.seek(SeekFrom::Start(offset_of(start)))?;
+                self.file.write_all(&buffer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_chunk(&mut self, chunk: usize, buffer: &mut Vec<u8>) -> io::Result<()> {
+        let start = chunk * self.chunk_len;
+        let end = (start + self.chunk_len).min(self.len);
+        buffer.resize((end - start) * ENTRY_SIZE, 0);
+        self.file.seek(SeekFrom::Start(offset_of(start)))?;
+        self.file.read_exact(buffer)
+    }
+}
+
+fn offset_of(element: usize) -> u64 {
+    (HEADER_SIZE + element * ENTRY_SIZE) as u64
+}
+
+fn read_entry(buffer: &[u8], index: usize) -> usize {
+    let mut entry = [0; ENTRY_SIZE];
+    entry.copy_from_slice(&buffer[index * ENTRY_SIZE .. (index + 1) * ENTRY_SIZE]);
+    u64::from_le_bytes(entry) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use MappedUnionFind;
+
+    // A path that no other test, and no concurrent run of this one, uses.
+    fn temp_path(name: &str) -> PathBuf {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        std::env::temp_dir().join(format!("disjoint-sets-{}-{}-{}-{}",
+                                          name, std::process::id(), nanos,
+                                          NEXT.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    #[test]
+    fn matches_in_memory() {
+        const SIZE: usize = 1000;
+
+        let path = temp_path("matches_in_memory");
+        let mut external = ExternalUnionFind::create(&path, SIZE).unwrap()
+            .with_batch_size(7)
+            .with_chunk_len(64);
+        let mut expected = UnionFind::<usize>::new(SIZE);
+
+        let mut state = 12345usize;
+        for _ in 0 .. 600 {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let a = (state >> 33) % SIZE;
+            let b = (state >> 13) % SIZE;
+            external.union(a, b).unwrap();
+            expected.union(a, b);
+        }
+
+        assert!(external.find(17).unwrap() < SIZE);
+
+        let mut bytes = Vec::new();
+        external.finish().unwrap().read_to_end(&mut bytes).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mapped = MappedUnionFind::new(&bytes).unwrap();
+        assert!(mapped.validate());
+        let actual: UnionFind<usize> = mapped.to_union_find();
+        assert!(actual.same_partition(&expected));
+    }
+
+    #[test]
+    fn find_flushes() {
+        let path = temp_path("find_flushes");
+        let mut uf = ExternalUnionFind::create(&path, 4).unwrap();
+        assert_eq!(uf.len(), 4);
+
+        uf.union(2, 3).unwrap();
+        uf.union(1, 2).unwrap();
+        let root = uf.find(1).unwrap();
+        assert_eq!(uf.find(2).unwrap(), root);
+        assert_eq!(uf.find(3).unwrap(), root);
+        assert_eq!(uf.find(0).unwrap(), 0);
+
+        drop(uf);
+        fs::remove_file(&path).unwrap();
+    }
+}
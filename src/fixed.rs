@@ -0,0 +1,343 @@
+use core::fmt::{self, Debug};
+
+use super::{ElementType, Error};
+
+/// Fixed-capacity union-find stored entirely in an array.
+///
+/// An `ArrayUnionFind<N, Element>` holds up to `N` elements of type
+/// `Element`, without allocating. It is `Copy`, which makes it convenient
+/// in inner loops over small, known universes.
+///
+/// Because it has no interior mutability, [`find`](#method.find) does not
+/// compress paths. Union by rank keeps every path no longer than the
+/// base-2 log of `N`, and [`union`](#method.union) and
+/// [`force`](#method.force) compress the paths they visit.
+///
+/// When `Element` is `usize`, the union-find can also be built and
+/// queried in constant expressions, using [`const_new`](#method.const_new),
+/// [`with_union`](#method.with_union), [`const_find`](#method.const_find),
+/// and [`const_equiv`](#method.const_equiv).
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::ArrayUnionFind;
+///
+/// let mut uf = ArrayUnionFind::<8>::new(8);
+/// uf.union(0, 1);
+/// uf.union(6, 1);
+///
+/// let copy = uf;
+/// uf.union(2, 3);
+///
+/// assert!(copy.equiv(0, 6));
+/// assert!(!copy.equiv(2, 3));
+/// assert!(uf.equiv(2, 3));
+/// ```
+#[derive(Clone, Copy)]
+pub struct ArrayUnionFind<const N: usize, Element: ElementType = usize> {
+    len:     usize,
+    parents: [Element; N],
+    ranks:   [u8; N],
+}
+// Invariants: self.len <= N, every element less than N is representable
+// as an `Element`, and every parent is less than self.len.
+
+impl<const N: usize, Element: ElementType> Debug for ArrayUnionFind<N, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "ArrayUnionFind({:?})", &self.parents[.. self.len])
+    }
+}
+
+impl<const N: usize, Element: ElementType> Default for ArrayUnionFind<N, Element> {
+    fn default() -> Self {
+        ArrayUnionFind::new(0)
+    }
+}
+
+impl<const N: usize, Element: ElementType> ArrayUnionFind<N, Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` exceeds `N`, or if `N` elements would overflow the
+    /// element type `Element`.
+    pub fn new(size: usize) -> Self {
+        Self::try_new(size).expect("ArrayUnionFind::new: overflow")
+    }
+
+    /// Creates a new union-find of `size` elements, if possible.
+    ///
+    /// Like [`new`](#method.new), but returns `Err(Error::Overflow)`
+    /// instead of panicking.
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        if size > N || (N > 0 && Element::from_usize(N - 1).is_none()) {
+            return Err(Error::Overflow);
+        }
+
+        let mut index = 0;
+        Ok(ArrayUnionFind {
+            len:     size,
+            parents: [(); N].map(|()| {
+                let element = Element::from_usize(index).unwrap();
+                index += 1;
+                element
+            }),
+            ranks:   [0; N],
+        })
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The maximum number of elements, `N`.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If the union-find already has `N` elements.
+    pub fn alloc(&mut self) -> Element {
+        self.try_alloc().expect("ArrayUnionFind::alloc: overflow")
+    }
+
+    /// Creates a new element in a singleton set, if possible.
+    ///
+    /// Returns `Err(Error::Overflow)` if the union-find already has `N`
+    /// elements.
+    pub fn try_alloc(&mut self) -> Result<Element, Error> {
+        if self.len == N {
+            return Err(Error::Overflow);
+        }
+
+        let result = self.parents[self.len];
+        self.len += 1;
+        Ok(result)
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find_compress(a).to_usize();
+        let b = self.find_compress(b).to_usize();
+
+        if a == b { return false; }
+
+        if self.ranks[a] > self.ranks[b] {
+            self.parents[b] = self.parents[a];
+        } else if self.ranks[b] > self.ranks[a] {
+            self.parents[a] = self.parents[b];
+        } else {
+            self.parents[a] = self.parents[b];
+            self.ranks[b] = self.ranks[b].saturating_add(1);
+        }
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: Element) -> Element {
+        let mut index = self.check(element);
+
+        loop {
+            let parent = self.parents[index];
+            if parent.to_usize() == index { return parent; }
+            index = parent.to_usize();
+        }
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Determines whether the given element is alone in its set.
+    pub fn is_singleton(&self, element: Element) -> bool {
+        let index = self.check(element);
+        self.parents[index] == element && self.ranks[index] == 0
+    }
+
+    /// Forces all laziness, so that each element points directly to its
+    /// set’s representative.
+    pub fn force(&mut self) {
+        for i in 0 .. self.len {
+            let element = self.parents[i];
+            let root = self.find_compress(element);
+            self.parents[i] = root;
+        }
+    }
+
+    // HELPERS
+
+    fn check(&self, element: Element) -> usize {
+        let index = element.to_usize();
+        assert!(index < self.len, "ArrayUnionFind: out of bounds");
+        index
+    }
+
+    // Finds the root, halving the path along the way.
+    fn find_compress(&mut self, element: Element) -> Element {
+        let mut index = self.check(element);
+        let mut parent = self.parents[index].to_usize();
+
+        while index != parent {
+            let grandparent = self.parents[parent];
+            self.parents[index] = grandparent;
+            index = parent;
+            parent = grandparent.to_usize();
+        }
+
+        self.parents[index]
+    }
+}
+
+impl<const N: usize> ArrayUnionFind<N, usize> {
+    /// Creates a new union-find of `size` elements in a constant
+    /// expression.
+    ///
+    /// # Panics
+    ///
+    /// If `size` exceeds `N`.
+    pub const fn const_new(size: usize) -> Self {
+        assert!(size <= N, "ArrayUnionFind::const_new: overflow");
+
+        let mut parents = [0; N];
+        let mut i = 0;
+        while i < N {
+            parents[i] = i;
+            i += 1;
+        }
+
+        ArrayUnionFind {
+            len:   size,
+            parents,
+            ranks: [0; N],
+        }
+    }
+
+    /// Returns the union-find with the sets of the two given elements
+    /// joined.
+    ///
+    /// This is a by-value form of [`union`](#method.union) for use in
+    /// constant expressions.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub const fn with_union(mut self, a: usize, b: usize) -> Self {
+        let a = self.const_find(a);
+        let b = self.const_find(b);
+
+        if a != b {
+            if self.ranks[a] > self.ranks[b] {
+                self.parents[b] = a;
+            } else if self.ranks[b] > self.ranks[a] {
+                self.parents[a] = b;
+            } else {
+                self.parents[a] = b;
+                self.ranks[b] = self.ranks[b].saturating_add(1);
+            }
+        }
+
+        self
+    }
+
+    /// Finds the representative element for the given element’s set in a
+    /// constant expression.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub const fn const_find(&self, mut element: usize) -> usize {
+        assert!(element < self.len, "ArrayUnionFind: out of bounds");
+
+        while self.parents[element] != element {
+            element = self.parents[element];
+        }
+
+        element
+    }
+
+    /// Determines whether two elements are in the same set in a constant
+    /// expression.
+    pub const fn const_equiv(&self, a: usize, b: usize) -> bool {
+        self.const_find(a) == self.const_find(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut uf = ArrayUnionFind::<8, u8>::new(8);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(1, 2));
+        assert!(uf.union(4, 3));
+        assert!(uf.union(3, 2));
+        assert!(! uf.union(0, 3));
+
+        assert!(uf.equiv(0, 4));
+        assert!(!uf.equiv(0, 5));
+        assert!(uf.is_singleton(5));
+        assert!(!uf.is_singleton(4));
+
+        uf.force();
+        let root = uf.find(0);
+        assert!((0 .. 5).all(|i| uf.parents[i] == root));
+    }
+
+    #[test]
+    fn const_construction() {
+        const UF: ArrayUnionFind<6> = ArrayUnionFind::const_new(6)
+            .with_union(0, 5)
+            .with_union(5, 4)
+            .with_union(1, 2);
+        const ROOT: usize = UF.const_find(4);
+
+        assert_eq!(ROOT, UF.find(0));
+        assert!(UF.const_equiv(2, 1));
+        assert!(!UF.const_equiv(0, 1));
+        assert!(UF.const_equiv(0, 5));
+    }
+
+    #[test]
+    fn alloc() {
+        let mut uf = ArrayUnionFind::<2>::new(0);
+        assert!(uf.is_empty());
+        assert_eq!(uf.try_alloc(), Ok(0));
+        assert_eq!(uf.try_alloc(), Ok(1));
+        assert_eq!(uf.try_alloc(), Err(Error::Overflow));
+        assert_eq!(uf.len(), 2);
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(ArrayUnionFind::<4>::try_new(5).is_err());
+        assert!(ArrayUnionFind::<300, u8>::try_new(3).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_bounds() {
+        ArrayUnionFind::<4>::new(2).find(2);
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt::{self, Debug};
+use std::mem;
+
+use super::{Merge, UnionFind};
+
+/// Arena-based union-find with associated data.
+///
+/// This offers the same operations as
+/// [`UnionFindNode`](struct.UnionFindNode.html), but the forest owns all
+/// of its nodes in a single arena and hands out [`NodeId`](struct.NodeId.html)
+/// handles, which are `Copy`. That avoids an allocation and reference
+/// count per node, and since handles do not own anything, data that
+/// refers to other nodes cannot form leaking cycles. Dropping the forest
+/// frees every node at once.
+///
+/// A `NodeId` is meaningful only to the forest that created it.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::UnionForest;
+///
+/// let mut forest = UnionForest::new();
+/// let a = forest.add(vec!["a"]);
+/// let b = forest.add(vec!["b"]);
+/// let c = forest.add(vec!["c"]);
+///
+/// forest.union_with(a, b, |mut x, y| { x.extend(y); x });
+///
+/// assert!(forest.equiv(a, b));
+/// assert!(!forest.equiv(b, c));
+/// assert_eq!(forest.data(b), &["a", "b"]);
+/// ```
+#[derive(Clone)]
+pub struct UnionForest<Data> {
+    union_find: UnionFind<usize>,
+    // The data of each set, at the index of its representative.
+    data:       Vec<Option<Data>>,
+}
+
+/// A handle to a node of a [`UnionForest`](struct.UnionForest.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The index of the node in its forest, counting from zero in the
+    /// order nodes were added.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl<Data: Debug> Debug for UnionForest<Data> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "UnionForest(")?;
+        formatter.debug_map()
+            .entries(self.data.iter().enumerate().filter_map(|(i, data)| {
+                data.as_ref().map(|data| (i, data))
+            }))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Data> Default for UnionForest<Data> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Data> UnionForest<Data> {
+    /// Creates a new, empty forest.
+    pub fn new() -> Self {
+        UnionForest {
+            union_find: UnionFind::new(0),
+            data:       Vec::new(),
+        }
+    }
+
+    /// The number of nodes in the forest.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Is the forest devoid of nodes?
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Adds a new node in a singleton set with associated data.
+    pub fn add(&mut self, data: Data) -> NodeId {
+        self.data.push(Some(data));
+        NodeId(self.union_find.alloc())
+    }
+
+    /// Unions two sets, combining their data as specified.
+    ///
+    /// The closure `f` is passed `a`’s data and `b`’s data (in that
+    /// order), and must return the data to associate with the unioned
+    /// set.
+    ///
+    /// Returns whether anything changed.
+    ///
+    /// # Panics
+    ///
+    /// If either node is out of bounds.
+    pub fn union_with<F>(&mut self, a: NodeId, b: NodeId, f: F) -> bool
+            where F: FnOnce(Data, Data) -> Data {
+
+        let a = self.find(a).0;
+        let b = self.find(b).0;
+
+        if !self.union_find.union(a, b) { return false; }
+
+        let data_a = self.data[a].take().unwrap();
+        let data_b = self.data[b].take().unwrap();
+        let root = self.union_find.find(a);
+        self.data[root] = Some(f(data_a, data_b));
+        true
+    }
+
+    /// Unions two sets, combining their data with
+    /// [`Merge::merge`](trait.Merge.html).
+    ///
+    /// # Panics
+    ///
+    /// If either node is out of bounds.
+    pub fn union_merged(&mut self, a: NodeId, b: NodeId) -> bool
+            where Data: Merge {
+        self.union_with(a, b, Data::merge)
+    }
+
+    /// Unions two sets.
+    ///
+    /// Retains the data of `a`’s set, returning the data of `b`’s set.
+    /// Returns `None` if `a` and `b` are already in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either node is out of bounds.
+    pub fn union(&mut self, a: NodeId, b: NodeId) -> Option<Data> {
+        let mut other = None;
+        self.union_with(a, b, |data_a, data_b| {
+            other = Some(data_b);
+            data_a
+        });
+        other
+    }
+
+    /// Finds the representative node for the given node’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `node` is out of bounds.
+    pub fn find(&self, node: NodeId) -> NodeId {
+        NodeId(self.union_find.find(node.0))
+    }
+
+    /// Are the two nodes in the same set?
+    pub fn equiv(&self, a: NodeId, b: NodeId) -> bool {
+        self.union_find.equiv(a.0, b.0)
+    }
+
+    /// The data associated with the node’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `node` is out of bounds.
+    pub fn data(&self, node: NodeId) -> &Data {
+        self.data[self.find(node).0].as_ref().unwrap()
+    }
+
+    /// The data associated with the node’s set, mutably.
+    ///
+    /// # Panics
+    ///
+    /// If `node` is out of bounds.
+    pub fn data_mut(&mut self, node: NodeId) -> &mut Data {
+        let root = self.find(node).0;
+        self.data[root].as_mut().unwrap()
+    }
+
+    /// Replaces the data associated with the node’s set.
+    pub fn replace_data(&mut self, node: NodeId, new: Data) -> Data {
+        mem::replace(self.data_mut(node), new)
+    }
+
+    /// Returns a clone of the data associated with the node’s set.
+    pub fn clone_data(&self, node: NodeId) -> Data
+            where Data: Clone {
+        self.data(node).clone()
+    }
+
+    /// Removes every node, dropping all data.
+    ///
+    /// Existing `NodeId`s become invalid.
+    pub fn clear(&mut self) {
+        self.union_find = UnionFind::new(0);
+        self.data.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut forest = UnionForest::new();
+        let nodes: Vec<_> = (0 .. 8).map(|i| forest.add(i)).collect();
+
+        assert_eq!(forest.union(nodes[0], nodes[1]), Some(1));
+        assert!(forest.union_with(nodes[2], nodes[3], |a, b| a * b));
+        assert!(forest.union_with(nodes[3], nodes[1], |a, b| a + b));
+        assert!(!forest.union_with(nodes[0], nodes[2], |_, _| unreachable!()));
+
+        assert_eq!(*forest.data(nodes[1]), 6);
+        assert_eq!(forest.replace_data(nodes[7], 70), 7);
+        *forest.data_mut(nodes[0]) += 1;
+        assert_eq!(forest.clone_data(nodes[2]), 7);
+        assert_eq!(forest.find(nodes[3]), forest.find(nodes[0]));
+        assert_eq!(nodes[4].index(), 4);
+
+        forest.clear();
+        assert!(forest.is_empty());
+    }
+
+    #[test]
+    fn cyclic_data_does_not_leak() {
+        use std::rc::Rc;
+
+        // Each node’s data refers to another node, and shares an `Rc` to
+        // count how much data is alive.
+        let alive = Rc::new(());
+        let mut forest = UnionForest::new();
+        let a = forest.add((None, alive.clone()));
+        let b = forest.add((Some(a), alive.clone()));
+        forest.data_mut(a).0 = Some(b);
+        forest.union(a, b);
+
+        assert_eq!(Rc::strong_count(&alive), 2);
+        drop(forest);
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+}
@@ -0,0 +1,344 @@
+use std::cmp;
+use std::fmt::{self, Debug};
+
+use super::{AUnionFind, ElementType, EntryLayout, Partition, UnionFind, UnionFindNode};
+use allocator::Allocator;
+
+/// The operations common to this crate’s union-finds, for writing
+/// algorithms that work with any of them.
+///
+/// Unions take `&mut self`, since most union-finds need exclusive access
+/// to join sets. Those that can join sets through a shared reference,
+/// from many threads at once, also implement
+/// [`ConcurrentDisjointSets`](trait.ConcurrentDisjointSets.html).
+///
+/// The types’ own methods of the same names take precedence over the
+/// trait’s, so the trait matters only to generic code.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{AUnionFind, DisjointSets, NodeUnionFind, UnionFind};
+///
+/// fn join_pairs<S: DisjointSets<Element = usize>>(sets: &mut S) {
+///     for i in (0 .. sets.len() - 1).step_by(2) {
+///         DisjointSets::union(sets, i, i + 1);
+///     }
+/// }
+///
+/// let mut array = UnionFind::<usize>::new(5);
+/// let mut atomic = AUnionFind::new(5);
+/// let mut nodes = NodeUnionFind::new(5);
+/// join_pairs(&mut array);
+/// join_pairs(&mut atomic);
+/// join_pairs(&mut nodes);
+///
+/// assert_eq!(array.groups(), vec![vec![0, 1], vec![2, 3], vec![4]]);
+/// assert_eq!(atomic.components(), array.components());
+/// assert_eq!(nodes.components(), array.components());
+/// ```
+pub trait DisjointSets {
+    /// The type of the elements, which are numbered consecutively from 0.
+    type Element: ElementType;
+
+    /// The number of elements in all the sets.
+    fn len(&self) -> usize;
+
+    /// Is the union-find devoid of elements?
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed, that is, whether the elements
+    /// were in different sets.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    fn union(&mut self, a: Self::Element, b: Self::Element) -> bool;
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    fn find(&self, element: Self::Element) -> Self::Element;
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    fn equiv(&self, a: Self::Element, b: Self::Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Labels the sets, numbering them consecutively from 0 in the order
+    /// of their least elements.
+    fn components(&self) -> Partition {
+        Partition::from_representatives((0 .. self.len())
+            .map(|i| self.find(element(i)).to_usize())
+            .collect())
+    }
+
+    /// Returns the elements of each set.
+    ///
+    /// The sets are in order of their least elements, and each set’s
+    /// elements are in increasing order.
+    fn groups(&self) -> Vec<Vec<Self::Element>> {
+        self.components().sets().into_iter()
+            .map(|set| set.into_iter().map(element).collect())
+            .collect()
+    }
+}
+
+/// A union-find whose unions need only a shared reference, so that many
+/// threads may join sets at once.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{AUnionFind, ConcurrentDisjointSets, DisjointSets};
+/// use std::thread;
+///
+/// fn join_all<S: ConcurrentDisjointSets<Element = usize>>(sets: &S) {
+///     thread::scope(|scope| {
+///         for i in 1 .. sets.len() {
+///             scope.spawn(move || sets.union_shared(i - 1, i));
+///         }
+///     });
+/// }
+///
+/// let uf = AUnionFind::new(4);
+/// join_all(&uf);
+/// assert_eq!(uf.groups(), vec![vec![0, 1, 2, 3]]);
+/// ```
+pub trait ConcurrentDisjointSets: DisjointSets + Sync {
+    /// Joins the sets of the two given elements, like
+    /// [`union`](trait.DisjointSets.html#tymethod.union), through a
+    /// shared reference.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    fn union_shared(&self, a: Self::Element, b: Self::Element) -> bool;
+}
+
+// Converts an index known to be in bounds to an element.
+fn element<Element: ElementType>(i: usize) -> Element {
+    Element::from_usize(i).expect("DisjointSets: element out of range")
+}
+
+impl<Element: ElementType, A: Allocator> DisjointSets for UnionFind<Element, A> {
+    type Element = Element;
+
+    fn len(&self) -> usize {
+        UnionFind::len(self)
+    }
+
+    fn union(&mut self, a: Element, b: Element) -> bool {
+        UnionFind::union(self, a, b)
+    }
+
+    fn find(&self, element: Element) -> Element {
+        UnionFind::find(self, element)
+    }
+
+    fn equiv(&self, a: Element, b: Element) -> bool {
+        UnionFind::equiv(self, a, b)
+    }
+
+    fn components(&self) -> Partition {
+        self.partition()
+    }
+}
+
+impl<L: EntryLayout> DisjointSets for AUnionFind<L> {
+    type Element = usize;
+
+    fn len(&self) -> usize {
+        AUnionFind::len(self)
+    }
+
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        AUnionFind::union(self, a, b)
+    }
+
+    fn find(&self, element: usize) -> usize {
+        AUnionFind::find(self, element)
+    }
+
+    fn equiv(&self, a: usize, b: usize) -> bool {
+        AUnionFind::equiv(self, a, b)
+    }
+
+    fn components(&self) -> Partition {
+        Partition::from_representatives(self.to_vec())
+    }
+}
+
+impl<L: EntryLayout> ConcurrentDisjointSets for AUnionFind<L> where Self: Sync {
+    fn union_shared(&self, a: usize, b: usize) -> bool {
+        AUnionFind::union(self, a, b)
+    }
+}
+
+/// A fixed number of [`UnionFindNode`](struct.UnionFindNode.html)s
+/// numbered from 0, so that pointer-based sets can be used through
+/// [`DisjointSets`](trait.DisjointSets.html).
+///
+/// Each set’s data is its least element, which serves as its
+/// representative.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::NodeUnionFind;
+///
+/// let mut uf = NodeUnionFind::new(4);
+/// assert!(uf.union(3, 1));
+/// assert!(uf.union(2, 3));
+/// assert_eq!(uf.find(2), 1);
+/// assert!(!uf.equiv(0, 3));
+/// ```
+pub struct NodeUnionFind {
+    nodes: Vec<UnionFindNode<usize>>,
+}
+// Invariant: the data of each set is its least element.
+
+impl NodeUnionFind {
+    /// Creates a new union-find of `len` singleton sets.
+    pub fn new(len: usize) -> Self {
+        NodeUnionFind {
+            nodes: (0 .. len).map(UnionFindNode::new).collect(),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let mut other = self.node(b, "NodeUnionFind::union").clone();
+        self.node(a, "NodeUnionFind::union").clone()
+            .union_with(&mut other, cmp::min)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// which is the set’s least element.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: usize) -> usize {
+        *self.node(element, "NodeUnionFind::find").data()
+    }
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        self.node(a, "NodeUnionFind::equiv")
+            .equiv(self.node(b, "NodeUnionFind::equiv"))
+    }
+
+    fn node(&self, element: usize, context: &str) -> &UnionFindNode<usize> {
+        match self.nodes.get(element) {
+            Some(node) => node,
+            None       => panic!("{}: out of bounds", context),
+        }
+    }
+}
+
+impl Default for NodeUnionFind {
+    fn default() -> Self {
+        NodeUnionFind::new(0)
+    }
+}
+
+impl Debug for NodeUnionFind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("NodeUnionFind")
+            .field("groups", &self.groups())
+            .finish()
+    }
+}
+
+impl DisjointSets for NodeUnionFind {
+    type Element = usize;
+
+    fn len(&self) -> usize {
+        NodeUnionFind::len(self)
+    }
+
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        NodeUnionFind::union(self, a, b)
+    }
+
+    fn find(&self, element: usize) -> usize {
+        NodeUnionFind::find(self, element)
+    }
+
+    fn equiv(&self, a: usize, b: usize) -> bool {
+        NodeUnionFind::equiv(self, a, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomized::splitmix64;
+
+    const SIZE: usize = 64;
+
+    // Applies the same pseudorandom unions to `sets` and returns its
+    // components, checking `union`’s result against `equiv` on the way.
+    fn run<S: DisjointSets>(mut sets: S) -> Partition {
+        for i in 0 .. SIZE as u64 / 2 {
+            let a = element(splitmix64(i) as usize % SIZE);
+            let b = element(splitmix64(!i) as usize % SIZE);
+            let joined = !sets.equiv(a, b);
+            assert_eq!(DisjointSets::union(&mut sets, a, b), joined);
+            assert!(sets.equiv(a, b));
+            assert!(sets.equiv(sets.find(a), b));
+        }
+        sets.components()
+    }
+
+    #[test]
+    fn implementations_agree() {
+        let expected = run(UnionFind::<u16>::new(SIZE));
+        assert_eq!(run(UnionFind::<usize>::new(SIZE)), expected);
+        assert_eq!(run(AUnionFind::new(SIZE)), expected);
+        assert_eq!(run(NodeUnionFind::new(SIZE)), expected);
+        assert!(expected.num_sets() > 1 && expected.num_sets() < SIZE);
+    }
+
+    #[test]
+    fn groups() {
+        let mut uf = UnionFind::<u8>::new(5);
+        uf.union(4, 1);
+        uf.union(3, 0);
+        uf.union(0, 1);
+
+        assert_eq!(uf.groups(), vec![vec![0, 1, 3, 4], vec![2]]);
+        assert!(DisjointSets::is_empty(&NodeUnionFind::default()));
+    }
+}
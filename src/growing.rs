@@ -0,0 +1,130 @@
+use super::{ElementType, MemoryUsage, UnionFind};
+
+/// Vector-based union-find that grows on demand.
+///
+/// This wraps a [`UnionFind`](struct.UnionFind.html), but rather than
+/// panicking when given an element beyond the current length, it first
+/// allocates singleton sets for all the missing elements. This is useful
+/// when the number of elements isn’t known up front.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::GrowingUnionFind;
+///
+/// let mut uf = GrowingUnionFind::<u32>::new();
+/// assert!(uf.union(3, 5));
+/// assert_eq!(uf.len(), 6);
+/// assert!(uf.equiv(5, 3));
+/// assert!(!uf.equiv(4, 9));
+/// assert_eq!(uf.len(), 10);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct GrowingUnionFind<Element: ElementType = usize>(UnionFind<Element>);
+
+impl<Element: ElementType> GrowingUnionFind<Element> {
+    /// Creates a new, empty growing union-find.
+    pub fn new() -> Self {
+        GrowingUnionFind(UnionFind::new(0))
+    }
+
+    /// The number of elements allocated so far.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.0.memory_usage()
+    }
+
+    /// Joins the sets of the two given elements, allocating them first
+    /// if necessary.
+    ///
+    /// Returns whether anything changed.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        self.ensure(a);
+        self.ensure(b);
+        self.0.union(a, b)
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// allocating it first if necessary.
+    pub fn find(&mut self, element: Element) -> Element {
+        self.ensure(element);
+        self.0.find(element)
+    }
+
+    /// Determines whether two elements are in the same set, allocating
+    /// them first if necessary.
+    pub fn equiv(&mut self, a: Element, b: Element) -> bool {
+        self.ensure(a);
+        self.ensure(b);
+        self.0.equiv(a, b)
+    }
+
+    /// Allocates singleton sets up to and including the given element, if
+    /// they don’t exist already.
+    pub fn ensure(&mut self, element: Element) {
+        let index = element.to_usize();
+        if index >= self.0.len() {
+            self.0.try_reserve((index - self.0.len()).saturating_add(1))
+                .expect("GrowingUnionFind::ensure: allocation failed");
+            while index >= self.0.len() {
+                self.0.alloc();
+            }
+        }
+    }
+
+    /// Returns a reference to the underlying union-find.
+    pub fn as_union_find(&self) -> &UnionFind<Element> {
+        &self.0
+    }
+
+    /// Returns the underlying union-find.
+    pub fn into_union_find(self) -> UnionFind<Element> {
+        self.0
+    }
+}
+
+impl<Element: ElementType> From<UnionFind<Element>> for GrowingUnionFind<Element> {
+    fn from(union_find: UnionFind<Element>) -> Self {
+        GrowingUnionFind(union_find)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows() {
+        let mut uf = GrowingUnionFind::<u8>::new();
+        assert!(uf.is_empty());
+        assert_eq!(uf.find(4), 4);
+        assert_eq!(uf.len(), 5);
+
+        assert!(uf.union(2, 7));
+        assert_eq!(uf.len(), 8);
+        assert!(uf.equiv(7, 2));
+        assert!(!uf.equiv(7, 3));
+
+        let uf = uf.into_union_find();
+        assert_eq!(uf.len(), 8);
+        assert!(uf.equiv(2, 7));
+    }
+
+    #[test]
+    fn from_union_find() {
+        let mut uf = GrowingUnionFind::from(UnionFind::<u32>::new(3));
+        uf.ensure(1);
+        assert_eq!(uf.len(), 3);
+        uf.ensure(3);
+        assert_eq!(uf.len(), 4);
+    }
+}
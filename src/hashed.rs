@@ -0,0 +1,440 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::collections::hash_map::{self, RandomState};
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::slice;
+
+use super::UnionFind;
+
+/// Union-find over arbitrary hashable keys.
+///
+/// Each key becomes an element the first time it is passed to
+/// [`insert`](#method.insert) or [`union`](#method.union), so there is no
+/// need to keep a separate map from keys to element numbers. A key that
+/// has never been inserted is treated as being alone in its set.
+///
+/// Each key is stored twice, once in a vector and once in a hash map, so
+/// for large keys consider using `Rc` or `Arc`.
+///
+/// Each operation on keys hashes them. To avoid hashing the same key
+/// repeatedly, [`entry`](#method.entry) looks up a key once and returns a
+/// [`KeyId`](struct.KeyId.html), which the `_by_id` methods accept
+/// instead of a key.
+///
+/// Like `HashMap`, the methods that look up keys accept any borrowed
+/// form of the key type, such as `&str` for `String` keys.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::HashUnionFind;
+///
+/// let mut uf = HashUnionFind::new();
+/// uf.union(&"alice@example.com", &"alice");
+/// uf.union(&"alice", &"a.smith");
+/// uf.insert("bob");
+///
+/// assert!( uf.equiv(&"alice@example.com", &"a.smith"));
+/// assert!(!uf.equiv(&"alice", &"bob"));
+/// assert!(!uf.equiv(&"alice", &"carol"));
+/// assert_eq!(uf.len(), 4);
+/// ```
+#[derive(Clone)]
+pub struct HashUnionFind<K, S = RandomState> {
+    keys:       Vec<K>,
+    indices:    HashMap<K, usize, S>,
+    union_find: UnionFind<usize>,
+}
+
+/// Identifies a key in a [`HashUnionFind`](struct.HashUnionFind.html)
+/// without hashing it.
+///
+/// Each key’s id is the number of keys inserted before it, and is only
+/// meaningful for the union-find that produced it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyId(usize);
+
+impl KeyId {
+    /// The number of keys inserted before this one.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A key looked up in a [`HashUnionFind`](struct.HashUnionFind.html),
+/// which is inserted if it was absent.
+///
+/// This is produced by [`HashUnionFind::entry`](struct.HashUnionFind.html#method.entry).
+pub struct KeyEntry<'a, K: 'a, S: 'a = RandomState> {
+    union_find: &'a mut HashUnionFind<K, S>,
+    id:         KeyId,
+    inserted:   bool,
+}
+
+impl<'a, K: Hash + Eq + Clone, S: BuildHasher> KeyEntry<'a, K, S> {
+    /// The id of the key.
+    pub fn id(&self) -> KeyId {
+        self.id
+    }
+
+    /// The key.
+    pub fn key(&self) -> &K {
+        self.union_find.key(self.id)
+    }
+
+    /// Was the key inserted by this lookup?
+    pub fn is_new(&self) -> bool {
+        self.inserted
+    }
+
+    /// Finds the representative key for the key’s set.
+    pub fn find(&self) -> &K {
+        let root = self.union_find.find_by_id(self.id);
+        self.union_find.key(root)
+    }
+
+    /// Joins the key’s set with the set of the key with the given id.
+    ///
+    /// Returns whether anything changed.
+    pub fn union(self, other: KeyId) -> bool {
+        self.union_find.union_by_id(self.id, other)
+    }
+}
+
+impl<'a, K: Debug, S> Debug for KeyEntry<'a, K, S> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("KeyEntry")
+            .field("key", &self.union_find.keys[self.id.0])
+            .field("id", &self.id)
+            .field("inserted", &self.inserted)
+            .finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone> HashUnionFind<K> {
+    /// Creates a new, empty union-find.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Creates a new, empty union-find with space for at least `capacity`
+    /// keys.
+    pub fn with_capacity(capacity: usize) -> Self {
+        HashUnionFind {
+            keys:       Vec::with_capacity(capacity),
+            indices:    HashMap::with_capacity(capacity),
+            union_find: UnionFind::new(0),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, S: BuildHasher + Default> Default for HashUnionFind<K, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: Debug, S> Debug for HashUnionFind<K, S> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "HashUnionFind(")?;
+        formatter.debug_map()
+            .entries(self.keys.iter().enumerate().map(|(i, key)| {
+                (key, &self.keys[self.union_find.find(i)])
+            }))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<K: Hash + Eq + Clone, S: BuildHasher> HashUnionFind<K, S> {
+    /// Creates a new, empty union-find that hashes keys with the given
+    /// hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        HashUnionFind {
+            keys:       Vec::new(),
+            indices:    HashMap::with_hasher(hasher),
+            union_find: UnionFind::new(0),
+        }
+    }
+
+    /// The number of keys inserted.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Is the union-find devoid of keys?
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Has the given key been inserted?
+    pub fn contains<Q>(&self, key: &Q) -> bool
+        where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+
+        self.indices.contains_key(key)
+    }
+
+    /// Inserts a key in a singleton set, if it is not already present.
+    ///
+    /// Returns whether the key was new.
+    pub fn insert(&mut self, key: K) -> bool {
+        let len = self.len();
+        self.index_or_insert(key) == len
+    }
+
+    /// Joins the sets of the two given keys, inserting them if they are
+    /// not already present.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union<Q>(&mut self, a: &Q, b: &Q) -> bool
+        where K: Borrow<Q>, Q: Hash + Eq + ToOwned<Owned = K> + ?Sized {
+
+        let a = self.entry_ref(a).id.0;
+        let b = self.entry_ref(b).id.0;
+        self.union_find.union(a, b)
+    }
+
+    /// Finds the representative key for the given key’s set.
+    ///
+    /// A key that has not been inserted is its own representative.
+    pub fn find<'a, Q>(&'a self, key: &'a Q) -> &'a Q
+        where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+
+        match self.indices.get(key) {
+            Some(&index) => self.keys[self.union_find.find(index)].borrow(),
+            None         => key,
+        }
+    }
+
+    /// Determines whether two keys are in the same set.
+    pub fn equiv<Q>(&self, a: &Q, b: &Q) -> bool
+        where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+
+        match (self.indices.get(a), self.indices.get(b)) {
+            (Some(&a), Some(&b)) => self.union_find.equiv(a, b),
+            _                    => a == b,
+        }
+    }
+
+    /// Looks up a key, inserting it in a singleton set if it is absent,
+    /// and returns a handle to it.
+    ///
+    /// This hashes the key once, and the handle’s [`id`](struct.KeyEntry.html#method.id)
+    /// can then be used without hashing it again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::HashUnionFind;
+    ///
+    /// let mut uf = HashUnionFind::new();
+    /// let alice = uf.entry("alice".to_string()).id();
+    ///
+    /// for alias in &["a.smith", "alice@example.com"] {
+    ///     uf.entry(alias.to_string()).union(alice);
+    /// }
+    ///
+    /// let smith = uf.id(&"a.smith".to_string()).unwrap();
+    /// assert!(uf.equiv_by_id(alice, smith));
+    /// ```
+    pub fn entry(&mut self, key: K) -> KeyEntry<'_, K, S> {
+        let (id, inserted) = match self.indices.entry(key) {
+            hash_map::Entry::Occupied(entry) => (*entry.get(), false),
+            hash_map::Entry::Vacant(entry) => {
+                let index = self.union_find.alloc();
+                self.keys.push(entry.key().clone());
+                entry.insert(index);
+                (index, true)
+            }
+        };
+
+        KeyEntry { union_find: self, id: KeyId(id), inserted }
+    }
+
+    /// Looks up a key by reference, inserting an owned copy of it in a
+    /// singleton set if it is absent, and returns a handle to it.
+    ///
+    /// Unlike [`entry`](#method.entry), this needs an owned key only when
+    /// the key is new, at the cost of hashing a new key twice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::HashUnionFind;
+    ///
+    /// let mut uf = HashUnionFind::<String>::new();
+    /// let alice = uf.entry_ref("alice").id();
+    /// assert!(!uf.entry_ref("alice").is_new());
+    /// uf.entry_ref("a.smith").union(alice);
+    ///
+    /// assert!(uf.equiv("alice", "a.smith"));
+    /// ```
+    pub fn entry_ref<Q>(&mut self, key: &Q) -> KeyEntry<'_, K, S>
+        where K: Borrow<Q>, Q: Hash + Eq + ToOwned<Owned = K> + ?Sized {
+
+        match self.indices.get(key) {
+            Some(&index) => KeyEntry { union_find: self, id: KeyId(index), inserted: false },
+            None         => self.entry(key.to_owned()),
+        }
+    }
+
+    /// The id of the given key, if it has been inserted.
+    pub fn id<Q>(&self, key: &Q) -> Option<KeyId>
+        where K: Borrow<Q>, Q: Hash + Eq + ?Sized {
+
+        self.indices.get(key).map(|&index| KeyId(index))
+    }
+
+    /// The key with the given id.
+    ///
+    /// # Panics
+    ///
+    /// If `id` is out of bounds.
+    pub fn key(&self, id: KeyId) -> &K {
+        &self.keys[id.0]
+    }
+
+    /// Joins the sets of the keys with the given ids.
+    ///
+    /// Returns whether anything changed.
+    ///
+    /// # Panics
+    ///
+    /// If either id is out of bounds.
+    pub fn union_by_id(&mut self, a: KeyId, b: KeyId) -> bool {
+        self.union_find.union(a.0, b.0)
+    }
+
+    /// Finds the id of the representative key for the set of the key with
+    /// the given id.
+    ///
+    /// # Panics
+    ///
+    /// If `id` is out of bounds.
+    pub fn find_by_id(&self, id: KeyId) -> KeyId {
+        KeyId(self.union_find.find(id.0))
+    }
+
+    /// Determines whether the keys with the given ids are in the same
+    /// set.
+    ///
+    /// # Panics
+    ///
+    /// If either id is out of bounds.
+    pub fn equiv_by_id(&self, a: KeyId, b: KeyId) -> bool {
+        self.union_find.equiv(a.0, b.0)
+    }
+
+    /// Returns an iterator over the keys, in the order they were
+    /// inserted.
+    pub fn keys(&self) -> slice::Iter<'_, K> {
+        self.keys.iter()
+    }
+
+    /// Returns the sets, each as a vector of keys in the order they were
+    /// inserted.
+    ///
+    /// The sets are ordered by their first key.
+    pub fn groups(&self) -> Vec<Vec<&K>> {
+        let mut group_of = HashMap::new();
+        let mut result: Vec<Vec<&K>> = Vec::new();
+
+        for (i, key) in self.keys.iter().enumerate() {
+            let root = self.union_find.find(i);
+            let group = *group_of.entry(root).or_insert_with(|| {
+                result.push(Vec::new());
+                result.len() - 1
+            });
+            result[group].push(key);
+        }
+
+        result
+    }
+
+    // HELPERS
+
+    fn index_or_insert(&mut self, key: K) -> usize {
+        self.entry(key).id.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups() {
+        let mut uf = HashUnionFind::<String>::new();
+        let words = ["apple", "avocado", "banana", "blueberry", "cherry", "apricot"];
+        for word in &words {
+            let word = word.to_string();
+            let initial = word[.. 1].to_string();
+            uf.union(&initial, &word);
+        }
+
+        let groups: Vec<Vec<&str>> = uf.groups().into_iter()
+            .map(|group| group.into_iter().map(|key| key.as_str()).collect())
+            .collect();
+
+        assert_eq!(groups, vec![vec!["a", "apple", "avocado", "apricot"],
+                                vec!["b", "banana", "blueberry"],
+                                vec!["c", "cherry"]]);
+        assert_eq!(uf.find(&"apricot".to_string()), uf.find(&"a".to_string()));
+        assert_eq!(uf.find(&"durian".to_string()), "durian");
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut uf = HashUnionFind::<String>::new();
+        assert!(uf.union("x", "y"));
+        assert!(uf.contains("x"));
+        assert!(!uf.contains("z"));
+        assert!(uf.equiv("y", "x"));
+        assert!(!uf.equiv("x", "z"));
+        assert!(uf.equiv("z", "z"));
+        assert_eq!(uf.find("x"), uf.find("y"));
+        assert_eq!(uf.find("z"), "z");
+        assert_eq!(uf.id("y"), Some(KeyId(1)));
+
+        let entry = uf.entry_ref("z");
+        assert!(entry.is_new());
+        assert_eq!(entry.id(), KeyId(2));
+        assert_eq!(uf.entry_ref("x").id(), KeyId(0));
+        assert_eq!(uf.len(), 3);
+    }
+
+    #[test]
+    fn insert() {
+        let mut uf = HashUnionFind::<u64>::with_capacity(4);
+        assert!( uf.insert(10));
+        assert!(!uf.insert(10));
+        assert!( uf.union(&10, &20));
+        assert!(!uf.union(&20, &10));
+        assert!( uf.equiv(&30, &30));
+        assert!(!uf.contains(&30));
+        assert_eq!(uf.keys().cloned().collect::<Vec<_>>(), vec![10, 20]);
+    }
+
+    #[test]
+    fn entries() {
+        let mut uf = HashUnionFind::<&str>::new();
+        let x = uf.entry("x").id();
+        assert!(!uf.entry("x").is_new());
+
+        let mut entry = uf.entry("y");
+        assert!(entry.is_new());
+        assert_eq!(*entry.key(), "y");
+        assert!(entry.union(x));
+
+        entry = uf.entry("y");
+        let y = entry.id();
+        let root = *entry.find();
+        assert_eq!(root, *uf.key(uf.find_by_id(x)));
+        assert_eq!(uf.id(&"y"), Some(y));
+        assert_eq!(y.index(), 1);
+        assert!(uf.equiv_by_id(x, y));
+    }
+}
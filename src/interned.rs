@@ -0,0 +1,311 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use super::{MemoryUsage, UnionFind};
+
+/// Union-find over strings, which are interned into compact ids.
+///
+/// Every string is stored once, in a single buffer, and is identified by
+/// a `u32` id in the order it was first seen. Rather than a
+/// `HashMap<String, usize>`, which would store each string separately
+/// with its own allocation, lookups go through a hash table of ids that
+/// compares against the buffer.
+///
+/// Beyond its text, each string costs the `usize` offset of its end, a
+/// `u32` parent and a `u8` rank in the union-find, and two to four `u32`
+/// slots in the hash table, which is kept at most half full. On a 64-bit
+/// target, that is 21 to 29 bytes, not counting the spare capacity of the
+/// vectors, which [`memory_usage`](#method.memory_usage) includes.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::InternedUnionFind;
+///
+/// let mut uf = InternedUnionFind::new();
+/// uf.union("ACME Corp", "Acme Corporation");
+/// uf.union("Acme Corporation", "ACME");
+///
+/// assert!(uf.equiv("ACME", "ACME Corp"));
+/// assert!(!uf.equiv("ACME", "Initech"));
+///
+/// let id = uf.id("ACME").unwrap();
+/// assert_eq!(uf.resolve(id), "ACME");
+/// ```
+#[derive(Clone)]
+pub struct InternedUnionFind<S = RandomState> {
+    text:       String,
+    // The end of each string in `text`; each starts where the previous
+    // one ends.
+    ends:       Vec<usize>,
+    // An open-addressing hash table, whose length is a power of two,
+    // holding each string’s id plus one, or zero for an empty slot.
+    table:      Vec<u32>,
+    hasher:     S,
+    union_find: UnionFind<u32>,
+}
+
+impl InternedUnionFind {
+    /// Creates a new, empty union-find.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<S: BuildHasher + Default> Default for InternedUnionFind<S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<S> Debug for InternedUnionFind<S> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "InternedUnionFind(")?;
+        formatter.debug_map()
+            .entries((0 .. self.ends.len() as u32).map(|id| {
+                (self.string(id), self.string(self.union_find.find(id)))
+            }))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<S> InternedUnionFind<S> {
+    fn string(&self, id: u32) -> &str {
+        let id = id as usize;
+        let start = if id == 0 { 0 } else { self.ends[id - 1] };
+        &self.text[start .. self.ends[id]]
+    }
+}
+
+impl<S: BuildHasher> InternedUnionFind<S> {
+    /// Creates a new, empty union-find that hashes strings with the given
+    /// hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        InternedUnionFind {
+            text:       String::new(),
+            ends:       Vec::new(),
+            table:      Vec::new(),
+            hasher,
+            union_find: UnionFind::new(0),
+        }
+    }
+
+    /// The number of distinct strings interned.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Is the union-find devoid of strings?
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// The total length of the interned strings, in bytes.
+    pub fn text_len(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Reports the heap memory used by the union-find, including the
+    /// interned text.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<u8>("text", self.text.len(), self.text.capacity())
+            .array::<usize>("ends", self.ends.len(), self.ends.capacity())
+            .array::<u32>("table", self.table.len(), self.table.capacity())
+            .include(self.union_find.memory_usage())
+    }
+
+    /// Interns a string, if it is not already present, and returns its
+    /// id.
+    ///
+    /// # Panics
+    ///
+    /// If there are already `u32::MAX` strings.
+    pub fn intern(&mut self, string: &str) -> u32 {
+        let hash = self.hash(string);
+        let slot = self.slot(string, hash);
+        if !self.table.is_empty() && self.table[slot] != 0 {
+            return self.table[slot] - 1;
+        }
+
+        assert!(self.len() < u32::MAX as usize, "InternedUnionFind::intern: overflow");
+        let id = self.union_find.alloc();
+        self.text.push_str(string);
+        self.ends.push(self.text.len());
+
+        if 2 * self.len() > self.table.len() {
+            self.grow();
+        } else {
+            self.table[slot] = id + 1;
+        }
+
+        id
+    }
+
+    /// The id of the given string, if it has been interned.
+    pub fn id(&self, string: &str) -> Option<u32> {
+        if self.table.is_empty() { return None; }
+
+        match self.table[self.slot(string, self.hash(string))] {
+            0  => None,
+            id => Some(id - 1),
+        }
+    }
+
+    /// The string with the given id.
+    ///
+    /// # Panics
+    ///
+    /// If `id` is out of bounds.
+    pub fn resolve(&self, id: u32) -> &str {
+        assert!((id as usize) < self.len(), "InternedUnionFind::resolve: out of bounds");
+        self.string(id)
+    }
+
+    /// Joins the sets of the two given strings, interning them if they
+    /// are not already present.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: &str, b: &str) -> bool {
+        let a = self.intern(a);
+        let b = self.intern(b);
+        self.union_find.union(a, b)
+    }
+
+    /// Finds the representative string for the given string’s set.
+    ///
+    /// A string that has not been interned is its own representative.
+    pub fn find<'a>(&'a self, string: &'a str) -> &'a str {
+        match self.id(string) {
+            Some(id) => self.string(self.union_find.find(id)),
+            None     => string,
+        }
+    }
+
+    /// Determines whether two strings are in the same set.
+    pub fn equiv(&self, a: &str, b: &str) -> bool {
+        match (self.id(a), self.id(b)) {
+            (Some(a), Some(b)) => self.union_find.equiv(a, b),
+            _                  => a == b,
+        }
+    }
+
+    /// Joins the sets of the strings with the given ids.
+    ///
+    /// # Panics
+    ///
+    /// If either id is out of bounds.
+    pub fn union_ids(&mut self, a: u32, b: u32) -> bool {
+        self.union_find.union(a, b)
+    }
+
+    /// Finds the id of the representative string for the set of the
+    /// string with the given id.
+    ///
+    /// # Panics
+    ///
+    /// If `id` is out of bounds.
+    pub fn find_id(&self, id: u32) -> u32 {
+        self.union_find.find(id)
+    }
+
+    // HELPERS
+
+    // `BuildHasher::hash_one` would do, but it needs Rust 1.71.
+    #[allow(clippy::manual_hash_one)]
+    fn hash(&self, string: &str) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        string.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Finds the slot that holds `string`, or else the empty slot where it
+    // belongs. The table must not be full.
+    fn slot(&self, string: &str, hash: u64) -> usize {
+        if self.table.is_empty() { return 0; }
+
+        let mask = self.table.len() - 1;
+        let mut slot = hash as usize & mask;
+        loop {
+            match self.table[slot] {
+                0 => return slot,
+                id if self.string(id - 1) == string => return slot,
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    // Doubles the table, reinserting every string.
+    fn grow(&mut self) {
+        let capacity = (2 * self.table.len()).max(8);
+        self.table = vec![0; capacity];
+        let mask = capacity - 1;
+
+        for id in 0 .. self.len() as u32 {
+            let mut slot = self.hash(self.string(id)) as usize & mask;
+            while self.table[slot] != 0 {
+                slot = (slot + 1) & mask;
+            }
+            self.table[slot] = id + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overhead_per_string() {
+        use std::mem::size_of;
+
+        const COUNT: usize = 100_000;
+        let mut uf = InternedUnionFind::new();
+        for i in 0 .. COUNT {
+            uf.intern(&format!("{:08}", i));
+        }
+
+        // The bytes in use, not counting spare capacity or the text.
+        let usage = uf.memory_usage();
+        let overhead: usize = usage.arrays()[1 ..].iter()
+            .map(|array| array.len * array.element_size)
+            .sum();
+        let fixed = size_of::<usize>() + size_of::<u32>() + 1;
+        assert!(overhead >= COUNT * (fixed + 2 * size_of::<u32>()));
+        assert!(overhead <= COUNT * (fixed + 4 * size_of::<u32>()));
+    }
+
+    #[test]
+    fn interning() {
+        let mut uf = InternedUnionFind::new();
+        let words: Vec<String> = (0 .. 1000).map(|i| format!("word{}", i % 300)).collect();
+
+        for word in &words {
+            let id = uf.intern(word);
+            assert_eq!(uf.resolve(id), word);
+        }
+
+        assert_eq!(uf.len(), 300);
+        assert_eq!(uf.id("word17"), Some(17));
+        assert_eq!(uf.id("word300"), None);
+        assert_eq!(uf.text_len(), words[.. 300].iter().map(String::len).sum::<usize>());
+    }
+
+    #[test]
+    fn unions() {
+        let mut uf = InternedUnionFind::new();
+        assert!(uf.union("a", "b"));
+        assert!(uf.union("c", "b"));
+        assert!(!uf.union("a", "c"));
+        assert_eq!(uf.find("c"), uf.find("a"));
+        assert_eq!(uf.find("d"), "d");
+        assert!(uf.equiv("", ""));
+        let (a, d) = (uf.intern("a"), uf.intern("d"));
+        assert!(uf.union_ids(a, d));
+        assert_eq!(uf.find_id(d), uf.find_id(a));
+    }
+}
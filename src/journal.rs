@@ -0,0 +1,409 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::{ElementType, Error, UnionFind};
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+
+/// A union-find that records every allocation and union in an
+/// append-only journal.
+///
+/// Each [`alloc`](#method.alloc) and [`union`](#method.union) is written
+/// to the sink `W` before it is applied, so if the sink is a file, a crash
+/// loses at most the operation in progress. Reading the sink back with
+/// [`Journal::read_from`](struct.Journal.html#method.read_from) and
+/// replaying it with [`Journal::replay`](struct.Journal.html#method.replay)
+/// rebuilds a union-find with the same sets.
+///
+/// The journal starts with the magic bytes `b"DSUJ"`, the version as a
+/// little-endian `u32`, and the initial number of elements as a
+/// little-endian `u64`. Each operation follows as a one-byte tag: 0 for
+/// an allocation, or 1 for a union, followed by the two elements as
+/// little-endian `u64`s.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{Journal, JournaledUnionFind};
+///
+/// let mut uf = JournaledUnionFind::<u32, _>::new(3, Vec::new()).unwrap();
+/// uf.union(0, 1).unwrap();
+/// let e = uf.alloc().unwrap();
+/// uf.union(e, 2).unwrap();
+///
+/// let (original, bytes) = uf.into_parts();
+/// let journal = Journal::<u32>::read_from(&bytes[..]).unwrap();
+/// let replayed = journal.replay().unwrap();
+/// assert!(replayed.same_partition(&original));
+/// ```
+#[derive(Clone, Debug)]
+pub struct JournaledUnionFind<Element: ElementType = usize, W: Write = Vec<u8>> {
+    union_find: UnionFind<Element>,
+    sink:       W,
+}
+
+/// An operation recorded in a [`Journal`](struct.Journal.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Operation<Element = usize> {
+    /// Allocation of a new element in a singleton set.
+    Alloc,
+    /// Union of the sets of two elements.
+    Union(Element, Element),
+}
+
+/// A sequence of operations on a union-find, which can be replayed to
+/// rebuild it.
+///
+/// This is produced by reading the output of a
+/// [`JournaledUnionFind`](struct.JournaledUnionFind.html), or can be built
+/// directly with [`push`](#method.push). It can be written back in the
+/// same format with [`write_to`](#method.write_to), or to a file with
+/// [`save`](#method.save).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Journal<Element = usize> {
+    initial_len: usize,
+    operations:  Vec<Operation<Element>>,
+}
+
+const MAGIC: &[u8; 4] = b"DSUJ";
+const VERSION: u32 = 1;
+const ALLOC_TAG: u8 = 0;
+const UNION_TAG: u8 = 1;
+
+impl<Element: ElementType, W: Write> JournaledUnionFind<Element, W> {
+    /// Creates a new union-find of `size` elements, writing the journal’s
+    /// header to `sink`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize, mut sink: W) -> io::Result<Self> {
+        let union_find = UnionFind::new(size);
+        write_header(&mut sink, size)?;
+        Ok(JournaledUnionFind { union_find, sink })
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.union_find.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.union_find.is_empty()
+    }
+
+    /// Records and creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> io::Result<Element> {
+        Element::from_usize(self.len())
+            .expect("JournaledUnionFind::alloc: overflow");
+        write_operation(&mut self.sink, Operation::<Element>::Alloc)?;
+        Ok(self.union_find.alloc())
+    }
+
+    /// Records and joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn union(&mut self, a: Element, b: Element) -> io::Result<bool> {
+        assert!(a.to_usize() < self.len() && b.to_usize() < self.len(),
+                "JournaledUnionFind::union: out of bounds");
+
+        write_operation(&mut self.sink, Operation::Union(a, b))?;
+        Ok(self.union_find.union(a, b))
+    }
+
+    /// Finds the representative element for the given element’s set.
+    pub fn find(&self, element: Element) -> Element {
+        self.union_find.find(element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.union_find.equiv(a, b)
+    }
+
+    /// Returns the underlying union-find.
+    pub fn as_union_find(&self) -> &UnionFind<Element> {
+        &self.union_find
+    }
+
+    /// Returns a reference to the journal’s sink.
+    pub fn sink(&self) -> &W {
+        &self.sink
+    }
+
+    /// Returns a mutable reference to the journal’s sink, for flushing.
+    pub fn sink_mut(&mut self) -> &mut W {
+        &mut self.sink
+    }
+
+    /// Returns the underlying union-find and the journal’s sink.
+    pub fn into_parts(self) -> (UnionFind<Element>, W) {
+        (self.union_find, self.sink)
+    }
+}
+
+impl<Element: ElementType> Journal<Element> {
+    /// Creates an empty journal for a union-find that starts with
+    /// `initial_len` elements.
+    pub fn new(initial_len: usize) -> Self {
+        Journal {
+            initial_len,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Reads a journal written by a
+    /// [`JournaledUnionFind`](struct.JournaledUnionFind.html).
+    ///
+    /// An incomplete final operation, as left by a crash while it was
+    /// being written, is ignored. Returns an error of kind `InvalidData`
+    /// if the header is wrong, a tag is unknown, or an element does not
+    /// fit the element type `Element`.
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 16];
+        reader.read_exact(&mut header)?;
+        if &header[.. 4] != MAGIC || header[4 .. 8] != VERSION.to_le_bytes() {
+            return Err(invalid_data("bad journal header"));
+        }
+
+        let initial_len: usize = decode(&header[8 ..])?;
+        let mut result = Journal::new(initial_len);
+
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut rest = &bytes[..];
+
+        while let Some((&tag, tail)) = rest.split_first() {
+            match tag {
+                ALLOC_TAG => {
+                    result.push(Operation::Alloc);
+                    rest = tail;
+                }
+                UNION_TAG if tail.len() >= 16 => {
+                    result.push(Operation::Union(decode(&tail[.. 8])?,
+                                                 decode(&tail[8 .. 16])?));
+                    rest = &tail[16 ..];
+                }
+                UNION_TAG => break,
+                _ => return Err(invalid_data("bad journal tag")),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Writes the journal in the format that
+    /// [`read_from`](#method.read_from) reads.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_header(&mut writer, self.initial_len)?;
+        for &operation in &self.operations {
+            write_operation(&mut writer, operation)?;
+        }
+        writer.flush()
+    }
+
+    /// Writes the journal to the file at `path`, replacing it.
+    ///
+    /// The journal is first written to a temporary file beside `path`,
+    /// which is then renamed over it, so if writing fails, any existing
+    /// file at `path` is left as it was rather than partly overwritten.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let file_name = path.file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                          "journal path has no file name"))?;
+        let mut temp_name = file_name.to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let result = File::create(&temp_path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            self.write_to(&mut writer)?;
+            writer.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+            fs::rename(&temp_path, path)
+        });
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+
+        result
+    }
+
+    /// The number of elements before the first operation.
+    pub fn initial_len(&self) -> usize {
+        self.initial_len
+    }
+
+    /// The recorded operations, in order.
+    pub fn operations(&self) -> &[Operation<Element>] {
+        &self.operations
+    }
+
+    /// The number of recorded operations.
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Does the journal lack operations?
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Appends an operation.
+    pub fn push(&mut self, operation: Operation<Element>) {
+        self.operations.push(operation);
+    }
+
+    /// Rebuilds a union-find by applying the operations in order.
+    ///
+    /// Returns an error if an operation would overflow the element type
+    /// or refers to an element that does not exist at that point.
+    pub fn replay(&self) -> Result<UnionFind<Element>, Error> {
+        let mut result = UnionFind::try_new(self.initial_len)?;
+
+        for &operation in &self.operations {
+            match operation {
+                Operation::Alloc => { result.try_alloc()?; }
+                Operation::Union(a, b) => { result.try_union(a, b)?; }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, initial_len: usize) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(initial_len as u64).to_le_bytes())
+}
+
+// Writes each operation with a single `write_all`, so that a sink that
+// writes whole buffers atomically never holds part of a record.
+fn write_operation<Element, W>(writer: &mut W, operation: Operation<Element>)
+                               -> io::Result<()>
+    where Element: ElementType, W: Write {
+
+    match operation {
+        Operation::Alloc => writer.write_all(&[ALLOC_TAG]),
+        Operation::Union(a, b) => {
+            let mut record = [UNION_TAG; 17];
+            record[1 .. 9].copy_from_slice(&(a.to_usize() as u64).to_le_bytes());
+            record[9 ..].copy_from_slice(&(b.to_usize() as u64).to_le_bytes());
+            writer.write_all(&record)
+        }
+    }
+}
+
+fn decode<Element: ElementType>(bytes: &[u8]) -> io::Result<Element> {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+    let value = u64::from_le_bytes(buf);
+
+    if value > usize::MAX as u64 {
+        return Err(invalid_data("journal element too large"));
+    }
+
+    Element::from_usize(value as usize)
+        .ok_or_else(|| invalid_data("journal element too large"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay() {
+        let mut uf = JournaledUnionFind::<u8, _>::new(4, Vec::new()).unwrap();
+        assert!(uf.union(0, 1).unwrap());
+        assert!(!uf.union(1, 0).unwrap());
+        assert_eq!(uf.alloc().unwrap(), 4);
+        assert!(uf.union(4, 3).unwrap());
+        assert_eq!(uf.len(), 5);
+
+        let (original, bytes) = uf.into_parts();
+        let journal = Journal::<u8>::read_from(&bytes[..]).unwrap();
+        assert_eq!(journal.initial_len(), 4);
+        assert_eq!(journal.operations(),
+                   &[Operation::Union(0, 1), Operation::Union(1, 0),
+                     Operation::Alloc, Operation::Union(4, 3)]);
+
+        let replayed = journal.replay().unwrap();
+        assert_eq!(replayed.len(), 5);
+        assert!(replayed.same_partition(&original));
+    }
+
+    #[test]
+    fn torn_write() {
+        let mut uf = JournaledUnionFind::<usize, _>::new(2, Vec::new()).unwrap();
+        uf.union(0, 1).unwrap();
+        let bytes = uf.into_parts().1;
+
+        let journal = Journal::<usize>::read_from(&bytes[.. bytes.len() - 3]).unwrap();
+        assert!(journal.is_empty());
+        assert!(!journal.replay().unwrap().equiv(0, 1));
+    }
+
+    #[test]
+    fn save() {
+        let mut journal = Journal::<u32>::new(3);
+        journal.push(Operation::Union(0, 2));
+        journal.push(Operation::Alloc);
+
+        let mut bytes = Vec::new();
+        journal.write_to(&mut bytes).unwrap();
+        assert_eq!(Journal::<u32>::read_from(&bytes[..]).unwrap(), journal);
+
+        let path = std::env::temp_dir()
+            .join(format!("disjoint-sets-journal-{}", std::process::id()));
+        journal.save(&path).unwrap();
+        let saved = Journal::<u32>::read_from(File::open(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved, journal);
+
+        // A directory cannot be replaced by the temporary file, so the
+        // save fails, leaving the directory and no temporary file.
+        let dir = std::env::temp_dir()
+            .join(format!("disjoint-sets-journal-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(journal.save(&dir).is_err());
+        assert!(dir.is_dir());
+        let mut temp_name = dir.file_name().unwrap().to_os_string();
+        temp_name.push(".tmp");
+        assert!(!dir.with_file_name(temp_name).exists());
+        fs::remove_dir(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid() {
+        let mut bytes = JournaledUnionFind::<u16, _>::new(1, Vec::new())
+            .unwrap().into_parts().1;
+        bytes.push(7);
+        assert!(Journal::<u16>::read_from(&bytes[..]).is_err());
+        assert!(Journal::<u16>::read_from(&b"nope"[..]).is_err());
+
+        let mut journal = Journal::<u16>::new(1);
+        journal.push(Operation::Union(0, 1));
+        assert_eq!(journal.replay().unwrap_err(),
+                   Error::OutOfBounds { element: 1, len: 1 });
+    }
+}
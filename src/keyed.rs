@@ -0,0 +1,247 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+
+use super::{HashUnionFind, KeyId};
+
+/// Union-find over hashable keys, with data associated with each set.
+///
+/// This is like [`UnionFindNode`](struct.UnionFindNode.html), but
+/// addressed by key rather than by node handle, and like
+/// [`HashUnionFind`](struct.HashUnionFind.html), but with data. Each key
+/// is [`insert`](#method.insert)ed with its data, and
+/// [`union_with`](#method.union_with) combines the data of two sets with
+/// a closure.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::KeyedUnionFind;
+///
+/// // Deduplicate customer records, counting how many were merged.
+/// let mut customers = KeyedUnionFind::new();
+/// customers.insert("alice@example.com", 1);
+/// customers.insert("alice@work.example", 1);
+/// customers.insert("bob@example.com", 1);
+///
+/// customers.union_with(&"alice@example.com", &"alice@work.example", |a, b| a + b);
+///
+/// assert_eq!(customers.data(&"alice@work.example"), Some(&2));
+/// assert_eq!(customers.data(&"bob@example.com"), Some(&1));
+/// assert_eq!(customers.sets().count(), 2);
+/// ```
+#[derive(Clone)]
+pub struct KeyedUnionFind<K, D, S = RandomState> {
+    keys: HashUnionFind<K, S>,
+    // The data of each set, at the id of its representative key.
+    data: Vec<Option<D>>,
+}
+
+impl<K: Hash + Eq + Clone, D> KeyedUnionFind<K, D> {
+    /// Creates a new, empty union-find.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq + Clone, D, S: BuildHasher + Default> Default for KeyedUnionFind<K, D, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, D, S> Debug for KeyedUnionFind<K, D, S>
+        where K: Hash + Eq + Clone + Debug, D: Debug, S: BuildHasher {
+
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "KeyedUnionFind(")?;
+        formatter.debug_map().entries(self.sets()).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<K: Hash + Eq + Clone, D, S: BuildHasher> KeyedUnionFind<K, D, S> {
+    /// Creates a new, empty union-find that hashes keys with the given
+    /// hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        KeyedUnionFind {
+            keys: HashUnionFind::with_hasher(hasher),
+            data: Vec::new(),
+        }
+    }
+
+    /// The number of keys inserted.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Is the union-find devoid of keys?
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Has the given key been inserted?
+    pub fn contains(&self, key: &K) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Inserts a key in a singleton set with the given data.
+    ///
+    /// If the key is already present, this instead replaces the data of
+    /// its set, and returns the old data.
+    pub fn insert(&mut self, key: K, data: D) -> Option<D> {
+        match self.keys.id(&key) {
+            Some(id) => {
+                let root = self.keys.find_by_id(id);
+                self.data[root.index()].replace(data)
+            }
+            None => {
+                self.keys.insert(key);
+                self.data.push(Some(data));
+                None
+            }
+        }
+    }
+
+    /// Unions the sets of two keys, combining their data as specified.
+    ///
+    /// The closure is passed `a`’s data and `b`’s data, in that order, and
+    /// returns the data of the joined set. Returns whether anything
+    /// changed.
+    ///
+    /// # Panics
+    ///
+    /// If either key has not been inserted.
+    pub fn union_with<F>(&mut self, a: &K, b: &K, f: F) -> bool
+            where F: FnOnce(D, D) -> D {
+
+        let a = self.root(a);
+        let b = self.root(b);
+        if a == b { return false; }
+
+        let data_a = self.data[a.index()].take().unwrap();
+        let data_b = self.data[b.index()].take().unwrap();
+        self.keys.union_by_id(a, b);
+
+        let root = self.keys.find_by_id(a);
+        self.data[root.index()] = Some(f(data_a, data_b));
+        true
+    }
+
+    /// Unions the sets of two keys.
+    ///
+    /// Retains the data of `a`’s set, returning the data of `b`’s set.
+    /// Returns `None` if the keys are already in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either key has not been inserted.
+    pub fn union(&mut self, a: &K, b: &K) -> Option<D> {
+        let mut other = None;
+        self.union_with(a, b, |data_a, data_b| {
+            other = Some(data_b);
+            data_a
+        });
+        other
+    }
+
+    /// Finds the representative key for the given key’s set.
+    ///
+    /// A key that has not been inserted is its own representative.
+    pub fn find<'a>(&'a self, key: &'a K) -> &'a K {
+        self.keys.find(key)
+    }
+
+    /// Determines whether two keys are in the same set.
+    pub fn equiv(&self, a: &K, b: &K) -> bool {
+        self.keys.equiv(a, b)
+    }
+
+    /// The data of the given key’s set, if the key has been inserted.
+    pub fn data(&self, key: &K) -> Option<&D> {
+        let root = self.keys.find_by_id(self.keys.id(key)?);
+        self.data[root.index()].as_ref()
+    }
+
+    /// The data of the given key’s set, mutably, if the key has been
+    /// inserted.
+    pub fn data_mut(&mut self, key: &K) -> Option<&mut D> {
+        let root = self.keys.find_by_id(self.keys.id(key)?);
+        self.data[root.index()].as_mut()
+    }
+
+    /// Returns an iterator over the sets, each as its representative key
+    /// and its data.
+    pub fn sets(&self) -> impl Iterator<Item = (&K, &D)> {
+        self.keys.keys().zip(&self.data)
+            .filter_map(|(key, data)| data.as_ref().map(|data| (key, data)))
+    }
+
+    /// Consumes the union-find, returning each set’s representative key
+    /// with its data.
+    pub fn into_sets(self) -> Vec<(K, D)> {
+        let KeyedUnionFind { keys, data } = self;
+        keys.keys().cloned().zip(data)
+            .filter_map(|(key, data)| data.map(|data| (key, data)))
+            .collect()
+    }
+
+    // HELPERS
+
+    fn root(&self, key: &K) -> KeyId {
+        let id = self.keys.id(key)
+            .expect("KeyedUnionFind: no such key");
+        self.keys.find_by_id(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_data() {
+        let mut uf = KeyedUnionFind::new();
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            assert_eq!(uf.insert(name.to_string(), vec![i]), None);
+        }
+
+        let (a, b, c, d) = ("a".to_string(), "b".to_string(),
+                            "c".to_string(), "d".to_string());
+        assert!(uf.union_with(&a, &b, |mut x, y| { x.extend(y); x }));
+        assert!(uf.union_with(&c, &b, |mut x, y| { x.extend(y); x }));
+        assert!(!uf.union_with(&a, &c, |_, _| unreachable!()));
+
+        assert_eq!(uf.data(&b), Some(&vec![2, 0, 1]));
+        assert_eq!(uf.find(&a), uf.find(&c));
+        assert!(!uf.equiv(&a, &d));
+        assert_eq!(uf.data(&"e".to_string()), None);
+
+        uf.data_mut(&d).unwrap().push(9);
+        assert_eq!(uf.insert(a.clone(), vec![]), Some(vec![2, 0, 1]));
+        assert_eq!(uf.len(), 4);
+
+        let mut sets = uf.into_sets();
+        sets.sort_by(|x, y| x.1.cmp(&y.1));
+        assert_eq!(sets.iter().map(|set| set.1.clone()).collect::<Vec<_>>(),
+                   vec![vec![], vec![3, 9]]);
+    }
+
+    #[test]
+    fn union_returns_other() {
+        let mut uf = KeyedUnionFind::new();
+        uf.insert(1, 'x');
+        uf.insert(2, 'y');
+        assert_eq!(uf.union(&1, &2), Some('y'));
+        assert_eq!(uf.data(&2), Some(&'x'));
+        assert_eq!(uf.union(&2, &1), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn union_missing_key() {
+        let mut uf = KeyedUnionFind::<u8, ()>::new();
+        uf.insert(0, ());
+        uf.union(&0, &1);
+    }
+}
@@ -1,7 +1,7 @@
 #![doc(html_root_url = "https://docs.rs/disjoint-sets/0.4.2")]
-//! Three union-find implementations.
+//! Union-find implementations, sequential and concurrent.
 //!
-//! The variants are:
+//! The main variants are:
 //!
 //! |           | structure | element type | data? | concurrent? |
 //! | :-------- | :-------- | :----------- | :---- | :---------- |
@@ -9,8 +9,12 @@
 //! | [`UnionFindNode`](struct.UnionFindNode.html) | tree | tree node | yes | no |
 //! | [`AUnionFind`](struct.AUnionFind.html) | array | `usize` | no | yes |
 //!
-//! All three perform rank-balanced path halving à la Tarjan,
-//! using interior mutability.
+//! All three balance by rank and use interior mutability. `UnionFind`
+//! and `AUnionFind` compact paths by splitting, à la Tarjan, while
+//! `UnionFindNode` compresses each path fully. The crate also offers
+//! many specialized variants, such as weighted, persistent, and
+//! concurrent union-finds with per-set data, each documented with its
+//! type.
 //!
 //! # Usage
 //!
@@ -28,11 +32,37 @@
 //! extern crate disjoint_sets;
 //! ```
 //!
-//! This crate supports Rust version 1.21 and later.
+//! This crate supports Rust version 1.63 and later.
 //!
 //! Pass Cargo feature `"serde"` to enable serialization and deserialization
 //! for `UnionFind` and `AUnionFind`.
 //!
+//! Pass Cargo feature `"rayon"` to enable parallel bulk operations on
+//! `AUnionFind`, using [Rayon](https://docs.rs/rayon).
+//!
+//! Pass Cargo feature `"crossbeam-epoch"` to enable
+//! [`EpochAUnionFindMap`](struct.EpochAUnionFindMap.html), whose readers
+//! never block.
+//!
+//! Cargo feature `"std"` is enabled by default. Without it, the crate is
+//! `no_std` and provides only [`UnionFindNode`](struct.UnionFindNode.html),
+//! which needs the `alloc` crate, and
+//! [`ArrayUnionFind`](struct.ArrayUnionFind.html) and
+//! [`UnionFindRef`](struct.UnionFindRef.html), which keep their elements
+//! inline or in caller-supplied slices, along with
+//! [`ElementType`](trait.ElementType.html), [`Merge`](trait.Merge.html),
+//! and [`Error`](enum.Error.html).
+//!
+//! Pass Cargo feature `"allocator-api2"` to let `UnionFind` store its
+//! elements in memory from any allocator that implements the
+//! [`allocator-api2`](https://docs.rs/allocator-api2) `Allocator` trait.
+//! Without it, `UnionFind` uses the global allocator.
+//!
+//! To use the standard library’s unstable allocator API instead, build
+//! with a nightly compiler and `RUSTFLAGS="--cfg disjoint_sets_nightly"`.
+//! This is a configuration flag rather than a Cargo feature so that
+//! `--all-features` builds on stable compilers.
+//!
 //! # Examples
 //!
 //! Kruskal’s algorithm to find the minimum spanning tree of a graph:
@@ -125,18 +155,145 @@
 //! ```
 
 #![warn(missing_docs)]
+#![cfg_attr(disjoint_sets_nightly, feature(allocator_api))]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-#[cfg(feature = "serde")]
 #[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "allocator-api2")]
+extern crate allocator_api2;
+
+#[cfg(feature = "serde")]
 extern crate serde;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "crossbeam-epoch")]
+extern crate crossbeam_epoch;
+#[cfg(all(test, loom))]
+extern crate loom;
+
+// Declares items that need the standard library.
+macro_rules! std_items {
+    ($($item:item)*) => { $( #[cfg(feature = "std")] $item )* };
+}
 
+mod error;
 mod traits;
-mod array;
 mod tree;
-mod concurrent;
+mod merge;
+mod borrowed;
+mod fixed;
 
+pub use error::Error;
 pub use traits::ElementType;
-pub use array::UnionFind;
-pub use tree::UnionFindNode;
-pub use concurrent::AUnionFind;
+pub use tree::{UnionFindNode, DataRef, DataRefMut};
+pub use merge::{Merge, Min, Max, Sum};
+pub use borrowed::UnionFindRef;
+pub use fixed::ArrayUnionFind;
+
+std_items! {
+    mod allocator;
+    mod memory;
+    mod array;
+    mod mapped;
+    mod external;
+    mod journal;
+    mod delta;
+    mod cow;
+    mod persistent;
+    mod undo;
+    mod randomized;
+    mod weighted;
+    mod parity;
+    mod ratio;
+    mod explained;
+    mod timestamped;
+    mod offline;
+    mod dynamic;
+    mod link_cut;
+    mod containers;
+    mod hashed;
+    mod interned;
+    mod keyed;
+    mod entity;
+    mod cluster;
+    mod growing;
+    mod compact;
+    mod small;
+    mod bits;
+    mod sync_tree;
+    mod forest;
+    mod backoff;
+    mod concurrent;
+    mod concurrent_forest;
+    mod concurrent_watch;
+    mod stats;
+    mod concurrent_growing;
+    mod concurrent_packed;
+    mod concurrent_randomized;
+    mod concurrent_map;
+    mod concurrent_shared;
+    #[cfg(feature = "crossbeam-epoch")]
+    mod concurrent_epoch;
+    mod components;
+    #[cfg(feature = "rayon")]
+    mod boruvka;
+    mod partition;
+    mod generic;
+    mod unify;
+    pub mod ena_compat;
+
+    pub use memory::{MemoryUsage, ArrayUsage};
+    pub use array::{UnionFind, UnionFindIter, PathToRoot};
+    pub use mapped::MappedUnionFind;
+    pub use external::ExternalUnionFind;
+    pub use journal::{JournaledUnionFind, Journal, Operation};
+    pub use delta::{DeltaUnionFind, Delta};
+    pub use cow::CowUnionFind;
+    pub use persistent::PersistentUnionFind;
+    pub use undo::{UnionFindUndo, Checkpoint};
+    pub use randomized::RandomizedUnionFind;
+    pub use weighted::{WeightedUnionFind, Group, Conflict};
+    pub use parity::{ParityUnionFind, Parity};
+    pub use ratio::RatioUnionFind;
+    pub use explained::ExplainedUnionFind;
+    pub use timestamped::TimestampedUnionFind;
+    pub use offline::OfflineConnectivity;
+    pub use dynamic::DynamicConnectivity;
+    pub use link_cut::LinkCutTree;
+    pub use containers::{ContainerUnionFind, Container};
+    pub use hashed::{HashUnionFind, KeyId, KeyEntry};
+    pub use interned::InternedUnionFind;
+    pub use keyed::KeyedUnionFind;
+    pub use entity::{RecordMerger, MergePolicy, MergeFn};
+    pub use cluster::Cluster;
+    pub use growing::GrowingUnionFind;
+    pub use compact::CompactUnionFind;
+    pub use small::SmallUnionFind;
+    pub use bits::BitUnionFind;
+    pub use sync_tree::SyncUnionFindNode;
+    pub use forest::{UnionForest, NodeId};
+    pub use stats::ForestStats;
+    pub use backoff::Backoff;
+    pub use concurrent::{AUnionFind, UnionOutcome, EntryLayout, Compact, CachePadded};
+    pub use concurrent_watch::Merged;
+    pub use concurrent_growing::GrowingAUnionFind;
+    pub use concurrent_packed::{PackedAUnionFind, AUnionFind32};
+    pub use concurrent_randomized::RandomizedAUnionFind;
+    pub use concurrent_map::AUnionFindMap;
+    pub use concurrent_shared::SharedAUnionFind;
+    #[cfg(feature = "crossbeam-epoch")]
+    pub use concurrent_epoch::EpochAUnionFindMap;
+    pub use components::{ComponentBuilder, EdgeSink};
+    #[cfg(feature = "rayon")]
+    pub use boruvka::par_minimum_spanning_forest;
+    pub use partition::Partition;
+    pub use generic::{DisjointSets, ConcurrentDisjointSets, NodeUnionFind};
+    pub use unify::{UnificationTable, TableKey, UnifyValue, EqUnifyValue, Snapshot};
+}
+
+#[cfg(feature = "serde")]
+mod validate;
 
@@ -0,0 +1,393 @@
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::mem;
+
+use super::ElementType;
+
+/// A forest of unrooted trees supporting links, cuts, and path queries, after
+/// Sleator and Tarjan.
+///
+/// Like a union-find, a link-cut tree answers whether two elements are
+/// [`connected`](#method.connected), but it also supports
+/// [`cut`](#method.cut), which removes an edge, and
+/// [`path_aggregate`](#method.path_aggregate), which combines the values
+/// of the elements on the path between two elements. These are the
+/// operations that incremental minimum spanning tree and maximum flow
+/// algorithms need once a union-find no longer suffices. Every operation
+/// takes O(log *n*) amortized time.
+///
+/// Each element carries a value of type `V`, and path aggregates are
+/// computed with a function given at construction. Since a path can be
+/// traversed in either direction, the function must be associative and
+/// commutative, like addition, minimum, or maximum.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::LinkCutTree;
+///
+/// // Each element’s value is its weight, and paths report their maximum.
+/// let mut forest = LinkCutTree::<u32, u32>::with_values(
+///     vec![5, 1, 7, 3],
+///     |a, b| *a.max(b));
+///
+/// forest.link(0, 1);
+/// forest.link(1, 2);
+/// forest.link(1, 3);
+/// assert_eq!(forest.path_aggregate(0, 3), Some(5));
+/// assert_eq!(forest.path_aggregate(3, 2), Some(7));
+///
+/// forest.cut(1, 2);
+/// assert!(!forest.connected(0, 2));
+/// assert_eq!(forest.path_aggregate(0, 2), None);
+/// ```
+#[derive(Clone)]
+pub struct LinkCutTree<V = (), Element: ElementType = usize> {
+    nodes:   Vec<Node<V>>,
+    combine: fn(&V, &V) -> V,
+    marker:  PhantomData<Element>,
+}
+
+// Each preferred path is a splay tree ordered by depth. A splay root’s
+// `parent` is the path-parent pointer to the node above its path.
+#[derive(Clone)]
+struct Node<V> {
+    left:      usize,
+    right:     usize,
+    parent:    usize,
+    reversed:  bool,
+    value:     V,
+    aggregate: V,
+}
+
+const NIL: usize = usize::MAX;
+
+impl<V: Debug, Element: ElementType> Debug for LinkCutTree<V, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "LinkCutTree(")?;
+        formatter.debug_list()
+            .entries(self.nodes.iter().map(|node| &node.value))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Element: ElementType> LinkCutTree<(), Element> {
+    /// Creates a new forest of `size` singleton trees, without values.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        Self::with_values(vec![(); size], |_, _| ())
+    }
+}
+
+impl<V: Clone, Element: ElementType> LinkCutTree<V, Element> {
+    /// Creates a new forest of singleton trees, one for each of the
+    /// given values, whose path aggregates are computed by `combine`.
+    ///
+    /// # Panics
+    ///
+    /// If the number of values would overflow the element type `Element`.
+    pub fn with_values(values: Vec<V>, combine: fn(&V, &V) -> V) -> Self {
+        if !values.is_empty() {
+            Element::from_usize(values.len() - 1)
+                .expect("LinkCutTree::with_values: overflow");
+        }
+
+        LinkCutTree {
+            nodes: values.into_iter().map(|value| Node {
+                left:      NIL,
+                right:     NIL,
+                parent:    NIL,
+                reversed:  false,
+                aggregate: value.clone(),
+                value,
+            }).collect(),
+            combine,
+            marker: PhantomData,
+        }
+    }
+
+    /// The number of elements in all the trees.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Is the forest devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The value of the given element.
+    pub fn value(&self, element: Element) -> &V {
+        &self.nodes[element.to_usize()].value
+    }
+
+    /// Changes the value of the given element, returning the old value.
+    pub fn set_value(&mut self, element: Element, value: V) -> V {
+        let x = element.to_usize();
+        self.access(x);
+        let result = mem::replace(&mut self.nodes[x].value, value);
+        self.update(x);
+        result
+    }
+
+    /// Adds an edge between two elements in different trees.
+    ///
+    /// Returns whether anything changed. That is, if the elements were in
+    /// different trees, it returns `true`, but if they were already in the
+    /// same tree, it returns `false`, since the edge would form a cycle.
+    pub fn link(&mut self, a: Element, b: Element) -> bool {
+        if self.connected(a, b) { return false; }
+
+        let a = a.to_usize();
+        self.evert(a);
+        self.nodes[a].parent = b.to_usize();
+        true
+    }
+
+    /// Removes the edge between two elements.
+    ///
+    /// Returns whether anything changed. That is, if there was an edge
+    /// between the elements, it returns `true`, but otherwise it returns
+    /// `false`.
+    pub fn cut(&mut self, a: Element, b: Element) -> bool {
+        let (a, b) = (a.to_usize(), b.to_usize());
+        if a == b { return false; }
+
+        // After this, the edge exists just when `a` is `b`’s predecessor
+        // on the path from the root `a` to `b`.
+        self.evert(a);
+        self.access(b);
+        self.push(a);
+
+        if self.nodes[b].left != a || self.nodes[a].right != NIL {
+            return false;
+        }
+
+        self.nodes[b].left = NIL;
+        self.nodes[a].parent = NIL;
+        self.update(b);
+        true
+    }
+
+    /// Determines whether two elements are in the same tree.
+    pub fn connected(&mut self, a: Element, b: Element) -> bool {
+        let (a, b) = (a.to_usize(), b.to_usize());
+        a == b || self.find_root(a) == self.find_root(b)
+    }
+
+    /// Combines the values of the elements on the path between two
+    /// elements, including both ends, or returns `None` if they are in
+    /// different trees.
+    pub fn path_aggregate(&mut self, a: Element, b: Element) -> Option<V> {
+        if !self.connected(a, b) { return None; }
+
+        let b = b.to_usize();
+        self.evert(a.to_usize());
+        self.access(b);
+        Some(self.nodes[b].aggregate.clone())
+    }
+
+    // HELPERS
+
+    fn is_splay_root(&self, x: usize) -> bool {
+        let parent = self.nodes[x].parent;
+        parent == NIL || (self.nodes[parent].left != x && self.nodes[parent].right != x)
+    }
+
+    // Applies a pending reversal to `x`’s children.
+    fn push(&mut self, x: usize) {
+        if !self.nodes[x].reversed { return; }
+
+        let node = &mut self.nodes[x];
+        node.reversed = false;
+        mem::swap(&mut node.left, &mut node.right);
+        let (left, right) = (node.left, node.right);
+
+        for &child in &[left, right] {
+            if child != NIL {
+                self.nodes[child].reversed ^= true;
+            }
+        }
+    }
+
+    fn update(&mut self, x: usize) {
+        let Node { left, right, .. } = self.nodes[x];
+        let mut aggregate = self.nodes[x].value.clone();
+        if left != NIL {
+            aggregate = (self.combine)(&self.nodes[left].aggregate, &aggregate);
+        }
+        if right != NIL {
+            aggregate = (self.combine)(&aggregate, &self.nodes[right].aggregate);
+        }
+        self.nodes[x].aggregate = aggregate;
+    }
+
+    fn rotate(&mut self, x: usize) {
+        let parent = self.nodes[x].parent;
+        let grandparent = self.nodes[parent].parent;
+
+        if !self.is_splay_root(parent) {
+            if self.nodes[grandparent].left == parent {
+                self.nodes[grandparent].left = x;
+            } else {
+                self.nodes[grandparent].right = x;
+            }
+        }
+        self.nodes[x].parent = grandparent;
+
+        if self.nodes[parent].left == x {
+            let child = self.nodes[x].right;
+            self.nodes[parent].left = child;
+            if child != NIL { self.nodes[child].parent = parent; }
+            self.nodes[x].right = parent;
+        } else {
+            let child = self.nodes[x].left;
+            self.nodes[parent].right = child;
+            if child != NIL { self.nodes[child].parent = parent; }
+            self.nodes[x].left = parent;
+        }
+        self.nodes[parent].parent = x;
+
+        self.update(parent);
+        self.update(x);
+    }
+
+    fn splay(&mut self, x: usize) {
+        // Pending reversals must be applied from the top down before the
+        // shape of the tree changes.
+        let mut ancestors = vec![x];
+        let mut y = x;
+        while !self.is_splay_root(y) {
+            y = self.nodes[y].parent;
+            ancestors.push(y);
+        }
+        for &y in ancestors.iter().rev() {
+            self.push(y);
+        }
+
+        while !self.is_splay_root(x) {
+            let parent = self.nodes[x].parent;
+            if !self.is_splay_root(parent) {
+                let grandparent = self.nodes[parent].parent;
+                let zig_zig = (self.nodes[grandparent].left == parent)
+                    == (self.nodes[parent].left == x);
+                self.rotate(if zig_zig { parent } else { x });
+            }
+            self.rotate(x);
+        }
+    }
+
+    // Makes the path from the root of `x`’s tree to `x` preferred, with
+    // `x` at the root of its splay tree.
+    fn access(&mut self, x: usize) {
+        let mut last = NIL;
+        let mut y = x;
+
+        while y != NIL {
+            self.splay(y);
+            self.nodes[y].right = last;
+            self.update(y);
+            last = y;
+            y = self.nodes[y].parent;
+        }
+
+        self.splay(x);
+    }
+
+    // Makes `x` the root of its tree.
+    fn evert(&mut self, x: usize) {
+        self.access(x);
+        self.nodes[x].reversed ^= true;
+        self.push(x);
+    }
+
+    fn find_root(&mut self, x: usize) -> usize {
+        self.access(x);
+
+        let mut root = x;
+        loop {
+            self.push(root);
+            let left = self.nodes[root].left;
+            if left == NIL { break; }
+            root = left;
+        }
+
+        self.splay(root);
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use randomized::splitmix64;
+    use std::collections::HashSet;
+
+    // Sums the values on the path from `a` to `b` in the forest of
+    // `edges`, by depth-first search.
+    fn path_sum(edges: &HashSet<(usize, usize)>, values: &[u64],
+                a: usize, b: usize) -> Option<u64> {
+        let mut stack = vec![(a, NIL, values[a])];
+        while let Some((x, from, sum)) = stack.pop() {
+            if x == b { return Some(sum); }
+            for &(p, q) in edges {
+                let next = if p == x { q } else if q == x { p } else { continue };
+                if next != from {
+                    stack.push((next, x, sum + values[next]));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn matches_search() {
+        const SIZE: usize = 16;
+        let values: Vec<u64> = (0 .. SIZE as u64).map(|i| i * i + 1).collect();
+        let mut forest = LinkCutTree::<u64, u8>::with_values(values.clone(), |a, b| a + b);
+        let mut edges = HashSet::new();
+        let mut state = 7u64;
+
+        for _ in 0 .. 2000 {
+            state = splitmix64(state);
+            let a = (state % SIZE as u64) as usize;
+            let b = ((state >> 20) % SIZE as u64) as usize;
+            let key = (a.min(b), a.max(b));
+
+            if state >> 62 == 0 {
+                assert_eq!(forest.cut(a as u8, b as u8), edges.remove(&key));
+            } else {
+                let expected = a != b && path_sum(&edges, &values, a, b).is_none();
+                assert_eq!(forest.link(a as u8, b as u8), expected);
+                if expected { edges.insert(key); }
+            }
+
+            let c = ((state >> 40) % SIZE as u64) as usize;
+            assert_eq!(forest.path_aggregate(a as u8, c as u8),
+                       path_sum(&edges, &values, a, c));
+        }
+    }
+
+    #[test]
+    fn set_value() {
+        let mut forest = LinkCutTree::<i32>::with_values(vec![1, 2, 3], |a, b| a + b);
+        forest.link(0, 1);
+        forest.link(1, 2);
+        assert_eq!(forest.set_value(1, 10), 2);
+        assert_eq!(forest.path_aggregate(0, 2), Some(14));
+        assert_eq!(*forest.value(1), 10);
+    }
+
+    #[test]
+    fn without_values() {
+        let mut forest = LinkCutTree::<(), u16>::new(3);
+        assert!( forest.link(0, 1));
+        assert!(!forest.link(1, 0));
+        assert!(!forest.cut(0, 2));
+        assert!( forest.connected(1, 0));
+    }
+}
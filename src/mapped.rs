@@ -0,0 +1,246 @@
+use std::io::{self, Write};
+
+use allocator::Allocator;
+
+use super::{ElementType, Error, UnionFind};
+
+/// Read-only union-find that queries a byte buffer in place.
+///
+/// The buffer is typically a memory-mapped file produced by
+/// [`write`](#method.write), so that a union-find of billions of elements
+/// can be queried without reading or deserializing it first. Construction
+/// checks only the header, so it takes constant time; each query reads a
+/// single entry.
+///
+/// # Layout
+///
+/// All integers are little-endian:
+///
+/// | offset       | size      | contents                                |
+/// | :----------- | :-------- | :-------------------------------------- |
+/// | 0            | 4         | the magic bytes `b"DSUF"`               |
+/// | 4            | 4         | the layout version, `u32` 1             |
+/// | 8            | 8         | the number of elements *n*, as a `u64`  |
+/// | 16 + 8*i*    | 8         | the representative of element *i*, as a `u64`, for each *i* < *n* |
+///
+/// Every representative is its own representative, so the entries
+/// describe a fully compressed forest. Queries do not follow chains of
+/// parents, and hence cannot loop on a corrupt buffer; they may, however,
+/// return wrong answers. Use [`validate`](#method.validate) to check the
+/// entries in linear time.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{MappedUnionFind, UnionFind};
+///
+/// let mut uf = UnionFind::<u32>::new(4);
+/// uf.union(0, 1);
+/// uf.union(2, 1);
+///
+/// let mut bytes = Vec::new();
+/// MappedUnionFind::write(&uf, &mut bytes).unwrap();
+///
+/// let mapped = MappedUnionFind::new(&bytes).unwrap();
+/// assert_eq!(mapped.len(), 4);
+/// assert!(mapped.equiv(0, 2));
+/// assert!(!mapped.equiv(0, 3));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct MappedUnionFind<'a> {
+    entries: &'a [u8],
+}
+// Invariant: `entries.len()` is a multiple of ENTRY_SIZE.
+
+const MAGIC: &[u8; 4] = b"DSUF";
+const VERSION: u32 = 1;
+pub(crate) const HEADER_SIZE: usize = 16;
+pub(crate) const ENTRY_SIZE: usize = 8;
+
+impl<'a> MappedUnionFind<'a> {
+    /// Creates a union-find over the given buffer, checking its header.
+    ///
+    /// Returns `Err(Error::InvalidFormat)` if the buffer does not start
+    /// with the expected magic bytes and version, or if its length does
+    /// not match the number of elements in the header.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE
+            || &bytes[0 .. 4] != MAGIC
+            || read_u64(bytes, 4, 4) != u64::from(VERSION) {
+            return Err(Error::InvalidFormat);
+        }
+
+        let entries = &bytes[HEADER_SIZE ..];
+        let len = read_u64(bytes, 8, 8);
+
+        if len.checked_mul(ENTRY_SIZE as u64) != Some(entries.len() as u64) {
+            return Err(Error::InvalidFormat);
+        }
+
+        Ok(MappedUnionFind { entries })
+    }
+
+    /// Writes the layout of the given union-find.
+    ///
+    /// This makes one small write per element, so `writer` should
+    /// usually be buffered.
+    pub fn write<Element, A, W>(union_find: &UnionFind<Element, A>,
+                                mut writer: W) -> io::Result<()>
+        where Element: ElementType,
+              A: Allocator,
+              W: Write
+    {
+        write_header(&mut writer, union_find.len())?;
+
+        union_find.force();
+        for (_, root) in union_find {
+            writer.write_all(&(root.to_usize() as u64).to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.entries.len() / ENTRY_SIZE
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: usize) -> usize {
+        self.try_find(element).expect("MappedUnionFind::find: out of bounds")
+    }
+
+    /// Finds the representative element for the given element’s set,
+    /// checking that it is in bounds.
+    ///
+    /// Like [`find`](#method.find), but returns an error instead of
+    /// panicking if the element is out of bounds.
+    pub fn try_find(&self, element: usize) -> Result<usize, Error> {
+        if element < self.len() {
+            Ok(read_u64(self.entries, element * ENTRY_SIZE, ENTRY_SIZE) as usize)
+        } else {
+            Err(Error::OutOfBounds { element, len: self.len() })
+        }
+    }
+
+    /// Finds the representative element for the given element’s set, or
+    /// returns `None` if the element is out of bounds.
+    pub fn get(&self, element: usize) -> Option<usize> {
+        self.try_find(element).ok()
+    }
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Checks that every entry is in bounds and is its own representative.
+    pub fn validate(&self) -> bool {
+        (0 .. self.len()).all(|i| {
+            let root = self.find(i);
+            root < self.len() && self.find(root) == root
+        })
+    }
+
+    /// Copies the sets into a new `UnionFind`.
+    ///
+    /// # Panics
+    ///
+    /// If the buffer does not [`validate`](#method.validate), or if the
+    /// elements would overflow the element type `Element`.
+    pub fn to_union_find<Element: ElementType>(&self) -> UnionFind<Element> {
+        assert!(self.validate(), "MappedUnionFind::to_union_find: invalid");
+
+        let mut result = UnionFind::new(self.len());
+        for i in 0 .. self.len() {
+            let root = self.find(i);
+            if root != i {
+                result.union(Element::from_usize(i).unwrap(),
+                             Element::from_usize(root).unwrap());
+            }
+        }
+
+        result
+    }
+}
+
+// Writes the header for a layout of `len` elements.
+pub(crate) fn write_header<W: Write>(mut writer: W, len: usize) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(len as u64).to_le_bytes())
+}
+
+// Reads an integer of `size` bytes (at most 8) starting at `offset`.
+fn read_u64(bytes: &[u8], offset: usize, size: usize) -> u64 {
+    let mut buf = [0; 8];
+    buf[.. size].copy_from_slice(&bytes[offset .. offset + size]);
+    u64::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes_of(uf: &UnionFind<u32>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        MappedUnionFind::write(uf, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut uf = UnionFind::<u32>::new(10);
+        uf.union(0, 9);
+        uf.union(3, 4);
+        uf.union(9, 4);
+        uf.union(5, 6);
+
+        let bytes = bytes_of(&uf);
+        assert_eq!(bytes.len(), HEADER_SIZE + 10 * ENTRY_SIZE);
+
+        let mapped = MappedUnionFind::new(&bytes).unwrap();
+        assert!(mapped.validate());
+        for a in 0 .. 10 {
+            assert_eq!(mapped.find(a), uf.find(a as u32) as usize);
+        }
+        assert_eq!(mapped.get(10), None);
+
+        let copy: UnionFind<u32> = mapped.to_union_find();
+        assert!(copy.same_partition(&uf));
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes = bytes_of(&UnionFind::new(3));
+        assert!(MappedUnionFind::new(&bytes).is_ok());
+        assert!(MappedUnionFind::new(&bytes[.. 15]).is_err());
+        assert!(MappedUnionFind::new(&bytes[.. bytes.len() - 1]).is_err());
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(MappedUnionFind::new(&bad_magic).unwrap_err(),
+                   Error::InvalidFormat);
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = 2;
+        assert!(MappedUnionFind::new(&bad_version).is_err());
+
+        let mut bad_entry = bytes.clone();
+        bad_entry[HEADER_SIZE] = 7;
+        assert!(!MappedUnionFind::new(&bad_entry).unwrap().validate());
+    }
+}
@@ -0,0 +1,175 @@
+use std::mem;
+
+/// The heap memory used by a union-find.
+///
+/// This is produced by the `memory_usage` methods of the union-find
+/// types. It lists each internal array, along with the number of
+/// separately allocated nodes for structures that have them. Memory that
+/// is shared with other union-finds, such as the chunks of a
+/// [`CowUnionFind`](struct.CowUnionFind.html), is counted in full.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::UnionFind;
+///
+/// let uf = UnionFind::<u32>::new(1000);
+/// let usage = uf.memory_usage();
+///
+/// assert_eq!(usage.arrays().len(), 2);
+/// assert!(usage.bytes() >= 1000 * 5);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryUsage {
+    arrays:     Vec<ArrayUsage>,
+    nodes:      usize,
+    node_bytes: usize,
+}
+
+/// The heap memory used by one internal array of a union-find.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ArrayUsage {
+    /// The name of the array, for display.
+    pub name:         &'static str,
+    /// The number of slots in use.
+    pub len:          usize,
+    /// The number of slots allocated.
+    pub capacity:     usize,
+    /// The size of each slot, in bytes.
+    pub element_size: usize,
+}
+
+impl ArrayUsage {
+    /// The number of bytes allocated for the array.
+    pub fn bytes(&self) -> usize {
+        self.capacity * self.element_size
+    }
+}
+
+impl MemoryUsage {
+    pub(crate) fn new() -> Self {
+        MemoryUsage::default()
+    }
+
+    pub(crate) fn array<T>(mut self, name: &'static str,
+                           len: usize, capacity: usize) -> Self {
+        self.arrays.push(ArrayUsage {
+            name,
+            len,
+            capacity,
+            element_size: mem::size_of::<T>(),
+        });
+        self
+    }
+
+    // Adds `count` nodes of `node_size` bytes each. Nodes of different
+    // sizes may be added by calling this more than once.
+    pub(crate) fn nodes(mut self, count: usize, node_size: usize) -> Self {
+        self.nodes += count;
+        self.node_bytes += count * node_size;
+        self
+    }
+
+    // Adds the arrays and nodes of a union-find stored inside this one.
+    pub(crate) fn include(mut self, other: MemoryUsage) -> Self {
+        self.arrays.extend(other.arrays);
+        self.nodes += other.nodes;
+        self.node_bytes += other.node_bytes;
+        self
+    }
+
+    /// The internal arrays.
+    pub fn arrays(&self) -> &[ArrayUsage] {
+        &self.arrays
+    }
+
+    /// The number of separately allocated nodes, apart from the arrays.
+    pub fn node_count(&self) -> usize {
+        self.nodes
+    }
+
+    /// The total number of bytes allocated for arrays and nodes.
+    pub fn bytes(&self) -> usize {
+        self.arrays.iter().map(ArrayUsage::bytes).sum::<usize>()
+            + self.node_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {UnionFind, UnionFindUndo, SmallUnionFind, PersistentUnionFind,
+         UnionFindNode, CompactUnionFind};
+
+    #[test]
+    fn union_find_arrays() {
+        let uf = UnionFind::<u32>::new(100);
+        let usage = uf.memory_usage();
+        let (elements, ranks) = (usage.arrays()[0], usage.arrays()[1]);
+        assert_eq!(elements.name, "elements");
+        assert_eq!(elements.len, 100);
+        assert!(elements.capacity >= 100);
+        assert_eq!(elements.element_size, 4);
+        assert_eq!(ranks.name, "ranks");
+        assert_eq!(ranks.element_size, 1);
+        assert_eq!(usage.bytes(), elements.capacity * 4 + ranks.capacity);
+        assert_eq!(usage.node_count(), 0);
+    }
+
+    #[test]
+    fn compact_saves_a_byte() {
+        let wide = UnionFind::<u32>::new(100).memory_usage();
+        let compact = CompactUnionFind::new(100).memory_usage();
+        assert_eq!(wide.arrays().iter().map(|array| array.element_size).sum::<usize>(), 5);
+        assert_eq!(compact.arrays().iter().map(|array| array.element_size).sum::<usize>(), 4);
+    }
+
+    #[test]
+    fn nodes_of_different_sizes() {
+        let usage = super::MemoryUsage::new()
+            .nodes(3, 16)
+            .nodes(2, 40);
+        assert_eq!(usage.node_count(), 5);
+        assert_eq!(usage.bytes(), 3 * 16 + 2 * 40);
+    }
+
+    #[test]
+    fn undo_counts_trail() {
+        let mut uf = UnionFindUndo::<u8>::new(10);
+        let before = uf.memory_usage();
+        uf.checkpoint();
+        for i in 1 .. 10 {
+            uf.union(0, i);
+        }
+        let after = uf.memory_usage();
+        assert_eq!(after.arrays().len(), 5);
+        assert_eq!(after.arrays()[2].len, 9);
+        assert!(after.bytes() > before.bytes());
+    }
+
+    #[test]
+    fn small_is_inline() {
+        let mut uf = SmallUnionFind::<u32>::new(2);
+        assert_eq!(uf.memory_usage().bytes(), 0);
+        while !uf.spilled() {
+            uf.alloc();
+        }
+        assert!(uf.memory_usage().bytes() > 0);
+    }
+
+    #[test]
+    fn persistent_nodes() {
+        let v0 = PersistentUnionFind::<u32>::new(10);
+        let v1 = v0.union(0, 1);
+        assert_eq!(v1.memory_usage().node_count(), 1);
+        // Each union of equal ranks changes two entries, so `v0` is now
+        // two differences away from the array.
+        assert_eq!(v0.memory_usage().node_count(), 3);
+        assert_eq!(v1.memory_usage().node_count(), 1);
+    }
+
+    #[test]
+    fn node_size() {
+        assert!(UnionFindNode::<[u64; 4]>::node_size()
+                > UnionFindNode::<()>::node_size());
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::ops::Add;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::mem;
+
+/// Data that knows how to combine with another of its kind when two sets
+/// are joined.
+///
+/// This lets [`UnionFindNode::union_merged`](struct.UnionFindNode.html#method.union_merged)
+/// and its counterparts stand in for `union_with` and a closure. The
+/// standard impls are:
+///
+///   - `HashSet`, `BTreeSet`, `HashMap`, and `BTreeMap` take the union,
+///     with `other`’s entries replacing `self`’s for maps;
+///   - `Vec` concatenates `self` and `other`, in that order;
+///   - `Option` takes the first `Some`;
+///   - `()` does nothing; and
+///   - [`Min`](struct.Min.html), [`Max`](struct.Max.html), and
+///     [`Sum`](struct.Sum.html) wrap a value to combine it as named.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{Max, UnionFindNode};
+///
+/// let mut a = UnionFindNode::new(Max(3));
+/// let mut b = UnionFindNode::new(Max(8));
+/// a.union_merged(&mut b);
+///
+/// assert_eq!(a.clone_data(), Max(8));
+/// ```
+pub trait Merge {
+    /// Combines `self` with `other`.
+    fn merge(self, other: Self) -> Self;
+}
+
+impl Merge for () {
+    fn merge(self, _: ()) { }
+}
+
+impl<T> Merge for Option<T> {
+    fn merge(self, other: Self) -> Self {
+        self.or(other)
+    }
+}
+
+impl<T> Merge for Vec<T> {
+    fn merge(mut self, other: Self) -> Self {
+        self.extend(other);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Hash + Eq, S: BuildHasher> Merge for HashSet<T, S> {
+    fn merge(mut self, mut other: Self) -> Self {
+        if self.len() < other.len() {
+            mem::swap(&mut self, &mut other);
+        }
+        self.extend(other);
+        self
+    }
+}
+
+impl<T: Ord> Merge for BTreeSet<T> {
+    fn merge(mut self, mut other: Self) -> Self {
+        self.append(&mut other);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Hash + Eq, V, S: BuildHasher> Merge for HashMap<K, V, S> {
+    fn merge(mut self, other: Self) -> Self {
+        self.extend(other);
+        self
+    }
+}
+
+impl<K: Ord, V> Merge for BTreeMap<K, V> {
+    fn merge(mut self, mut other: Self) -> Self {
+        self.append(&mut other);
+        self
+    }
+}
+
+/// A value that merges by keeping the minimum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Min<T>(pub T);
+
+/// A value that merges by keeping the maximum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Max<T>(pub T);
+
+/// A value that merges by adding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sum<T>(pub T);
+
+impl<T: Ord> Merge for Min<T> {
+    fn merge(self, other: Self) -> Self {
+        Min(self.0.min(other.0))
+    }
+}
+
+impl<T: Ord> Merge for Max<T> {
+    fn merge(self, other: Self) -> Self {
+        Max(self.0.max(other.0))
+    }
+}
+
+impl<T: Add<Output = T>> Merge for Sum<T> {
+    fn merge(self, other: Self) -> Self {
+        Sum(self.0 + other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_impls() {
+        assert_eq!(Some(1).merge(Some(2)), Some(1));
+        assert_eq!(None.merge(Some(2)), Some(2));
+        assert_eq!(vec![1, 2].merge(vec![3]), vec![1, 2, 3]);
+        assert_eq!(Min(4).merge(Min(2)), Min(2));
+        assert_eq!(Sum(4).merge(Sum(2)), Sum(6));
+
+        let small: HashSet<_> = [1].iter().cloned().collect();
+        let large: HashSet<_> = [1, 2, 3].iter().cloned().collect();
+        assert_eq!(small.merge(large).len(), 3);
+
+        let a: BTreeMap<_, _> = vec![(1, 'a'), (2, 'b')].into_iter().collect();
+        let b: BTreeMap<_, _> = vec![(2, 'c')].into_iter().collect();
+        assert_eq!(a.merge(b).into_iter().collect::<Vec<_>>(), vec![(1, 'a'), (2, 'c')]);
+    }
+
+    #[test]
+    fn union_merged() {
+        use {SyncUnionFindNode, UnionForest, UnionFindNode};
+
+        let mut a = UnionFindNode::new(vec![1]);
+        assert!(a.union_merged(&mut UnionFindNode::new(vec![2])));
+        assert_eq!(a.clone_data(), vec![1, 2]);
+
+        let b = SyncUnionFindNode::new(Sum(1));
+        assert!(b.union_merged(&SyncUnionFindNode::new(Sum(2))));
+        assert_eq!(b.clone_data(), Sum(3));
+
+        let mut forest = UnionForest::new();
+        let (x, y) = (forest.add(Min('x')), forest.add(Min('y')));
+        assert!(forest.union_merged(y, x));
+        assert!(!forest.union_merged(x, y));
+        assert_eq!(forest.data(y), &Min('x'));
+    }
+}
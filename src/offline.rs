@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use super::UnionFindUndo;
+
+/// Answers connectivity queries over a timeline of edge insertions and
+/// deletions, offline.
+///
+/// Record the whole timeline with [`insert`](#method.insert),
+/// [`delete`](#method.delete), and [`query`](#method.query), and then
+/// [`solve`](#method.solve) answers every query at once. This uses the
+/// standard divide-and-conquer over time: each edge is alive for an
+/// interval of the timeline, which is split across the nodes of a
+/// segment tree, and a depth-first walk of the tree applies each node’s
+/// edges to a [`UnionFindUndo`](struct.UnionFindUndo.html) on the way
+/// down and rolls them back on the way up. For a timeline of *m* events,
+/// solving takes O(*m* log *m* log *n*) time.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::OfflineConnectivity;
+///
+/// let mut timeline = OfflineConnectivity::new(3);
+/// timeline.insert(0, 1);
+/// timeline.insert(1, 2);
+/// timeline.query(0, 2);
+/// timeline.delete(0, 1);
+/// timeline.query(0, 2);
+/// timeline.query(1, 2);
+///
+/// assert_eq!(timeline.solve(), vec![true, false, true]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OfflineConnectivity {
+    size:    usize,
+    events:  Vec<Event>,
+    // The start times of the copies of each edge that are currently
+    // inserted, keyed by the edge with its smaller endpoint first.
+    open:    HashMap<(usize, usize), Vec<usize>>,
+    // Each edge with the interval of event indices during which it is
+    // alive.
+    closed:  Vec<(usize, usize, usize, usize)>,
+    queries: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Event {
+    Update,
+    Query(usize, usize),
+}
+
+impl OfflineConnectivity {
+    /// Creates a new, empty timeline over `size` vertices.
+    pub fn new(size: usize) -> Self {
+        OfflineConnectivity {
+            size,
+            ..OfflineConnectivity::default()
+        }
+    }
+
+    /// The number of vertices.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Does the timeline have no vertices?
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Appends the insertion of an edge to the timeline.
+    ///
+    /// Inserting the same edge more than once is allowed, and each copy
+    /// must be deleted separately.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn insert(&mut self, a: usize, b: usize) {
+        let edge = self.edge(a, b);
+        self.open.entry(edge).or_default().push(self.events.len());
+        self.events.push(Event::Update);
+    }
+
+    /// Appends the deletion of an edge to the timeline.
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds, or if the edge is not present.
+    pub fn delete(&mut self, a: usize, b: usize) {
+        let edge = self.edge(a, b);
+        let start = self.open.get_mut(&edge)
+            .and_then(|starts| starts.pop())
+            .expect("OfflineConnectivity::delete: no such edge");
+        self.closed.push((edge.0, edge.1, start, self.events.len()));
+        self.events.push(Event::Update);
+    }
+
+    /// Appends a query, asking whether two vertices are connected, to the
+    /// timeline.
+    ///
+    /// Returns the index of the query’s answer in the result of
+    /// [`solve`](#method.solve).
+    ///
+    /// # Panics
+    ///
+    /// If either vertex is out of bounds.
+    pub fn query(&mut self, a: usize, b: usize) -> usize {
+        let edge = self.edge(a, b);
+        self.events.push(Event::Query(edge.0, edge.1));
+        self.queries += 1;
+        self.queries - 1
+    }
+
+    /// Answers all the queries, in order.
+    pub fn solve(&self) -> Vec<bool> {
+        let time = self.events.len();
+        if time == 0 { return Vec::new(); }
+
+        let mut tree = vec![Vec::new(); 4 * time];
+
+        let still_open = self.open.iter().flat_map(|(&(a, b), starts)| {
+            starts.iter().map(move |&start| (a, b, start, time))
+        });
+        for (a, b, start, end) in self.closed.iter().cloned().chain(still_open) {
+            add_interval(&mut tree, 1, 0, time, start, end, (a, b));
+        }
+
+        let mut union_find = UnionFindUndo::new(self.size);
+        let mut answers = Vec::with_capacity(self.queries);
+        self.visit(&tree, 1, 0, time, &mut union_find, &mut answers);
+        answers
+    }
+
+    // HELPERS
+
+    fn edge(&self, a: usize, b: usize) -> (usize, usize) {
+        assert!(a < self.size && b < self.size,
+                "OfflineConnectivity: out of bounds");
+        if a <= b { (a, b) } else { (b, a) }
+    }
+
+    // Applies the edges at `node`, which covers the events in `lo .. hi`,
+    // and answers the queries beneath it.
+    fn visit(&self, tree: &[Vec<(usize, usize)>], node: usize, lo: usize, hi: usize,
+             union_find: &mut UnionFindUndo, answers: &mut Vec<bool>) {
+        let checkpoint = union_find.checkpoint();
+        for &(a, b) in &tree[node] {
+            union_find.union(a, b);
+        }
+
+        if hi - lo == 1 {
+            if let Event::Query(a, b) = self.events[lo] {
+                answers.push(union_find.equiv(a, b));
+            }
+        } else {
+            let mid = lo + (hi - lo) / 2;
+            self.visit(tree, 2 * node, lo, mid, union_find, answers);
+            self.visit(tree, 2 * node + 1, mid, hi, union_find, answers);
+        }
+
+        union_find.rollback_to(checkpoint);
+    }
+}
+
+// Adds `edge` to the nodes that exactly cover `start .. end`, beneath
+// `node`, which covers `lo .. hi`.
+fn add_interval(tree: &mut [Vec<(usize, usize)>], node: usize, lo: usize, hi: usize,
+                start: usize, end: usize, edge: (usize, usize)) {
+    if end <= lo || hi <= start { return; }
+
+    if start <= lo && hi <= end {
+        tree[node].push(edge);
+    } else {
+        let mid = lo + (hi - lo) / 2;
+        add_interval(tree, 2 * node, lo, mid, start, end, edge);
+        add_interval(tree, 2 * node + 1, mid, hi, start, end, edge);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use UnionFind;
+
+    #[test]
+    fn matches_rebuilding() {
+        const SIZE: usize = 12;
+
+        let mut timeline = OfflineConnectivity::new(SIZE);
+        let mut live: Vec<(usize, usize)> = Vec::new();
+        let mut expected = Vec::new();
+
+        for i in 0 .. 300usize {
+            match i % 5 {
+                0 | 1 => {
+                    let edge = (i * 7 % SIZE, i * 11 % SIZE);
+                    timeline.insert(edge.0, edge.1);
+                    live.push(edge);
+                }
+                2 if !live.is_empty() => {
+                    let (a, b) = live.remove(i * 3 % live.len());
+                    // Either orientation names the same edge.
+                    timeline.delete(b, a);
+                }
+                _ => {
+                    let (a, b) = (i % SIZE, i * 5 % SIZE);
+                    assert_eq!(timeline.query(a, b), expected.len());
+
+                    let mut uf = UnionFind::<usize>::new(SIZE);
+                    for &(x, y) in &live {
+                        uf.union(x, y);
+                    }
+                    expected.push(uf.equiv(a, b));
+                }
+            }
+        }
+
+        assert_eq!(timeline.solve(), expected);
+    }
+
+    #[test]
+    fn duplicate_edges() {
+        let mut timeline = OfflineConnectivity::new(2);
+        timeline.insert(0, 1);
+        timeline.insert(1, 0);
+        timeline.delete(0, 1);
+        timeline.query(0, 1);
+        timeline.delete(0, 1);
+        timeline.query(0, 1);
+        assert_eq!(timeline.solve(), vec![true, false]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn delete_missing_edge() {
+        let mut timeline = OfflineConnectivity::new(2);
+        timeline.delete(0, 1);
+    }
+}
@@ -0,0 +1,165 @@
+use super::{Conflict, ElementType, Group, WeightedUnionFind};
+
+/// Whether two elements are on the same side or on opposite sides.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Parity {
+    /// The elements are on the same side.
+    Same,
+    /// The elements are on opposite sides.
+    Opposite,
+}
+
+impl Group for Parity {
+    fn identity() -> Self {
+        Parity::Same
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        if self == other { Parity::Same } else { Parity::Opposite }
+    }
+
+    fn inverse(&self) -> Self {
+        *self
+    }
+}
+
+/// Union-find that tracks whether elements are on the same side or on
+/// opposite sides of a two-way split.
+///
+/// This is the usual tool for maintaining a 2-coloring, or for checking
+/// incrementally that a graph stays bipartite: for each edge, call
+/// [`union_opposite`](#method.union_opposite), and the first edge that
+/// closes an odd cycle is rejected with a [`Conflict`](struct.Conflict.html).
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{ParityUnionFind, Parity};
+///
+/// let mut uf = ParityUnionFind::<u32>::new(4);
+/// uf.union_opposite(0, 1).unwrap();
+/// uf.union_opposite(1, 2).unwrap();
+///
+/// assert_eq!(uf.relation(0, 2), Some(Parity::Same));
+/// assert_eq!(uf.relation(0, 3), None);
+///
+/// // The triangle 0–1–2 is an odd cycle.
+/// assert!(uf.union_opposite(2, 0).is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParityUnionFind<Element: ElementType = usize>(WeightedUnionFind<Parity, Element>);
+
+impl<Element: ElementType> ParityUnionFind<Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        ParityUnionFind(WeightedUnionFind::new(size))
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        self.0.alloc()
+    }
+
+    /// Joins the sets of the two given elements, placing them on the same
+    /// side.
+    ///
+    /// Returns whether anything changed, or a `Conflict` if the elements
+    /// are already known to be on opposite sides.
+    pub fn union_same(&mut self, a: Element, b: Element)
+                      -> Result<bool, Conflict<Parity>> {
+        self.0.union(a, b, Parity::Same)
+    }
+
+    /// Joins the sets of the two given elements, placing them on opposite
+    /// sides.
+    ///
+    /// Returns whether anything changed, or a `Conflict` if the elements
+    /// are already known to be on the same side, which means the
+    /// constraints so far contain an odd cycle.
+    pub fn union_opposite(&mut self, a: Element, b: Element)
+                          -> Result<bool, Conflict<Parity>> {
+        self.0.union(a, b, Parity::Opposite)
+    }
+
+    /// Returns the relation between two elements if they are in the same
+    /// set, or `None` if they are not.
+    pub fn relation(&mut self, a: Element, b: Element) -> Option<Parity> {
+        self.0.delta(a, b)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&mut self, element: Element) -> Element {
+        self.0.find(element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&mut self, a: Element, b: Element) -> bool {
+        self.0.equiv(a, b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&mut self) -> Vec<Element> {
+        self.0.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn even_cycle_is_bipartite() {
+        let mut uf = ParityUnionFind::<u8>::new(6);
+        for i in 0 .. 6 {
+            assert!(uf.union_opposite(i, (i + 1) % 6).is_ok());
+        }
+        for i in 0 .. 6 {
+            let expected = if i % 2 == 0 { Parity::Same } else { Parity::Opposite };
+            assert_eq!(uf.relation(0, i), Some(expected));
+        }
+    }
+
+    #[test]
+    fn odd_cycle_conflicts() {
+        let mut uf = ParityUnionFind::<usize>::new(5);
+        for i in 0 .. 4 {
+            uf.union_opposite(i, i + 1).unwrap();
+        }
+        assert_eq!(uf.union_opposite(4, 0),
+                   Err(Conflict { current: Parity::Same, requested: Parity::Opposite }));
+        assert_eq!(uf.union_same(4, 0), Ok(false));
+    }
+
+    #[test]
+    fn mixed_constraints() {
+        let mut uf = ParityUnionFind::<u16>::new(4);
+        uf.union_same(0, 1).unwrap();
+        uf.union_opposite(2, 3).unwrap();
+        uf.union_opposite(1, 3).unwrap();
+        assert_eq!(uf.relation(0, 2), Some(Parity::Same));
+        assert!(uf.union_opposite(0, 2).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(feature = "rayon")]
+use rayon::iter::IntoParallelIterator;
+#[cfg(feature = "rayon")]
+use rayon::vec::IntoIter as ParIter;
+
+/// An immutable, canonical representation of a partition.
+///
+/// Each element is labeled with the number of its set, where sets are
+/// numbered consecutively from 0 in the order of their first elements.
+/// Consequently, two `Partition`s compare equal (and hash equally)
+/// exactly when they partition the same elements the same way, regardless
+/// of the internal structure of the union-finds they came from. This makes
+/// `Partition` suitable as a key for memoization.
+///
+/// Elements are identified by their `usize` indices. Obtain a `Partition`
+/// from [`UnionFind::partition`](struct.UnionFind.html#method.partition)
+/// or [`AUnionFind::freeze`](struct.AUnionFind.html#method.freeze).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Partition {
+    labels: Vec<usize>,
+    sizes:  Vec<usize>,
+}
+// Invariant: self.labels is canonical, that is, each label is at most one
+// greater than every label preceding it, and self.sizes[n] is the number
+// of elements labeled n.
+
+impl Partition {
+    // PRECONDITION: `labels` is canonical.
+    pub(crate) fn from_labels(labels: Vec<usize>) -> Self {
+        let num_sets = labels.iter().max().map_or(0, |&max| max + 1);
+        let mut sizes = vec![0; num_sets];
+        for &label in &labels {
+            sizes[label] += 1;
+        }
+        Partition { labels, sizes }
+    }
+
+    // Numbers the sets in order of their first elements, given each
+    // element’s representative, reusing the vector for the labels.
+    //
+    // PRECONDITION: Every representative is an element.
+    pub(crate) fn from_representatives(mut representatives: Vec<usize>) -> Self {
+        const UNLABELED: usize = !0;
+
+        let mut root_labels = vec![UNLABELED; representatives.len()];
+        let mut next_label = 0;
+
+        for representative in &mut representatives {
+            let label = &mut root_labels[*representative];
+            if *label == UNLABELED {
+                *label = next_label;
+                next_label += 1;
+            }
+            *representative = *label;
+        }
+
+        Partition::from_labels(representatives)
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Is the partition devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// The number of sets in the partition.
+    pub fn num_sets(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Returns the number of elements in the set with the given number.
+    ///
+    /// # Panics
+    ///
+    /// If `label` is not less than [`num_sets`](#method.num_sets).
+    pub fn set_size(&self, label: usize) -> usize {
+        self.sizes[label]
+    }
+
+    /// Returns the number of elements in each set, indexed by set number.
+    pub fn set_sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// Returns the number of the set containing the given element.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn label(&self, element: usize) -> usize {
+        self.labels[element]
+    }
+
+    /// Determines whether two elements are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// If either element is out of bounds.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        self.label(a) == self.label(b)
+    }
+
+    /// Returns the set number of each element.
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    /// Returns the elements of each set.
+    ///
+    /// The sets are in order of their numbers, and each set’s elements
+    /// are in increasing order.
+    pub fn sets(&self) -> Vec<Vec<usize>> {
+        let mut sets: Vec<_> = self.sizes.iter()
+            .map(|&size| Vec::with_capacity(size))
+            .collect();
+        for (element, &label) in self.labels.iter().enumerate() {
+            sets[label].push(element);
+        }
+        sets
+    }
+
+    /// Returns a parallel iterator over the elements of each set, as
+    /// given by [`sets`](#method.sets). Requires Cargo feature `"rayon"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disjoint_sets;
+    /// extern crate rayon;
+    ///
+    /// use disjoint_sets::UnionFind;
+    /// use rayon::prelude::*;
+    ///
+    /// let mut uf = UnionFind::<usize>::new(6);
+    /// uf.union(0, 3);
+    /// uf.union(4, 5);
+    ///
+    /// let sizes: Vec<_> = uf.partition().par_sets().map(|set| set.len()).collect();
+    /// assert_eq!(sizes, vec![2, 1, 1, 2]);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_sets(&self) -> ParIter<Vec<usize>> {
+        self.sets().into_par_iter()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl IntoParallelIterator for &Partition {
+    type Item = Vec<usize>;
+    type Iter = ParIter<Vec<usize>>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_sets()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_labels() {
+        let partition = Partition::from_labels(vec![0, 1, 0, 2, 1]);
+        assert_eq!(partition.len(), 5);
+        assert_eq!(partition.num_sets(), 3);
+        assert!(partition.equiv(0, 2));
+        assert!(!partition.equiv(0, 1));
+
+        assert_eq!(partition.set_sizes(), &[2, 2, 1]);
+        assert_eq!(partition.set_size(2), 1);
+
+        assert_eq!(Partition::from_labels(vec![]).num_sets(), 0);
+    }
+
+    #[test]
+    fn from_representatives() {
+        assert_eq!(Partition::from_representatives(vec![2, 3, 2, 4, 3]),
+                   Partition::from_labels(vec![0, 1, 0, 2, 1]));
+    }
+
+    #[test]
+    fn sets() {
+        let partition = Partition::from_labels(vec![0, 1, 0, 2, 1]);
+        assert_eq!(partition.sets(), vec![vec![0, 2], vec![1, 4], vec![3]]);
+        assert!(Partition::default().sets().is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_sets() {
+        use rayon::iter::ParallelIterator;
+
+        let partition = Partition::from_labels(vec![0, 1, 0, 2, 1]);
+        let sets: Vec<_> = (&partition).into_par_iter().collect();
+        assert_eq!(sets, partition.sets());
+    }
+}
@@ -0,0 +1,360 @@
+use std::cell::RefCell;
+use std::fmt::{self, Debug};
+use std::mem;
+use std::rc::Rc;
+
+use super::{ElementType, MemoryUsage};
+
+/// Fully persistent union-find, after Conchon and Filliâtre.
+///
+/// Each [`union`](#method.union) returns a new version and leaves the
+/// original unchanged, so any number of versions can be kept and
+/// queried. Versions share storage: the most recently used version holds
+/// an array, and every other version records its difference from a
+/// neighbor. Accessing a version *reroots* it, reversing the differences
+/// along the way so that it holds the array. Going back and forth between
+/// nearby versions, as a backtracking search does, is therefore cheap.
+///
+/// [`find`](#method.find) compresses paths, which changes the version’s
+/// internal representation but not its sets.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::PersistentUnionFind;
+///
+/// let v0 = PersistentUnionFind::<u32>::new(4);
+/// let v1 = v0.union(0, 1);
+/// let v2 = v1.union(1, 2);
+///
+/// assert!( v2.equiv(0, 2));
+/// assert!( v1.equiv(0, 1));
+/// assert!(!v1.equiv(0, 2));
+/// assert!(!v0.equiv(0, 1));
+/// ```
+pub struct PersistentUnionFind<Element: ElementType = usize> {
+    entries: RefCell<Version<Entry<Element>>>,
+    len:     usize,
+}
+
+#[derive(Clone, Copy)]
+struct Entry<Element> {
+    parent: Element,
+    rank:   u8,
+}
+
+// A version of a persistent array.
+struct Version<T>(Link<T>);
+
+type Link<T> = Rc<RefCell<Node<T>>>;
+
+enum Node<T> {
+    Array(Vec<T>),
+    // The version equal to the linked one, except at the given index. The
+    // link is `None` only while the node is being dropped.
+    Diff(usize, T, Option<Link<T>>),
+}
+
+impl<Element: ElementType> Clone for PersistentUnionFind<Element> {
+    fn clone(&self) -> Self {
+        PersistentUnionFind {
+            entries: RefCell::new(self.entries.borrow().clone()),
+            len:     self.len,
+        }
+    }
+}
+
+impl<Element: Debug + ElementType> Debug for PersistentUnionFind<Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "PersistentUnionFind(")?;
+        let entries = self.entries.borrow();
+        formatter.debug_list()
+            .entries((0 .. self.len).map(|i| entries.get(i).parent))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Element: ElementType> PersistentUnionFind<Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        if size > 0 {
+            Element::from_usize(size - 1)
+                .expect("PersistentUnionFind::new: overflow");
+        }
+
+        let entries = (0 .. size)
+            .map(|i| Entry { parent: Element::from_usize(i).unwrap(), rank: 0 })
+            .collect();
+
+        PersistentUnionFind {
+            entries: RefCell::new(Version::new(entries)),
+            len:     size,
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reports the heap memory used by the union-find.
+    ///
+    /// This counts the array and the nodes that record differences along
+    /// the chain from this version to the array. Versions on other
+    /// branches of the history are not counted.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let entries = self.entries.borrow();
+        let mut capacity = 0;
+        let mut nodes = 0;
+        let mut link = entries.0.clone();
+
+        loop {
+            nodes += 1;
+            let next = match *link.borrow() {
+                Node::Array(ref array) => { capacity = array.capacity(); None }
+                Node::Diff(_, _, ref next) => next.clone(),
+            };
+            match next {
+                Some(next) => link = next,
+                None       => break,
+            }
+        }
+
+        MemoryUsage::new()
+            .array::<Entry<Element>>("entries", self.len, capacity)
+            .nodes(nodes, 2 * mem::size_of::<usize>()
+                          + mem::size_of::<RefCell<Node<Entry<Element>>>>())
+    }
+
+    /// Returns a new version in which the sets of the two given elements
+    /// are joined.
+    ///
+    /// If the elements are already in the same set, the result is a copy
+    /// of `self`.
+    pub fn union(&self, a: Element, b: Element) -> Self {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        let entries = self.entries.borrow();
+        let rank_a = entries.get(a.to_usize()).rank;
+        let rank_b = entries.get(b.to_usize()).rank;
+
+        let result = if a == b {
+            entries.clone()
+        } else if rank_a > rank_b {
+            entries.set(b.to_usize(), Entry { parent: a, rank: rank_b })
+        } else if rank_b > rank_a {
+            entries.set(a.to_usize(), Entry { parent: b, rank: rank_a })
+        } else {
+            entries.set(a.to_usize(), Entry { parent: b, rank: rank_a })
+                .set(b.to_usize(), Entry { parent: b, rank: rank_b.saturating_add(1) })
+        };
+
+        PersistentUnionFind {
+            entries: RefCell::new(result),
+            len:     self.len,
+        }
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, mut element: Element) -> Element {
+        assert!(element.to_usize() < self.len,
+                "PersistentUnionFind::find: out of bounds");
+
+        let mut entries = self.entries.borrow_mut();
+
+        loop {
+            let entry = entries.get(element.to_usize());
+            if entry.parent == element { return element; }
+
+            let grandparent = entries.get(entry.parent.to_usize()).parent;
+            if grandparent != entry.parent {
+                let halved = entries.set(element.to_usize(),
+                                         Entry { parent: grandparent, ..entry });
+                *entries = halved;
+            }
+
+            element = entry.parent;
+        }
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<Element> {
+        (0 .. self.len)
+            .map(|i| self.find(Element::from_usize(i).unwrap()))
+            .collect()
+    }
+}
+
+impl<T> Clone for Version<T> {
+    fn clone(&self) -> Self {
+        Version(self.0.clone())
+    }
+}
+
+impl<T: Copy> Version<T> {
+    fn new(array: Vec<T>) -> Self {
+        Version(Rc::new(RefCell::new(Node::Array(array))))
+    }
+
+    fn get(&self, index: usize) -> T {
+        self.reroot();
+        match *self.0.borrow() {
+            Node::Array(ref array) => array[index],
+            Node::Diff(..) => unreachable!(),
+        }
+    }
+
+    // Returns a new version that differs from `self` at `index`. The new
+    // version takes the array, and `self` becomes a difference from it.
+    fn set(&self, index: usize, value: T) -> Self {
+        self.reroot();
+
+        let mut node = self.0.borrow_mut();
+        let array = match *node {
+            Node::Array(ref mut array) => array,
+            Node::Diff(..) => unreachable!(),
+        };
+
+        let old = mem::replace(&mut array[index], value);
+        let result = Version::new(mem::take(array));
+        *node = Node::Diff(index, old, Some(result.0.clone()));
+        result
+    }
+
+    // Makes `self` hold the array by reversing the chain of differences
+    // that leads to it.
+    fn reroot(&self) {
+        let mut chain = vec![self.0.clone()];
+        loop {
+            let next = match *chain.last().unwrap().borrow() {
+                Node::Array(_) => break,
+                Node::Diff(_, _, ref next) => next.clone().unwrap(),
+            };
+            chain.push(next);
+        }
+
+        // Each step moves the array from `next` to `node`, leaving in
+        // `next` the difference that undoes the step.
+        for pair in chain.windows(2).rev() {
+            let (node, next) = (&pair[0], &pair[1]);
+
+            let (index, value) = match *node.borrow() {
+                Node::Diff(index, value, _) => (index, value),
+                Node::Array(_) => unreachable!(),
+            };
+
+            let mut array = match *next.borrow_mut() {
+                Node::Array(ref mut array) => mem::take(array),
+                Node::Diff(..) => unreachable!(),
+            };
+
+            let old = mem::replace(&mut array[index], value);
+            *next.borrow_mut() = Node::Diff(index, old, Some(node.clone()));
+            *node.borrow_mut() = Node::Array(array);
+        }
+    }
+}
+
+// Dropping a long chain of differences one node at a time, rather than
+// recursively, keeps it from overflowing the stack.
+impl<T> Drop for Node<T> {
+    fn drop(&mut self) {
+        let mut next = match *self {
+            Node::Diff(_, _, ref mut next) => next.take(),
+            Node::Array(_) => None,
+        };
+
+        while let Some(link) = next {
+            next = match Rc::try_unwrap(link) {
+                Ok(cell) => match cell.into_inner() {
+                    Node::Diff(_, _, ref mut link) => link.take(),
+                    Node::Array(_) => None,
+                },
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use UnionFind;
+
+    #[test]
+    fn versions_are_independent() {
+        let mut versions = vec![PersistentUnionFind::<u16>::new(20)];
+        let mut expected = vec![UnionFind::<u16>::new(20)];
+
+        for i in 0 .. 19u16 {
+            let previous = (i as usize * 7) % versions.len();
+            let a = i * 3 % 20;
+            let b = (i * 11 + 5) % 20;
+
+            versions.push(versions[previous].union(a, b));
+            let mut uf = expected[previous].clone();
+            uf.union(a, b);
+            expected.push(uf);
+        }
+
+        // Query forward and then backward, to force rerooting in both
+        // directions.
+        let order: Vec<usize> = (0 .. versions.len())
+            .chain((0 .. versions.len()).rev())
+            .collect();
+
+        for k in order {
+            for a in 0 .. 20 {
+                for b in 0 .. 20 {
+                    assert_eq!(versions[k].equiv(a, b), expected[k].equiv(a, b));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn union_of_same_set() {
+        let v0 = PersistentUnionFind::<usize>::new(3).union(0, 1);
+        let v1 = v0.union(1, 0);
+        assert!(v1.equiv(0, 1));
+        assert_eq!(v1.to_vec(), v0.to_vec());
+    }
+
+    #[test]
+    fn long_chain() {
+        const SIZE: usize = 200_000;
+
+        let base = PersistentUnionFind::<u32>::new(SIZE);
+        let mut current = base.clone();
+        for i in 1 .. SIZE as u32 {
+            current = current.union(i - 1, i);
+        }
+        assert!(current.equiv(0, SIZE as u32 - 1));
+
+        // Rerooting to `base` reverses the whole chain, and dropping
+        // `current` then frees it.
+        assert!(!base.equiv(0, 1));
+        drop(current);
+        assert!(!base.equiv(0, SIZE as u32 - 1));
+    }
+}
@@ -0,0 +1,232 @@
+use std::cell::Cell;
+use std::fmt::{self, Debug};
+
+use super::{ElementType, MemoryUsage};
+
+/// Union-find that links by random priorities rather than by rank.
+///
+/// Each element has a pseudo-random priority determined by its index and
+/// a seed, and [`union`](#method.union) links the root with the lower
+/// priority beneath the other. This gives the same expected performance
+/// as union by rank without storing ranks, and because the priorities
+/// depend only on the seed, the same seed and the same sequence of
+/// operations always build exactly the same forest, which helps when
+/// debugging.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::RandomizedUnionFind;
+///
+/// let mut uf1 = RandomizedUnionFind::<u32>::new(8, 42);
+/// let mut uf2 = RandomizedUnionFind::<u32>::new(8, 42);
+///
+/// for &(a, b) in &[(0, 1), (2, 3), (1, 3), (5, 6)] {
+///     uf1.union(a, b);
+///     uf2.union(a, b);
+/// }
+///
+/// assert!(uf1.equiv(0, 2));
+/// assert_eq!(uf1.find(0), uf2.find(0));
+/// ```
+#[derive(Clone)]
+pub struct RandomizedUnionFind<Element: ElementType = usize> {
+    parents: Vec<Cell<Element>>,
+    seed:    u64,
+}
+
+impl<Element: Debug + ElementType> Debug for RandomizedUnionFind<Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "RandomizedUnionFind(")?;
+        formatter.debug_list().entries(&self.parents).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Element: ElementType> RandomizedUnionFind<Element> {
+    /// Creates a new union-find of `size` elements, with priorities
+    /// determined by `seed`.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize, seed: u64) -> Self {
+        let mut result = RandomizedUnionFind {
+            parents: Vec::with_capacity(size),
+            seed,
+        };
+
+        for _ in 0 .. size {
+            result.alloc();
+        }
+
+        result
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<Cell<Element>>("parents", self.parents.len(),
+                                    self.parents.capacity())
+    }
+
+    /// The seed that determines the priorities.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The priority of the given element.
+    pub fn priority(&self, element: Element) -> u64 {
+        splitmix64(splitmix64(self.seed) ^ element.to_usize() as u64)
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = Element::from_usize(self.len())
+            .expect("RandomizedUnionFind::alloc: overflow");
+        self.parents.push(Cell::new(result));
+        result
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b { return false; }
+
+        // Ties are broken by index, so that linking is deterministic even
+        // if two priorities collide.
+        let key_a = (self.priority(a), a.to_usize());
+        let key_b = (self.priority(b), b.to_usize());
+
+        if key_a < key_b {
+            self.set_parent(a, b);
+        } else {
+            self.set_parent(b, a);
+        }
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, mut element: Element) -> Element {
+        let mut parent = self.parent(element);
+
+        while element != parent {
+            let grandparent = self.parent(parent);
+            self.set_parent(element, grandparent);
+            element = parent;
+            parent = grandparent;
+        }
+
+        element
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<Element> {
+        (0 .. self.len())
+            .map(|i| self.find(Element::from_usize(i).unwrap()))
+            .collect()
+    }
+
+    // HELPERS
+
+    fn parent(&self, element: Element) -> Element {
+        self.parents[element.to_usize()].get()
+    }
+
+    fn set_parent(&self, element: Element, parent: Element) {
+        self.parents[element.to_usize()].set(parent);
+    }
+}
+
+// The SplitMix64 finalizer, which maps consecutive inputs to well-mixed
+// outputs.
+pub(crate) fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use UnionFind;
+
+    fn build(seed: u64) -> RandomizedUnionFind<u32> {
+        let mut uf = RandomizedUnionFind::new(100, seed);
+        for i in 0 .. 100 {
+            uf.union(i, i * 37 % 100);
+            uf.union(i, (i + 13) % 50);
+        }
+        uf
+    }
+
+    #[test]
+    fn same_seed_same_forest() {
+        let uf1 = build(7);
+        let uf2 = build(7);
+        let parents = |uf: &RandomizedUnionFind<u32>| -> Vec<u32> {
+            uf.parents.iter().map(Cell::get).collect()
+        };
+        assert_eq!(parents(&uf1), parents(&uf2));
+
+        let others: Vec<_> = (0 .. 8).map(|seed| uf1.find(0) == build(seed).find(0))
+            .collect();
+        assert!(others.contains(&false));
+    }
+
+    #[test]
+    fn matches_union_find() {
+        let mut expected = UnionFind::<u32>::new(100);
+        for i in 0 .. 100 {
+            expected.union(i, i * 37 % 100);
+            expected.union(i, (i + 13) % 50);
+        }
+
+        let actual = build(3);
+        for a in 0 .. 100 {
+            for b in 0 .. 100 {
+                assert_eq!(actual.equiv(a, b), expected.equiv(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn higher_priority_becomes_root() {
+        let mut uf = RandomizedUnionFind::<usize>::new(2, 0);
+        uf.union(0, 1);
+        let winner = if uf.priority(0) > uf.priority(1) { 0 } else { 1 };
+        assert_eq!(uf.find(0), winner);
+    }
+}
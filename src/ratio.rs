@@ -0,0 +1,217 @@
+use super::{Conflict, ElementType, Group, WeightedUnionFind};
+
+// Nonzero, finite `f64`s under multiplication. Rounding means the group
+// laws hold only approximately, which is why `RatioUnionFind` compares
+// ratios with a tolerance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Ratio(f64);
+
+impl Group for Ratio {
+    fn identity() -> Self {
+        Ratio(1.0)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Ratio(self.0 * other.0)
+    }
+
+    fn inverse(&self) -> Self {
+        Ratio(1.0 / self.0)
+    }
+}
+
+/// Union-find that tracks multiplicative ratios between the elements of
+/// each set.
+///
+/// [`union(a, b, r)`](#method.union) records that one `a` is worth `r`
+/// of `b`, as in a currency exchange rate or a unit conversion, and once
+/// two elements are connected [`ratio`](#method.ratio) returns the rate
+/// between them accumulated along the way. A union that disagrees with
+/// the known ratio by more than the relative
+/// [`tolerance`](#method.tolerance) is rejected with a
+/// [`Conflict`](struct.Conflict.html).
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::RatioUnionFind;
+///
+/// const METER: usize = 0;
+/// const FOOT: usize = 1;
+/// const INCH: usize = 2;
+///
+/// let mut units = RatioUnionFind::<usize>::new(3);
+/// units.union(FOOT, INCH, 12.0).unwrap();
+/// units.union(INCH, METER, 0.0254).unwrap();
+///
+/// let feet_per_meter = units.ratio(METER, FOOT).unwrap();
+/// assert!((feet_per_meter - 3.28084).abs() < 1e-5);
+///
+/// assert!(units.union(METER, INCH, 40.0).is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct RatioUnionFind<Element: ElementType = usize> {
+    union_find: WeightedUnionFind<Ratio, Element>,
+    tolerance:  f64,
+}
+
+impl<Element: ElementType> Default for RatioUnionFind<Element> {
+    fn default() -> Self {
+        RatioUnionFind::new(0)
+    }
+}
+
+impl<Element: ElementType> RatioUnionFind<Element> {
+    /// The default relative tolerance for comparing ratios.
+    pub const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+    /// Creates a new union-find of `size` elements, with the
+    /// [default tolerance](#associatedconstant.DEFAULT_TOLERANCE).
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        Self::with_tolerance(size, Self::DEFAULT_TOLERANCE)
+    }
+
+    /// Creates a new union-find of `size` elements, which considers two
+    /// ratios to agree if they differ by at most `tolerance` times the
+    /// larger of them.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`, or
+    /// if `tolerance` is negative or NaN.
+    pub fn with_tolerance(size: usize, tolerance: f64) -> Self {
+        assert!(tolerance >= 0.0, "RatioUnionFind::with_tolerance: bad tolerance");
+
+        RatioUnionFind {
+            union_find: WeightedUnionFind::new(size),
+            tolerance,
+        }
+    }
+
+    /// The relative tolerance for comparing ratios.
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.union_find.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.union_find.is_empty()
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        self.union_find.alloc()
+    }
+
+    /// Joins the sets of the two given elements, recording that one `a`
+    /// is worth `ratio` of `b`.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `Ok(true)`, and if they were already the
+    /// same and `ratio` agrees with their known ratio, it returns
+    /// `Ok(false)`. If `ratio` disagrees, it returns a `Conflict` and
+    /// leaves the union-find unchanged.
+    ///
+    /// # Panics
+    ///
+    /// If `ratio` is zero, infinite, or NaN.
+    pub fn union(&mut self, a: Element, b: Element, ratio: f64)
+                 -> Result<bool, Conflict<f64>> {
+        assert!(ratio.is_finite() && ratio != 0.0,
+                "RatioUnionFind::union: bad ratio");
+
+        match self.ratio(a, b) {
+            Some(current) if self.agree(current, ratio) => Ok(false),
+            Some(current) => Err(Conflict { current, requested: ratio }),
+            None => {
+                self.union_find.union(a, b, Ratio(ratio))
+                    .expect("RatioUnionFind::union: sets are disjoint");
+                Ok(true)
+            }
+        }
+    }
+
+    /// Returns how much of `b` one `a` is worth, if `a` and `b` are in the
+    /// same set, or `None` if they are not.
+    pub fn ratio(&mut self, a: Element, b: Element) -> Option<f64> {
+        self.union_find.delta(a, b).map(|ratio| ratio.0)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&mut self, element: Element) -> Element {
+        self.union_find.find(element)
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&mut self, a: Element, b: Element) -> bool {
+        self.union_find.equiv(a, b)
+    }
+
+    // HELPERS
+
+    fn agree(&self, x: f64, y: f64) -> bool {
+        (x - y).abs() <= self.tolerance * x.abs().max(y.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn currency_conversion() {
+        let (usd, eur, gbp, jpy) = (0u8, 1, 2, 3);
+        let mut rates = RatioUnionFind::<u8>::new(4);
+        rates.union(eur, usd, 1.08).unwrap();
+        rates.union(gbp, eur, 1.17).unwrap();
+        rates.union(usd, jpy, 150.0).unwrap();
+
+        let gbp_in_jpy = rates.ratio(gbp, jpy).unwrap();
+        assert!((gbp_in_jpy - 1.17 * 1.08 * 150.0).abs() < 1e-9);
+        let jpy_in_gbp = rates.ratio(jpy, gbp).unwrap();
+        assert!((gbp_in_jpy * jpy_in_gbp - 1.0).abs() < 1e-12);
+
+        assert_eq!(rates.union(gbp, jpy, 1.17 * 1.08 * 150.0), Ok(false));
+    }
+
+    #[test]
+    fn tolerance() {
+        let mut uf = RatioUnionFind::<usize>::with_tolerance(3, 0.01);
+        uf.union(0, 1, 2.0).unwrap();
+        uf.union(1, 2, 3.0).unwrap();
+        assert_eq!(uf.union(0, 2, 6.05), Ok(false));
+
+        match uf.union(0, 2, 6.2) {
+            Err(conflict) => {
+                assert!((conflict.current - 6.0).abs() < 1e-12);
+                assert_eq!(conflict.requested, 6.2);
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_ratio() {
+        let mut uf = RatioUnionFind::<usize>::new(2);
+        let _ = uf.union(0, 1, 0.0);
+    }
+}
@@ -0,0 +1,308 @@
+use std::cell::Cell;
+use std::fmt::{self, Debug};
+
+use super::{ElementType, Error, MemoryUsage, UnionFind};
+
+// The number of elements stored inline before spilling to the heap. This
+// must not exceed 256, since inline parents are stored as `u8`s.
+const INLINE_LEN: usize = 32;
+
+/// Union-find that stores small universes inline, without allocating.
+///
+/// Up to [`INLINE_CAPACITY`](#associatedconstant.INLINE_CAPACITY)
+/// elements are stored directly in the structure. Allocating more than
+/// that moves the elements to the heap, into a
+/// [`UnionFind`](struct.UnionFind.html). This makes creating many small
+/// union-finds cheap.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::SmallUnionFind;
+///
+/// let mut uf = SmallUnionFind::<u32>::new(4);
+/// assert!(!uf.spilled());
+///
+/// uf.union(0, 3);
+/// assert!(uf.equiv(3, 0));
+///
+/// for _ in 0 .. 40 {
+///     uf.alloc();
+/// }
+/// assert!(uf.spilled());
+/// assert!(uf.equiv(3, 0));
+/// ```
+#[derive(Clone)]
+pub struct SmallUnionFind<Element: ElementType = usize>(Storage<Element>);
+
+#[derive(Clone)]
+enum Storage<Element: ElementType> {
+    Inline {
+        len:     u8,
+        parents: [Cell<u8>; INLINE_LEN],
+        ranks:   [u8; INLINE_LEN],
+    },
+    Heap(UnionFind<Element>),
+}
+// Invariant: if inline, len <= INLINE_LEN and every parent is less than len.
+
+impl<Element: ElementType> Debug for SmallUnionFind<Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Storage::Inline { len, ref parents, .. } => {
+                write!(formatter, "SmallUnionFind(")?;
+                formatter.debug_list()
+                    .entries(parents[.. len as usize].iter().map(|parent| {
+                        Element::from_usize(parent.get() as usize).unwrap()
+                    }))
+                    .finish()?;
+                write!(formatter, ")")
+            }
+            Storage::Heap(ref uf) => write!(formatter, "SmallUnionFind({:?})", uf),
+        }
+    }
+}
+
+impl<Element: ElementType> Default for SmallUnionFind<Element> {
+    fn default() -> Self {
+        SmallUnionFind::new(0)
+    }
+}
+
+impl<Element: ElementType> SmallUnionFind<Element> {
+    /// The number of elements that can be stored without allocating.
+    pub const INLINE_CAPACITY: usize = INLINE_LEN;
+
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// This allocates only if `size` exceeds
+    /// [`INLINE_CAPACITY`](#associatedconstant.INLINE_CAPACITY).
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        Self::try_new(size).expect("SmallUnionFind::new: overflow")
+    }
+
+    /// Creates a new union-find of `size` elements, if possible.
+    ///
+    /// Like [`new`](#method.new), but returns an error instead of
+    /// panicking.
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        if size > INLINE_LEN {
+            return UnionFind::try_new(size).map(|uf| SmallUnionFind(Storage::Heap(uf)));
+        }
+
+        if size > 0 && Element::from_usize(size - 1).is_none() {
+            return Err(Error::Overflow);
+        }
+
+        let parents: [Cell<u8>; INLINE_LEN] = Default::default();
+        for (i, parent) in parents.iter().enumerate() {
+            parent.set(i as u8);
+        }
+
+        Ok(SmallUnionFind(Storage::Inline {
+            len:   size as u8,
+            parents,
+            ranks: [0; INLINE_LEN],
+        }))
+    }
+
+    /// Has the union-find moved its elements to the heap?
+    pub fn spilled(&self) -> bool {
+        match self.0 {
+            Storage::Inline { .. } => false,
+            Storage::Heap(_)       => true,
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        match self.0 {
+            Storage::Inline { len, .. } => len as usize,
+            Storage::Heap(ref uf)       => uf.len(),
+        }
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reports the heap memory used by the union-find, which is nothing
+    /// until it has [`spilled`](#method.spilled).
+    pub fn memory_usage(&self) -> MemoryUsage {
+        match self.0 {
+            Storage::Inline { .. } => MemoryUsage::new(),
+            Storage::Heap(ref uf)  => uf.memory_usage(),
+        }
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// If the inline storage is full, this moves the elements to the heap.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        if self.len() == INLINE_LEN {
+            self.spill();
+        }
+
+        match self.0 {
+            Storage::Inline { ref mut len, .. } => {
+                let result = Element::from_usize(*len as usize)
+                               .expect("SmallUnionFind::alloc: overflow");
+                *len += 1;
+                result
+            }
+            Storage::Heap(ref mut uf) => uf.alloc(),
+        }
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        match self.0 {
+            Storage::Inline { ref parents, ref mut ranks, .. } => {
+                let (a, b) = (a.to_usize(), b.to_usize());
+                if a == b { return false; }
+
+                if ranks[a] > ranks[b] {
+                    parents[b].set(a as u8);
+                } else if ranks[b] > ranks[a] {
+                    parents[a].set(b as u8);
+                } else {
+                    parents[a].set(b as u8);
+                    ranks[b] = ranks[b].saturating_add(1);
+                }
+
+                true
+            }
+            Storage::Heap(ref mut uf) => uf.union(a, b),
+        }
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, element: Element) -> Element {
+        match self.0 {
+            Storage::Inline { len, ref parents, .. } => {
+                let mut index = element.to_usize();
+                assert!(index < len as usize, "SmallUnionFind::find: out of bounds");
+
+                let mut parent = parents[index].get() as usize;
+                while index != parent {
+                    let grandparent = parents[parent].get();
+                    parents[index].set(grandparent);
+                    index = parent;
+                    parent = grandparent as usize;
+                }
+
+                Element::from_usize(index).unwrap()
+            }
+            Storage::Heap(ref uf) => uf.find(element),
+        }
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&self) -> Vec<Element> {
+        (0 .. self.len())
+            .map(|i| self.find(Element::from_usize(i).unwrap()))
+            .collect()
+    }
+
+    /// Converts to a heap-allocated [`UnionFind`](struct.UnionFind.html)
+    /// representing the same partition.
+    pub fn into_union_find(mut self) -> UnionFind<Element> {
+        self.spill();
+        match self.0 {
+            Storage::Heap(uf) => uf,
+            Storage::Inline { .. } => unreachable!(),
+        }
+    }
+
+    // HELPERS
+
+    // Moves the elements to the heap, if they aren’t already.
+    fn spill(&mut self) {
+        if self.spilled() { return; }
+
+        let len = self.len();
+        let mut uf = UnionFind::try_with_capacity(2 * len)
+                         .expect("SmallUnionFind::spill: allocation failed");
+        for _ in 0 .. len {
+            uf.alloc();
+        }
+        for i in 0 .. len {
+            let element = Element::from_usize(i).unwrap();
+            uf.union(element, self.find(element));
+        }
+
+        self.0 = Storage::Heap(uf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_unions() {
+        let mut uf = SmallUnionFind::<u8>::new(8);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(1, 2));
+        assert!(uf.union(4, 3));
+        assert!(uf.union(3, 2));
+        assert!(! uf.union(0, 3));
+
+        assert!(uf.equiv(0, 4));
+        assert!(!uf.equiv(0, 5));
+        assert!(!uf.spilled());
+    }
+
+    #[test]
+    fn spills() {
+        let mut uf = SmallUnionFind::<u32>::new(SmallUnionFind::<u32>::INLINE_CAPACITY);
+        uf.union(0, 31);
+        uf.union(5, 6);
+        assert!(!uf.spilled());
+
+        assert_eq!(uf.alloc(), 32);
+        assert!(uf.spilled());
+        assert!(uf.equiv(0, 31));
+        assert!(uf.equiv(5, 6));
+        assert!(!uf.equiv(0, 5));
+
+        uf.union(32, 6);
+        assert!(uf.equiv(5, 32));
+
+        let big = SmallUnionFind::<u32>::new(100);
+        assert!(big.spilled());
+        assert_eq!(big.len(), 100);
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(SmallUnionFind::<u8>::try_new(257).map(|uf| uf.len()),
+                   Err(Error::Overflow));
+    }
+}
@@ -0,0 +1,164 @@
+/// The shape of a union-find’s forest.
+///
+/// This is produced by [`AUnionFind::stats`](struct.AUnionFind.html#method.stats)
+/// for diagnosing performance. A path’s length is the number of parent
+/// links from an element to its root, so roots have length 0, and after
+/// [`force`](struct.AUnionFind.html#method.force) every path has length
+/// at most 1.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::AUnionFind;
+///
+/// let uf = AUnionFind::new(4);
+/// uf.union(0, 1);
+/// uf.union(2, 3);
+/// uf.union(1, 3);
+///
+/// let stats = uf.stats();
+/// assert_eq!(stats.root_count(), 1);
+/// assert_eq!(stats.rank_counts(), &[2, 1, 1]);
+/// assert_eq!(stats.max_path_length(), 2);
+/// assert_eq!(stats.average_path_length(), 1.0);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ForestStats {
+    len:               usize,
+    roots:             usize,
+    rank_counts:       Vec<usize>,
+    total_path_length: usize,
+    max_path_length:   usize,
+    flat:              usize,
+}
+
+impl ForestStats {
+    // Walks the forest without compressing it, memoizing each element’s
+    // path length so the walk takes linear time.
+    //
+    // PRECONDITION: `parent` describes a forest over `0 .. len`. If it is
+    // read while the forest changes, it must only ever move an element
+    // to one of its ancestors, so that every walk reaches a root.
+    pub(crate) fn from_forest<P, R>(len: usize, parent: P, rank: R) -> Self
+        where P: Fn(usize) -> usize,
+              R: Fn(usize) -> usize {
+
+        const UNKNOWN: usize = !0;
+
+        let mut result = ForestStats { len, ..ForestStats::default() };
+        let mut lengths = vec![UNKNOWN; len];
+        let mut path = Vec::new();
+
+        for i in 0 .. len {
+            let rank = rank(i);
+            if rank >= result.rank_counts.len() {
+                result.rank_counts.resize(rank + 1, 0);
+            }
+            result.rank_counts[rank] += 1;
+
+            let mut element = i;
+            let mut length = loop {
+                if lengths[element] != UNKNOWN { break lengths[element]; }
+                let next = parent(element);
+                if next == element { break 0; }
+                path.push(element);
+                element = next;
+            };
+            if lengths[element] == UNKNOWN {
+                lengths[element] = length;
+            }
+            while let Some(element) = path.pop() {
+                length += 1;
+                lengths[element] = length;
+            }
+
+            let length = lengths[i];
+            if length == 0 { result.roots += 1; }
+            if length <= 1 { result.flat += 1; }
+            result.total_path_length += length;
+            result.max_path_length = result.max_path_length.max(length);
+        }
+
+        result
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the forest devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of roots, which is the number of sets.
+    pub fn root_count(&self) -> usize {
+        self.roots
+    }
+
+    /// The number of elements of each rank, indexed by rank.
+    pub fn rank_counts(&self) -> &[usize] {
+        &self.rank_counts
+    }
+
+    /// The greatest rank of any element, or 0 if there are none.
+    pub fn max_rank(&self) -> usize {
+        self.rank_counts.len().saturating_sub(1)
+    }
+
+    /// The mean length of the paths from elements to their roots, or 0 if
+    /// there are no elements.
+    pub fn average_path_length(&self) -> f64 {
+        if self.len == 0 {
+            0.0
+        } else {
+            self.total_path_length as f64 / self.len as f64
+        }
+    }
+
+    /// The length of the longest path from an element to its root.
+    pub fn max_path_length(&self) -> usize {
+        self.max_path_length
+    }
+
+    /// The fraction of elements that are roots or point directly at
+    /// their roots, or 1 if there are no elements.
+    pub fn flat_fraction(&self) -> f64 {
+        if self.len == 0 {
+            1.0
+        } else {
+            self.flat as f64 / self.len as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain() {
+        // 0 → 1 → 2 → 3, and 4 alone.
+        let parents = [1, 2, 3, 3, 4];
+        let ranks = [0, 1, 2, 3, 0];
+        let stats = ForestStats::from_forest(5, |i| parents[i], |i| ranks[i]);
+
+        assert_eq!(stats.len(), 5);
+        assert_eq!(stats.root_count(), 2);
+        assert_eq!(stats.max_path_length(), 3);
+        assert_eq!(stats.average_path_length(), 6.0 / 5.0);
+        assert_eq!(stats.flat_fraction(), 3.0 / 5.0);
+        assert_eq!(stats.rank_counts(), &[2, 1, 1, 1]);
+        assert_eq!(stats.max_rank(), 3);
+    }
+
+    #[test]
+    fn empty() {
+        let stats = ForestStats::from_forest(0, |i| i, |_| 0);
+        assert!(stats.is_empty());
+        assert_eq!(stats.max_rank(), 0);
+        assert_eq!(stats.average_path_length(), 0.0);
+        assert_eq!(stats.flat_fraction(), 1.0);
+    }
+}
@@ -0,0 +1,350 @@
+//! Thread-safe tree-based union-find with associated data.
+
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::Merge;
+
+/// Pointer-based union-find with associated data that can be shared
+/// between threads.
+///
+/// This is like [`UnionFindNode`](struct.UnionFindNode.html), but uses
+/// `Arc` and a `Mutex` per node in place of `Rc` and `RefCell`, so nodes
+/// are `Send` and `Sync` whenever `Data` is `Send`. Since locking is
+/// internal, every operation, including union, takes `&self`.
+///
+/// The closures passed to [`with_data`](#method.with_data) and
+/// [`union_with`](#method.union_with) run while holding the locks of the
+/// sets involved, so they must not operate on nodes of those sets, or
+/// they will deadlock.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::SyncUnionFindNode;
+/// use std::thread;
+///
+/// let nodes: Vec<_> = (0 .. 8).map(|_| SyncUnionFindNode::new(1)).collect();
+///
+/// // The edges form a path, so every union joins two different sets.
+/// let handles: Vec<_> = (0 .. 7).map(|i| {
+///     let (a, b) = (nodes[i].clone(), nodes[i + 1].clone());
+///     thread::spawn(move || a.union_with(&b, |x, y| x + y))
+/// }).collect();
+///
+/// for handle in handles {
+///     assert!(handle.join().unwrap());
+/// }
+///
+/// assert!(nodes[0].equiv(&nodes[5]));
+/// assert_eq!(nodes[0].clone_data(), 8);
+/// assert_eq!(nodes[3].find(), nodes[7].find());
+/// ```
+pub struct SyncUnionFindNode<Data = ()>(Arc<Mutex<NodeImpl<Data>>>);
+
+enum NodeImpl<Data> {
+    Root {
+        data: Data,
+        rank: u8,
+    },
+    Link(SyncUnionFindNode<Data>),
+    Dummy,
+}
+
+use self::NodeImpl::*;
+
+impl<Data> SyncUnionFindNode<Data> {
+    fn addr(&self) -> usize {
+        &*self.0 as *const _ as usize
+    }
+
+    // A panic while the lock is held leaves a `Dummy` behind rather than
+    // inconsistent links, so poisoning is ignored as it is for `RefCell`.
+    fn lock(&self) -> MutexGuard<'_, NodeImpl<Data>> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl<Data: Default> Default for SyncUnionFindNode<Data> {
+    fn default() -> Self {
+        Self::new(Data::default())
+    }
+}
+
+impl<Data> Clone for SyncUnionFindNode<Data> {
+    fn clone(&self) -> Self {
+        SyncUnionFindNode(Arc::clone(&self.0))
+    }
+}
+
+impl<Data> Debug for SyncUnionFindNode<Data> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "SyncUnionFindNode({:p})", self.0)
+    }
+}
+
+impl<Data> PartialEq for SyncUnionFindNode<Data> {
+    fn eq(&self, other: &SyncUnionFindNode<Data>) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<Data> Eq for SyncUnionFindNode<Data> { }
+
+impl<Data> PartialOrd for SyncUnionFindNode<Data> {
+    fn partial_cmp(&self, other: &SyncUnionFindNode<Data>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Data> Ord for SyncUnionFindNode<Data> {
+    fn cmp(&self, other: &SyncUnionFindNode<Data>) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl<Data> Hash for SyncUnionFindNode<Data> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state)
+    }
+}
+
+impl<Data> SyncUnionFindNode<Data> {
+    /// Creates a new singleton set with associated data.
+    ///
+    /// Initially this set is disjoint from all other sets, but can
+    /// be joined with other sets using [`union`](#method.union).
+    pub fn new(data: Data) -> Self {
+        SyncUnionFindNode(Arc::new(Mutex::new(Root {
+            data,
+            rank: 0,
+        })))
+    }
+
+    /// Unions two sets, combining their data as specified.
+    ///
+    /// To determine the data associated with the set resulting from a
+    /// union, we pass a closure `f`, which will be passed `self`’s data
+    /// and `other`’s data (in that order). Then `f` must return the data to
+    /// associate with the unioned set.
+    pub fn union_with<F>(&self, other: &Self, f: F) -> bool
+            where F: FnOnce(Data, Data) -> Data {
+
+        self.union_roots(other, |a, b| (f(a, b), ())).is_some()
+    }
+
+    /// Unions two sets, combining their data with
+    /// [`Merge::merge`](trait.Merge.html).
+    pub fn union_merged(&self, other: &Self) -> bool
+            where Data: Merge {
+        self.union_with(other, Data::merge)
+    }
+
+    /// Unions two sets.
+    ///
+    /// Retains the data associated with an arbitrary set, returning the
+    /// data of the other. Returns `None` if `self` and `other` are
+    /// already elements of the same set.
+    pub fn union(&self, other: &Self) -> Option<Data> {
+        self.union_roots(other, |a, b| (a, b))
+    }
+
+    /// Finds a node representing the set of a given node.
+    ///
+    /// For two nodes in the same set, `find` returns the same node,
+    /// unless the set is concurrently joined with another.
+    pub fn find(&self) -> Self {
+        let mut path = Vec::new();
+        let mut node = self.clone();
+
+        loop {
+            let parent = match *node.lock() {
+                Root { .. } => break,
+                Link(ref parent) => parent.clone(),
+                Dummy => panic!("find: got dummy"),
+            };
+            path.push(mem::replace(&mut node, parent));
+        }
+
+        // A node’s parent only ever changes to one of its ancestors, so
+        // `node` remains a valid parent even if it is no longer the root.
+        for step in path {
+            if let Link(ref mut parent) = *step.lock() {
+                *parent = node.clone();
+            }
+        }
+
+        node
+    }
+
+    /// Are the two nodes representatives of the same set?
+    pub fn equiv(&self, other: &Self) -> bool {
+        loop {
+            let a = self.find();
+            let b = other.find();
+
+            if a == b {
+                return true;
+            }
+
+            // If `a` is still a root, then the sets were different when
+            // `b` was found.
+            let still_root = matches!(*a.lock(), Root { .. });
+            if still_root {
+                return false;
+            }
+        }
+    }
+
+    /// Replaces the data associated with the set.
+    pub fn replace_data(&self, new: Data) -> Data {
+        self.with_data(|data| mem::replace(data, new))
+    }
+
+    /// Returns a clone of the data associated with the set.
+    pub fn clone_data(&self) -> Data
+            where Data: Clone {
+        self.with_data(|data| data.clone())
+    }
+
+    /// Allows modifying the data associated with a set.
+    ///
+    /// The set’s lock is held while `f` runs.
+    pub fn with_data<R, F>(&self, f: F) -> R
+            where F: FnOnce(&mut Data) -> R {
+
+        loop {
+            let root = self.find();
+            let mut guard = root.lock();
+
+            match *guard {
+                Root { ref mut data, .. } => return f(data),
+                Link(_) => continue,
+                Dummy => panic!("with_data: got dummy"),
+            }
+        }
+    }
+
+    // HELPERS
+
+    // Links the roots of two different sets, combining their data with
+    // `f`, which also returns a result to pass along. Returns `None` if
+    // the sets are the same.
+    fn union_roots<R, F>(&self, other: &Self, f: F) -> Option<R>
+            where F: FnOnce(Data, Data) -> (Data, R) {
+
+        loop {
+            let a = self.find();
+            let b = other.find();
+
+            if a == b {
+                return None;
+            }
+
+            // Locking in address order prevents deadlock.
+            let (mut guard_a, mut guard_b) = if a < b {
+                let guard_a = a.lock();
+                (guard_a, b.lock())
+            } else {
+                let guard_b = b.lock();
+                (a.lock(), guard_b)
+            };
+
+            let (rank_a, rank_b) = match (&*guard_a, &*guard_b) {
+                (&Root { rank: rank_a, .. }, &Root { rank: rank_b, .. }) =>
+                    (rank_a, rank_b),
+                _ => continue,
+            };
+
+            // Make `b` the parent.
+            let a_is_parent = rank_a > rank_b;
+            if a_is_parent {
+                mem::swap(&mut guard_a, &mut guard_b);
+            }
+            let parent = if a_is_parent { &a } else { &b };
+
+            let contents_child = mem::replace(&mut *guard_a, Link(parent.clone()));
+            let contents_parent = mem::replace(&mut *guard_b, Dummy);
+
+            match (contents_child, contents_parent) {
+                (Root { data: data_child, .. },
+                 Root { data: data_parent, rank }) => {
+                    let (data, result) = if a_is_parent {
+                        f(data_parent, data_child)
+                    } else {
+                        f(data_child, data_parent)
+                    };
+                    *guard_b = Root {
+                        data,
+                        rank: if rank_a == rank_b { rank.saturating_add(1) } else { rank },
+                    };
+                    return Some(result);
+                }
+                _ => panic!("union: non-root"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn union() {
+        let a = SyncUnionFindNode::new('a');
+        let b = SyncUnionFindNode::new('b');
+        assert!(!a.equiv(&b));
+        assert_eq!(a.union(&b), Some('b'));
+        assert_eq!(b.union(&a), None);
+        assert_eq!(b.clone_data(), 'a');
+        assert_eq!(a.find(), b.find());
+    }
+
+    #[test]
+    fn opposite_lock_orders() {
+        const PAIRS: usize = 1 << 12;
+
+        let a: Vec<_> = (0 .. PAIRS).map(|_| SyncUnionFindNode::new(1)).collect();
+        let b: Vec<_> = (0 .. PAIRS).map(|_| SyncUnionFindNode::new(1)).collect();
+
+        // The two threads make the same unions with the arguments
+        // swapped, so unless the locks are taken in address order, each
+        // can hold the lock the other is waiting for.
+        let barrier = Arc::new(Barrier::new(2));
+        let forward = {
+            let (a, b) = (a.clone(), b.clone());
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                let mut links = 0;
+                for k in 0 .. PAIRS {
+                    links += a[k].union_with(&b[k], |x, y| x + y) as usize;
+                    if k > 0 {
+                        links += a[k - 1].union_with(&b[k], |x, y| x + y) as usize;
+                    }
+                }
+                links
+            })
+        };
+
+        barrier.wait();
+        let mut links = 0;
+        for k in 0 .. PAIRS {
+            links += b[k].union_with(&a[k], |x, y| x + y) as usize;
+            if k > 0 {
+                links += b[k].union_with(&a[k - 1], |x, y| x + y) as usize;
+            }
+        }
+        links += forward.join().unwrap();
+
+        assert_eq!(links, 2 * PAIRS - 1);
+        assert!(a.iter().chain(&b).all(|node| node.equiv(&a[0])));
+        assert_eq!(a[0].clone_data(), 2 * PAIRS);
+    }
+}
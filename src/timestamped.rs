@@ -0,0 +1,249 @@
+use std::fmt::{self, Debug};
+
+use super::ElementType;
+
+/// Union-find that remembers when each pair of elements became connected.
+///
+/// Each [`union`](#method.union) is stamped with a time, which may be a
+/// clock reading or any other non-decreasing weight, such as the edge
+/// weights of Kruskal’s algorithm in sorted order. Then
+/// [`connected_since`](#method.connected_since) returns the time at which
+/// two elements first became connected, and
+/// [`connected_at`](#method.connected_at) asks whether they were
+/// connected at a given time.
+///
+/// To keep the history, this union-find uses union by rank without path
+/// compression, so every query takes time logarithmic in the size of the
+/// set.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::TimestampedUnionFind;
+///
+/// let mut uf = TimestampedUnionFind::<u32, u32>::new(4);
+/// uf.union(0, 1, 10);
+/// uf.union(2, 3, 20);
+/// uf.union(1, 2, 30);
+///
+/// assert_eq!(uf.connected_since(0, 1), Some(10));
+/// assert_eq!(uf.connected_since(0, 3), Some(30));
+/// assert!( uf.connected_at(2, 3, 25));
+/// assert!(!uf.connected_at(0, 3, 25));
+/// ```
+#[derive(Clone)]
+pub struct TimestampedUnionFind<T, Element: ElementType = usize> {
+    parents: Vec<Element>,
+    ranks:   Vec<u8>,
+    // The time each element was linked beneath its parent, or `None` for
+    // roots. Times increase along every path toward the root.
+    stamps:  Vec<Option<T>>,
+    latest:  Option<T>,
+}
+
+impl<T: Ord + Copy, Element: ElementType> Default for TimestampedUnionFind<T, Element> {
+    fn default() -> Self {
+        TimestampedUnionFind::new(0)
+    }
+}
+
+impl<T, Element: Debug + ElementType> Debug for TimestampedUnionFind<T, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "TimestampedUnionFind(")?;
+        formatter.debug_list().entries(&self.parents).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<T: Ord + Copy, Element: ElementType> TimestampedUnionFind<T, Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        if size > 0 {
+            Element::from_usize(size - 1)
+                .expect("TimestampedUnionFind::new: overflow");
+        }
+
+        TimestampedUnionFind {
+            parents: (0 .. size).map(|i| Element::from_usize(i).unwrap()).collect(),
+            ranks:   vec![0; size],
+            stamps:  vec![None; size],
+            latest:  None,
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// The time of the most recent union, if any.
+    pub fn latest(&self) -> Option<T> {
+        self.latest
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = Element::from_usize(self.len())
+            .expect("TimestampedUnionFind::alloc: overflow");
+        self.parents.push(result);
+        self.ranks.push(0);
+        self.stamps.push(None);
+        result
+    }
+
+    /// Joins the sets of the two given elements at the given time.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// If `time` is earlier than the time of a previous union.
+    pub fn union(&mut self, a: Element, b: Element, time: T) -> bool {
+        if let Some(latest) = self.latest {
+            assert!(time >= latest, "TimestampedUnionFind::union: time went backward");
+        }
+        self.latest = Some(time);
+
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b { return false; }
+
+        let rank_a = self.ranks[a.to_usize()];
+        let rank_b = self.ranks[b.to_usize()];
+
+        let (child, parent) = if rank_a > rank_b { (b, a) } else { (a, b) };
+        self.parents[child.to_usize()] = parent;
+        self.stamps[child.to_usize()] = Some(time);
+        if rank_a == rank_b {
+            self.ranks[parent.to_usize()] += 1;
+        }
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, mut element: Element) -> Element {
+        loop {
+            let parent = self.parents[element.to_usize()];
+            if parent == element { return element; }
+            element = parent;
+        }
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns the time of the union that connected two elements.
+    ///
+    /// This is the largest time on the path between them in the forest of
+    /// unions. If the elements are in different sets, or are the same
+    /// element, the result is `None`.
+    pub fn connected_since(&self, mut a: Element, mut b: Element) -> Option<T> {
+        let mut result = None;
+
+        // Climbing from whichever side was linked earlier meets at the
+        // lowest common ancestor, since times increase toward the root.
+        while a != b {
+            let stamp_a = self.stamps[a.to_usize()];
+            let stamp_b = self.stamps[b.to_usize()];
+
+            let (element, stamp) = match (stamp_a, stamp_b) {
+                (None, None) => return None,
+                (Some(s), None) => (&mut a, s),
+                (None, Some(s)) => (&mut b, s),
+                (Some(s), Some(t)) => if s <= t { (&mut a, s) } else { (&mut b, t) },
+            };
+
+            *element = self.parents[element.to_usize()];
+            result = Some(stamp);
+        }
+
+        result
+    }
+
+    /// Determines whether two elements were in the same set at the given
+    /// time, counting unions stamped with exactly that time.
+    pub fn connected_at(&self, a: Element, b: Element, time: T) -> bool {
+        if a == b { return true; }
+
+        match self.connected_since(a, b) {
+            Some(since) => since <= time,
+            None        => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use UnionFind;
+
+    #[test]
+    fn matches_replay() {
+        let edges: Vec<(usize, usize)> = (0 .. 40)
+            .map(|i| (i * 7 % 30, (i * 13 + 5) % 30))
+            .collect();
+
+        let mut uf = TimestampedUnionFind::<usize>::new(30);
+        for (time, &(a, b)) in edges.iter().enumerate() {
+            uf.union(a, b, time);
+        }
+
+        // At each time, compare against a union-find built from the edges
+        // so far.
+        for time in 0 .. edges.len() {
+            let mut expected = UnionFind::<usize>::new(30);
+            for &(a, b) in &edges[.. time + 1] {
+                expected.union(a, b);
+            }
+
+            for a in 0 .. 30 {
+                for b in 0 .. 30 {
+                    assert_eq!(uf.connected_at(a, b, time), expected.equiv(a, b));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn equal_times() {
+        let mut uf = TimestampedUnionFind::<u8, u8>::new(4);
+        uf.union(0, 1, 5);
+        uf.union(2, 3, 5);
+        uf.union(1, 3, 5);
+        assert_eq!(uf.connected_since(0, 2), Some(5));
+        assert_eq!(uf.connected_since(0, 0), None);
+        assert_eq!(uf.latest(), Some(5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn time_goes_backward() {
+        let mut uf = TimestampedUnionFind::<u32>::new(3);
+        uf.union(0, 1, 2);
+        uf.union(1, 2, 1);
+    }
+}
@@ -1,4 +1,6 @@
-use std::fmt::Debug;
+use core::convert::TryFrom;
+use core::fmt::Debug;
+use core::num::{NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize};
 
 /// A type that can be used as a [`UnionFind`](struct.UnionFind.html)
 /// element.
@@ -10,12 +12,20 @@ use std::fmt::Debug;
 /// -  For all `n: usize`, if `Self::from_usize(n)` = `Some(t)` then
 ///    `t.to_usize()` = `n`.
 /// -  For all `t: Self`, if `t.to_usize()` = `n` then
-///    `Self::from_usize(n)` = `Some(t)`.
+///    `Self::from_usize(n)` = `Some(t)`, except that a value too large
+///    for `usize` may convert to `usize::MAX`, which no union-find
+///    accepts as in bounds.
 /// -  For all `n: usize`, if `Self::from_usize(n)` = `None` then for all
 ///    `m: usize` such that `m > n`, `Self::from_usize(m)` = `None`.
 ///
 /// In other words, `ElementType` sets up a bijection between the first
 /// *k* `usize` values and some *k* values of the `Self` type.
+///
+/// [`UnionFind`](struct.UnionFind.html) skips bounds checks on the paths
+/// it walks for the implementations in this crate, whose conversions are
+/// known to obey these laws. For other implementations it keeps the
+/// checks, so a misbehaving one can cause panics, but not undefined
+/// behavior.
 pub trait ElementType : Copy + Debug + Eq {
     /// Converts from `usize` to the element type.
     ///
@@ -24,13 +34,27 @@ pub trait ElementType : Copy + Debug + Eq {
 
     /// Converts from the element type to `usize`.
     fn to_usize(self) -> usize;
+
+    // Is this one of the implementations above, so that unsafe code may
+    // rely on the laws? Other crates cannot override it, since they
+    // cannot name `Sealed`.
+    #[doc(hidden)]
+    #[inline]
+    fn is_trusted(_: Sealed) -> bool { false }
 }
 
+// The argument that keeps `ElementType::is_trusted` to this crate. It is
+// public only so that it may appear in the trait, and not exported.
+#[derive(Clone, Copy, Debug)]
+pub struct Sealed;
+
 impl ElementType for usize {
     #[inline]
     fn from_usize(n: usize) -> Option<usize> { Some(n) }
     #[inline]
     fn to_usize(self) -> usize { self }
+    #[inline]
+    fn is_trusted(_: Sealed) -> bool { true }
 }
 
 macro_rules! element_type_impl {
@@ -46,6 +70,9 @@ macro_rules! element_type_impl {
             fn to_usize(self) -> usize {
                 self as usize
             }
+
+            #[inline]
+            fn is_trusted(_: Sealed) -> bool { true }
         }
     }
 }
@@ -53,3 +80,49 @@ macro_rules! element_type_impl {
 element_type_impl!(u8);
 element_type_impl!(u16);
 element_type_impl!(u32);
+
+// A `u64` may not fit in a 32-bit `usize`. Such an element can never be
+// in bounds, so it converts to `usize::MAX`, and every bounds check
+// rejects it as it would any other out-of-bounds element.
+impl ElementType for u64 {
+    #[inline]
+    fn from_usize(n: usize) -> Option<u64> {
+        u64::try_from(n).ok()
+    }
+
+    #[inline]
+    fn to_usize(self) -> usize {
+        usize::try_from(self).unwrap_or(usize::MAX)
+    }
+
+    #[inline]
+    fn is_trusted(_: Sealed) -> bool { true }
+}
+
+// Non-zero types number the elements from 1, so that `Option<Element>`
+// takes no more space than `Element`. Element `n` is represented by
+// `n + 1`.
+macro_rules! nonzero_element_type_impl {
+    ($type_:ident, $prim:ident) => {
+        impl ElementType for $type_ {
+            #[inline]
+            fn from_usize(n: usize) -> Option<Self> {
+                $prim::from_usize(n.checked_add(1)?).and_then($type_::new)
+            }
+
+            #[inline]
+            fn to_usize(self) -> usize {
+                (self.get() - 1).to_usize()
+            }
+
+            #[inline]
+            fn is_trusted(_: Sealed) -> bool { true }
+        }
+    }
+}
+
+nonzero_element_type_impl!(NonZeroU8, u8);
+nonzero_element_type_impl!(NonZeroU16, u16);
+nonzero_element_type_impl!(NonZeroU32, u32);
+nonzero_element_type_impl!(NonZeroU64, u64);
+nonzero_element_type_impl!(NonZeroUsize, usize);
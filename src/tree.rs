@@ -1,11 +1,20 @@
 //! Tree-based union-find with associated data.
 
-use std::cell::RefCell;
-use std::cmp::Ordering;
-use std::fmt::{self, Debug};
-use std::hash::{Hash, Hasher};
-use std::rc::Rc;
-use std::mem;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::rc::{Rc, Weak};
+use alloc::vec::Vec;
+use core::cell::{Ref, RefCell, RefMut};
+use core::cmp::Ordering;
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::mem;
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use super::{Error, Merge};
+#[cfg(feature = "std")]
+use super::UnionFind;
 
 /// Pointer-based union-find representing disjoint sets with associated data.
 ///
@@ -76,6 +85,13 @@ use std::mem;
 ///         params: Vec<Variable>,
 ///     },
 /// }
+///
+/// impl Default for Term_ {
+///     fn default() -> Self {
+///         Term_::Indeterminate
+///     }
+/// }
+///
 /// type Variable = UnionFindNode<Term_>;
 ///
 /// // To convert from external `Term`s to internal `Term_`s we use an
@@ -85,14 +101,11 @@ use std::mem;
 /// #[derive(Debug)]
 /// struct Environment(HashMap<String, Variable>);
 ///
-/// // The environment can get Rc-cycles in it (because we don’t do an
-/// // occurs check, hence terms can be recursive). To avoid leaking, we
-/// // need to clear the references out of it.
-/// impl Drop for Environment {
-///     fn drop(&mut self) {
-///         for (_, v) in self.0.drain() {
-///             v.replace_data(Term_::Indeterminate);
-///         }
+/// // The variables that a term refers to, for the occurs check.
+/// fn children(term: &Term_) -> Vec<Variable> {
+///     match *term {
+///         Term_::Indeterminate => vec![],
+///         Term_::Fixed { ref params, .. } => params.clone(),
 ///     }
 /// }
 ///
@@ -157,13 +170,25 @@ use std::mem;
 ///     fn unify(&mut self, mut v1: Variable, mut v2: Variable)
 ///              -> Result<(), String> {
 ///
+///         if v1.equiv(&v2) {
+///             return Ok(());
+///         }
+///
 ///         match (v1.clone_data(), v2.clone_data()) {
 ///             (Term_::Indeterminate, _) => {
+///                 // Without the occurs check, terms could be recursive,
+///                 // and their Rc-cycles would leak.
+///                 if v1.occurs_in(&v2, children) {
+///                     return Err("Occurs check failed".to_owned());
+///                 }
 ///                 v1.union_with(&mut v2, |_, t2| t2);
 ///                 Ok(())
 ///             },
 ///
 ///             (_, Term_::Indeterminate) => {
+///                 if v2.occurs_in(&v1, children) {
+///                     return Err("Occurs check failed".to_owned());
+///                 }
 ///                 v1.union_with(&mut v2, |t1, _| t1);
 ///                 Ok(())
 ///             },
@@ -234,15 +259,21 @@ use std::mem;
 ///                         term![ (a   A   B   C   (g)) ]));
 ///     assert!(  unifiable(term![ (a   (f) A   B   C  ) ],
 ///                         term![ (a   A   D   C   (g)) ]));
+///
+///     assert!(! unifiable(term![ A ], term![ (f A) ]));
+///     assert!(! unifiable(term![ (a A (f B)) ], term![ (a B A) ]));
 /// }
 /// ```
-#[derive(Default)]
 pub struct UnionFindNode<Data = ()>(Rc<RefCell<NodeImpl<Data>>>);
 
 enum NodeImpl<Data> {
     Root {
-        data: Data,
-        rank: u8,
+        data:    Data,
+        rank:    u8,
+        // A weak reference to each node in the set, some of which may
+        // have since been dropped. Dead references are pruned whenever the
+        // list would otherwise grow, so they take at most half of it.
+        members: Vec<Weak<RefCell<NodeImpl<Data>>>>,
     },
     Link(UnionFindNode<Data>),
     Dummy,
@@ -250,12 +281,75 @@ enum NodeImpl<Data> {
 
 use self::NodeImpl::*;
 
+/// A shared borrow of the data of a [`UnionFindNode`](struct.UnionFindNode.html)’s
+/// set.
+///
+/// This is produced by [`UnionFindNode::data`](struct.UnionFindNode.html#method.data).
+pub struct DataRef<'a, Data: 'a> {
+    // Borrows from `_root`’s cell, so it is declared first, to be
+    // dropped first.
+    data:  Ref<'a, Data>,
+    _root: UnionFindNode<Data>,
+}
+
+/// A mutable borrow of the data of a
+/// [`UnionFindNode`](struct.UnionFindNode.html)’s set.
+///
+/// This is produced by
+/// [`UnionFindNode::data_mut`](struct.UnionFindNode.html#method.data_mut).
+pub struct DataRefMut<'a, Data: 'a> {
+    // Borrows from `_root`’s cell, so it is declared first, to be
+    // dropped first.
+    data:  RefMut<'a, Data>,
+    _root: UnionFindNode<Data>,
+}
+
+impl<'a, Data> Deref for DataRef<'a, Data> {
+    type Target = Data;
+
+    fn deref(&self) -> &Data {
+        &self.data
+    }
+}
+
+impl<'a, Data> Deref for DataRefMut<'a, Data> {
+    type Target = Data;
+
+    fn deref(&self) -> &Data {
+        &self.data
+    }
+}
+
+impl<'a, Data> DerefMut for DataRefMut<'a, Data> {
+    fn deref_mut(&mut self) -> &mut Data {
+        &mut self.data
+    }
+}
+
+impl<'a, Data: Debug> Debug for DataRef<'a, Data> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&*self.data, formatter)
+    }
+}
+
+impl<'a, Data: Debug> Debug for DataRefMut<'a, Data> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&*self.data, formatter)
+    }
+}
+
 impl<Data> UnionFindNode<Data> {
     fn addr(&self) -> usize {
         &*self.0 as *const _ as usize
     }
 }
 
+impl<Data: Default> Default for UnionFindNode<Data> {
+    fn default() -> Self {
+        Self::new(Data::default())
+    }
+}
+
 impl<Data> Clone for UnionFindNode<Data> {
     fn clone(&self) -> Self {
         UnionFindNode(Rc::clone(&self.0))
@@ -294,28 +388,37 @@ impl<Data> Hash for UnionFindNode<Data> {
     }
 }
 
-impl<Data: Default> Default for NodeImpl<Data> {
-    fn default() -> Self {
-        Self::new(Data::default())
-    }
-}
-
-impl<Data> NodeImpl<Data> {
-    fn new(data: Data) -> Self {
-        Root {
-            data: data,
-            rank: 0,
-        }
-    }
-}
-
 impl<Data> UnionFindNode<Data> {
     /// Creates a new singleton set with associated data.
     ///
     /// Initially this set is disjoint from all other sets, but can
     /// be joined with other sets using [`union`](#method.union).
     pub fn new(data: Data) -> Self {
-        UnionFindNode(Rc::new(RefCell::new(NodeImpl::new(data))))
+        let node = Rc::new(RefCell::new(Root {
+            data,
+            rank:    0,
+            members: Vec::new(),
+        }));
+
+        if let Root { ref mut members, .. } = *node.borrow_mut() {
+            members.push(Rc::downgrade(&node));
+        }
+
+        UnionFindNode(node)
+    }
+
+    /// The number of heap bytes allocated for each node, not counting any
+    /// memory owned by its `Data` or by the member list of its set.
+    ///
+    /// Nodes are allocated individually, so a forest of `n` nodes uses
+    /// `n * UnionFindNode::<Data>::node_size()` bytes, plus the
+    /// allocator’s per-allocation overhead.
+    pub fn node_size() -> usize {
+        // An `Rc` allocation holds the strong and weak counts, followed
+        // by the value at its own alignment.
+        let align = mem::align_of::<RefCell<NodeImpl<Data>>>();
+        let header = 2 * mem::size_of::<usize>();
+        ((header + align - 1) & !(align - 1)) + mem::size_of::<RefCell<NodeImpl<Data>>>()
     }
 
     /// Unions two sets, combining their data as specified.
@@ -327,6 +430,49 @@ impl<Data> UnionFindNode<Data> {
     pub fn union_with<F>(&mut self, other: &mut Self, f: F) -> bool
             where F: FnOnce(Data, Data) -> Data {
 
+        self.union_with_veto(other, |a_data, b_data| Ok(f(a_data, b_data)))
+    }
+
+    /// Unions two sets, combining their data with a closure that may
+    /// decline.
+    ///
+    /// The closure `f` is passed `self`’s data and `other`’s data (in
+    /// that order). To accept the union, it returns `Ok` with the data
+    /// for the unioned set. To decline, it returns `Err` with the two
+    /// data values, again in that order, which go back to their sets
+    /// unchanged. Nothing is linked until `f` accepts.
+    ///
+    /// Returns whether the sets were joined: `false` if they were already
+    /// the same or if `f` declined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// // Join teams only if the result has at most three members.
+    /// fn merge(a: Vec<&'static str>, b: Vec<&'static str>)
+    ///          -> Result<Vec<&'static str>, (Vec<&'static str>, Vec<&'static str>)> {
+    ///     if a.len() + b.len() <= 3 {
+    ///         Ok(a.into_iter().chain(b).collect())
+    ///     } else {
+    ///         Err((a, b))
+    ///     }
+    /// }
+    ///
+    /// let mut ann = UnionFindNode::new(vec!["Ann"]);
+    /// let mut bob = UnionFindNode::new(vec!["Bob", "Bo"]);
+    /// let mut cat = UnionFindNode::new(vec!["Cat"]);
+    ///
+    /// assert!( ann.union_with_veto(&mut bob, merge));
+    /// assert!(!ann.union_with_veto(&mut cat, merge));
+    ///
+    /// assert!(!ann.equiv(&cat));
+    /// assert_eq!(cat.clone_data(), ["Cat"]);
+    /// ```
+    pub fn union_with_veto<F>(&mut self, other: &mut Self, f: F) -> bool
+            where F: FnOnce(Data, Data) -> Result<Data, (Data, Data)> {
+
         let (a, rank_a) = self.find_with_rank();
         let (b, rank_b) = other.find_with_rank();
 
@@ -334,16 +480,95 @@ impl<Data> UnionFindNode<Data> {
             return false;
         }
 
+        let linked = if rank_a > rank_b {
+            b.set_parent_with(&a, false, |b_data, a_data| {
+                f(a_data, b_data).map_err(|(a_data, b_data)| (b_data, a_data))
+            })
+        } else {
+            a.set_parent_with(&b, rank_a == rank_b, f)
+        };
+
+        linked.unwrap_or_else(|error| panic!("union_with: {}", error))
+    }
+
+    /// Unions two sets, combining their data with a closure that may
+    /// refuse.
+    ///
+    /// The closure `f` is passed references to `self`’s data and
+    /// `other`’s data (in that order). If it returns `Ok`, the sets are
+    /// joined with the returned data, and the old data is dropped. If it
+    /// returns `Err`, the error is passed along and neither set changes.
+    ///
+    /// Rather than panicking if either set is in use, as when called from
+    /// within a closure operating on it, this returns
+    /// `Err(Error::AlreadyBorrowed.into())`, again leaving both sets
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::{Error, UnionFindNode};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum UnifyError {
+    ///     Mismatch(&'static str, &'static str),
+    ///     Borrowed,
+    /// }
+    ///
+    /// impl From<Error> for UnifyError {
+    ///     fn from(_: Error) -> Self {
+    ///         UnifyError::Borrowed
+    ///     }
+    /// }
+    ///
+    /// fn unify(a: &Option<&'static str>, b: &Option<&'static str>)
+    ///          -> Result<Option<&'static str>, UnifyError> {
+    ///     match (*a, *b) {
+    ///         (Some(x), Some(y)) if x != y => Err(UnifyError::Mismatch(x, y)),
+    ///         _ => Ok(a.or(*b)),
+    ///     }
+    /// }
+    ///
+    /// let mut var = UnionFindNode::new(None);
+    /// let mut int = UnionFindNode::new(Some("int"));
+    /// let mut bool = UnionFindNode::new(Some("bool"));
+    ///
+    /// assert_eq!(var.try_union_with(&mut int, unify), Ok(true));
+    /// assert_eq!(var.try_union_with(&mut bool, unify),
+    ///            Err(UnifyError::Mismatch("int", "bool")));
+    ///
+    /// assert!(!var.equiv(&bool));
+    /// assert_eq!(var.clone_data(), Some("int"));
+    /// assert_eq!(bool.clone_data(), Some("bool"));
+    /// ```
+    pub fn try_union_with<E, F>(&mut self, other: &mut Self, f: F) -> Result<bool, E>
+            where E: From<Error>,
+                  F: FnOnce(&Data, &Data) -> Result<Data, E> {
+
+        let (a, rank_a) = self.try_find_with_rank()?;
+        let (b, rank_b) = other.try_find_with_rank()?;
+
+        if a == b {
+            return Ok(false);
+        }
+
         if rank_a > rank_b {
-            b.set_parent_with(&a, |b_data, a_data| f(a_data, b_data))
-        } else if rank_b > rank_a {
-            a.set_parent_with(&b, f)
+            b.try_set_parent_with(&a, false, |b_data, a_data| f(a_data, b_data))?;
         } else {
-            b.increment_rank();
-            a.set_parent_with(&b, f)
+            a.try_set_parent_with(&b, rank_a == rank_b, f)?;
         }
 
-        true
+        Ok(true)
+    }
+
+    /// Unions two sets, combining their data with
+    /// [`Merge::merge`](trait.Merge.html).
+    ///
+    /// This is `union_with(other, Data::merge)`, so `self`’s data is
+    /// merged with `other`’s.
+    pub fn union_merged(&mut self, other: &mut Self) -> bool
+            where Data: Merge {
+        self.union_with(other, Data::merge)
     }
 
     /// Unions two sets.
@@ -369,42 +594,453 @@ impl<Data> UnionFindNode<Data> {
 
     // Can we do find iteratively?
 
+    /// The number of nodes in the set that are still alive, which is the
+    /// number of nodes that [`members`](#method.members) returns.
+    ///
+    /// This takes time linear in the number of nodes joined into the set,
+    /// including those since dropped whose references have not yet been
+    /// pruned.
+    pub fn size(&self) -> usize {
+        match *self.find().0.borrow() {
+            Root { ref members, .. } => {
+                members.iter().filter(|member| member.strong_count() > 0).count()
+            }
+            _ => panic!("size: non-root"),
+        }
+    }
+
+    /// Returns all the nodes in the set that are still alive, including
+    /// `self`.
+    ///
+    /// The representative keeps a weak reference to each member, so
+    /// this does not prevent nodes from being dropped. Members are
+    /// returned in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// let mut a = UnionFindNode::new('a');
+    /// let mut b = UnionFindNode::new('b');
+    /// a.union(&mut b);
+    ///
+    /// let members = a.members();
+    /// assert_eq!(members.len(), 2);
+    /// assert!(members.contains(&a) && members.contains(&b));
+    /// assert_eq!(b.size(), 2);
+    /// ```
+    pub fn members(&self) -> Vec<Self> {
+        match *self.find().0.borrow_mut() {
+            Root { ref mut members, .. } => {
+                members.retain(|member| member.strong_count() > 0);
+                members.iter()
+                    .filter_map(|member| member.upgrade().map(UnionFindNode))
+                    .collect()
+            }
+            _ => panic!("members: non-root"),
+        }
+    }
+
+    /// Clones the sets of the given nodes into an independent forest.
+    ///
+    /// Unlike `clone`, which copies a handle to the same node, this copies
+    /// the nodes themselves: every node in each given node’s set, the
+    /// links between them, and each set’s data. Returns a map from each
+    /// original node to its copy, so unions in either forest do not
+    /// affect the other.
+    ///
+    /// The data is copied with `Clone`, so any nodes that it contains
+    /// still refer to the original forest. Use the returned map to
+    /// redirect them if necessary.
+    ///
+    /// This needs Cargo feature `"std"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// let mut a = UnionFindNode::new(1);
+    /// let mut b = UnionFindNode::new(2);
+    /// let mut c = UnionFindNode::new(3);
+    /// a.union_with(&mut b, |x, y| x + y);
+    ///
+    /// let copies = UnionFindNode::deep_clone(&[a.clone(), c.clone()]);
+    /// let mut a2 = copies[&a].clone();
+    /// let mut c2 = copies[&c].clone();
+    ///
+    /// assert!(a2.equiv(&copies[&b]));
+    /// assert_eq!(a2.clone_data(), 3);
+    ///
+    /// a2.union_with(&mut c2, |x, y| x + y);
+    /// assert!(!a.equiv(&c));
+    /// assert_eq!(a.clone_data(), 3);
+    /// ```
+    // Nodes hash by address, which interior mutability does not change.
+    #[allow(clippy::mutable_key_type)]
+    #[cfg(feature = "std")]
+    pub fn deep_clone<'a, I>(nodes: I) -> HashMap<Self, Self>
+            where I: IntoIterator<Item = &'a Self>,
+                  Data: Clone + 'a {
+
+        let mut copies = HashMap::new();
+
+        for node in nodes {
+            if copies.contains_key(node) { continue; }
+
+            let members = node.members();
+            for member in &members {
+                copies.insert(member.clone(),
+                              UnionFindNode(Rc::new(RefCell::new(Dummy))));
+            }
+
+            let mut new_members = Vec::with_capacity(members.len());
+            let mut new_root = None;
+
+            for member in &members {
+                let copy = &copies[member];
+                let contents = match *member.0.borrow() {
+                    Root { ref data, rank, .. } => {
+                        new_root = Some(copy.clone());
+                        Root {
+                            data: data.clone(),
+                            rank,
+                            members: Vec::new(),
+                        }
+                    }
+                    Link(ref parent) => Link(copies[parent].clone()),
+                    Dummy => panic!("deep_clone: got dummy"),
+                };
+                *copy.0.borrow_mut() = contents;
+                new_members.push(Rc::downgrade(&copy.0));
+            }
+
+            if let Some(new_root) = new_root {
+                if let Root { ref mut members, .. } = *new_root.0.borrow_mut() {
+                    *members = new_members;
+                }
+            }
+        }
+
+        copies
+    }
+
+    /// Converts nodes into an array-based union-find and a table of set
+    /// data.
+    ///
+    /// The `i`th node yielded by `nodes` becomes element `i` of the
+    /// returned `UnionFind`, and two elements are in the same set exactly
+    /// when their nodes are. Each set’s data is cloned once into the
+    /// returned vector, indexed by set number as in
+    /// [`Partition`](struct.Partition.html): sets are numbered
+    /// consecutively from 0 in the order of their first elements. Once
+    /// the sets are built, this flat form is smaller and faster to query.
+    ///
+    /// This needs Cargo feature `"std"`.
+    ///
+    /// # Panics
+    ///
+    /// If there are more nodes than fit in a `u32`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// let mut nodes: Vec<_> = "abcd".chars().map(UnionFindNode::new).collect();
+    /// let mut c = nodes[2].clone();
+    /// nodes[0].union_with(&mut c, |x, _| x);
+    ///
+    /// let (uf, data) = UnionFindNode::flatten(&nodes);
+    /// let partition = uf.partition();
+    ///
+    /// assert!(uf.equiv(0, 2));
+    /// assert_eq!(data, vec!['a', 'b', 'd']);
+    /// assert_eq!(data[partition.label(3)], 'd');
+    /// ```
+    #[allow(clippy::mutable_key_type)]
+    #[cfg(feature = "std")]
+    pub fn flatten<'a, I>(nodes: I) -> (UnionFind<u32>, Vec<Data>)
+            where I: IntoIterator<Item = &'a Self>,
+                  Data: Clone + 'a {
+
+        let mut union_find = UnionFind::new(0);
+        let mut data = Vec::new();
+        // Each root seen so far, with its first element.
+        let mut firsts = HashMap::new();
+
+        for node in nodes {
+            let element = union_find.alloc();
+            let root = node.find();
+
+            match firsts.get(&root) {
+                Some(&first) => { union_find.union(first, element); }
+                None => {
+                    data.push(root.clone_data());
+                    firsts.insert(root, element);
+                }
+            }
+        }
+
+        (union_find, data)
+    }
+
+    /// Dissolves the set, making each of its nodes a singleton with
+    /// default data.
+    ///
+    /// The set’s data is dropped, as are the links between its nodes.
+    /// When `Data` refers to other nodes, reference cycles can keep a
+    /// forest of nodes alive after the last outside reference is gone;
+    /// dissolving every set in such a forest before dropping it breaks
+    /// the cycles, so nothing leaks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// #[derive(Default)]
+    /// struct Refs(Vec<UnionFindNode<Refs>>);
+    ///
+    /// let mut a = UnionFindNode::new(Refs::default());
+    /// let mut b = UnionFindNode::new(Refs::default());
+    /// a.union(&mut b);
+    ///
+    /// // The set’s data refers to one of its own nodes.
+    /// a.replace_data(Refs(vec![b.clone()]));
+    ///
+    /// a.dissolve();
+    /// assert!(!a.equiv(&b));
+    /// assert!(b.with_data(|refs| refs.0.is_empty()));
+    /// ```
+    pub fn dissolve(&self)
+            where Data: Default {
+
+        let members = self.members();
+        let mut old_contents = Vec::with_capacity(members.len());
+
+        for member in &members {
+            let fresh = Root {
+                data:    Data::default(),
+                rank:    0,
+                members: vec![Rc::downgrade(&member.0)],
+            };
+            old_contents.push(mem::replace(&mut *member.0.borrow_mut(), fresh));
+        }
+
+        // Dropping the old data may drop other nodes, so it must wait
+        // until no node is borrowed.
+        drop(old_contents);
+    }
+
     /// Finds a node representing the set of a given node.
     ///
     /// For two nodes in the same set, `find` returns the same node.
     pub fn find(&self) -> Self {
-        match *self.0.borrow_mut() {
-            Root { .. } => self.clone(),
-            Link(ref mut parent) => {
-                let root = parent.find();
-                *parent = root.clone();
-                root
-            }
-            Dummy => panic!("find: got dummy"),
-        }
+        self.find_with_rank().0
     }
 
     fn find_with_rank(&self) -> (Self, u8) {
-        match *self.0.borrow_mut() {
-            Root { rank, .. } => (self.clone(), rank),
-            Link(ref mut parent) => {
-                let (root, rank) = parent.find_with_rank();
+        self.try_find_with_rank()
+            .unwrap_or_else(|error| panic!("find: {}", error))
+    }
+
+    fn try_find_with_rank(&self) -> Result<(Self, u8), Error> {
+        // A root is only borrowed immutably, so that it can be found while
+        // its data is borrowed.
+        let parent = match *self.0.try_borrow().map_err(|_| Error::AlreadyBorrowed)? {
+            Root { rank, .. } => return Ok((self.clone(), rank)),
+            Link(ref parent) => parent.clone(),
+            Dummy => panic!("find: got dummy"),
+        };
+
+        let (root, rank) = parent.try_find_with_rank()?;
+        // Compression is an optimization, so skip it if `self` is in use.
+        if let Ok(mut node) = self.0.try_borrow_mut() {
+            if let Link(ref mut parent) = *node {
                 *parent = root.clone();
-                (root, rank)
             }
-            Dummy => panic!("find: got dummy"),
+        }
+        Ok((root, rank))
+    }
+
+    /// Is this node the root of its set’s tree?
+    ///
+    /// The root is the node that [`find`](#method.find) returns.
+    pub fn is_root(&self) -> bool {
+        matches!(*self.0.borrow(), Root { .. })
+    }
+
+    /// The rank of this node, if it is a root.
+    ///
+    /// A root’s rank bounds the height of its tree. Nodes that are not
+    /// roots no longer track their rank, so this returns `None` for them.
+    pub fn rank(&self) -> Option<u8> {
+        match *self.0.borrow() {
+            Root { rank, .. } => Some(rank),
+            _ => None,
         }
     }
 
+    /// An identifier for the set, taken from the address of its root.
+    ///
+    /// Two nodes have the same identifier exactly when they are in the
+    /// same set. The identifier remains stable until the set is joined
+    /// with another, after which the set takes the identifier of the new
+    /// root, and until the set is dropped, after which the identifier may
+    /// be reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// let mut a = UnionFindNode::new(());
+    /// let mut b = UnionFindNode::new(());
+    /// assert_ne!(a.set_ptr_id(), b.set_ptr_id());
+    ///
+    /// a.union(&mut b);
+    /// assert_eq!(a.set_ptr_id(), b.set_ptr_id());
+    /// assert!(a.is_root() != b.is_root());
+    /// ```
+    pub fn set_ptr_id(&self) -> usize {
+        self.find().addr()
+    }
+
     /// Are the two nodes representatives of the same set?
     pub fn equiv(&self, other: &Self) -> bool {
         self.find() == other.find()
     }
 
+    /// Does this node’s set occur in `term`?
+    ///
+    /// This is the occurs check of unification. Sets refer to other sets
+    /// through their data, and `children` extracts the nodes that a set’s
+    /// data refers to. Then `self` occurs in `term` if they are in the
+    /// same set, or if `self` occurs in any child of `term`. Linking
+    /// `self` to a term that it occurs in would make the data cyclic,
+    /// which leaks unless the sets are [dissolved](#method.dissolve).
+    ///
+    /// Each set is visited at most once, so this terminates even if the
+    /// data is cyclic already.
+    ///
+    /// # Panics
+    ///
+    /// If the data of a visited set is borrowed mutably, as from within
+    /// [`with_data`](#method.with_data).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// // Each set’s data lists the sets that it refers to.
+    /// struct Refs(Vec<UnionFindNode<Refs>>);
+    /// let children = |refs: &Refs| refs.0.clone();
+    ///
+    /// let a = UnionFindNode::new(Refs(vec![]));
+    /// let b = UnionFindNode::new(Refs(vec![]));
+    /// let f_a = UnionFindNode::new(Refs(vec![a.clone()]));
+    ///
+    /// assert!(a.occurs_in(&f_a, children));
+    /// assert!(!b.occurs_in(&f_a, children));
+    /// assert!(!f_a.occurs_in(&a, children));
+    /// ```
+    // Nodes order by address, which interior mutability does not change.
+    #[allow(clippy::mutable_key_type)]
+    pub fn occurs_in<F, I>(&self, term: &Self, mut children: F) -> bool
+            where F: FnMut(&Data) -> I,
+                  I: IntoIterator<Item = Self> {
+
+        let target = self.find();
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![term.clone()];
+
+        while let Some(node) = stack.pop() {
+            let root = node.find();
+            if root == target { return true; }
+            if !visited.insert(root.clone()) { continue; }
+            stack.extend(children(&root.data()));
+        }
+
+        false
+    }
+
+    /// Is the data reachable from this node’s set cyclic?
+    ///
+    /// As for [`occurs_in`](#method.occurs_in), `children` extracts the
+    /// nodes that a set’s data refers to. This returns whether following
+    /// children from `self` ever returns to a set already on the path,
+    /// which means that the sets form a reference cycle.
+    ///
+    /// # Panics
+    ///
+    /// If the data of a visited set is borrowed mutably, as from within
+    /// [`with_data`](#method.with_data).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// #[derive(Default)]
+    /// struct Refs(Vec<UnionFindNode<Refs>>);
+    /// let children = |refs: &Refs| refs.0.clone();
+    ///
+    /// let a = UnionFindNode::new(Refs(vec![]));
+    /// let b = UnionFindNode::new(Refs(vec![a.clone(), a.clone()]));
+    /// assert!(!b.has_cycle(children));
+    ///
+    /// a.replace_data(Refs(vec![b.clone()]));
+    /// assert!(b.has_cycle(children));
+    ///
+    /// // Break the cycle so that it does not leak.
+    /// a.dissolve();
+    /// ```
+    // Nodes order by address, which interior mutability does not change.
+    #[allow(clippy::mutable_key_type)]
+    pub fn has_cycle<F, I>(&self, mut children: F) -> bool
+            where F: FnMut(&Data) -> I,
+                  I: IntoIterator<Item = Self> {
+
+        // Maps each set visited to whether it is finished, as opposed to
+        // on the current path. Each path entry holds a set and the
+        // children that remain to visit.
+        let mut finished = BTreeMap::new();
+        let mut path = Vec::new();
+        let mut next = Some(self.clone());
+
+        loop {
+            if let Some(node) = next.take() {
+                let root = node.find();
+                match finished.get(&root) {
+                    Some(&false) => return true,
+                    Some(&true) => (),
+                    None => {
+                        let kids: Vec<_> =
+                            children(&root.data()).into_iter().collect();
+                        finished.insert(root.clone(), false);
+                        path.push((root, kids));
+                    }
+                }
+            }
+
+            next = match path.last_mut() {
+                Some(&mut (_, ref mut kids)) => kids.pop(),
+                None => return false,
+            };
+
+            if next.is_none() {
+                let (root, _) = path.pop().unwrap();
+                finished.insert(root, true);
+            }
+        }
+    }
+
     /// Replaces the data associated with the set.
     pub fn replace_data(&self, new: Data) -> Data {
-        use std::mem::replace;
-        self.with_data(|data| replace(data, new))
+        self.with_data(|data| mem::replace(data, new))
     }
 
     /// Returns a clone of the data associated with the set.
@@ -413,14 +1049,125 @@ impl<Data> UnionFindNode<Data> {
         self.with_data(|data| data.clone())
     }
 
+    /// Allows modifying the data associated with a set, unless it is
+    /// already borrowed.
+    ///
+    /// Like [`with_data`](#method.with_data), but returns
+    /// `Err(Error::AlreadyBorrowed)` instead of panicking if the set is
+    /// in use, as when called from within a closure operating on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::{Error, UnionFindNode};
+    ///
+    /// let node = UnionFindNode::new(0);
+    /// let other = node.clone();
+    ///
+    /// let result = node.with_data(|_| other.try_with_data(|n| *n += 1));
+    /// assert_eq!(result, Err(Error::AlreadyBorrowed));
+    /// assert_eq!(node.try_with_data(|n| *n), Ok(0));
+    /// ```
+    pub fn try_with_data<R, F>(&self, f: F) -> Result<R, Error>
+            where F: FnOnce(&mut Data) -> R {
+
+        let (root, _) = self.try_find_with_rank()?;
+        let mut node = root.0.try_borrow_mut().map_err(|_| Error::AlreadyBorrowed)?;
+        match *node {
+            Root { ref mut data, .. } => Ok(f(data)),
+            _ => panic!("try_with_data: non-root"),
+        }
+    }
+
     /// Allows modifying the data associated with a set.
     pub fn with_data<R, F>(&self, f: F) -> R
             where F: FnOnce(&mut Data) -> R {
         self.find().root_with_data(f)
     }
 
+    /// Borrows the data associated with the set.
+    ///
+    /// Like [`with_data`](#method.with_data), but returns a guard rather
+    /// than taking a closure. The guard holds on to the root of the set’s
+    /// tree, and leaves the tree as it is.
+    ///
+    /// While the guard is alive, the set cannot be joined with another or
+    /// have its data borrowed mutably; doing either panics. Other shared
+    /// guards, on any node of the set, may be taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// let mut a = UnionFindNode::new(Some(3));
+    /// let mut b = UnionFindNode::new(None);
+    /// a.union_with(&mut b, |x, y| x.or(y));
+    ///
+    /// match *b.data() {
+    ///     Some(n) => assert_eq!(n, 3),
+    ///     None    => panic!(),
+    /// }
+    ///
+    /// // Guards may be held on several nodes of the set at once.
+    /// let (x, y) = (a.data(), b.data());
+    /// assert_eq!(*x, *y);
+    /// ```
+    pub fn data(&self) -> DataRef<'_, Data> {
+        let root = self.find();
+        // SAFETY: The guard keeps `root`.
+        let cell = unsafe { root.cell() };
+        let data = Ref::map(cell.borrow(), |node| match *node {
+            Root { ref data, .. } => data,
+            _ => panic!("data: non-root"),
+        });
+
+        DataRef { data, _root: root }
+    }
+
+    /// Mutably borrows the data associated with the set.
+    ///
+    /// Like [`with_data`](#method.with_data), but returns a guard rather
+    /// than taking a closure. The guard holds on to the root of the set’s
+    /// tree, and leaves the tree as it is.
+    ///
+    /// While the guard is alive, any other operation on the set panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use disjoint_sets::UnionFindNode;
+    ///
+    /// let mut a = UnionFindNode::new(vec![1]);
+    /// let mut b = UnionFindNode::new(vec![2]);
+    /// a.union_with(&mut b, |mut x, y| { x.extend(y); x });
+    ///
+    /// b.data_mut().push(3);
+    /// assert_eq!(*a.data(), [1, 2, 3]);
+    /// ```
+    pub fn data_mut(&self) -> DataRefMut<'_, Data> {
+        let root = self.find();
+        // SAFETY: The guard keeps `root`.
+        let cell = unsafe { root.cell() };
+        let data = RefMut::map(cell.borrow_mut(), |node| match *node {
+            Root { ref mut data, .. } => data,
+            _ => panic!("data_mut: non-root"),
+        });
+
+        DataRefMut { data, _root: root }
+    }
+
     // HELPERS
 
+    // The node’s cell, borrowed for as long as the caller likes.
+    //
+    // PRECONDITION: The caller keeps a handle to the node for as long as
+    // it uses the result, since the cell lives only as long as some
+    // handle does.
+    unsafe fn cell<'a>(&self) -> &'a RefCell<NodeImpl<Data>> {
+        &*Rc::as_ptr(&self.0)
+    }
+
     fn root_with_data<R, F>(&self, f: F) -> R
             where F: FnOnce(&mut Data) -> R {
 
@@ -433,15 +1180,22 @@ impl<Data> UnionFindNode<Data> {
     fn increment_rank(&self) {
         match *self.0.borrow_mut() {
             Root { ref mut rank, .. } => {
-                *rank += 1;
+                *rank = rank.saturating_add(1);
             }
             _ => panic!("increment_rank: non-root")
         }
     }
 
     fn set_parent(&self, new_parent: Self) -> Data {
-        match mem::replace(&mut *self.0.borrow_mut(), Link(new_parent)) {
-            Root { data, .. } => data,
+        let contents = mem::replace(&mut *self.0.borrow_mut(),
+                                    Link(new_parent.clone()));
+
+        match (contents, &mut *new_parent.0.borrow_mut()) {
+            (Root { data, members: members_self, .. },
+             &mut Root { ref mut members, .. }) => {
+                merge_members(members, members_self);
+                data
+            }
             _ => panic!("set_parent: non-root"),
         }
     }
@@ -449,27 +1203,112 @@ impl<Data> UnionFindNode<Data> {
     // PRECONDITION:
     //  - self != parent
     //  - self and parent are both root nodes
-    fn set_parent_with<F>(&self, parent: &Self, f: F)
-            where F: FnOnce(Data, Data) -> Data {
-        let mut guard_self = self.0.borrow_mut();
-        let mut guard_parent = parent.0.borrow_mut();
+    //
+    // Returns whether `f` accepted the union.
+    fn set_parent_with<F>(&self, parent: &Self, increment_rank: bool, f: F)
+            -> Result<bool, Error>
+            where F: FnOnce(Data, Data) -> Result<Data, (Data, Data)> {
 
-        let contents_self = mem::replace(&mut *guard_self,
-                                         Link(parent.clone()));
+        let mut guard_self = self.0.try_borrow_mut()
+            .map_err(|_| Error::AlreadyBorrowed)?;
+        let mut guard_parent = parent.0.try_borrow_mut()
+            .map_err(|_| Error::AlreadyBorrowed)?;
+
+        let contents_self = mem::replace(&mut *guard_self, Dummy);
         let contents_parent = mem::replace(&mut *guard_parent, Dummy);
 
         match (contents_self, contents_parent) {
-            (Root { data: data_self, .. },
-             Root { data: data_parent, rank }) => {
-                let new_data = f(data_self, data_parent);
-                mem::replace(&mut *guard_parent, Root {
-                    data: new_data,
-                    rank: rank,
-                });
+            (Root { data: data_self, rank: rank_self, members: members_self },
+             Root { data: data_parent, rank, mut members }) => {
+                match f(data_self, data_parent) {
+                    Ok(new_data) => {
+                        merge_members(&mut members, members_self);
+                        *guard_self = Link(parent.clone());
+                        *guard_parent = Root {
+                            data: new_data,
+                            rank: if increment_rank { rank.saturating_add(1) } else { rank },
+                            members,
+                        };
+                        Ok(true)
+                    }
+                    Err((data_self, data_parent)) => {
+                        *guard_self = Root {
+                            data:    data_self,
+                            rank:    rank_self,
+                            members: members_self,
+                        };
+                        *guard_parent = Root {
+                            data: data_parent,
+                            rank,
+                            members,
+                        };
+                        Ok(false)
+                    }
+                }
             }
             _ => panic!("set_parent_with: non-root"),
         }
     }
+
+    // PRECONDITION:
+    //  - self != parent
+    //  - self and parent are both root nodes
+    fn try_set_parent_with<E, F>(&self, parent: &Self, increment_rank: bool, f: F)
+            -> Result<(), E>
+            where E: From<Error>,
+                  F: FnOnce(&Data, &Data) -> Result<Data, E> {
+
+        let mut guard_self = self.0.try_borrow_mut()
+            .map_err(|_| Error::AlreadyBorrowed)?;
+        let mut guard_parent = parent.0.try_borrow_mut()
+            .map_err(|_| Error::AlreadyBorrowed)?;
+
+        // Nothing changes until `f` succeeds.
+        let new_data = match (&*guard_self, &*guard_parent) {
+            (Root { data: data_self, .. }, Root { data: data_parent, .. }) =>
+                f(data_self, data_parent)?,
+            _ => panic!("try_set_parent_with: non-root"),
+        };
+
+        let contents_self = mem::replace(&mut *guard_self, Link(parent.clone()));
+
+        match (contents_self, &mut *guard_parent) {
+            (Root { data: old_data_self, members: members_self, .. },
+             &mut Root { ref mut data, ref mut rank, ref mut members }) => {
+                let old_data_parent = mem::replace(data, new_data);
+                if increment_rank { *rank = rank.saturating_add(1); }
+                merge_members(members, members_self);
+
+                // The old data may own other nodes, so drop it only once
+                // nothing is borrowed.
+                drop(guard_self);
+                drop(guard_parent);
+                drop((old_data_self, old_data_parent));
+                Ok(())
+            }
+            _ => panic!("try_set_parent_with: non-root"),
+        }
+    }
+}
+
+// Moves the smaller member list into the larger, dropping the references
+// to nodes that no longer exist.
+//
+// The larger list is pruned only when it is full. Reserving room for as
+// many again afterward means that at least half its capacity is pushed
+// between prunings, which pays for them.
+fn merge_members<T>(members: &mut Vec<Weak<T>>, mut other: Vec<Weak<T>>) {
+    if members.len() < other.len() {
+        mem::swap(members, &mut other);
+    }
+
+    if members.len() + other.len() > members.capacity() {
+        members.retain(|member| member.strong_count() > 0);
+        let len = members.len();
+        members.reserve(len);
+    }
+
+    members.extend(other.into_iter().filter(|member| member.strong_count() > 0));
 }
 
 #[cfg(test)]
@@ -517,6 +1356,301 @@ mod tests {
         assert!(uf5.equiv(&uf7));
     }
 
+    #[test]
+    fn members() {
+        let mut nodes: Vec<_> = (0 .. 8).map(UnionFindNode::new).collect();
+        for i in 1 .. 4 {
+            let (left, right) = nodes.split_at_mut(i);
+            left[i - 1].union(&mut right[0]);
+        }
+        let mut seventh = nodes[7].clone();
+        nodes[6].union_with(&mut seventh, |a, b| a + b);
+
+        let mut members: Vec<_> = nodes[2].members().iter()
+            .map(|node| nodes.iter().position(|other| other == node).unwrap())
+            .collect();
+        members.sort();
+        assert_eq!(members, vec![0, 1, 2, 3]);
+        assert_eq!(nodes[0].size(), 4);
+        assert_eq!(nodes[7].size(), 2);
+        assert_eq!(nodes[4].members(), vec![nodes[4].clone()]);
+
+        // A dropped node no longer counts.
+        let mut extra = UnionFindNode::new(0);
+        extra.union(&mut nodes[6]);
+        assert_eq!(nodes[6].size(), 3);
+        drop(extra);
+        assert_eq!(nodes[6].size(), 2);
+        assert_eq!(nodes[6].members().len(), 2);
+    }
+
+    #[test]
+    fn dead_members_pruned() {
+        let mut root = UnionFindNode::new(());
+        for _ in 0 .. 1000 {
+            let mut node = UnionFindNode::new(());
+            root.union(&mut node);
+        }
+
+        // Only `root` is alive, and the set’s root, if that is another
+        // node, which `root` links to.
+        let alive = if root.is_root() { 1 } else { 2 };
+        assert_eq!(root.size(), alive);
+        assert_eq!(root.members().len(), alive);
+        match *root.find().0.borrow() {
+            Root { ref members, .. } => assert!(members.capacity() <= 8),
+            _ => panic!("dead_members_pruned: non-root"),
+        }
+    }
+
+    #[test]
+    fn introspection() {
+        let mut nodes: Vec<_> = (0 .. 4).map(UnionFindNode::new).collect();
+        assert!(nodes.iter().all(|node| node.is_root() && node.rank() == Some(0)));
+
+        let mut first = nodes[0].clone();
+        first.union(&mut nodes[1]);
+        nodes[2].clone().union(&mut nodes[3]);
+        first.union(&mut nodes[3]);
+
+        let roots: Vec<_> = nodes.iter().filter(|node| node.is_root()).collect();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].rank(), Some(2));
+        assert_eq!(roots[0].set_ptr_id(), nodes[0].set_ptr_id());
+        assert!(nodes.iter().filter(|node| !node.is_root()).all(|node| node.rank().is_none()));
+    }
+
+    #[test]
+    fn occurs_and_cycles() {
+        type Node = UnionFindNode<Vec<usize>>;
+        let nodes: Vec<Node> = (0 .. 5).map(|_| UnionFindNode::new(vec![])).collect();
+        let children = |kids: &Vec<usize>| kids.iter().map(|&i| nodes[i].clone()).collect::<Vec<_>>();
+
+        // A diamond: 0 -> 1, 2; 1 -> 3; 2 -> 3.
+        nodes[0].replace_data(vec![1, 2]);
+        nodes[1].replace_data(vec![3]);
+        nodes[2].replace_data(vec![3]);
+
+        assert!(nodes[3].occurs_in(&nodes[0], children));
+        assert!(nodes[0].occurs_in(&nodes[0], children));
+        assert!(!nodes[0].occurs_in(&nodes[3], children));
+        assert!(!nodes[4].occurs_in(&nodes[0], children));
+        assert!(!nodes[0].has_cycle(children));
+
+        nodes[3].replace_data(vec![4]);
+        nodes[4].replace_data(vec![2]);
+        assert!(nodes[0].has_cycle(children));
+        assert!(!nodes[1].occurs_in(&nodes[4], children));
+    }
+
+    #[test]
+    fn flatten() {
+        let mut nodes: Vec<_> = (0 .. 6).map(UnionFindNode::new).collect();
+        let (mut n1, mut n3) = (nodes[1].clone(), nodes[3].clone());
+        nodes[4].union_with(&mut n1, |x, y| x + y);
+        nodes[5].union_with(&mut n3, |x, y| x + y);
+        nodes[1].union_with(&mut n3, |x, y| x * y);
+
+        let subset = [nodes[5].clone(), nodes[2].clone(), nodes[4].clone()];
+        let (uf, data) = UnionFindNode::flatten(&subset);
+
+        assert_eq!(uf.len(), 3);
+        assert!(uf.equiv(0, 2));
+        assert!(!uf.equiv(0, 1));
+        assert_eq!(data, vec![40, 2]);
+        assert_eq!(UnionFindNode::<()>::flatten(&[]).1, vec![]);
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn deep_clone() {
+        let mut nodes: Vec<_> = (0 .. 6).map(|i| UnionFindNode::new(vec![i])).collect();
+        for i in 0 .. 3 {
+            let mut next = nodes[i + 1].clone();
+            nodes[i].union_with(&mut next, |mut x, y| { x.extend(y); x });
+        }
+
+        let copies = UnionFindNode::deep_clone(&nodes[2 .. 5]);
+        assert_eq!(copies.len(), 5);
+
+        for (old, new) in &copies {
+            assert!(old != new);
+            assert_eq!(old.is_root(), new.is_root());
+            assert_eq!(old.rank(), new.rank());
+            assert_eq!(old.size(), new.size());
+            assert_eq!(old.clone_data(), new.clone_data());
+            assert_eq!(copies[&old.find()], new.find());
+        }
+
+        let mut copy = copies[&nodes[0]].clone();
+        copy.union(&mut copies[&nodes[4]].clone());
+        copy.replace_data(vec![]);
+        assert_eq!(copy.members().len(), 5);
+        assert!(!nodes[0].equiv(&nodes[4]));
+        assert_eq!(nodes[0].clone_data(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn data_guards() {
+        let mut a = UnionFindNode::new(String::from("a"));
+        let mut b = UnionFindNode::new(String::from("b"));
+        a.union_with(&mut b, |x, y| x + &y);
+
+        a.data_mut().push('!');
+        assert_eq!(b.data().as_str(), "ab!");
+        assert!(b.is_root());
+        assert_eq!(b.size(), 2);
+        assert_eq!(a.members().len(), 2);
+
+        let (x, y) = (a.data(), a.data());
+        assert_eq!((x.as_str(), y.as_str()), ("ab!", "ab!"));
+        assert!(b.is_root());
+    }
+
+    #[test]
+    fn data_guards_on_two_nodes() {
+        let mut a = UnionFindNode::new(1);
+        let mut b = UnionFindNode::new(2);
+        a.union(&mut b);
+
+        let x = a.data();
+        let y = b.data();
+        assert_eq!(*x, *y);
+        assert!(a.equiv(&b));
+    }
+
+    #[test]
+    fn data_guards_leave_the_tree() {
+        let mut nodes: Vec<_> = (0 .. 8).map(UnionFindNode::new).collect();
+        for i in 1 .. nodes.len() {
+            let (left, right) = nodes.split_at_mut(i);
+            left[0].union(&mut right[0]);
+        }
+
+        let root = nodes[0].find();
+        let rank = root.rank();
+        let data = root.clone_data();
+        for node in &nodes {
+            let _ = *node.data();
+            *node.data_mut() += 1;
+        }
+
+        for node in &nodes {
+            assert_eq!(node.find(), root);
+        }
+        assert_eq!(root.rank(), rank);
+        assert_eq!(root.clone_data(), data + nodes.len() as i32);
+    }
+
+    #[test]
+    fn try_union_while_borrowed() {
+        let mut a = UnionFindNode::new(1);
+        let mut b = UnionFindNode::new(2);
+        let mut c = a.clone();
+
+        let result = a.with_data(|_| c.try_union_with(&mut b, |x, y| Ok(x + y)));
+        assert_eq!(result, Err(Error::AlreadyBorrowed));
+        assert!(!a.equiv(&b));
+
+        let result = a.try_union_with(&mut b, |x, y| {
+            assert_eq!(c.try_with_data(|_| ()), Err(Error::AlreadyBorrowed));
+            Ok::<_, Error>(x + y)
+        });
+        assert_eq!(result, Ok(true));
+        assert_eq!(b.try_with_data(|n| *n), Ok(3));
+    }
+
+    #[test]
+    fn try_union_refused() {
+        #[derive(Debug, PartialEq)]
+        struct Refused;
+
+        impl From<Error> for Refused {
+            fn from(_: Error) -> Self { Refused }
+        }
+
+        let mut nodes: Vec<_> = (0 .. 4).map(|i| UnionFindNode::new(vec![i])).collect();
+        let mut first = nodes[0].clone();
+        first.union(&mut nodes[1]);
+
+        assert_eq!(first.try_union_with(&mut nodes[1], |_, _| Err(Refused)), Ok(false));
+        for node in &mut nodes[2 ..] {
+            assert_eq!(first.try_union_with(node, |_, _| Err(Refused)), Err(Refused));
+        }
+
+        assert_eq!(nodes[0].size(), 2);
+        assert_eq!(nodes[2].size(), 1);
+        assert_eq!(nodes[3].clone_data(), vec![3]);
+        assert_eq!(nodes[3].members(), vec![nodes[3].clone()]);
+
+        let result = nodes[2].try_union_with(&mut first, |x, y| {
+            Ok::<_, Refused>(x.iter().chain(y).cloned().collect())
+        });
+        assert_eq!(result, Ok(true));
+        assert_eq!(first.size(), 3);
+        assert_eq!(first.clone_data().len(), 2);
+    }
+
+    #[test]
+    fn union_with_veto() {
+        let mut nodes: Vec<_> = (0 .. 4).map(|i| UnionFindNode::new(1 << i)).collect();
+        let mut first = nodes[0].clone();
+
+        // Accept only unions whose total stays below 8.
+        for node in &mut nodes[1 ..] {
+            first.union_with_veto(node, |a, b| if a + b < 8 { Ok(a + b) } else { Err((a, b)) });
+        }
+
+        assert_eq!(first.clone_data(), 7);
+        assert_eq!(first.size(), 3);
+        assert_eq!(nodes[3].clone_data(), 8);
+        assert_eq!(nodes[3].members(), vec![nodes[3].clone()]);
+
+        // Declining preserves the order of the data even when `other`
+        // becomes the parent.
+        let mut last = nodes[3].clone();
+        assert!(!last.union_with_veto(&mut first, |a, b| {
+            assert_eq!((a, b), (8, 7));
+            Err((a, b))
+        }));
+        assert_eq!((last.clone_data(), first.clone_data()), (8, 7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn union_while_borrowed() {
+        let mut a = UnionFindNode::new(());
+        let mut b = UnionFindNode::new(());
+        let c = a.clone();
+        let _guard = c.data();
+        a.union(&mut b);
+    }
+
+    #[test]
+    fn dissolve_breaks_cycles() {
+        // Each node’s data may refer to another node, and shares an `Rc`
+        // to count how much data is alive.
+        #[derive(Default)]
+        #[allow(dead_code)]
+        struct Cyclic(Option<UnionFindNode<Cyclic>>, Rc<()>);
+
+        let alive = Rc::new(());
+        let mut a = UnionFindNode::new(Cyclic(None, alive.clone()));
+        let mut b = UnionFindNode::new(Cyclic(None, alive.clone()));
+        let c = UnionFindNode::new(Cyclic(Some(b.clone()), alive.clone()));
+        a.union_with(&mut b, |x, _| Cyclic(Some(c.clone()), x.1));
+        c.replace_data(Cyclic(Some(a.clone()), alive.clone()));
+
+        let nodes: Vec<_> = [&a, &b, &c].iter().map(|node| Rc::downgrade(&node.0)).collect();
+        assert_eq!(Rc::strong_count(&alive), 3);
+
+        a.dissolve();
+        assert_eq!(b.size(), 1);
+        drop((a, b, c));
+        assert!(nodes.iter().all(|node| node.upgrade().is_none()));
+        assert_eq!(Rc::strong_count(&alive), 1);
+    }
+
     //
     // Unification example
     //
@@ -555,6 +1689,15 @@ mod tests {
             params: Vec<Variable>,
         },
     }
+
+    // Written out rather than derived, which needs `#[default]`.
+    #[allow(clippy::derivable_impls)]
+    impl Default for Term_ {
+        fn default() -> Self {
+            Term_::Indeterminate
+        }
+    }
+
     type Variable = UnionFindNode<Term_>;
 
     use std::collections::HashMap;
@@ -563,11 +1706,11 @@ mod tests {
 
     // The environment can get Rc-cycles in it (because we don’t do an
     // occurs check, hence terms can be recursive). To avoid leaking, we
-    // need to clear the references out of it.
+    // dissolve its sets, which drops their data.
     impl Drop for Environment {
         fn drop(&mut self) {
             for (_, v) in self.0.drain() {
-                v.replace_data(Term_::Indeterminate);
+                v.dissolve();
             }
         }
     }
@@ -586,8 +1729,8 @@ mod tests {
                         .map(|term| Term::intern(term, env))
                         .collect::<Vec<_>>();
                     UnionFindNode::new(Term_::Fixed {
-                        symbol: symbol,
-                        params: params,
+                        symbol,
+                        params,
                     })
                 },
             }
@@ -651,8 +1794,7 @@ mod tests {
                         return Err(msg);
                     }
 
-                    for (u1, u2) in params1.into_iter()
-                                           .zip(params2.into_iter()) {
+                    for (u1, u2) in params1.into_iter().zip(params2) {
                         self.eqs.push((u1, u2));
                     }
 
@@ -665,7 +1807,7 @@ mod tests {
 
         fn solve(mut self) -> Result<Environment, String> {
             while let Some((v1, v2)) = self.eqs.pop() {
-                try!(self.unify(v1, v2));
+                self.unify(v1, v2)?;
             }
 
             Ok(self.env)
@@ -0,0 +1,487 @@
+use std::fmt::{self, Debug};
+
+use super::{ElementType, MemoryUsage};
+
+/// Union-find whose unions can be undone.
+///
+/// While a [`checkpoint`](#method.checkpoint) is open, every change is
+/// recorded on a trail, so that [`rollback_to`](#method.rollback_to) can
+/// restore the state at the checkpoint, undoing each change in constant
+/// time. A checkpoint stays open until it, or one taken before it, is
+/// rolled back to or [`commit`](#method.commit)ted; once none is open,
+/// the trail is discarded and changes are no longer recorded. For the
+/// common case of nested levels, as in a SAT or constraint solver,
+/// [`push_level`](#method.push_level) and [`pop_level`](#method.pop_level)
+/// keep the checkpoints on a stack.
+///
+/// Path compression would make undoing expensive, so this union-find
+/// uses union by rank alone, and [`find`](#method.find) takes time
+/// logarithmic in the size of the set.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::UnionFindUndo;
+///
+/// let mut uf = UnionFindUndo::<u32>::new(4);
+/// uf.union(0, 1);
+///
+/// let checkpoint = uf.checkpoint();
+/// uf.union(1, 2);
+/// uf.union(2, 3);
+/// assert!(uf.equiv(0, 3));
+///
+/// uf.rollback_to(checkpoint);
+/// assert!( uf.equiv(0, 1));
+/// assert!(!uf.equiv(1, 2));
+///
+/// let checkpoint = uf.checkpoint();
+/// uf.union(1, 2);
+/// uf.commit(checkpoint);
+/// assert!( uf.equiv(0, 2));
+/// ```
+#[derive(Clone, Default)]
+pub struct UnionFindUndo<Element: ElementType = usize> {
+    parents: Vec<Element>,
+    ranks:   Vec<u8>,
+    trail:   Vec<Change<Element>>,
+    levels:  Vec<Checkpoint>,
+    serial:  u64,
+    // The checkpoints neither rolled back to nor committed, oldest first.
+    open:    Vec<Checkpoint>,
+}
+// Invariants: self.open is sorted by both fields, self.levels is a
+// subsequence of it, and self.trail is empty when self.open is.
+
+/// A point in the history of a [`UnionFindUndo`](struct.UnionFindUndo.html)
+/// to which it can be rolled back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checkpoint {
+    // The length of the trail.
+    len:    usize,
+    // Counts the checkpoints ever taken, so that no two are equal.
+    serial: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Change<Element> {
+    Alloc,
+    // The child was linked beneath its new parent, whose rank was
+    // incremented if `bumped`.
+    Link { child: Element, bumped: bool },
+}
+
+impl<Element: Debug + ElementType> Debug for UnionFindUndo<Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "UnionFindUndo(")?;
+        formatter.debug_list().entries(&self.parents).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<Element: ElementType> UnionFindUndo<Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        if size > 0 {
+            Element::from_usize(size - 1)
+                .expect("UnionFindUndo::new: overflow");
+        }
+
+        UnionFindUndo {
+            parents: (0 .. size).map(|i| Element::from_usize(i).unwrap()).collect(),
+            ranks:   vec![0; size],
+            trail:   Vec::new(),
+            levels:  Vec::new(),
+            serial:  0,
+            open:    Vec::new(),
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Reports the heap memory used by the union-find, including the
+    /// trail of changes that can be undone.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage::new()
+            .array::<Element>("parents", self.parents.len(), self.parents.capacity())
+            .array::<u8>("ranks", self.ranks.len(), self.ranks.capacity())
+            .array::<Change<Element>>("trail", self.trail.len(), self.trail.capacity())
+            .array::<Checkpoint>("levels", self.levels.len(), self.levels.capacity())
+            .array::<Checkpoint>("open", self.open.len(), self.open.capacity())
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// Rolling back to a checkpoint taken before the allocation removes
+    /// the element.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = Element::from_usize(self.len())
+            .expect("UnionFindUndo::alloc: overflow");
+        self.parents.push(result);
+        self.ranks.push(0);
+        self.record(Change::Alloc);
+        result
+    }
+
+    /// Joins the sets of the two given elements.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `true`, but if they were already the same
+    /// then it returns `false`.
+    pub fn union(&mut self, a: Element, b: Element) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a == b { return false; }
+
+        let rank_a = self.ranks[a.to_usize()];
+        let rank_b = self.ranks[b.to_usize()];
+
+        let (child, parent) = if rank_a > rank_b { (b, a) } else { (a, b) };
+        let bumped = rank_a == rank_b;
+
+        self.parents[child.to_usize()] = parent;
+        if bumped {
+            self.ranks[parent.to_usize()] += 1;
+        }
+        self.record(Change::Link { child, bumped });
+
+        true
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&self, mut element: Element) -> Element {
+        loop {
+            let parent = self.parents[element.to_usize()];
+            if parent == element { return element; }
+            element = parent;
+        }
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns a checkpoint for the current state, and opens it, so that
+    /// changes are recorded until it is rolled back to or committed.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.serial += 1;
+        let checkpoint = Checkpoint {
+            len:    self.trail.len(),
+            serial: self.serial,
+        };
+        self.open.push(checkpoint);
+        checkpoint
+    }
+
+    /// Determines whether the given checkpoint is still open, so that the
+    /// union-find can be rolled back to it.
+    ///
+    /// A checkpoint is closed when it, or a checkpoint taken before it,
+    /// is rolled back to or committed.
+    pub fn is_valid(&self, checkpoint: Checkpoint) -> bool {
+        self.position(checkpoint).is_some()
+    }
+
+    /// Restores the state at the given checkpoint, undoing every union and
+    /// allocation since, and closes the checkpoint.
+    ///
+    /// Checkpoints taken after `checkpoint` are closed too, and levels
+    /// pushed after it are removed.
+    ///
+    /// # Panics
+    ///
+    /// If `checkpoint` is not open.
+    pub fn rollback_to(&mut self, checkpoint: Checkpoint) {
+        let position = self.position(checkpoint)
+            .expect("UnionFindUndo::rollback_to: invalid checkpoint");
+
+        while self.trail.len() > checkpoint.len {
+            match self.trail.pop().unwrap() {
+                Change::Alloc => {
+                    self.parents.pop();
+                    self.ranks.pop();
+                }
+                Change::Link { child, bumped } => {
+                    let parent = self.parents[child.to_usize()];
+                    self.parents[child.to_usize()] = child;
+                    if bumped {
+                        self.ranks[parent.to_usize()] -= 1;
+                    }
+                }
+            }
+        }
+
+        self.close(position);
+    }
+
+    /// Keeps every change since the given checkpoint, and closes the
+    /// checkpoint.
+    ///
+    /// Checkpoints taken after `checkpoint` are closed too, and levels
+    /// pushed after it are removed. Committing the oldest open checkpoint
+    /// discards the trail.
+    ///
+    /// # Panics
+    ///
+    /// If `checkpoint` is not open.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        let position = self.position(checkpoint)
+            .expect("UnionFindUndo::commit: invalid checkpoint");
+        self.close(position);
+    }
+
+    /// Pushes a new level, which [`pop_level`](#method.pop_level) will
+    /// roll back to.
+    pub fn push_level(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.levels.push(checkpoint);
+    }
+
+    /// Undoes every change since the most recent
+    /// [`push_level`](#method.push_level), and removes that level.
+    ///
+    /// # Panics
+    ///
+    /// If there are no levels.
+    pub fn pop_level(&mut self) {
+        let checkpoint = self.levels.pop()
+            .expect("UnionFindUndo::pop_level: no levels");
+        self.rollback_to(checkpoint);
+    }
+
+    /// The number of levels pushed and not yet popped.
+    pub fn level(&self) -> usize {
+        self.levels.len()
+    }
+
+    // Is any checkpoint open, so that changes are recorded?
+    pub(crate) fn is_recording(&self) -> bool {
+        !self.open.is_empty()
+    }
+
+    fn record(&mut self, change: Change<Element>) {
+        if self.is_recording() {
+            self.trail.push(change);
+        }
+    }
+
+    fn position(&self, checkpoint: Checkpoint) -> Option<usize> {
+        self.open.binary_search(&checkpoint).ok()
+    }
+
+    // Closes the open checkpoint at `position` and every later one,
+    // discarding the trail once none is open.
+    fn close(&mut self, position: usize) {
+        let checkpoint = self.open[position];
+        self.open.truncate(position);
+
+        while self.levels.last().map_or(false, |&level| level >= checkpoint) {
+            self.levels.pop();
+        }
+
+        if self.open.is_empty() {
+            self.trail.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback() {
+        let mut uf = UnionFindUndo::<u16>::new(6);
+        uf.union(0, 1);
+        let before = uf.clone();
+
+        let checkpoint = uf.checkpoint();
+        uf.union(2, 3);
+        uf.union(1, 3);
+        let e = uf.alloc();
+        uf.union(e, 5);
+        assert!(uf.equiv(0, 2));
+        assert!(uf.equiv(5, 6));
+
+        uf.rollback_to(checkpoint);
+        assert_eq!(uf.len(), 6);
+        assert_eq!(uf.parents, before.parents);
+        assert_eq!(uf.ranks, before.ranks);
+        assert!( uf.equiv(0, 1));
+        assert!(!uf.equiv(0, 2));
+    }
+
+    #[test]
+    fn levels() {
+        let mut uf = UnionFindUndo::<usize>::new(4);
+
+        uf.push_level();
+        uf.union(0, 1);
+        uf.push_level();
+        uf.union(2, 3);
+        uf.push_level();
+        uf.union(1, 2);
+        assert_eq!(uf.level(), 3);
+        assert!(uf.equiv(0, 3));
+
+        uf.pop_level();
+        assert!(!uf.equiv(0, 3));
+        assert!( uf.equiv(2, 3));
+
+        uf.pop_level();
+        assert!(!uf.equiv(2, 3));
+        assert!( uf.equiv(0, 1));
+
+        uf.pop_level();
+        assert!(!uf.equiv(0, 1));
+        assert_eq!(uf.level(), 0);
+    }
+
+    #[test]
+    fn trail_only_while_open() {
+        let mut uf = UnionFindUndo::<usize>::new(4);
+        uf.union(0, 1);
+        assert!(uf.trail.is_empty());
+
+        let outer = uf.checkpoint();
+        uf.union(1, 2);
+        let inner = uf.checkpoint();
+        uf.alloc();
+        uf.commit(inner);
+        assert_eq!(uf.trail.len(), 2);
+
+        uf.commit(outer);
+        assert!(uf.trail.is_empty());
+        uf.union(2, 4);
+        assert!(uf.trail.is_empty());
+        assert!(uf.equiv(0, 4));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_without_checkpoint() {
+        let mut uf = UnionFindUndo::<usize>::new(2);
+        let checkpoint = uf.checkpoint();
+        uf.commit(checkpoint);
+        uf.rollback_to(checkpoint);
+    }
+
+    #[test]
+    fn rollback_discards_later_levels() {
+        let mut uf = UnionFindUndo::<usize>::new(2);
+        let checkpoint = uf.checkpoint();
+        uf.union(0, 1);
+        uf.push_level();
+        uf.rollback_to(checkpoint);
+        assert_eq!(uf.level(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn stale_checkpoint() {
+        let mut uf = UnionFindUndo::<usize>::new(2);
+        let start = uf.checkpoint();
+        uf.union(0, 1);
+        let later = uf.checkpoint();
+        uf.rollback_to(start);
+        uf.rollback_to(later);
+    }
+
+    #[test]
+    fn replaced_checkpoint() {
+        let mut uf = UnionFindUndo::<usize>::new(4);
+        let start = uf.checkpoint();
+        uf.union(0, 1);
+        let middle = uf.checkpoint();
+        let later = uf.checkpoint();
+        assert!(uf.is_valid(later));
+
+        // `again` is taken at the same point as `later`, but does not
+        // revive it.
+        uf.rollback_to(middle);
+        assert!(!uf.is_valid(later));
+        let again = uf.checkpoint();
+        assert!(uf.is_valid(again));
+        assert!(!uf.is_valid(later));
+
+        uf.rollback_to(start);
+        assert!(!uf.is_valid(again));
+        assert!(!uf.equiv(0, 1));
+    }
+
+    #[test]
+    fn outer_rollback_closes_inner() {
+        let mut uf = UnionFindUndo::<usize>::new(4);
+        let outer = uf.checkpoint();
+        uf.union(0, 1);
+        let inner = uf.checkpoint();
+        uf.union(1, 2);
+
+        uf.rollback_to(outer);
+        assert!(!uf.is_valid(outer));
+        assert!(!uf.is_valid(inner));
+        assert!(!uf.equiv(0, 1));
+
+        uf.union(2, 3);
+        assert!(uf.trail.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn double_commit() {
+        let mut uf = UnionFindUndo::<usize>::new(4);
+        let outer = uf.checkpoint();
+        uf.union(0, 1);
+        uf.checkpoint();
+        uf.union(1, 2);
+        uf.rollback_to(outer);
+        uf.commit(outer);
+    }
+
+    #[test]
+    fn closed_checkpoint_on_empty_trail() {
+        let mut uf = UnionFindUndo::<usize>::new(2);
+        let first = uf.checkpoint();
+        uf.commit(first);
+        assert!(!uf.is_valid(first));
+
+        let second = uf.checkpoint();
+        assert_ne!(first, second);
+        assert!(!uf.is_valid(first));
+        uf.commit(second);
+    }
+
+    #[test]
+    fn commit_removes_later_levels() {
+        let mut uf = UnionFindUndo::<usize>::new(2);
+        let checkpoint = uf.checkpoint();
+        uf.push_level();
+        uf.union(0, 1);
+        uf.commit(checkpoint);
+        assert_eq!(uf.level(), 0);
+        assert!(uf.equiv(0, 1));
+    }
+}
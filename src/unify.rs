@@ -0,0 +1,421 @@
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::mem;
+
+use super::{Checkpoint, UnionFindUndo};
+
+/// A key type for a [`UnificationTable`](struct.UnificationTable.html).
+///
+/// Keys are dense indices in disguise, so that a compiler can have
+/// separate key types for, say, type variables and region variables,
+/// and the type checker keeps them apart.
+pub trait TableKey: Copy + Eq {
+    /// The index of the key, counting from zero in the order keys were
+    /// created.
+    fn index(self) -> u32;
+
+    /// The key with the given index.
+    fn from_index(index: u32) -> Self;
+}
+
+impl TableKey for u32 {
+    fn index(self) -> u32 { self }
+    fn from_index(index: u32) -> Self { index }
+}
+
+/// A value that two sets of a [`UnificationTable`](struct.UnificationTable.html)
+/// must agree on to be unified.
+///
+/// This has the same shape as the trait of the same name in the
+/// [`ena`](https://docs.rs/ena) crate.
+pub trait UnifyValue: Clone + Debug {
+    /// The error returned when two values cannot be unified.
+    type Error;
+
+    /// Combines the values of two sets being unified, or explains why
+    /// they cannot be.
+    fn unify_values(value1: &Self, value2: &Self) -> Result<Self, Self::Error>;
+}
+
+/// A value that unifies with another exactly when they are equal, as in
+/// `ena`.
+///
+/// Every `EqUnifyValue` is a [`UnifyValue`](trait.UnifyValue.html) whose
+/// error is the pair of unequal values.
+pub trait EqUnifyValue: Eq + Clone + Debug {}
+
+impl<T: EqUnifyValue> UnifyValue for T {
+    type Error = (T, T);
+
+    fn unify_values(value1: &Self, value2: &Self) -> Result<Self, (T, T)> {
+        if value1 == value2 {
+            Ok(value1.clone())
+        } else {
+            Err((value1.clone(), value2.clone()))
+        }
+    }
+}
+
+/// Union-find over typed keys, with a value per set and snapshots.
+///
+/// This is the structure at the heart of type inference: each key stands
+/// for an inference variable, and its set’s value records what is known
+/// about it so far. [`unify`](#method.unify) joins two sets only if
+/// their values unify according to [`UnifyValue`](trait.UnifyValue.html),
+/// and [`rollback_to`](#method.rollback_to) undoes every change since a
+/// [`snapshot`](#method.snapshot), as when speculatively trying one
+/// typing before another. Changes are recorded only while a snapshot is
+/// open, until it is rolled back to or [`commit`](#method.commit)ted.
+///
+/// The sets are kept in a [`UnionFindUndo`](struct.UnionFindUndo.html),
+/// so [`find`](#method.find) takes time logarithmic in the size of the
+/// set.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::{UnificationTable, UnifyValue};
+///
+/// // What is known about a type variable: nothing, or its type.
+/// #[derive(Clone, Debug, PartialEq)]
+/// enum Type { Unknown, Int, Bool }
+///
+/// impl UnifyValue for Type {
+///     type Error = (Type, Type);
+///
+///     fn unify_values(a: &Type, b: &Type) -> Result<Type, (Type, Type)> {
+///         match (a, b) {
+///             (Type::Unknown, _) => Ok(b.clone()),
+///             (_, Type::Unknown) => Ok(a.clone()),
+///             _ if a == b => Ok(a.clone()),
+///             _ => Err((a.clone(), b.clone())),
+///         }
+///     }
+/// }
+///
+/// let mut table = UnificationTable::<u32, Type>::new();
+/// let x = table.new_key(Type::Unknown);
+/// let y = table.new_key(Type::Int);
+/// let z = table.new_key(Type::Unknown);
+///
+/// table.unify(x, z).unwrap();
+///
+/// let snapshot = table.snapshot();
+/// table.unify_value(z, Type::Bool).unwrap();
+/// assert_eq!(table.unify(x, y), Err((Type::Bool, Type::Int)));
+/// table.rollback_to(snapshot);
+///
+/// table.unify(x, y).unwrap();
+/// assert_eq!(table.value(z), &Type::Int);
+/// ```
+#[derive(Clone)]
+pub struct UnificationTable<K, V> {
+    union_find: UnionFindUndo<u32>,
+    // The value of each set, at the index of its representative.
+    values:     Vec<V>,
+    // The values overwritten, with their indices, for rolling back.
+    undo_log:   Vec<(u32, V)>,
+    marker:     PhantomData<K>,
+}
+// Invariant: the open snapshots are those whose checkpoints are open in
+// self.union_find, and self.undo_log is empty when none is.
+
+/// A point in the history of a
+/// [`UnificationTable`](struct.UnificationTable.html) to which it can be
+/// rolled back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Snapshot {
+    checkpoint: Checkpoint,
+    undo_len:   usize,
+}
+
+impl<K, V: Debug> Debug for UnificationTable<K, V> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "UnificationTable(")?;
+        formatter.debug_list()
+            .entries((0 .. self.values.len()).map(|i| {
+                &self.values[self.union_find.find(i as u32) as usize]
+            }))
+            .finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<K, V> Default for UnificationTable<K, V> {
+    fn default() -> Self {
+        UnificationTable {
+            union_find: UnionFindUndo::new(0),
+            values:     Vec::new(),
+            undo_log:   Vec::new(),
+            marker:     PhantomData,
+        }
+    }
+}
+
+impl<K: TableKey, V: UnifyValue> UnificationTable<K, V> {
+    /// Creates a new, empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of keys in the table.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Is the table devoid of keys?
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Creates a new key in a singleton set with the given value.
+    ///
+    /// Rolling back to a snapshot taken before the key was created
+    /// removes it.
+    ///
+    /// # Panics
+    ///
+    /// If there are already 2<sup>32</sup> keys.
+    pub fn new_key(&mut self, value: V) -> K {
+        let index = self.union_find.alloc();
+        self.values.push(value);
+        K::from_index(index)
+    }
+
+    /// Finds the representative key for the given key’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `key` is not in the table.
+    pub fn find(&self, key: K) -> K {
+        K::from_index(self.union_find.find(key.index()))
+    }
+
+    /// Are the two keys in the same set?
+    pub fn equiv(&self, a: K, b: K) -> bool {
+        self.union_find.equiv(a.index(), b.index())
+    }
+
+    /// The value of the key’s set.
+    pub fn value(&self, key: K) -> &V {
+        &self.values[self.find(key).index() as usize]
+    }
+
+    /// Unifies the sets of two keys, combining their values with
+    /// [`UnifyValue::unify_values`](trait.UnifyValue.html#tymethod.unify_values).
+    ///
+    /// If the values do not unify, returns the error and changes nothing.
+    pub fn unify(&mut self, a: K, b: K) -> Result<(), V::Error> {
+        let a = self.union_find.find(a.index());
+        let b = self.union_find.find(b.index());
+        if a == b { return Ok(()); }
+
+        let value = V::unify_values(&self.values[a as usize],
+                                    &self.values[b as usize])?;
+        self.union_find.union(a, b);
+        let root = self.union_find.find(a);
+        self.set_value(root, value);
+        Ok(())
+    }
+
+    /// Unifies the value of the key’s set with the given value.
+    ///
+    /// If the values do not unify, returns the error and changes nothing.
+    pub fn unify_value(&mut self, key: K, value: V) -> Result<(), V::Error> {
+        let root = self.union_find.find(key.index());
+        let value = V::unify_values(&self.values[root as usize], &value)?;
+        self.set_value(root, value);
+        Ok(())
+    }
+
+    /// Returns a snapshot of the current state, and opens it, so that
+    /// changes are recorded until it is rolled back to or committed.
+    pub fn snapshot(&mut self) -> Snapshot {
+        Snapshot {
+            checkpoint: self.union_find.checkpoint(),
+            undo_len:   self.undo_log.len(),
+        }
+    }
+
+    /// Restores the state at the given snapshot, undoing every key
+    /// creation, unification, and change of value since, and closes the
+    /// snapshot.
+    ///
+    /// Snapshots taken after `snapshot` are closed too.
+    ///
+    /// # Panics
+    ///
+    /// If `snapshot` is not open, because it, or a snapshot taken before
+    /// it, has already been rolled back to or committed.
+    pub fn rollback_to(&mut self, snapshot: Snapshot) {
+        assert!(self.union_find.is_valid(snapshot.checkpoint),
+                "UnificationTable::rollback_to: invalid snapshot");
+
+        for (index, value) in self.undo_log.drain(snapshot.undo_len ..).rev() {
+            self.values[index as usize] = value;
+        }
+
+        self.union_find.rollback_to(snapshot.checkpoint);
+        self.values.truncate(self.union_find.len());
+        self.close_snapshot();
+    }
+
+    /// Keeps every change since the given snapshot, and closes the
+    /// snapshot.
+    ///
+    /// Snapshots taken after `snapshot` are closed too. Committing the
+    /// oldest open snapshot discards the undo log.
+    ///
+    /// # Panics
+    ///
+    /// If `snapshot` is not open, because it, or a snapshot taken before
+    /// it, has already been rolled back to or committed.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        assert!(self.union_find.is_valid(snapshot.checkpoint),
+                "UnificationTable::commit: invalid snapshot");
+
+        self.union_find.commit(snapshot.checkpoint);
+        self.close_snapshot();
+    }
+
+    // HELPERS
+
+    fn set_value(&mut self, root: u32, value: V) {
+        let old = mem::replace(&mut self.values[root as usize], value);
+        if self.union_find.is_recording() {
+            self.undo_log.push((root, old));
+        }
+    }
+
+    // Called after the union-find has closed the snapshot’s checkpoint.
+    fn close_snapshot(&mut self) {
+        if !self.union_find.is_recording() {
+            self.undo_log.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct TyVar(u32);
+
+    impl TableKey for TyVar {
+        fn index(self) -> u32 { self.0 }
+        fn from_index(index: u32) -> Self { TyVar(index) }
+    }
+
+    // A value unifies with another if neither is known or they agree.
+    impl UnifyValue for Option<char> {
+        type Error = ();
+
+        fn unify_values(a: &Self, b: &Self) -> Result<Self, ()> {
+            match (*a, *b) {
+                (Some(x), Some(y)) if x != y => Err(()),
+                _ => Ok(a.or(*b)),
+            }
+        }
+    }
+
+    #[test]
+    fn unify() {
+        let mut table = UnificationTable::<TyVar, Option<char>>::new();
+        let keys: Vec<_> = vec![None, Some('a'), None, Some('b')]
+            .into_iter().map(|value| table.new_key(value)).collect();
+
+        assert_eq!(table.unify(keys[0], keys[1]), Ok(()));
+        assert_eq!(table.unify(keys[2], keys[3]), Ok(()));
+        assert_eq!(table.unify(keys[1], keys[2]), Err(()));
+        assert!(!table.equiv(keys[0], keys[3]));
+        assert_eq!(table.value(keys[0]), &Some('a'));
+        assert_eq!(table.unify_value(keys[2], Some('a')), Err(()));
+        assert_eq!(table.unify_value(keys[2], None), Ok(()));
+        assert_eq!(table.find(keys[3]), table.find(keys[2]));
+    }
+
+    #[test]
+    fn rollback() {
+        let mut table = UnificationTable::<u32, Option<char>>::new();
+        let a = table.new_key(None);
+        let b = table.new_key(None);
+
+        let outer = table.snapshot();
+        table.unify_value(a, Some('x')).unwrap();
+        let inner = table.snapshot();
+        let c = table.new_key(Some('x'));
+        table.unify(a, c).unwrap();
+        table.unify(b, c).unwrap();
+        assert_eq!(table.len(), 3);
+
+        table.rollback_to(inner);
+        assert_eq!(table.len(), 2);
+        assert!(!table.equiv(a, b));
+        assert_eq!(table.value(a), &Some('x'));
+
+        table.rollback_to(outer);
+        assert_eq!(table.value(a), &None);
+        assert_eq!(format!("{:?}", table), "UnificationTable([None, None])");
+    }
+
+    #[test]
+    fn undo_log_only_while_open() {
+        let mut table = UnificationTable::<u32, Option<char>>::new();
+        let a = table.new_key(None);
+        let b = table.new_key(None);
+        table.unify_value(a, Some('a')).unwrap();
+        assert!(table.undo_log.is_empty());
+
+        let snapshot = table.snapshot();
+        table.unify(a, b).unwrap();
+        assert_eq!(table.undo_log.len(), 1);
+        table.commit(snapshot);
+        assert!(table.undo_log.is_empty());
+        assert_eq!(table.value(b), &Some('a'));
+    }
+
+    #[test]
+    fn outer_rollback_closes_inner() {
+        let mut table = UnificationTable::<u32, Option<char>>::new();
+        let a = table.new_key(None);
+
+        let outer = table.snapshot();
+        table.unify_value(a, Some('a')).unwrap();
+        let inner = table.snapshot();
+        table.new_key(None);
+
+        table.rollback_to(outer);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.value(a), &None);
+
+        assert!(!table.union_find.is_valid(inner.checkpoint));
+
+        table.unify_value(a, Some('b')).unwrap();
+        assert!(table.undo_log.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn repeated_outer_rollback() {
+        let mut table = UnificationTable::<u32, Option<char>>::new();
+        let a = table.new_key(None);
+        let outer = table.snapshot();
+        table.unify_value(a, Some('a')).unwrap();
+        table.snapshot();
+        table.rollback_to(outer);
+        table.rollback_to(outer);
+    }
+
+    #[test]
+    #[should_panic]
+    fn stale_snapshot() {
+        let mut table = UnificationTable::<u32, Option<char>>::new();
+        let a = table.new_key(None);
+        let start = table.snapshot();
+        table.unify_value(a, Some('a')).unwrap();
+        let later = table.snapshot();
+        table.rollback_to(start);
+        table.rollback_to(later);
+    }
+}
@@ -0,0 +1,110 @@
+//! Checking parent-pointer forests that come from untrusted sources.
+
+// Checks that `parent` and `rank` describe a rank-balanced forest over
+// `0 .. len`: every parent is in bounds, every non-root’s rank is less
+// than its parent’s, and no rank exceeds the base-2 log of `len`.
+//
+// Since ranks increase strictly along every path, following parents
+// from any element eventually reaches a root, which is its own parent.
+// And since a root of rank `r` has at least `2^r` descendants, the bound
+// on ranks holds for every forest that unions can build, and it keeps
+// later unions from overflowing a narrow rank field.
+pub(crate) fn validate_forest<P, R>(len: usize, parent: P, rank: R)
+        -> Result<(), String>
+        where P: Fn(usize) -> usize,
+              R: Fn(usize) -> usize {
+
+    validate(len, parent, rank, |child, parent| child.0 < parent.0)
+}
+
+// Like `validate_forest`, but allows a non-root to have the same rank as
+// its parent if its index is less.
+//
+// `AUnionFind` links the root with the lesser rank, or with equal ranks
+// the lesser index, beneath the other, and then tries to increment the
+// new root’s rank. When unions race, that increment may fail, leaving a
+// child with its parent’s rank.
+pub(crate) fn validate_concurrent_forest<P, R>(len: usize, parent: P, rank: R)
+        -> Result<(), String>
+        where P: Fn(usize) -> usize,
+              R: Fn(usize) -> usize {
+
+    validate(len, parent, rank, |child, parent| child < parent)
+}
+
+fn validate<P, R, L>(len: usize, parent: P, rank: R, less: L)
+        -> Result<(), String>
+        where P: Fn(usize) -> usize,
+              R: Fn(usize) -> usize,
+              L: Fn((usize, usize), (usize, usize)) -> bool {
+
+    for element in 0 .. len {
+        let element_rank = rank(element);
+        if element_rank >= BITS || len >> element_rank == 0 {
+            return Err(format!("element {} has rank {}, too large for {} elements",
+                               element, element_rank, len));
+        }
+
+        let next = parent(element);
+        if next >= len {
+            return Err(format!("element {} has out-of-bounds parent {}",
+                               element, next));
+        }
+
+        if next != element && !less((element_rank, element), (rank(next), next)) {
+            return Err(format!("element {} has rank {} but its parent {} has rank {}",
+                               element, element_rank, next, rank(next)));
+        }
+    }
+
+    Ok(())
+}
+
+const BITS: usize = 8 * ::std::mem::size_of::<usize>();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        assert_eq!(validate_forest(0, |i| i, |_| 0), Ok(()));
+        assert_eq!(validate_forest(4, |i| i, |_| 0), Ok(()));
+
+        let parents = [1, 3, 3, 3, 2];
+        let ranks   = [0, 1, 1, 2, 0];
+        assert_eq!(validate_forest(5, |i| parents[i], |i| ranks[i]), Ok(()));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let parents = [0, 5, 1];
+        assert!(validate_forest(3, |i| parents[i], |i| 2 - i).is_err());
+    }
+
+    #[test]
+    fn cycle() {
+        let parents = [0, 2, 3, 1];
+        assert!(validate_forest(4, |i| parents[i], |_| 0).is_err());
+
+        let parents = [1, 0];
+        assert!(validate_forest(2, |i| parents[i], |i| i).is_err());
+        assert!(validate_concurrent_forest(2, |i| parents[i], |_| 0).is_err());
+    }
+
+    #[test]
+    fn ranks() {
+        let parents = [1, 1];
+        assert!(validate_forest(2, |i| parents[i], |_| 0).is_err());
+        assert!(validate_forest(2, |i| parents[i], |i| i).is_ok());
+        assert!(validate_concurrent_forest(2, |i| parents[i], |_| 0).is_ok());
+
+        let parents = [0, 0];
+        assert!(validate_concurrent_forest(2, |i| parents[i], |_| 0).is_err());
+
+        assert!(validate_forest(4, |i| i, |i| i.min(2)).is_ok());
+        assert!(validate_forest(3, |i| i, |i| i + 1).is_err());
+        assert!(validate_forest(1, |i| i, |_| BITS).is_err());
+        assert!(validate_forest(1, |i| i, |_| !0).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use std::error;
+use std::fmt::{self, Debug, Display};
+
+use super::ElementType;
+
+/// An abelian group, whose values can be used as offsets by
+/// [`WeightedUnionFind`](struct.WeightedUnionFind.html).
+///
+/// The operations must satisfy the group laws: `combine` is associative
+/// and commutative, `identity` is its identity, and combining a value
+/// with its `inverse` gives the identity.
+///
+/// The integer types implement addition modulo their range, which is a
+/// group because it wraps rather than overflowing.
+pub trait Group: Clone + PartialEq {
+    /// The identity element.
+    fn identity() -> Self;
+
+    /// Combines two values.
+    fn combine(&self, other: &Self) -> Self;
+
+    /// The inverse of a value.
+    fn inverse(&self) -> Self;
+
+    /// Combines `self` with the inverse of `other`.
+    fn difference(&self, other: &Self) -> Self {
+        self.combine(&other.inverse())
+    }
+}
+
+macro_rules! impl_group_for_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl Group for $ty {
+                fn identity() -> Self { 0 }
+
+                fn combine(&self, other: &Self) -> Self {
+                    self.wrapping_add(*other)
+                }
+
+                fn inverse(&self) -> Self {
+                    self.wrapping_neg()
+                }
+            }
+        )*
+    };
+}
+
+impl_group_for_integer!(i8, i16, i32, i64, i128, isize,
+                        u8, u16, u32, u64, u128, usize);
+
+/// A union that contradicts the offsets already known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Conflict<W> {
+    /// The offset already implied by earlier unions.
+    pub current:   W,
+    /// The offset that the rejected union asked for.
+    pub requested: W,
+}
+
+impl<W: Debug> Display for Conflict<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "conflicting offset: requested {:?} but already {:?}",
+               self.requested, self.current)
+    }
+}
+
+impl<W: Debug> error::Error for Conflict<W> {}
+
+/// Union-find that tracks offsets between the elements of each set.
+///
+/// Each element has an unknown weight `w(x)` drawn from a
+/// [`Group`](trait.Group.html), and [`union(a, b, d)`](#method.union)
+/// records the constraint `w(a) - w(b) = d`. Once two elements are in the
+/// same set, [`delta`](#method.delta) returns the difference of their
+/// weights implied by the constraints, and a union that contradicts it is
+/// rejected with a [`Conflict`](struct.Conflict.html).
+///
+/// Path compression updates offsets, so unlike
+/// [`UnionFind`](struct.UnionFind.html), queries take `&mut self`.
+///
+/// # Examples
+///
+/// ```
+/// use disjoint_sets::WeightedUnionFind;
+///
+/// // Three items on a shelf, each measured relative to another.
+/// let mut uf = WeightedUnionFind::<i64>::new(3);
+/// uf.union(0, 1, 5).unwrap();    // w(0) - w(1) = 5
+/// uf.union(2, 1, -3).unwrap();   // w(2) - w(1) = -3
+///
+/// assert_eq!(uf.delta(0, 2), Some(8));
+/// assert!(uf.union(0, 2, 7).is_err());
+/// ```
+#[derive(Clone)]
+pub struct WeightedUnionFind<W: Group, Element: ElementType = usize> {
+    parents: Vec<Element>,
+    ranks:   Vec<u8>,
+    // The weight of each element minus the weight of its parent.
+    offsets: Vec<W>,
+}
+
+impl<W: Group, Element: ElementType> Default for WeightedUnionFind<W, Element> {
+    fn default() -> Self {
+        WeightedUnionFind::new(0)
+    }
+}
+
+impl<W: Group, Element: Debug + ElementType> Debug for WeightedUnionFind<W, Element> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "WeightedUnionFind(")?;
+        formatter.debug_list().entries(&self.parents).finish()?;
+        write!(formatter, ")")
+    }
+}
+
+impl<W: Group, Element: ElementType> WeightedUnionFind<W, Element> {
+    /// Creates a new union-find of `size` elements.
+    ///
+    /// # Panics
+    ///
+    /// If `size` elements would overflow the element type `Element`.
+    pub fn new(size: usize) -> Self {
+        if size > 0 {
+            Element::from_usize(size - 1)
+                .expect("WeightedUnionFind::new: overflow");
+        }
+
+        WeightedUnionFind {
+            parents: (0 .. size).map(|i| Element::from_usize(i).unwrap()).collect(),
+            ranks:   vec![0; size],
+            offsets: vec![W::identity(); size],
+        }
+    }
+
+    /// The number of elements in all the sets.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    /// Is the union-find devoid of elements?
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// Creates a new element in a singleton set.
+    ///
+    /// # Panics
+    ///
+    /// If allocating another element would overflow the element type
+    /// `Element`.
+    pub fn alloc(&mut self) -> Element {
+        let result = Element::from_usize(self.len())
+            .expect("WeightedUnionFind::alloc: overflow");
+        self.parents.push(result);
+        self.ranks.push(0);
+        self.offsets.push(W::identity());
+        result
+    }
+
+    /// Joins the sets of the two given elements, constraining their
+    /// weights so that `w(a) - w(b) = delta`.
+    ///
+    /// Returns whether anything changed. That is, if the sets were
+    /// different, it returns `Ok(true)`, and if they were already the
+    /// same and `delta` agrees with their known offset, it returns
+    /// `Ok(false)`. If `delta` disagrees, it returns a `Conflict` and
+    /// leaves the union-find unchanged.
+    pub fn union(&mut self, a: Element, b: Element, delta: W)
+                 -> Result<bool, Conflict<W>> {
+        let (root_a, offset_a) = self.find_with_offset(a);
+        let (root_b, offset_b) = self.find_with_offset(b);
+
+        if root_a == root_b {
+            let current = offset_a.difference(&offset_b);
+            return if current == delta {
+                Ok(false)
+            } else {
+                Err(Conflict { current, requested: delta })
+            };
+        }
+
+        // w(root_a) - w(root_b) = delta - offset_a + offset_b
+        let roots_delta = delta.difference(&offset_a).combine(&offset_b);
+
+        let rank_a = self.ranks[root_a.to_usize()];
+        let rank_b = self.ranks[root_b.to_usize()];
+
+        if rank_a > rank_b {
+            self.parents[root_b.to_usize()] = root_a;
+            self.offsets[root_b.to_usize()] = roots_delta.inverse();
+        } else {
+            self.parents[root_a.to_usize()] = root_b;
+            self.offsets[root_a.to_usize()] = roots_delta;
+            if rank_a == rank_b {
+                self.ranks[root_b.to_usize()] += 1;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Finds the representative element for the given element’s set.
+    ///
+    /// # Panics
+    ///
+    /// If `element` is out of bounds.
+    pub fn find(&mut self, element: Element) -> Element {
+        self.find_with_offset(element).0
+    }
+
+    /// Determines whether two elements are in the same set.
+    pub fn equiv(&mut self, a: Element, b: Element) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Returns `w(a) - w(b)` if `a` and `b` are in the same set, or `None`
+    /// if they are not.
+    pub fn delta(&mut self, a: Element, b: Element) -> Option<W> {
+        let (root_a, offset_a) = self.find_with_offset(a);
+        let (root_b, offset_b) = self.find_with_offset(b);
+
+        if root_a == root_b {
+            Some(offset_a.difference(&offset_b))
+        } else {
+            None
+        }
+    }
+
+    /// Returns a vector of set representatives.
+    pub fn to_vec(&mut self) -> Vec<Element> {
+        (0 .. self.len())
+            .map(|i| self.find(Element::from_usize(i).unwrap()))
+            .collect()
+    }
+
+    // HELPERS
+
+    // Finds the root of `element`’s set and the weight of `element` minus
+    // the weight of the root, compressing the path along the way.
+    //
+    // The first pass finds the root and sums the offsets along the path.
+    // The second walks the path again, pointing each element at the root
+    // with the part of the sum that remains from that element up.
+    fn find_with_offset(&mut self, element: Element) -> (Element, W) {
+        let mut root = element;
+        let mut total = W::identity();
+
+        loop {
+            let parent = self.parents[root.to_usize()];
+            if parent == root { break; }
+            total = total.combine(&self.offsets[root.to_usize()]);
+            root = parent;
+        }
+
+        let mut current = element;
+        let mut remaining = total.clone();
+
+        while current != root {
+            let index = current.to_usize();
+            let next = self.parents[index];
+            let offset = self.offsets[index].clone();
+            self.parents[index] = root;
+            self.offsets[index] = remaining.clone();
+            remaining = remaining.difference(&offset);
+            current = next;
+        }
+
+        (root, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_compose() {
+        let mut uf = WeightedUnionFind::<i32, u16>::new(10);
+        // w(i) - w(i + 1) = 1 for each i, joined in a scattered order.
+        for &i in &[3, 7, 0, 5, 1, 8, 2, 6, 4] {
+            assert_eq!(uf.union(i, i + 1, 1), Ok(true));
+        }
+
+        for a in 0 .. 10 {
+            for b in 0 .. 10 {
+                assert_eq!(uf.delta(a, b), Some(b as i32 - a as i32));
+            }
+        }
+    }
+
+    #[test]
+    fn conflict_leaves_union_find_unchanged() {
+        let mut uf = WeightedUnionFind::<i64>::new(3);
+        uf.union(0, 1, 2).unwrap();
+        uf.union(1, 2, 3).unwrap();
+        assert_eq!(uf.union(0, 2, 5), Ok(false));
+        assert_eq!(uf.union(2, 0, 4),
+                   Err(Conflict { current: -5, requested: 4 }));
+        assert_eq!(uf.delta(2, 0), Some(-5));
+    }
+
+    #[test]
+    fn disconnected() {
+        let mut uf = WeightedUnionFind::<u8>::new(4);
+        uf.union(0, 1, 200).unwrap();
+        assert_eq!(uf.delta(0, 2), None);
+        assert!(!uf.equiv(1, 3));
+        let e = uf.alloc();
+        uf.union(e, 0, 100).unwrap();
+        // Arithmetic wraps modulo 256.
+        assert_eq!(uf.delta(e, 1), Some(44));
+    }
+}
@@ -0,0 +1,42 @@
+// Exercises the union-finds that need neither the standard library nor
+// an allocator. Run with `cargo test --no-default-features --test no_std`
+// to check that they are available without feature `"std"`.
+
+#![no_std]
+
+extern crate disjoint_sets;
+
+use disjoint_sets::{ArrayUnionFind, ElementType, Error, UnionFindRef};
+
+#[test]
+fn array_union_find() {
+    let mut uf = ArrayUnionFind::<8, u8>::new(6);
+    assert!(uf.union(0, 1));
+    assert!(uf.union(5, 1));
+    assert!(!uf.union(0, 5));
+
+    assert!(uf.equiv(0, 5));
+    assert!(!uf.equiv(0, 2));
+    assert_eq!(uf.try_alloc(), Ok(6));
+    assert_eq!(uf.try_alloc(), Ok(7));
+    assert_eq!(uf.try_alloc(), Err(Error::Overflow));
+}
+
+#[test]
+fn union_find_ref() {
+    let mut parents = [0u16; 4];
+    let mut ranks = [0u8; 4];
+
+    let mut uf = UnionFindRef::new(&mut parents, &mut ranks);
+    assert!(uf.union(3, 2));
+    assert!(uf.equiv(2, 3));
+    assert!(!uf.equiv(0, 3));
+    assert_eq!(uf.find(2), uf.find(3));
+}
+
+#[test]
+fn element_type() {
+    assert_eq!(u8::from_usize(255), Some(255));
+    assert_eq!(u8::from_usize(256), None);
+    assert_eq!(7u16.to_usize(), 7);
+}
@@ -123,7 +123,7 @@ impl Arbitrary for MultiScript {
         MultiScript(result)
     }
 
-    fn shrink(&self) -> Box<Iterator<Item=Self>> {
+    fn shrink(&self) -> Box<dyn Iterator<Item=Self>> {
         Box::new(
             self.0.shrink()
                 .flat_map(|scripts| scripts.shrink())
@@ -144,7 +144,7 @@ impl Arbitrary for Script {
         Script(result)
     }
 
-    fn shrink(&self) -> Box<Iterator<Item=Self>> {
+    fn shrink(&self) -> Box<dyn Iterator<Item=Self>> {
         Box::new(self.0.shrink().map(Script))
     }
 }
@@ -155,7 +155,7 @@ impl Arbitrary for Cmd {
         let mut gen_index = || g.gen_range(0, UF_LEN);
 
         match choice {
-            1...FIND_PCT => Cmd::Find(gen_index()),
+            1..=FIND_PCT => Cmd::Find(gen_index()),
             _            => Cmd::Union(gen_index(), gen_index()),
         }
     }